@@ -0,0 +1,107 @@
+// Compares eval_instrs over MemFetch with eval_ops on the same program after
+// threaded::compile. Each program loops forever, so both run exactly FUEL
+// instructions and stop with Thrash.
+//
+//     cargo bench --bench threaded
+
+extern crate tpm;
+
+use std::time::{Duration, Instant};
+use tpm::evaluator::*;
+use tpm::evaluator::Reg::*;
+use tpm::evaluator::Instruction::*;
+use tpm::evaluator::Cond::*;
+use tpm::evaluator::M2Op::*;
+use tpm::evaluator::Addr::*;
+use tpm::evaluator::threaded::{self, Op};
+use tpm::mem::MemFetch;
+
+const FUEL : u32 = 50_000_000;
+const RUNS : usize = 5;
+
+// The loop from main.rs, without the exit
+const COUNT : &[Instruction<u32>] = &[
+    Lit{val:0, reg:R0},
+    Um2I{op:Add, r1:R0, imm:1, r3:R0},
+    Um2I{op:Sub, r1:R0, imm:!0, r3:R3},
+    UJumpI{cond:NeZ, flag:R3, mode:Rel, target:(-2i32) as u32},
+    Halt
+];
+
+// Sums RAM over and over, jumping through a register
+const SUM : &[Instruction<u32>] = &[
+    Lit{val:0, reg:R0},
+    Lit{val:0, reg:R1},
+    Ram{dir:Dir::Read, ptr:R0, val:R2},
+    Um2{op:Add, r1:R1, r2:R2, r3:R1},
+    Um2I{op:Add, r1:R0, imm:1, r3:R0},
+    Um2I{op:Sub, r1:R0, imm:1024, r3:R3},
+    Lit{val:2, reg:R4},
+    UJump{cond:NeZ, flag:R3, dest:R4},
+    Lit{val:0, reg:R0},
+    UJumpI{cond:Always, flag:R0, mode:Abs, target:2}
+];
+
+// Mostly instructions without an op of their own, which go through
+// eval_instr either way
+const MIXED : &[Instruction<u32>] = &[
+    Lit{val:7, reg:R0},
+    Lit{val:3, reg:R1},
+    Um2{op:Mul, r1:R0, r2:R1, r3:R2},
+    Um2{op:Div, r1:R2, r2:R1, r3:R0},
+    Um4{op:M4Op::AddC, r1:R0, r2:R2, r3:R5, r4:R6},
+    USelect{cond:GtZ, flag:R6, r1:R0, r2:R1, r3:R7},
+    UJumpCmp{cond:LtZ, r1:R0, r2:R1, dest:R8},
+    UJumpI{cond:Always, flag:R0, mode:Abs, target:2}
+];
+
+fn best<F : FnMut() -> [u32;16]>(mut f : F) -> (Duration, [u32;16]) {
+    let mut time = Duration::from_secs(1 << 20);
+    let mut regs = [0;16];
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        regs = f();
+        time = time.min(start.elapsed());
+    }
+    (time, regs)
+}
+
+fn rate(time : Duration) -> f64 {
+    FUEL as f64 / time.as_secs_f64() / 1e6
+}
+
+fn bench(name : &str, program : &[Instruction<u32>]) {
+    let mut ram = vec![0u32; 1024];
+    for (i, w) in ram.iter_mut().enumerate() {
+        *w = i as u32;
+    }
+    let (fetch, fetch_regs) = best(|| {
+        let mut ram = ram.clone();
+        let mut state : State<u32,i32> = State::new(&mut ram);
+        match state.eval_instrs(FUEL, &mut MemFetch(program)) {
+            Ok(MutNotice::Thrash) => (),
+            other => panic!("{}: {:?}", name, other)
+        }
+        *state.regs()
+    });
+    let mut ops = vec![Op::default(); program.len()];
+    let ops = threaded::compile(program, &mut ops).unwrap();
+    let (threaded, threaded_regs) = best(|| {
+        let mut ram = ram.clone();
+        let mut state : State<u32,i32> = State::new(&mut ram);
+        match state.eval_ops(FUEL, ops) {
+            Ok(MutNotice::Thrash) => (),
+            other => panic!("{}: {:?}", name, other)
+        }
+        *state.regs()
+    });
+    assert_eq!(fetch_regs, threaded_regs, "{}", name);
+    println!("{:<8} eval_instrs {:>8.1} M/s   eval_ops {:>8.1} M/s   {:.2}x",
+             name, rate(fetch), rate(threaded), fetch.as_secs_f64() / threaded.as_secs_f64());
+}
+
+fn main() {
+    bench("count", COUNT);
+    bench("sum", SUM);
+    bench("mixed", MIXED);
+}
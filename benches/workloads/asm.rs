@@ -0,0 +1,70 @@
+// Just enough of an assembler to write the workloads: labels, with forward
+// references patched when the program is finished.
+//
+// Subroutines follow one convention: the caller puts its return address in
+// RE and jumps through RD, and the callee returns with a jump through RE.
+
+use std::collections::HashMap;
+use tpm::evaluator::*;
+use tpm::evaluator::Instruction::*;
+use tpm::evaluator::Reg::*;
+
+pub struct Asm {
+    code : Vec<Instruction<u32>>,
+    labels : HashMap<&'static str, u32>,
+    fixups : Vec<(usize, &'static str)>
+}
+
+impl Asm {
+    pub fn new() -> Asm {
+        Asm { code : Vec::new(), labels : HashMap::new(), fixups : Vec::new() }
+    }
+
+    pub fn here(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    pub fn label(&mut self, name : &'static str) {
+        let pc = self.here();
+        assert!(self.labels.insert(name, pc).is_none(), "label {} defined twice", name);
+    }
+
+    pub fn op(&mut self, instr : Instruction<u32>) {
+        self.code.push(instr)
+    }
+
+    // Lit of a label's address
+    pub fn lit_label(&mut self, name : &'static str, reg : Reg) {
+        self.fixups.push((self.code.len(), name));
+        self.op(Lit{val : 0, reg});
+    }
+
+    // UJumpI to a label
+    pub fn goto(&mut self, cond : Cond, flag : Reg, name : &'static str) {
+        self.fixups.push((self.code.len(), name));
+        self.op(UJumpI{cond, flag, mode : Addr::Abs, target : 0});
+    }
+
+    pub fn call(&mut self, name : &'static str) {
+        let ret = self.here() + 3;
+        self.op(Lit{val : ret, reg : RE});
+        self.lit_label(name, RD);
+        self.op(UJump{cond : Cond::Always, flag : R0, dest : RD});
+    }
+
+    pub fn ret(&mut self) {
+        self.op(UJump{cond : Cond::Always, flag : R0, dest : RE});
+    }
+
+    pub fn finish(mut self) -> Vec<Instruction<u32>> {
+        for &(pc, name) in self.fixups.iter() {
+            let addr = *self.labels.get(name).unwrap_or_else(|| panic!("no label {}", name));
+            match self.code[pc] {
+                Lit{ref mut val, ..} => *val = addr,
+                UJumpI{ref mut target, ..} => *target = addr,
+                ref other => panic!("can't patch {:?}", other)
+            }
+        }
+        self.code
+    }
+}
@@ -0,0 +1,200 @@
+// Guest workloads under each way of feeding the evaluator instructions:
+// MemFetch, an LRU4x64 cache in front of it, and eval_ops on the threaded
+// form. Criterion-style, without the dependency: warm up, take timed
+// samples, report the median with the fastest and slowest, and compare with
+// the previous run.
+//
+//     cargo bench --bench workloads [-- <filter>]
+//
+// The previous run's medians are kept in target/workloads-baseline. Changes
+// over NOISE are flagged.
+
+extern crate tpm;
+
+mod asm;
+mod programs;
+
+use programs::Workload;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tpm::evaluator::*;
+use tpm::evaluator::threaded::{self, Op};
+use tpm::mem::*;
+
+const WARMUP : Duration = Duration::from_millis(300);
+const MEASURE : Duration = Duration::from_secs(2);
+const MIN_SAMPLES : usize = 10;
+const MAX_SAMPLES : usize = 500;
+const NOISE : f64 = 0.05;
+
+#[derive(Copy, Clone)]
+enum Fetch {
+    Mem, Lru, Threaded
+}
+
+impl Fetch {
+    fn name(self) -> &'static str {
+        match self {
+            Fetch::Mem => "MemFetch",
+            Fetch::Lru => "LRU4x64",
+            Fetch::Threaded => "threaded"
+        }
+    }
+}
+
+// Counts fetches, i.e. instructions run
+struct Counted<F> {
+    inner : F,
+    fetches : usize
+}
+
+impl<F : Fetcher<u32, Instruction<u32>>> Fetcher<u32, Instruction<u32>> for Counted<F> {
+    fn fetch(&mut self, ptr : u32) -> Option<Instruction<u32>> {
+        self.fetches += 1;
+        self.inner.fetch(ptr)
+    }
+}
+
+// Counts the lines an LRU4x64 loads, i.e. its misses
+struct Loads<'c, L> {
+    inner : L,
+    count : &'c Cell<usize>
+}
+
+impl<'c, L : Load64<u32, Instruction<u32>>> Load64<u32, Instruction<u32>> for Loads<'c, L> {
+    fn load(&mut self, ptr : u32) -> Option<([Instruction<u32>; 64], usize)> {
+        self.count.set(self.count.get() + 1);
+        self.inner.load(ptr)
+    }
+}
+
+fn start<'r>(w : &Workload, ram : &'r mut [u32]) -> State<'r, u32, i32> {
+    let mut state = State::new(ram);
+    if let Some((base, top)) = w.stack {
+        state.set_stack(base, top).unwrap();
+    }
+    state
+}
+
+fn halted<'t>(name : &str, res : Result<MutNotice<'t, u32>, Failure<u32>>) {
+    match res {
+        Ok(MutNotice::Halt) => (),
+        other => panic!("{}: {:?}", name, other)
+    }
+}
+
+// Runs w to Halt on ram and returns the registers.
+fn run(w : &Workload, fetch : Fetch, ops : &[Op<u32>], ram : &mut [u32]) -> [u32; 16] {
+    let mut state = start(w, ram);
+    match fetch {
+        Fetch::Mem => halted(w.name, state.eval_instrs(!0, &mut MemFetch(&w.code[..]))),
+        Fetch::Lru => halted(w.name, state.eval_instrs(!0, &mut LRU4x64::new(MemFetch(&w.code[..])))),
+        Fetch::Threaded => halted(w.name, state.eval_ops(!0, ops))
+    }
+    *state.regs()
+}
+
+// Instructions run, and how many fetches LRU4x64 had to load a line for.
+fn profile(w : &Workload) -> (usize, usize) {
+    let misses = Cell::new(0);
+    let mut ram = w.ram.clone();
+    let mut fetcher = Counted {
+        inner : LRU4x64::new(Loads {inner : MemFetch(&w.code[..]), count : &misses}),
+        fetches : 0
+    };
+    halted(w.name, start(w, &mut ram).eval_instrs(!0, &mut fetcher));
+    (fetcher.fetches, misses.get())
+}
+
+// Sorted sample times
+fn sample(w : &Workload, fetch : Fetch, ops : &[Op<u32>]) -> Vec<Duration> {
+    let timed = || {
+        let mut ram = w.ram.clone();
+        let start = Instant::now();
+        let regs = run(w, fetch, ops, &mut ram);
+        let time = start.elapsed();
+        assert!((w.check)(&regs, &ram), "{} under {} gave the wrong answer", w.name, fetch.name());
+        time
+    };
+    let begin = Instant::now();
+    while begin.elapsed() < WARMUP {
+        timed();
+    }
+    let mut times = Vec::new();
+    let begin = Instant::now();
+    while times.len() < MIN_SAMPLES || (times.len() < MAX_SAMPLES && begin.elapsed() < MEASURE) {
+        times.push(timed());
+    }
+    times.sort();
+    times
+}
+
+fn baseline_path() -> PathBuf {
+    let target = env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/target").to_string());
+    PathBuf::from(target).join("workloads-baseline")
+}
+
+// Benchmark id to median in nanoseconds
+fn read_baseline() -> HashMap<String, f64> {
+    let text = fs::read_to_string(baseline_path()).unwrap_or_default();
+    text.lines().filter_map(|line| {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next().and_then(|ns| ns.parse().ok())) {
+            (Some(id), Some(ns)) => Some((id.to_string(), ns)),
+            _ => None
+        }
+    }).collect()
+}
+
+fn ms(d : Duration) -> String {
+    format!("{:.3} ms", d.as_secs_f64() * 1e3)
+}
+
+fn main() {
+    // cargo passes --bench; anything else is a filter on the benchmark id
+    let filters : Vec<String> = env::args().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let old = read_baseline();
+    let mut new = old.clone();
+    for w in programs::all() {
+        let mut ops = vec![Op::default(); w.code.len()];
+        let ops = threaded::compile(&w.code, &mut ops).unwrap();
+        let (instrs, misses) = profile(&w);
+        let mut header = false;
+        for &fetch in [Fetch::Mem, Fetch::Lru, Fetch::Threaded].iter() {
+            let id = format!("{}/{}", w.name, fetch.name());
+            if !filters.is_empty() && !filters.iter().any(|f| id.contains(&f[..])) {
+                continue
+            }
+            if !header {
+                println!("{}: {} instructions of code, {} run, LRU4x64 hit rate {:.3}% ({} misses)",
+                         w.name, w.code.len(), instrs, 100.0 * (instrs - misses) as f64 / instrs as f64, misses);
+                header = true;
+            }
+            let times = sample(&w, fetch, ops);
+            let median = times[times.len() / 2];
+            let ns = median.as_secs_f64() * 1e9;
+            let change = match old.get(&id) {
+                None => String::new(),
+                Some(&before) => {
+                    let delta = ns / before - 1.0;
+                    let verdict = if delta > NOISE {"  regressed"} else if delta < -NOISE {"  improved"} else {""};
+                    format!("  {:+.1}%{}", delta * 100.0, verdict)
+                }
+            };
+            println!("  {:<24} [{} {} {}]  {:>7.1} Minstr/s{}",
+                     id, ms(times[0]), ms(median), ms(times[times.len() - 1]),
+                     instrs as f64 / median.as_secs_f64() / 1e6, change);
+            new.insert(id, ns);
+        }
+    }
+    let mut ids : Vec<&String> = new.keys().collect();
+    ids.sort();
+    let text : String = ids.iter().map(|id| format!("{} {:.0}\n", id, new[*id])).collect();
+    if let Err(e) = fs::write(baseline_path(), text) {
+        println!("couldn't save the baseline: {}", e);
+    }
+}
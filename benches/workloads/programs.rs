@@ -0,0 +1,428 @@
+// The guest programs. Each one runs to Halt, and check() confirms it got
+// the right answer, so a broken program can't pass for a fast one.
+
+use asm::Asm;
+use tpm::bytes;
+use tpm::crypto::sha256;
+use tpm::evaluator::*;
+use tpm::evaluator::Instruction::*;
+use tpm::evaluator::Reg::*;
+use tpm::evaluator::Cond::*;
+use tpm::evaluator::M2Op::*;
+
+// Given the registers and RAM after Halt, whether the answer is right
+pub type Check = Box<dyn Fn(&[u32;16], &[u32]) -> bool>;
+
+pub struct Workload {
+    pub name : &'static str,
+    pub code : Vec<Instruction<u32>>,
+    pub ram : Vec<u32>,
+    // Stack base and top, if the program uses one
+    pub stack : Option<(u32, u32)>,
+    pub check : Check
+}
+
+pub fn all() -> Vec<Workload> {
+    vec![sha256(false), sha256(true), bubble_sort(), fibonacci(), collatz()]
+}
+
+fn load(a : &mut Asm, addr : u32, reg : Reg) {
+    a.op(RamI{dir : Dir::Read, ptr : RF, off : addr, val : reg});
+}
+
+fn store(a : &mut Asm, addr : u32, reg : Reg) {
+    a.op(RamI{dir : Dir::Write, ptr : RF, off : addr, val : reg});
+}
+
+fn mov(a : &mut Asm, src : Reg, dst : Reg) {
+    a.op(Um2I{op : Add, r1 : src, imm : 0, r3 : dst});
+}
+
+// dst = x rotated right by n, with R0 as scratch
+fn rotr(a : &mut Asm, x : Reg, n : u32, dst : Reg) {
+    a.op(Um2I{op : MulHi, r1 : x, imm : 1 << (32 - n), r3 : R0});
+    a.op(Um2I{op : Mul, r1 : x, imm : 1 << (32 - n), r3 : dst});
+    a.op(Um2{op : Add, r1 : R0, r2 : dst, r3 : dst});
+}
+
+fn shr(a : &mut Asm, x : Reg, n : u32, dst : Reg) {
+    a.op(Um2I{op : MulHi, r1 : x, imm : 1 << (32 - n), r3 : dst});
+}
+
+// SHA-256 compression over a padded message, as firmware would run it. The
+// ISA has no bitwise operations, so "xor" and "and" go a byte at a time
+// through 256x256 tables in RAM, and rotations are a MulHi and a Mul. They
+// are either subroutines or inlined at every use; inlined, the program no
+// longer fits in an LRU4x64.
+//
+// Register use: R2 round counter, R3 block pointer, RF zero for absolute
+// addressing with RamI. xor and and take R4 and R5, return R6, and use R0,
+// R1, R7 and R8.
+const XOR : u32 = 0;
+const AND : u32 = 0x10000;
+const KS : u32 = 0x20000;
+const HS : u32 = KS + 64;
+const WS : u32 = HS + 8;
+const VS : u32 = WS + 64;
+// Temporaries T0 to T2, then the operands of xor and and
+const TS : u32 = VS + 8;
+const SA : u32 = TS + 3;
+const SB : u32 = TS + 4;
+const MS : u32 = TS + 8;
+const BLOCKS : usize = 4;
+
+// R6 = R4 op R5, a byte at a time through the table at R7
+fn table_op(a : &mut Asm) {
+    store(a, SA, R4);
+    store(a, SB, R5);
+    a.op(Lit{val : 0, reg : R6});
+    for i in 0 .. 4 {
+        a.op(Lit{val : SA * 4 + i, reg : R8});
+        a.op(ULoad{width : Width::Byte, ptr : R8, val : R0});
+        a.op(Lit{val : SB * 4 + i, reg : R8});
+        a.op(ULoad{width : Width::Byte, ptr : R8, val : R1});
+        a.op(Um2I{op : Mul, r1 : R0, imm : 256, r3 : R0});
+        a.op(Um2{op : Add, r1 : R0, r2 : R1, r3 : R0});
+        a.op(Um2{op : Add, r1 : R0, r2 : R7, r3 : R0});
+        a.op(Ram{dir : Dir::Read, ptr : R0, val : R0});
+        a.op(Um2I{op : Mul, r1 : R6, imm : 256, r3 : R6});
+        a.op(Um2{op : Add, r1 : R6, r2 : R0, r3 : R6});
+    }
+}
+
+fn xor(a : &mut Asm, inline : bool) {
+    if inline {
+        a.op(Lit{val : XOR, reg : R7});
+        table_op(a);
+    } else {
+        a.call("xor");
+    }
+}
+
+fn and(a : &mut Asm, inline : bool) {
+    if inline {
+        a.op(Lit{val : AND, reg : R7});
+        table_op(a);
+    } else {
+        a.call("and");
+    }
+}
+
+fn sha256(inline : bool) -> Workload {
+    let message : Vec<u8> = (0 .. BLOCKS * 64 - 9).map(|i| (i * 7 + 3) as u8).collect();
+    let mut padded = message.clone();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+    assert_eq!(padded.len(), BLOCKS * 64);
+
+    let mut ram = vec![0u32; MS as usize + BLOCKS * 16];
+    for x in 0 .. 256 {
+        for y in 0 .. 256 {
+            ram[(XOR + x * 256 + y) as usize] = x ^ y;
+            ram[(AND + x * 256 + y) as usize] = x & y;
+        }
+    }
+    // The constants are the fractional parts of the cube roots of the first
+    // 64 primes and the square roots of the first 8.
+    let primes : Vec<u32> = (2u32 ..).filter(|&n| (2 .. n).all(|d| n % d != 0)).take(64).collect();
+    let frac = |x : f64| ((x - x.floor()) * 4294967296.0) as u32;
+    for (i, &p) in primes.iter().enumerate() {
+        ram[KS as usize + i] = frac((p as f64).cbrt());
+    }
+    for (i, &p) in primes[.. 8].iter().enumerate() {
+        ram[HS as usize + i] = frac((p as f64).sqrt());
+    }
+    bytes::write_bytes(&mut ram, MS as usize * 4, &padded).unwrap();
+
+    let mut a = Asm::new();
+    a.op(Lit{val : 0, reg : RF});
+    a.op(Lit{val : MS, reg : R3});
+    a.label("block");
+    // W[0..16] is the block
+    a.op(Lit{val : WS, reg : R9});
+    a.op(Lit{val : 16, reg : RA});
+    a.op(MemCopy{dst : R9, src : R3, len : RA});
+    a.op(Lit{val : 16, reg : R2});
+    a.label("schedule");
+    // s0 = rotr(w15, 7) ^ rotr(w15, 18) ^ w15 >> 3
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : WS.wrapping_sub(15), val : R9});
+    rotr(&mut a, R9, 7, R4);
+    rotr(&mut a, R9, 18, R5);
+    xor(&mut a, inline);
+    mov(&mut a, R6, R4);
+    shr(&mut a, R9, 3, R5);
+    xor(&mut a, inline);
+    store(&mut a, TS, R6);
+    // s1 = rotr(w2, 17) ^ rotr(w2, 19) ^ w2 >> 10
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : WS.wrapping_sub(2), val : R9});
+    rotr(&mut a, R9, 17, R4);
+    rotr(&mut a, R9, 19, R5);
+    xor(&mut a, inline);
+    mov(&mut a, R6, R4);
+    shr(&mut a, R9, 10, R5);
+    xor(&mut a, inline);
+    // W[t] = W[t-16] + s0 + W[t-7] + s1
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : WS.wrapping_sub(16), val : RA});
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : WS.wrapping_sub(7), val : RA});
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    load(&mut a, TS, RA);
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    a.op(RamI{dir : Dir::Write, ptr : R2, off : WS, val : R6});
+    a.op(Um2I{op : Add, r1 : R2, imm : 1, r3 : R2});
+    a.op(Um2I{op : Sub, r1 : R2, imm : 64, r3 : RA});
+    a.goto(NeZ, RA, "schedule");
+
+    // a..h start as H
+    a.op(Lit{val : VS, reg : R9});
+    a.op(Lit{val : HS, reg : RA});
+    a.op(Lit{val : 8, reg : RB});
+    a.op(MemCopy{dst : R9, src : RA, len : RB});
+    a.op(Lit{val : 0, reg : R2});
+    a.label("round");
+    // T0 = S1 = rotr(e, 6) ^ rotr(e, 11) ^ rotr(e, 25)
+    load(&mut a, VS + 4, R9);
+    rotr(&mut a, R9, 6, R4);
+    rotr(&mut a, R9, 11, R5);
+    xor(&mut a, inline);
+    mov(&mut a, R6, R4);
+    rotr(&mut a, R9, 25, R5);
+    xor(&mut a, inline);
+    store(&mut a, TS, R6);
+    // ch = (e & f) ^ (!e & g)
+    mov(&mut a, R9, R4);
+    load(&mut a, VS + 5, R5);
+    and(&mut a, inline);
+    store(&mut a, TS + 1, R6);
+    a.op(Lit{val : !0, reg : RA});
+    a.op(Um2{op : Sub, r1 : RA, r2 : R9, r3 : R4});
+    load(&mut a, VS + 6, R5);
+    and(&mut a, inline);
+    mov(&mut a, R6, R4);
+    load(&mut a, TS + 1, R5);
+    xor(&mut a, inline);
+    // T0 = temp1 = h + S1 + ch + K[t] + W[t]
+    load(&mut a, VS + 7, RA);
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    load(&mut a, TS, RA);
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : KS, val : RA});
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : WS, val : RA});
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    store(&mut a, TS, R6);
+    // T1 = S0 = rotr(a, 2) ^ rotr(a, 13) ^ rotr(a, 22)
+    load(&mut a, VS, R9);
+    rotr(&mut a, R9, 2, R4);
+    rotr(&mut a, R9, 13, R5);
+    xor(&mut a, inline);
+    mov(&mut a, R6, R4);
+    rotr(&mut a, R9, 22, R5);
+    xor(&mut a, inline);
+    store(&mut a, TS + 1, R6);
+    // maj = (a & b) ^ (a & c) ^ (b & c)
+    mov(&mut a, R9, R4);
+    load(&mut a, VS + 1, R5);
+    and(&mut a, inline);
+    store(&mut a, TS + 2, R6);
+    mov(&mut a, R9, R4);
+    load(&mut a, VS + 2, R5);
+    and(&mut a, inline);
+    mov(&mut a, R6, R4);
+    load(&mut a, TS + 2, R5);
+    xor(&mut a, inline);
+    store(&mut a, TS + 2, R6);
+    load(&mut a, VS + 1, R4);
+    load(&mut a, VS + 2, R5);
+    and(&mut a, inline);
+    mov(&mut a, R6, R4);
+    load(&mut a, TS + 2, R5);
+    xor(&mut a, inline);
+    // R6 = temp2 = S0 + maj
+    load(&mut a, TS + 1, RA);
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    // Shift a..g down to b..h, then e += temp1 and a = temp1 + temp2
+    a.op(Lit{val : VS + 1, reg : RA});
+    a.op(Lit{val : VS, reg : RB});
+    a.op(Lit{val : 7, reg : RC});
+    a.op(MemCopy{dst : RA, src : RB, len : RC});
+    load(&mut a, TS, RA);
+    load(&mut a, VS + 4, RB);
+    a.op(Um2{op : Add, r1 : RB, r2 : RA, r3 : RB});
+    store(&mut a, VS + 4, RB);
+    a.op(Um2{op : Add, r1 : R6, r2 : RA, r3 : R6});
+    store(&mut a, VS, R6);
+    a.op(Um2I{op : Add, r1 : R2, imm : 1, r3 : R2});
+    a.op(Um2I{op : Sub, r1 : R2, imm : 64, r3 : RA});
+    a.goto(NeZ, RA, "round");
+
+    // H += a..h
+    for i in 0 .. 8 {
+        load(&mut a, HS + i, R9);
+        load(&mut a, VS + i, RA);
+        a.op(Um2{op : Add, r1 : R9, r2 : RA, r3 : R9});
+        store(&mut a, HS + i, R9);
+    }
+    a.op(Um2I{op : Add, r1 : R3, imm : 16, r3 : R3});
+    a.op(Um2I{op : Sub, r1 : R3, imm : MS + BLOCKS as u32 * 16, r3 : RA});
+    a.goto(NeZ, RA, "block");
+    a.op(Halt);
+
+    if !inline {
+        a.label("xor");
+        a.op(Lit{val : XOR, reg : R7});
+        a.goto(Always, R0, "table_op");
+        a.label("and");
+        a.op(Lit{val : AND, reg : R7});
+        a.label("table_op");
+        table_op(&mut a);
+        a.ret();
+    }
+
+    let digest = sha256::digest(&message);
+    Workload {
+        name : if inline {"sha256-inline"} else {"sha256"},
+        code : a.finish(),
+        ram,
+        stack : None,
+        check : Box::new(move |_, ram| {
+            let mut out = [0u8; sha256::DIGEST_LEN];
+            bytes::read_bytes(ram, HS as usize * 4, &mut out).unwrap();
+            out == digest
+        })
+    }
+}
+
+// Sorts N words in place. Nearly every instruction is a RAM access or a
+// branch.
+fn bubble_sort() -> Workload {
+    const N : u32 = 200;
+    let mut x = 12345u32;
+    let ram : Vec<u32> = (0 .. N).map(|_| {
+        x = x.wrapping_mul(1103515245).wrapping_add(12345);
+        x >> 8
+    }).collect();
+    let mut sorted = ram.clone();
+    sorted.sort();
+
+    let mut a = Asm::new();
+    a.op(Lit{val : N - 1, reg : R1});
+    a.label("outer");
+    a.op(Lit{val : 0, reg : R2});
+    a.label("inner");
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : 0, val : R3});
+    a.op(RamI{dir : Dir::Read, ptr : R2, off : 1, val : R4});
+    a.lit_label("next", R6);
+    a.op(UJumpCmp{cond : LeZ, r1 : R3, r2 : R4, dest : R6});
+    a.op(RamI{dir : Dir::Write, ptr : R2, off : 0, val : R4});
+    a.op(RamI{dir : Dir::Write, ptr : R2, off : 1, val : R3});
+    a.label("next");
+    a.op(Um2I{op : Add, r1 : R2, imm : 1, r3 : R2});
+    a.op(Um2{op : Sub, r1 : R1, r2 : R2, r3 : R7});
+    a.goto(NeZ, R7, "inner");
+    a.op(Um2I{op : Sub, r1 : R1, imm : 1, r3 : R1});
+    a.goto(NeZ, R1, "outer");
+    a.op(Halt);
+
+    Workload {
+        name : "sort",
+        code : a.finish(),
+        ram,
+        stack : None,
+        check : Box::new(move |_, ram| ram == &sorted[..])
+    }
+}
+
+// Naive recursive Fibonacci: a call, a return and stack traffic for every
+// handful of instructions.
+fn fibonacci() -> Workload {
+    const N : u32 = 22;
+    let mut a = Asm::new();
+    a.op(Lit{val : N, reg : R0});
+    a.call("fib");
+    a.op(Halt);
+    // R1 = fib(R0)
+    a.label("fib");
+    a.op(Lit{val : 2, reg : R2});
+    a.lit_label("base", R3);
+    a.op(UJumpCmp{cond : LtZ, r1 : R0, r2 : R2, dest : R3});
+    a.op(Push{reg : RE});
+    a.op(Push{reg : R0});
+    a.op(Um2I{op : Sub, r1 : R0, imm : 1, r3 : R0});
+    a.call("fib");
+    a.op(Pop{reg : R0});
+    a.op(Push{reg : R1});
+    a.op(Um2I{op : Sub, r1 : R0, imm : 2, r3 : R0});
+    a.call("fib");
+    a.op(Pop{reg : R2});
+    a.op(Um2{op : Add, r1 : R1, r2 : R2, r3 : R1});
+    a.op(Pop{reg : RE});
+    a.ret();
+    a.label("base");
+    mov(&mut a, R0, R1);
+    a.ret();
+
+    let (mut x, mut y) = (0u32, 1u32);
+    for _ in 0 .. N {
+        let z = x + y;
+        x = y;
+        y = z;
+    }
+    Workload {
+        name : "fib",
+        code : a.finish(),
+        ram : vec![0; 128],
+        stack : Some((0, 128)),
+        check : Box::new(move |regs, _| regs[1] == x)
+    }
+}
+
+// Total Collatz steps for 1..=N. Short blocks, a data-dependent branch in
+// every one.
+fn collatz() -> Workload {
+    const N : u32 = 3000;
+    let mut a = Asm::new();
+    a.op(Lit{val : 1, reg : R0});
+    a.op(Lit{val : 0, reg : R5});
+    a.label("start");
+    mov(&mut a, R0, R1);
+    a.label("step");
+    a.op(Um2I{op : Sub, r1 : R1, imm : 1, r3 : R2});
+    a.goto(EqZ, R2, "done");
+    a.op(Um2I{op : Add, r1 : R5, imm : 1, r3 : R5});
+    a.op(Um2I{op : Div, r1 : R1, imm : 2, r3 : R3});
+    a.op(Um2I{op : Mul, r1 : R3, imm : 2, r3 : R4});
+    a.op(Um2{op : Sub, r1 : R1, r2 : R4, r3 : R4});
+    a.lit_label("odd", R6);
+    a.op(UJump{cond : NeZ, flag : R4, dest : R6});
+    mov(&mut a, R3, R1);
+    a.goto(Always, R0, "step");
+    a.label("odd");
+    a.op(Um2I{op : Mul, r1 : R1, imm : 3, r3 : R1});
+    a.op(Um2I{op : Add, r1 : R1, imm : 1, r3 : R1});
+    a.goto(Always, R0, "step");
+    a.label("done");
+    a.op(Um2I{op : Add, r1 : R0, imm : 1, r3 : R0});
+    a.op(Um2I{op : Sub, r1 : R0, imm : N + 1, r3 : R2});
+    a.goto(NeZ, R2, "start");
+    a.op(Halt);
+
+    let steps : u32 = (1 .. N + 1).map(|mut n| {
+        let mut k = 0;
+        while n != 1 {
+            n = if n % 2 == 0 {n / 2} else {3 * n + 1};
+            k += 1;
+        }
+        k
+    }).sum();
+    Workload {
+        name : "collatz",
+        code : a.finish(),
+        ram : vec![],
+        stack : None,
+        check : Box::new(move |regs, _| regs[5] == steps)
+    }
+}
@@ -0,0 +1,46 @@
+use evaluator::*;
+
+// Byte view of word-addressed memory.
+// Bytes are packed big-endian, BYTES to a word: byte i lives in word i / BYTES,
+// and the first byte of each word is its most significant one. This is the same
+// order Write uses to serialize words, so a packed buffer dumped with write_iter
+// comes out as the original byte string.
+
+pub fn words_for<U : Prim>(bytes : usize) -> usize {
+    bytes.div_ceil(U::BYTES)
+}
+
+fn shift<U : Prim>(i : usize) -> u64 {
+    8 * (U::BYTES - 1 - i % U::BYTES) as u64
+}
+
+pub fn get_byte<U : Prim>(words : &[U], i : usize) -> Option<u8> {
+    words.get(i / U::BYTES).map(|w| (w.to_u64() >> shift::<U>(i)) as u8)
+}
+
+pub fn set_byte<U : Prim>(words : &mut [U], i : usize, byte : u8) -> Option<()> {
+    let sh = shift::<U>(i);
+    words.get_mut(i / U::BYTES).map(|w| {
+        let cleared = w.to_u64() & !(0xFFu64 << sh);
+        *w = U::from_u64(cleared | ((byte as u64) << sh))
+    })
+}
+
+pub fn read_bytes<U : Prim>(words : &[U], start : usize, dst : &mut [u8]) -> Result<(),()> {
+    for (i, b) in dst.iter_mut().enumerate() {
+        match get_byte(words, start + i) {
+            None => return Err(()),
+            Some(byte) => *b = byte
+        }
+    }
+    Ok(())
+}
+
+pub fn write_bytes<U : Prim>(words : &mut [U], start : usize, src : &[u8]) -> Result<(),()> {
+    for (i, b) in src.iter().enumerate() {
+        if set_byte(words, start + i, *b).is_none() {
+            return Err(())
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,394 @@
+use evaluator::*;
+use bytes::*;
+use calls::*;
+
+// Major 3: monotonic counters.
+//
+//   minor  function   layout
+//   0      read       [id] [value:8]
+//   1      increment  [id] [value:8]
+//
+// Counters are 64 bits and start at zero. Increment adds one and writes the
+// new value; it only returns once the store reports the new value durable,
+// so a guest never acts on a count that a power cut could take back.
+// There is no way to set or decrement a counter, and stores refuse to write a
+// value lower than the one they hold. Once a counter reaches u64::MAX further
+// increments fail with Exhausted.
+
+pub const MAJOR : u32 = 3;
+
+pub const READ : u32 = 0;
+pub const INCREMENT : u32 = 1;
+
+pub trait CounterStore {
+    // Counters that were never stored read as zero.
+    fn load(&mut self, id : u32) -> Result<u64, StoreError>;
+    // Must not return until the value survives a power cut, and must fail
+    // with Rollback if value is below the stored one.
+    fn store(&mut self, id : u32, value : u64) -> Result<(), StoreError>;
+}
+
+pub struct Counters<S> {
+    store : S
+}
+
+impl<S : CounterStore> Counters<S> {
+    pub fn new(store : S) -> Counters<S> {
+        Counters { store }
+    }
+
+    pub fn store(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    fn counter<U : Prim>(&mut self, slice : &mut [U], increment : bool) -> Result<(), CallError> {
+        let mut l = Layout::new(slice);
+        let id = l.word().map(|i| slice[i].to_u64())?;
+        let value = l.bytes(8)?;
+        if id > u32::MAX as u64 {
+            return Err(CallError::BadLayout)
+        }
+        let id = id as u32;
+        let mut v = self.store.load(id).map_err(CallError::Storage)?;
+        if increment {
+            v = v.checked_add(1).ok_or(CallError::Exhausted)?;
+            self.store.store(id, v).map_err(CallError::Storage)?;
+        }
+        let _ = write_bytes(slice, value, &v.to_be_bytes());
+        Ok(())
+    }
+}
+
+impl<U : Prim, S : CounterStore> Library<U> for Counters<S> {
+    fn major(&self) -> u32 {
+        MAJOR
+    }
+
+    fn call(&mut self, minor : U, slice : &mut [U]) -> Result<(), CallError> {
+        match minor_number(minor) {
+            READ => self.counter(slice, false),
+            INCREMENT => self.counter(slice, true),
+            _ => Err(CallError::UnknownMinor)
+        }
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+pub use self::stores::*;
+
+#[cfg(any(test, feature = "std"))]
+mod stores {
+    use super::*;
+    use crypto::{ct_eq, sha256};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::path::PathBuf;
+    use std::vec::Vec;
+
+    fn check(table : &BTreeMap<u32, u64>, id : u32, value : u64) -> Result<(), StoreError> {
+        match table.get(&id) {
+            Some(old) if *old > value => Err(StoreError::Rollback),
+            _ => Ok(())
+        }
+    }
+
+    // Keeps counters in memory. Meant for tests.
+    #[derive(Default)]
+    pub struct MemCounters {
+        table : BTreeMap<u32, u64>
+    }
+
+    impl MemCounters {
+        pub fn new() -> MemCounters {
+            MemCounters::default()
+        }
+    }
+
+    impl CounterStore for MemCounters {
+        fn load(&mut self, id : u32) -> Result<u64, StoreError> {
+            Ok(self.table.get(&id).cloned().unwrap_or(0))
+        }
+
+        fn store(&mut self, id : u32, value : u64) -> Result<(), StoreError> {
+            check(&self.table, id, value)?;
+            self.table.insert(id, value);
+            Ok(())
+        }
+    }
+
+    // Double-buffered file store. The whole table lives in each of two files,
+    // counters.0 and counters.1, as
+    //
+    //   "FTPC" | seq:8 | count:4 | (id:4 | value:8) * count | sha256 of the above:32
+    //
+    // with all integers big-endian. Each write goes to the file not holding the
+    // newest table, with a higher seq, and is synced before store() returns.
+    // On open the valid copy with the highest seq wins. A write cut short by a
+    // power failure fails its checksum, leaving the other copy, which still
+    // holds every value any guest has been told about. With no files at all
+    // the store starts empty, but if there are files and neither is valid,
+    // open fails rather than start every counter again from 0.
+    //
+    // This protects against crashes, not against someone restoring old copies
+    // of both files; that needs storage the host can't rewrite, such as RPMB.
+    pub struct FileCounters {
+        dir : PathBuf,
+        seq : u64,
+        table : BTreeMap<u32, u64>
+    }
+
+    const MAGIC : &[u8] = b"FTPC";
+
+    fn decode(buf : &[u8]) -> Option<(u64, BTreeMap<u32, u64>)> {
+        if buf.len() < MAGIC.len() + 12 + sha256::DIGEST_LEN || &buf[..4] != MAGIC {
+            return None
+        }
+        let (body, sum) = buf.split_at(buf.len() - sha256::DIGEST_LEN);
+        if !ct_eq(&sha256::digest(body), sum) {
+            return None
+        }
+        let mut be = [0u8; 8];
+        be.copy_from_slice(&body[4..12]);
+        let seq = u64::from_be_bytes(be);
+        let count = u32::from_be_bytes([body[12], body[13], body[14], body[15]]) as usize;
+        let entries = &body[16..];
+        if entries.len() != count * 12 {
+            return None
+        }
+        let mut table = BTreeMap::new();
+        for e in entries.chunks(12) {
+            be.copy_from_slice(&e[4..]);
+            table.insert(u32::from_be_bytes([e[0], e[1], e[2], e[3]]), u64::from_be_bytes(be));
+        }
+        Some((seq, table))
+    }
+
+    fn encode(seq : u64, table : &BTreeMap<u32, u64>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(16 + table.len() * 12 + sha256::DIGEST_LEN);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&seq.to_be_bytes());
+        buf.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        for (id, value) in table {
+            buf.extend_from_slice(&id.to_be_bytes());
+            buf.extend_from_slice(&value.to_be_bytes());
+        }
+        let sum = sha256::digest(&buf);
+        buf.extend_from_slice(&sum);
+        buf
+    }
+
+    impl FileCounters {
+        pub fn open<P : Into<PathBuf>>(dir : P) -> io::Result<FileCounters> {
+            let dir = dir.into();
+            fs::create_dir_all(&dir)?;
+            let mut best : Option<(u64, BTreeMap<u32, u64>)> = None;
+            let mut found = false;
+            for i in 0..2 {
+                let copy = match fs::read(dir.join(format!("counters.{}", i))) {
+                    Ok(buf) => {found = true; decode(&buf)},
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(e)
+                };
+                if let Some((seq, table)) = copy {
+                    if best.as_ref().is_none_or(|b| seq > b.0) {
+                        best = Some((seq, table));
+                    }
+                }
+            }
+            let (seq, table) = match best {
+                Some(best) => best,
+                None if found => return Err(io::Error::new(io::ErrorKind::InvalidData, "no valid copy of the counters")),
+                None => (0, BTreeMap::new())
+            };
+            Ok(FileCounters { dir, seq, table })
+        }
+
+        fn write(&self, seq : u64, table : &BTreeMap<u32, u64>) -> io::Result<()> {
+            let mut f = fs::File::create(self.dir.join(format!("counters.{}", seq % 2)))?;
+            f.write_all(&encode(seq, table))?;
+            f.sync_all()?;
+            fs::File::open(&self.dir)?.sync_all()
+        }
+    }
+
+    impl CounterStore for FileCounters {
+        fn load(&mut self, id : u32) -> Result<u64, StoreError> {
+            Ok(self.table.get(&id).cloned().unwrap_or(0))
+        }
+
+        fn store(&mut self, id : u32, value : u64) -> Result<(), StoreError> {
+            check(&self.table, id, value)?;
+            let mut table = self.table.clone();
+            table.insert(id, value);
+            let seq = self.seq + 1;
+            self.write(seq, &table).map_err(|_| StoreError::Io)?;
+            self.seq = seq;
+            self.table = table;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use std::process;
+    use std::vec::Vec;
+
+    fn call<U : Prim, S : CounterStore>(c : &mut Counters<S>, minor : u32, id : U) -> Result<u64, CallError> {
+        let mut slice = [U::zero(); 9];
+        slice[0] = id;
+        c.call(U::from_u64(minor as u64), &mut slice)?;
+        let mut value = [0u8; 8];
+        read_bytes(&slice[1 ..], 0, &mut value).unwrap();
+        Ok(u64::from_be_bytes(value))
+    }
+
+    fn increment<S : CounterStore>(c : &mut Counters<S>, id : u32) -> Result<u64, CallError> {
+        call(c, INCREMENT, id)
+    }
+
+    fn read<S : CounterStore>(c : &mut Counters<S>, id : u32) -> Result<u64, CallError> {
+        call(c, READ, id)
+    }
+
+    // An empty directory of its own
+    fn dir(name : &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("tpm-counter-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn file_counters(dir : &PathBuf) -> Counters<FileCounters> {
+        Counters::new(FileCounters::open(dir).unwrap())
+    }
+
+    #[test]
+    fn counts() {
+        let mut c = Counters::new(MemCounters::new());
+        assert_eq!(read(&mut c, 1), Ok(0));
+        assert_eq!(increment(&mut c, 1), Ok(1));
+        assert_eq!(increment(&mut c, 1), Ok(2));
+        assert_eq!(call(&mut c, READ, 1u64), Ok(2));
+        assert_eq!(read(&mut c, 2), Ok(0));
+        assert_eq!(call(&mut c, READ, 1u64 << 32), Err(CallError::BadLayout));
+        assert_eq!(c.call(READ, &mut [1u32, 0]), Err(CallError::BadLayout));
+        assert_eq!(c.call(2u32, &mut [1u32, 0, 0]), Err(CallError::UnknownMinor));
+    }
+
+    #[test]
+    fn exhausted() {
+        let mut c = Counters::new(MemCounters::new());
+        c.store().store(1, u64::MAX - 1).unwrap();
+        assert_eq!(increment(&mut c, 1), Ok(u64::MAX));
+        assert_eq!(increment(&mut c, 1), Err(CallError::Exhausted));
+        assert_eq!(read(&mut c, 1), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn rollback() {
+        let mut mem = MemCounters::new();
+        mem.store(1, 5).unwrap();
+        assert_eq!(mem.store(1, 4), Err(StoreError::Rollback));
+        assert_eq!(mem.load(1), Ok(5));
+        let dir = dir("rollback");
+        let mut file = FileCounters::open(&dir).unwrap();
+        file.store(1, 5).unwrap();
+        file.store(1, 5).unwrap();
+        assert_eq!(file.store(1, 4), Err(StoreError::Rollback));
+        assert_eq!(file.load(1), Ok(5));
+        assert_eq!(FileCounters::open(&dir).unwrap().load(1), Ok(5));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn persists_across_open() {
+        let dir = dir("persists");
+        {
+            let mut c = file_counters(&dir);
+            for _ in 0..3 {
+                increment(&mut c, 1).unwrap();
+            }
+            increment(&mut c, 7).unwrap();
+        }
+        let mut c = file_counters(&dir);
+        assert_eq!(read(&mut c, 1), Ok(3));
+        assert_eq!(read(&mut c, 7), Ok(1));
+        assert_eq!(increment(&mut c, 7), Ok(2));
+        let mut c = file_counters(&dir);
+        assert_eq!((read(&mut c, 1), read(&mut c, 7)), (Ok(3), Ok(2)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn newest_valid_copy_wins() {
+        // Whichever file holds it
+        for &writes in [3, 4].iter() {
+            let dir = dir(&format!("newest-{}", writes));
+            let mut c = file_counters(&dir);
+            for _ in 0..writes {
+                increment(&mut c, 1).unwrap();
+            }
+            assert!(dir.join("counters.0").exists() && dir.join("counters.1").exists());
+            assert_eq!(read(&mut file_counters(&dir), 1), Ok(writes));
+            // And keeps counting from there
+            assert_eq!(increment(&mut file_counters(&dir), 1), Ok(writes + 1));
+            assert_eq!(read(&mut file_counters(&dir), 1), Ok(writes + 1));
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn falls_back_when_the_newest_copy_is_damaged() {
+        let dir = dir("damaged");
+        let mut c = file_counters(&dir);
+        for _ in 0..5 {
+            increment(&mut c, 1).unwrap();
+        }
+        // seq 5 is in counters.1, seq 4 in counters.0
+        let newest = dir.join("counters.1");
+        let good = fs::read(&newest).unwrap();
+        let mut damaged = vec![Vec::new(), good[.. good.len() - 1].to_vec(), good[.. 20].to_vec()];
+        for i in 0..good.len() {
+            let mut flipped = good.clone();
+            flipped[i] ^= 0x10;
+            damaged.push(flipped);
+        }
+        for bad in damaged.iter() {
+            fs::write(&newest, bad).unwrap();
+            assert_eq!(read(&mut file_counters(&dir), 1), Ok(4));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_open_when_no_copy_is_valid() {
+        let dir = dir("invalid");
+        let mut c = file_counters(&dir);
+        for _ in 0..5 {
+            increment(&mut c, 1).unwrap();
+        }
+        for i in 0..2 {
+            let path = dir.join(format!("counters.{}", i));
+            let mut bytes = fs::read(&path).unwrap();
+            bytes[10] ^= 1;
+            fs::write(&path, bytes).unwrap();
+        }
+        let err = FileCounters::open(&dir).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // Nor when only one copy was ever written, and it is damaged
+        fs::remove_file(dir.join("counters.0")).unwrap();
+        assert_eq!(FileCounters::open(&dir).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        fs::write(dir.join("counters.1"), b"").unwrap();
+        assert_eq!(FileCounters::open(&dir).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        // No files at all is a fresh store
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read(&mut file_counters(&dir), 1), Ok(0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use evaluator::*;
+use bytes::*;
+use calls::*;
+use crypto::{self, aes, gcm, sha256};
+use crypto::hmac::HmacSha256;
+
+// Major 1: crypto.
+//
+//   minor  function      layout
+//   0      SHA-256       [n] [digest:32] [msg:n]
+//   1      HMAC-SHA256   [klen] [n] [mac:32] [key:klen] [msg:n]
+//   2      AES-CTR       [klen] [n] [key:klen] [ctr:16] [data:n]
+//   3      AES-GCM seal  [klen] [alen] [n] [key:klen] [nonce:12] [tag:16] [aad:alen] [data:n]
+//   4      AES-GCM open  same as seal
+//   5      compare       [n] [equal] [a:n] [b:n]
+//
+// AES keys are 16 or 32 bytes. CTR treats the whole 16-byte counter block as
+// one big-endian integer, encrypts data in place and writes the advanced
+// counter back, so a long stream can be processed over several calls.
+// GCM seal encrypts data in place and writes the tag; open checks the tag,
+// decrypts in place, and on failure zeroes data and returns AuthFailed.
+// Compare runs in time independent of the contents and writes 1 to equal if
+// a and b match, 0 otherwise.
+
+pub const MAJOR : u32 = 1;
+
+pub const SHA256 : u32 = 0;
+pub const HMAC_SHA256 : u32 = 1;
+pub const AES_CTR : u32 = 2;
+pub const AES_GCM_SEAL : u32 = 3;
+pub const AES_GCM_OPEN : u32 = 4;
+pub const COMPARE : u32 = 5;
+
+pub struct Crypto;
+
+fn read<U : Prim>(slice : &[U], start : usize, dst : &mut [u8]) {
+    // Layout has already checked that every field fits
+    let _ = read_bytes(slice, start, dst);
+}
+
+fn write<U : Prim>(slice : &mut [U], start : usize, src : &[u8]) {
+    let _ = write_bytes(slice, start, src);
+}
+
+fn len<U : Prim>(slice : &[U], idx : usize) -> usize {
+    slice[idx].to_usize()
+}
+
+// Feeds the n bytes at start to f in chunks of chunk_len (at most 64),
+// the last one possibly short.
+fn each_chunk<U : Prim, F : FnMut(&[u8])>(slice : &[U], start : usize, n : usize, chunk_len : usize, f : &mut F) {
+    let mut buf = [0u8; sha256::BLOCK_LEN];
+    let mut off = 0;
+    while off < n {
+        let take = if n - off < chunk_len {n - off} else {chunk_len};
+        read(slice, start + off, &mut buf[..take]);
+        f(&buf[..take]);
+        off += take;
+    }
+}
+
+// Like each_chunk, but in 16-byte blocks that f may rewrite in place.
+fn each_block_mut<U : Prim, F : FnMut(&mut [u8])>(slice : &mut [U], start : usize, n : usize, f : &mut F) {
+    let mut buf = [0u8; aes::BLOCK_LEN];
+    let mut off = 0;
+    while off < n {
+        let take = if n - off < buf.len() {n - off} else {buf.len()};
+        read(slice, start + off, &mut buf[..take]);
+        f(&mut buf[..take]);
+        write(slice, start + off, &buf[..take]);
+        off += take;
+    }
+}
+
+fn aes_key<U : Prim>(slice : &[U], start : usize, klen : usize, key : &mut [u8; 32]) -> Result<(), CallError> {
+    if klen != 16 && klen != 32 {
+        return Err(CallError::BadKey)
+    }
+    read(slice, start, &mut key[..klen]);
+    Ok(())
+}
+
+fn sha256<U : Prim>(slice : &mut [U]) -> Result<(), CallError> {
+    let mut l = Layout::new(slice);
+    let n = l.word().map(|i| len(slice, i))?;
+    let digest = l.bytes(sha256::DIGEST_LEN)?;
+    let msg = l.bytes(n)?;
+    let mut h = sha256::Sha256::new();
+    each_chunk(slice, msg, n, sha256::BLOCK_LEN, &mut |chunk| h.update(chunk));
+    write(slice, digest, &h.finish());
+    Ok(())
+}
+
+fn hmac_sha256<U : Prim>(slice : &mut [U]) -> Result<(), CallError> {
+    let mut l = Layout::new(slice);
+    let klen = l.word().map(|i| len(slice, i))?;
+    let n = l.word().map(|i| len(slice, i))?;
+    let mac = l.bytes(sha256::DIGEST_LEN)?;
+    let key = l.bytes(klen)?;
+    let msg = l.bytes(n)?;
+    // Long keys are hashed down first, so the key never needs to sit in one buffer
+    let mut kbuf = [0u8; sha256::BLOCK_LEN];
+    let k = if klen > sha256::BLOCK_LEN {
+        let mut h = sha256::Sha256::new();
+        each_chunk(slice, key, klen, sha256::BLOCK_LEN, &mut |chunk| h.update(chunk));
+        kbuf[..sha256::DIGEST_LEN].copy_from_slice(&h.finish());
+        &kbuf[..sha256::DIGEST_LEN]
+    } else {
+        read(slice, key, &mut kbuf[..klen]);
+        &kbuf[..klen]
+    };
+    let mut h = HmacSha256::new(k);
+    each_chunk(slice, msg, n, sha256::BLOCK_LEN, &mut |chunk| h.update(chunk));
+    write(slice, mac, &h.finish());
+    Ok(())
+}
+
+fn aes_ctr<U : Prim>(slice : &mut [U]) -> Result<(), CallError> {
+    let mut l = Layout::new(slice);
+    let klen = l.word().map(|i| len(slice, i))?;
+    let n = l.word().map(|i| len(slice, i))?;
+    let key = l.bytes(klen)?;
+    let ctr = l.bytes(aes::BLOCK_LEN)?;
+    let data = l.bytes(n)?;
+    let mut kbuf = [0u8; 32];
+    aes_key(slice, key, klen, &mut kbuf)?;
+    let mut counter = [0u8; aes::BLOCK_LEN];
+    read(slice, ctr, &mut counter);
+    let cipher = aes::Aes::new(&kbuf[..klen]).ok_or(CallError::BadKey)?;
+    let mut stream = aes::Ctr::new(cipher, counter);
+    each_block_mut(slice, data, n, &mut |block| stream.apply_block(block));
+    write(slice, ctr, &stream.counter());
+    Ok(())
+}
+
+fn aes_gcm<U : Prim>(slice : &mut [U], seal : bool) -> Result<(), CallError> {
+    let mut l = Layout::new(slice);
+    let klen = l.word().map(|i| len(slice, i))?;
+    let alen = l.word().map(|i| len(slice, i))?;
+    let n = l.word().map(|i| len(slice, i))?;
+    let key = l.bytes(klen)?;
+    let nonce = l.bytes(gcm::NONCE_LEN)?;
+    let tag = l.bytes(gcm::TAG_LEN)?;
+    let aad = l.bytes(alen)?;
+    let data = l.bytes(n)?;
+    let mut kbuf = [0u8; 32];
+    aes_key(slice, key, klen, &mut kbuf)?;
+    let mut nbuf = [0u8; gcm::NONCE_LEN];
+    read(slice, nonce, &mut nbuf);
+    let mut g = gcm::Gcm::new(&kbuf[..klen], &nbuf).ok_or(CallError::BadKey)?;
+    each_chunk(slice, aad, alen, aes::BLOCK_LEN, &mut |chunk| g.aad_block(chunk));
+    if seal {
+        each_block_mut(slice, data, n, &mut |block| g.encrypt_block(block));
+        write(slice, tag, &g.tag());
+        Ok(())
+    } else {
+        each_block_mut(slice, data, n, &mut |block| g.decrypt_block(block));
+        let mut expected = [0u8; gcm::TAG_LEN];
+        read(slice, tag, &mut expected);
+        if crypto::ct_eq(&g.tag(), &expected) {
+            Ok(())
+        } else {
+            for i in 0..n {
+                set_byte(slice, data + i, 0);
+            }
+            Err(CallError::AuthFailed)
+        }
+    }
+}
+
+fn compare<U : Prim>(slice : &mut [U]) -> Result<(), CallError> {
+    let mut l = Layout::new(slice);
+    let n = l.word().map(|i| len(slice, i))?;
+    let equal = l.word()?;
+    let a = l.bytes(n)?;
+    let b = l.bytes(n)?;
+    let mut diff = 0u8;
+    for i in 0..n {
+        let x = get_byte(slice, a + i).unwrap_or(0);
+        let y = get_byte(slice, b + i).unwrap_or(0);
+        diff |= x ^ y;
+    }
+    slice[equal] = if diff == 0 {U::one()} else {U::zero()};
+    Ok(())
+}
+
+impl<U : Prim> Library<U> for Crypto {
+    fn major(&self) -> u32 {
+        MAJOR
+    }
+
+    fn call(&mut self, minor : U, slice : &mut [U]) -> Result<(), CallError> {
+        match minor_number(minor) {
+            SHA256 => sha256(slice),
+            HMAC_SHA256 => hmac_sha256(slice),
+            AES_CTR => aes_ctr(slice),
+            AES_GCM_SEAL => aes_gcm(slice, true),
+            AES_GCM_OPEN => aes_gcm(slice, false),
+            COMPARE => compare(slice),
+            _ => Err(CallError::UnknownMinor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::tests::hex;
+
+    fn sha256_abc<U : Prim>() {
+        let mut slice = [U::zero(); 48];
+        slice[0] = U::from_u64(3);
+        let msg = words_for::<U>(32) + 1;
+        write_bytes(&mut slice[msg..], 0, b"abc").unwrap();
+        dispatch(&mut [&mut Crypto], U::from_u64(1), U::zero(), &mut slice).unwrap();
+        let mut digest = [0u8; 32];
+        read_bytes(&slice[1..], 0, &mut digest).unwrap();
+        assert_eq!(digest, hex::<32>("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+    }
+
+    #[test]
+    fn packing_both_widths() {
+        sha256_abc::<u32>();
+        sha256_abc::<u64>();
+    }
+
+    #[test]
+    fn gcm_round_trip_through_ram() {
+        // [klen] [alen] [n] [key:16] [nonce:12] [tag:16] [aad:5] [data:9]
+        let mut slice = [0u32; 3 + 4 + 3 + 4 + 2 + 3];
+        slice[0] = 16;
+        slice[1] = 5;
+        slice[2] = 9;
+        write_bytes(&mut slice, 12 + 16 + 12 + 16, b"hello").unwrap();
+        write_bytes(&mut slice, 12 + 16 + 12 + 16 + 8, b"plaintext").unwrap();
+        let orig = slice;
+        dispatch(&mut [&mut Crypto], 1u32, AES_GCM_SEAL, &mut slice).unwrap();
+        assert!(slice[16..] != orig[16..]);
+        dispatch(&mut [&mut Crypto], 1u32, AES_GCM_OPEN, &mut slice).unwrap();
+        assert_eq!(slice[14..], orig[14..]);
+        slice[16] ^= 1;
+        assert_eq!(dispatch(&mut [&mut Crypto], 1u32, AES_GCM_OPEN, &mut slice), Err(CallError::AuthFailed));
+        assert_eq!(slice[16..], [0; 3]);
+    }
+}
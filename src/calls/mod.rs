@@ -0,0 +1,138 @@
+use evaluator::*;
+
+// Host libraries reachable from guests through the Call instruction.
+//
+// When a guest executes Call{major,minor,arg,len,res}, eval_instrs stops with
+// MutNotice::Call{major,minor,slice,reply}, where slice is ram[arg .. arg+len].
+// The host hands that to dispatch() along with the libraries it provides,
+// then passes status() of the outcome to reply.resume(), which puts it
+// in res: 0 on success, otherwise the code of the CallError.
+//
+// Major numbers:
+//   0        reserved
+//   1        crypto (see calls::crypto)
+//   2        sealed storage (see calls::seal)
+//   3        monotonic counters (see calls::counter)
+//   4        random numbers (see calls::rng)
+//   5..0xFF  reserved for libraries shipped with this crate
+//   0x100..  free for application-specific libraries
+//
+// Minor numbers select a function within a library and are documented there.
+//
+// Arguments are passed in the slice. Each function lists its layout as a
+// sequence of fields, e.g. [n] [digest:32] [msg:n]:
+//   [x]     one word, read or written as a plain integer
+//   [x:n]   a buffer of n bytes, packed BYTES to a word as described in
+//           the bytes module, starting on a fresh word and taking
+//           words_for(n) words
+// Fields follow each other with no gaps. Lengths are always in bytes.
+// Trailing words after the last field are left alone.
+
+pub mod crypto;
+pub mod seal;
+pub mod counter;
+pub mod rng;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CallError {
+    UnknownMajor,
+    UnknownMinor,
+    // The slice is too short for the layout, or a length field is out of range
+    BadLayout,
+    BadKey,
+    AuthFailed,
+    Exhausted,
+    NoEntropy,
+    Storage(StoreError)
+}
+
+impl CallError {
+    // Status codes seen by guests. Zero means success.
+    pub fn code(&self) -> u32 {
+        match *self {
+            CallError::UnknownMajor => 1,
+            CallError::UnknownMinor => 2,
+            CallError::BadLayout => 3,
+            CallError::BadKey => 4,
+            CallError::AuthFailed => 5,
+            CallError::Exhausted => 6,
+            CallError::NoEntropy => 7,
+            CallError::Storage(e) => 0x10 + match e {
+                StoreError::NotFound => 0,
+                StoreError::TooLarge => 1,
+                StoreError::Rollback => 2,
+                StoreError::Io => 3
+            }
+        }
+    }
+}
+
+// Failures of the persistent backends behind stateful libraries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StoreError {
+    NotFound,
+    TooLarge,
+    // A write would have moved a value backwards
+    Rollback,
+    Io
+}
+
+pub trait Library<U> {
+    fn major(&self) -> u32;
+    fn call(&mut self, minor : U, slice : &mut [U]) -> Result<(), CallError>;
+}
+
+pub fn dispatch<U : Prim>(libs : &mut [&mut dyn Library<U>], major : U, minor : U, slice : &mut [U]) -> Result<(), CallError> {
+    for lib in libs.iter_mut() {
+        if lib.major() as u64 == major.to_u64() {
+            return lib.call(minor, slice)
+        }
+    }
+    Err(CallError::UnknownMajor)
+}
+
+// The value to hand to Reply::resume for the outcome of a call.
+pub fn status<U : Prim>(res : Result<(), CallError>) -> U {
+    match res {
+        Ok(()) => U::zero(),
+        Err(e) => U::from_u64(e.code() as u64)
+    }
+}
+
+// Minor numbers are u32. Anything wider maps to u32::MAX, which no library uses.
+pub fn minor_number<U : Prim>(minor : U) -> u32 {
+    let m = minor.to_u64();
+    if m > u32::MAX as u64 {u32::MAX} else {m as u32}
+}
+
+// Walks a call slice field by field, checking every field fits.
+pub struct Layout {
+    next : usize,
+    len : usize,
+    bytes_per_word : usize
+}
+
+impl Layout {
+    pub fn new<U : Prim>(slice : &[U]) -> Layout {
+        Layout { next : 0, len : slice.len(), bytes_per_word : U::BYTES }
+    }
+
+    // Index of the next [x] field.
+    pub fn word(&mut self) -> Result<usize, CallError> {
+        if self.next >= self.len {
+            return Err(CallError::BadLayout)
+        }
+        self.next += 1;
+        Ok(self.next - 1)
+    }
+
+    // Byte offset of the next [x:n] field.
+    pub fn bytes(&mut self, n : usize) -> Result<usize, CallError> {
+        let words = n.div_ceil(self.bytes_per_word);
+        if words > self.len - self.next {
+            return Err(CallError::BadLayout)
+        }
+        self.next += words;
+        Ok((self.next - words) * self.bytes_per_word)
+    }
+}
@@ -0,0 +1,291 @@
+use evaluator::*;
+use calls::*;
+use crypto::{chacha20, sha256};
+
+// Major 4: random numbers.
+//
+//   minor  function  layout
+//   0      fill      the whole slice
+//
+// Fill overwrites every word of the slice with output from a ChaCha20 DRBG.
+// The DRBG is seeded from a pluggable Entropy source and reseeds from it as
+// its Reseed policy dictates. In test mode it is seeded from a fixed value and
+// never touches a source, so a guest run replays exactly.
+//
+// Each request is served from the keystream under the current key, and the
+// key is then replaced by the next keystream block, so a later compromise of
+// the DRBG state doesn't reveal earlier output.
+
+pub const MAJOR : u32 = 4;
+
+pub const FILL : u32 = 0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EntropyError;
+
+pub trait Entropy {
+    fn fill(&mut self, buf : &mut [u8]) -> Result<(), EntropyError>;
+}
+
+// Hook for MCU hardware RNGs: wraps a function that fills a buffer from the
+// peripheral and returns false if it couldn't (e.g. a failed health test).
+pub struct Hardware<F>(pub F);
+
+impl<F : FnMut(&mut [u8]) -> bool> Entropy for Hardware<F> {
+    fn fill(&mut self, buf : &mut [u8]) -> Result<(), EntropyError> {
+        let &mut Hardware(ref mut f) = self;
+        if f(buf) {Ok(())} else {Err(EntropyError)}
+    }
+}
+
+// A source that never yields anything. Used by test mode.
+pub struct NoEntropy;
+
+impl Entropy for NoEntropy {
+    fn fill(&mut self, _ : &mut [u8]) -> Result<(), EntropyError> {
+        Err(EntropyError)
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::os::OsEntropy;
+
+#[cfg(feature = "std")]
+mod os {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+
+    // The Linux kernel RNG.
+    pub struct OsEntropy;
+
+    impl Entropy for OsEntropy {
+        fn fill(&mut self, buf : &mut [u8]) -> Result<(), EntropyError> {
+            File::open("/dev/urandom")
+                .and_then(|mut f| f.read_exact(buf))
+                .map_err(|_| EntropyError)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Reseed {
+    Never,
+    // Reseed before a request once this many bytes have been produced
+    // since the last (re)seed.
+    AfterBytes(u64),
+    // Reseed before every this many requests.
+    AfterRequests(u64)
+}
+
+pub struct Drbg<E> {
+    source : E,
+    policy : Reseed,
+    key : [u8; chacha20::KEY_LEN],
+    bytes : u64,
+    requests : u64
+}
+
+const NONCE : [u8; chacha20::NONCE_LEN] = [0; chacha20::NONCE_LEN];
+
+impl<E : Entropy> Drbg<E> {
+    pub fn new(mut source : E, policy : Reseed) -> Result<Drbg<E>, EntropyError> {
+        let mut key = [0u8; chacha20::KEY_LEN];
+        source.fill(&mut key)?;
+        Ok(Drbg { source, policy, key, bytes : 0, requests : 0 })
+    }
+
+    pub fn reseed(&mut self) -> Result<(), EntropyError> {
+        let mut fresh = [0u8; chacha20::KEY_LEN];
+        self.source.fill(&mut fresh)?;
+        let mut h = sha256::Sha256::new();
+        h.update(&self.key);
+        h.update(&fresh);
+        self.key = h.finish();
+        self.bytes = 0;
+        self.requests = 0;
+        Ok(())
+    }
+
+    fn due(&self) -> bool {
+        match self.policy {
+            Reseed::Never => false,
+            Reseed::AfterBytes(n) => self.bytes >= n,
+            Reseed::AfterRequests(n) => self.requests >= n
+        }
+    }
+
+    // Streams n bytes of output to f a block at a time, as a single request.
+    pub fn stream<F : FnMut(&[u8])>(&mut self, n : usize, f : &mut F) -> Result<(), EntropyError> {
+        if self.due() {
+            self.reseed()?;
+        }
+        let mut counter = 1;
+        let mut left = n;
+        while left > 0 {
+            let ks = chacha20::block(&self.key, counter, &NONCE);
+            let take = if left < ks.len() {left} else {ks.len()};
+            f(&ks[..take]);
+            left -= take;
+            counter += 1;
+        }
+        let next = chacha20::block(&self.key, 0, &NONCE);
+        self.key.copy_from_slice(&next[..chacha20::KEY_LEN]);
+        self.bytes = self.bytes.saturating_add(n as u64);
+        self.requests += 1;
+        Ok(())
+    }
+
+    pub fn generate(&mut self, buf : &mut [u8]) -> Result<(), EntropyError> {
+        let mut off = 0;
+        self.stream(buf.len(), &mut |chunk| {
+            buf[off..off + chunk.len()].copy_from_slice(chunk);
+            off += chunk.len();
+        })
+    }
+}
+
+impl Drbg<NoEntropy> {
+    // Deterministic generator for tests and replay.
+    pub fn test_mode(seed : [u8; chacha20::KEY_LEN]) -> Drbg<NoEntropy> {
+        Drbg { source : NoEntropy, policy : Reseed::Never, key : seed, bytes : 0, requests : 0 }
+    }
+}
+
+impl<E : Entropy> Entropy for Drbg<E> {
+    fn fill(&mut self, buf : &mut [u8]) -> Result<(), EntropyError> {
+        self.generate(buf)
+    }
+}
+
+impl<U : Prim, E : Entropy> Library<U> for Drbg<E> {
+    fn major(&self) -> u32 {
+        MAJOR
+    }
+
+    fn call(&mut self, minor : U, slice : &mut [U]) -> Result<(), CallError> {
+        if minor_number(minor) != FILL {
+            return Err(CallError::UnknownMinor)
+        }
+        // Blocks are a multiple of the word size, so words never straddle them
+        let mut next = 0;
+        self.stream(slice.len() * U::BYTES, &mut |chunk| {
+            for b in chunk.chunks(U::BYTES) {
+                slice[next] = U::from_u64(b.iter().fold(0, |acc, x| acc << 8 | *x as u64));
+                next += 1;
+            }
+        }).map_err(|_| CallError::NoEntropy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    // Counts its fills and fails once it has run dry
+    struct Source {
+        fills : usize,
+        left : usize
+    }
+
+    impl Entropy for Source {
+        fn fill(&mut self, buf : &mut [u8]) -> Result<(), EntropyError> {
+            if self.left == 0 {
+                return Err(EntropyError)
+            }
+            self.left -= 1;
+            self.fills += 1;
+            for b in buf.iter_mut() {
+                *b = self.fills as u8;
+            }
+            Ok(())
+        }
+    }
+
+    fn drbg(policy : Reseed, left : usize) -> Drbg<Source> {
+        Drbg::new(Source { fills : 0, left }, policy).unwrap()
+    }
+
+    fn bytes<E : Entropy>(d : &mut Drbg<E>, n : usize) -> Vec<u8> {
+        let mut buf = vec![0u8; n];
+        d.generate(&mut buf).unwrap();
+        buf
+    }
+
+    // Fills seen by the source after each of a run of requests
+    fn fills(policy : Reseed, sizes : &[usize]) -> Vec<usize> {
+        let mut d = drbg(policy, usize::MAX);
+        sizes.iter().map(|&n| {bytes(&mut d, n); d.source.fills}).collect()
+    }
+
+    #[test]
+    fn test_mode_replays() {
+        let mut a = Drbg::test_mode([7; 32]);
+        let mut b = Drbg::test_mode([7; 32]);
+        let first = bytes(&mut a, 100);
+        assert_eq!(first[..64], chacha20::block(&[7; 32], 1, &NONCE)[..]);
+        assert_eq!(first, bytes(&mut b, 100));
+        for n in [1, 64, 65, 1000].iter() {
+            assert_eq!(bytes(&mut a, *n), bytes(&mut b, *n));
+        }
+        // The key moves on between requests
+        assert_ne!(bytes(&mut a, 32), bytes(&mut a, 32));
+        assert_ne!(bytes(&mut Drbg::test_mode([8; 32]), 100), first);
+    }
+
+    #[test]
+    fn reseeds() {
+        assert_eq!(fills(Reseed::Never, &[64, 64, 64, 1000]), [1, 1, 1, 1]);
+        assert_eq!(fills(Reseed::AfterRequests(2), &[1, 1, 1, 1, 1]), [1, 1, 2, 2, 3]);
+        assert_eq!(fills(Reseed::AfterRequests(1), &[1, 1, 1]), [1, 2, 3]);
+        assert_eq!(fills(Reseed::AfterBytes(100), &[64, 64, 64, 1, 200, 1]), [1, 1, 2, 2, 2, 3]);
+        // Explicitly, and with the fresh input mixed into the key
+        let mut a = drbg(Reseed::Never, usize::MAX);
+        let mut b = drbg(Reseed::Never, usize::MAX);
+        bytes(&mut a, 10);
+        bytes(&mut b, 10);
+        a.reseed().unwrap();
+        assert_eq!(a.source.fills, 2);
+        assert_ne!(bytes(&mut a, 32), bytes(&mut b, 32));
+    }
+
+    #[test]
+    fn no_entropy() {
+        assert!(Drbg::new(NoEntropy, Reseed::Never).is_err());
+        assert!(Drbg::new(Hardware(|_ : &mut [u8]| false), Reseed::Never).is_err());
+        let mut d = drbg(Reseed::AfterRequests(1), 1);
+        let mut slice = [0u32; 4];
+        assert_eq!(d.call(FILL, &mut slice), Ok(()));
+        assert_eq!(d.call(FILL, &mut slice), Err(CallError::NoEntropy));
+        assert_eq!(d.reseed(), Err(EntropyError));
+        // Test mode never asks
+        let mut t = Drbg::test_mode([0; 32]);
+        t.policy = Reseed::AfterRequests(1);
+        assert_eq!(t.call(FILL, &mut slice), Ok(()));
+        assert_eq!(t.call(FILL, &mut slice), Err(CallError::NoEntropy));
+        assert_eq!(Drbg::test_mode([0; 32]).call(1u32, &mut slice), Err(CallError::UnknownMinor));
+    }
+
+    #[test]
+    fn packs_words_big_endian() {
+        let expect = bytes(&mut Drbg::test_mode([3; 32]), 20 * 4);
+        let mut words = [0u32; 20];
+        Drbg::test_mode([3; 32]).call(FILL, &mut words).unwrap();
+        for (w, b) in words.iter().zip(expect.chunks(4)) {
+            assert_eq!(*w, u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        }
+        let expect = bytes(&mut Drbg::test_mode([3; 32]), 9 * 8);
+        let mut words = [0u64; 9];
+        Drbg::test_mode([3; 32]).call(FILL as u64, &mut words).unwrap();
+        for (w, b) in words.iter().zip(expect.chunks(8)) {
+            let mut be = [0u8; 8];
+            be.copy_from_slice(b);
+            assert_eq!(*w, u64::from_be_bytes(be));
+        }
+        // An empty slice is still a request
+        let mut d = Drbg::test_mode([3; 32]);
+        d.call(FILL, &mut [0u32; 0]).unwrap();
+        assert_eq!(d.requests, 1);
+    }
+}
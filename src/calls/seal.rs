@@ -0,0 +1,438 @@
+use evaluator::*;
+use bytes::*;
+use calls::*;
+use crypto::{gcm, sha256};
+use crypto::hmac::HmacSha256;
+
+// Major 2: sealed storage.
+//
+//   minor  function  layout
+//   0      seal      [slot] [n] [data:n]
+//   1      unseal    [slot] [n] [data:n]
+//
+// Seal encrypts data and stores it under slot, replacing whatever was there.
+// Unseal reads slot back into data. On entry n is the room available; on
+// return it holds the sealed length. A blob sealed by a different program, or
+// one that has been tampered with, fails with AuthFailed and data is zeroed.
+//
+// Blobs are AES-256-GCM under a key derived from the device key and the
+// measurement of the running program, with the measurement and slot as
+// associated data, so only the same program on the same device can unseal.
+// The nonce is an HMAC of the slot and plaintext, which keeps it unique
+// without needing an entropy source; sealing identical data to the same slot
+// twice yields the same blob. The HMAC and the cipher get separate subkeys,
+// HMAC(key, "nonce") and HMAC(key, "enc").
+
+pub const MAJOR : u32 = 2;
+
+pub const SEAL : u32 = 0;
+pub const UNSEAL : u32 = 1;
+
+pub const MAX_DATA_LEN : usize = 1024;
+pub const OVERHEAD : usize = gcm::NONCE_LEN + gcm::TAG_LEN;
+pub const MAX_BLOB_LEN : usize = MAX_DATA_LEN + OVERHEAD;
+
+pub type Measurement = [u8; sha256::DIGEST_LEN];
+
+pub trait SealStore {
+    fn put(&mut self, slot : u32, blob : &[u8]) -> Result<(), StoreError>;
+    // Copies the blob into buf and returns its length.
+    fn get(&mut self, slot : u32, buf : &mut [u8]) -> Result<usize, StoreError>;
+}
+
+impl WriteSink for sha256::Sha256 {
+    fn write(&mut self, val : u8) {
+        self.update(&[val])
+    }
+}
+
+// SHA-256 of the program in its serialized form.
+pub fn measure<U : Prim + Write>(program : &[Instruction<U>]) -> Measurement {
+    let mut h = sha256::Sha256::new();
+    for instr in program {
+        instr.write(&mut h);
+    }
+    h.finish()
+}
+
+pub struct Sealer<S> {
+    nonce_key : [u8; 32],
+    enc_key : [u8; 32],
+    measurement : Measurement,
+    store : S
+}
+
+impl<S : SealStore> Sealer<S> {
+    pub fn new(device_key : &[u8], measurement : Measurement, store : S) -> Sealer<S> {
+        let mut h = HmacSha256::new(device_key);
+        h.update(b"flextpm seal");
+        h.update(&measurement);
+        let key = h.finish();
+        let subkey = |label : &[u8]| {
+            let mut h = HmacSha256::new(&key);
+            h.update(label);
+            h.finish()
+        };
+        Sealer { nonce_key : subkey(b"nonce"), enc_key : subkey(b"enc"), measurement, store }
+    }
+
+    pub fn store(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    fn aad(&self, slot : u32) -> [u8; sha256::DIGEST_LEN + 4] {
+        let mut aad = [0u8; sha256::DIGEST_LEN + 4];
+        aad[..sha256::DIGEST_LEN].copy_from_slice(&self.measurement);
+        aad[sha256::DIGEST_LEN..].copy_from_slice(&slot.to_be_bytes());
+        aad
+    }
+
+    fn seal<U : Prim>(&mut self, slice : &mut [U]) -> Result<(), CallError> {
+        let mut l = Layout::new(slice);
+        let slot = l.word().map(|i| slot(slice[i]))??;
+        let n = l.word().map(|i| slice[i].to_usize())?;
+        let data = l.bytes(n)?;
+        if n > MAX_DATA_LEN {
+            return Err(CallError::BadLayout)
+        }
+        let mut blob = [0u8; MAX_BLOB_LEN];
+        let (head, text) = blob.split_at_mut(OVERHEAD);
+        let text = &mut text[..n];
+        let _ = read_bytes(slice, data, text);
+        let mut nonce = [0u8; gcm::NONCE_LEN];
+        {
+            let mut h = HmacSha256::new(&self.nonce_key);
+            h.update(&slot.to_be_bytes());
+            h.update(text);
+            nonce.copy_from_slice(&h.finish()[..gcm::NONCE_LEN]);
+        }
+        let tag = gcm::seal(&self.enc_key, &nonce, &self.aad(slot), text).ok_or(CallError::BadKey)?;
+        head[..gcm::NONCE_LEN].copy_from_slice(&nonce);
+        head[gcm::NONCE_LEN..].copy_from_slice(&tag);
+        self.store.put(slot, &blob[..OVERHEAD + n]).map_err(CallError::Storage)
+    }
+
+    fn unseal<U : Prim>(&mut self, slice : &mut [U]) -> Result<(), CallError> {
+        let mut l = Layout::new(slice);
+        let slot = l.word().map(|i| slot(slice[i]))??;
+        let n_idx = l.word()?;
+        let room = slice[n_idx].to_usize();
+        let data = l.bytes(room)?;
+        let mut blob = [0u8; MAX_BLOB_LEN];
+        let len = self.store.get(slot, &mut blob).map_err(CallError::Storage)?;
+        if !(OVERHEAD..=MAX_BLOB_LEN).contains(&len) {
+            return Err(CallError::AuthFailed)
+        }
+        let n = len - OVERHEAD;
+        if n > room {
+            return Err(CallError::BadLayout)
+        }
+        let mut nonce = [0u8; gcm::NONCE_LEN];
+        nonce.copy_from_slice(&blob[..gcm::NONCE_LEN]);
+        let mut tag = [0u8; gcm::TAG_LEN];
+        tag.copy_from_slice(&blob[gcm::NONCE_LEN..OVERHEAD]);
+        let aad = self.aad(slot);
+        let text = &mut blob[OVERHEAD..len];
+        let ok = gcm::open(&self.enc_key, &nonce, &aad, text, &tag);
+        let _ = write_bytes(slice, data, text);
+        if ok {
+            slice[n_idx] = U::from_u64(n as u64);
+            Ok(())
+        } else {
+            Err(CallError::AuthFailed)
+        }
+    }
+}
+
+fn slot<U : Prim>(word : U) -> Result<u32, CallError> {
+    let s = word.to_u64();
+    if s > u32::MAX as u64 {Err(CallError::BadLayout)} else {Ok(s as u32)}
+}
+
+impl<U : Prim, S : SealStore> Library<U> for Sealer<S> {
+    fn major(&self) -> u32 {
+        MAJOR
+    }
+
+    fn call(&mut self, minor : U, slice : &mut [U]) -> Result<(), CallError> {
+        match minor_number(minor) {
+            SEAL => self.seal(slice),
+            UNSEAL => self.unseal(slice),
+            _ => Err(CallError::UnknownMinor)
+        }
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+pub use self::stores::*;
+
+#[cfg(any(test, feature = "std"))]
+mod stores {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use std::vec::Vec;
+
+    // Keeps blobs in memory. Meant for tests.
+    #[derive(Default)]
+    pub struct MemStore {
+        slots : BTreeMap<u32, Vec<u8>>
+    }
+
+    impl MemStore {
+        pub fn new() -> MemStore {
+            MemStore::default()
+        }
+
+        pub fn raw(&mut self, slot : u32) -> Option<&mut Vec<u8>> {
+            self.slots.get_mut(&slot)
+        }
+    }
+
+    impl SealStore for MemStore {
+        fn put(&mut self, slot : u32, blob : &[u8]) -> Result<(), StoreError> {
+            self.slots.insert(slot, blob.to_vec());
+            Ok(())
+        }
+
+        fn get(&mut self, slot : u32, buf : &mut [u8]) -> Result<usize, StoreError> {
+            match self.slots.get(&slot) {
+                None => Err(StoreError::NotFound),
+                Some(blob) if blob.len() > buf.len() => Err(StoreError::TooLarge),
+                Some(blob) => {
+                    buf[..blob.len()].copy_from_slice(blob);
+                    Ok(blob.len())
+                }
+            }
+        }
+    }
+
+    // One file per slot under a directory. Writes go to a temporary file that
+    // is synced and then renamed over the old blob, so a crash leaves either
+    // the old or the new blob in place.
+    pub struct FileStore {
+        dir : PathBuf
+    }
+
+    impl FileStore {
+        pub fn new<P : Into<PathBuf>>(dir : P) -> io::Result<FileStore> {
+            let dir = dir.into();
+            fs::create_dir_all(&dir)?;
+            Ok(FileStore { dir })
+        }
+
+        fn path(&self, slot : u32, suffix : &str) -> PathBuf {
+            self.dir.join(format!("slot-{:08x}.sealed{}", slot, suffix))
+        }
+    }
+
+    fn io_error(e : io::Error) -> StoreError {
+        match e.kind() {
+            io::ErrorKind::NotFound => StoreError::NotFound,
+            _ => StoreError::Io
+        }
+    }
+
+    impl SealStore for FileStore {
+        fn put(&mut self, slot : u32, blob : &[u8]) -> Result<(), StoreError> {
+            use std::io::Write;
+            let tmp = self.path(slot, ".tmp");
+            let write = || -> io::Result<()> {
+                let mut f = fs::File::create(&tmp)?;
+                f.write_all(blob)?;
+                f.sync_all()?;
+                fs::rename(&tmp, self.path(slot, ""))?;
+                fs::File::open(&self.dir)?.sync_all()
+            };
+            write().map_err(io_error)
+        }
+
+        fn get(&mut self, slot : u32, buf : &mut [u8]) -> Result<usize, StoreError> {
+            let blob = fs::read(self.path(slot, "")).map_err(io_error)?;
+            if blob.len() > buf.len() {
+                return Err(StoreError::TooLarge)
+            }
+            buf[..blob.len()].copy_from_slice(&blob);
+            Ok(blob.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::vec::Vec;
+
+    const KEY : &[u8] = b"device key";
+    const MEASUREMENT : Measurement = [7; sha256::DIGEST_LEN];
+    const SECRET : &[u8] = b"attack at dawn";
+
+    fn sealer() -> Sealer<MemStore> {
+        Sealer::new(KEY, MEASUREMENT, MemStore::new())
+    }
+
+    // [slot] [n] [data:n], with room for room bytes of data
+    fn args<U : Prim>(slot : u32, data : &[u8], room : usize) -> Vec<U> {
+        let mut slice = vec![U::zero(); 2 + words_for::<U>(room)];
+        slice[0] = U::from_u64(slot as u64);
+        slice[1] = U::from_u64(data.len() as u64);
+        write_bytes(&mut slice[2 ..], 0, data).unwrap();
+        slice
+    }
+
+    fn seal<U : Prim, S : SealStore>(sealer : &mut Sealer<S>, slot : u32, data : &[u8]) {
+        let mut slice = args::<U>(slot, data, data.len());
+        sealer.call(U::from_u64(SEAL as u64), &mut slice).unwrap();
+    }
+
+    // Unseals into junk, giving what unseal leaves in the slice
+    fn unseal<U : Prim, S : SealStore>(sealer : &mut Sealer<S>, slot : u32) -> (Result<(), CallError>, Vec<U>) {
+        let mut slice = args::<U>(slot, &[0xAA; 64], 64);
+        let res = sealer.call(U::from_u64(UNSEAL as u64), &mut slice);
+        (res, slice)
+    }
+
+    fn unsealed<U : Prim, S : SealStore>(sealer : &mut Sealer<S>, slot : u32) -> Vec<u8> {
+        let (res, slice) = unseal::<U, S>(sealer, slot);
+        res.unwrap();
+        let mut data = vec![0; slice[1].to_usize()];
+        read_bytes(&slice[2 ..], 0, &mut data).unwrap();
+        data
+    }
+
+    // AuthFailed, with the blob's length of data zeroed
+    fn auth_fails<S : SealStore>(sealer : &mut Sealer<S>, slot : u32, n : usize) {
+        let (res, slice) = unseal::<u32, S>(sealer, slot);
+        assert_eq!(res, Err(CallError::AuthFailed));
+        let mut data = vec![0xFF; n];
+        read_bytes(&slice[2 ..], 0, &mut data).unwrap();
+        assert_eq!(data, vec![0; n]);
+    }
+
+    fn blob(sealer : &mut Sealer<MemStore>, slot : u32) -> Vec<u8> {
+        sealer.store().raw(slot).unwrap().clone()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut s = sealer();
+        seal::<u32, _>(&mut s, 3, SECRET);
+        assert_eq!(blob(&mut s, 3).len(), OVERHEAD + SECRET.len());
+        assert_eq!(unsealed::<u32, _>(&mut s, 3), SECRET);
+        seal::<u64, _>(&mut s, 4, b"");
+        assert_eq!(unsealed::<u64, _>(&mut s, 4), b"");
+        // Replaces the old blob
+        seal::<u64, _>(&mut s, 3, b"retreat");
+        assert_eq!(unsealed::<u32, _>(&mut s, 3), b"retreat");
+        let (res, _) = unseal::<u32, _>(&mut s, 5);
+        assert_eq!(res, Err(CallError::Storage(StoreError::NotFound)));
+    }
+
+    #[test]
+    fn only_the_same_program_on_the_same_device_unseals() {
+        let mut s = sealer();
+        seal::<u32, _>(&mut s, 3, SECRET);
+        let blob = blob(&mut s, 3);
+        let mut other = [Sealer::new(KEY, [8; sha256::DIGEST_LEN], MemStore::new()),
+                         Sealer::new(b"other key", MEASUREMENT, MemStore::new())];
+        for o in other.iter_mut() {
+            o.store().put(3, &blob).unwrap();
+            auth_fails(o, 3, SECRET.len());
+        }
+        let mut same = sealer();
+        same.store().put(3, &blob).unwrap();
+        assert_eq!(unsealed::<u32, _>(&mut same, 3), SECRET);
+    }
+
+    #[test]
+    fn nonce_and_cipher_keys_differ() {
+        let mut s = sealer();
+        assert!(s.nonce_key != s.enc_key);
+        seal::<u32, _>(&mut s, 3, SECRET);
+        let blob = blob(&mut s, 3);
+        let mut h = HmacSha256::new(&s.nonce_key);
+        h.update(&3u32.to_be_bytes());
+        h.update(SECRET);
+        assert_eq!(blob[.. gcm::NONCE_LEN], h.finish()[.. gcm::NONCE_LEN]);
+        let mut nonce = [0; gcm::NONCE_LEN];
+        nonce.copy_from_slice(&blob[.. gcm::NONCE_LEN]);
+        let mut tag = [0; gcm::TAG_LEN];
+        tag.copy_from_slice(&blob[gcm::NONCE_LEN .. OVERHEAD]);
+        let mut text = blob[OVERHEAD ..].to_vec();
+        assert!(gcm::open(&s.enc_key, &nonce, &s.aad(3), &mut text, &tag));
+        assert_eq!(text, SECRET);
+    }
+
+    #[test]
+    fn tampering_fails() {
+        let mut s = sealer();
+        seal::<u32, _>(&mut s, 3, SECRET);
+        // In the nonce, the tag and the ciphertext
+        for &i in [0, gcm::NONCE_LEN, OVERHEAD - 1, OVERHEAD, OVERHEAD + SECRET.len() - 1].iter() {
+            let mut t = sealer();
+            let mut blob = blob(&mut s, 3);
+            blob[i] ^= 1;
+            t.store().put(3, &blob).unwrap();
+            auth_fails(&mut t, 3, SECRET.len());
+        }
+        let mut t = sealer();
+        let blob = blob(&mut s, 3);
+        t.store().put(3, &blob[.. OVERHEAD - 1]).unwrap();
+        assert_eq!(unseal::<u32, _>(&mut t, 3).0, Err(CallError::AuthFailed));
+    }
+
+    #[test]
+    fn blobs_are_bound_to_their_slot() {
+        let mut s = sealer();
+        seal::<u32, _>(&mut s, 3, SECRET);
+        let blob = blob(&mut s, 3);
+        s.store().put(4, &blob).unwrap();
+        auth_fails(&mut s, 4, SECRET.len());
+    }
+
+    #[test]
+    fn bad_layout() {
+        let mut s = sealer();
+        // n past the end of the slice
+        let mut short = args::<u32>(3, SECRET, SECRET.len());
+        short[1] = SECRET.len() as u32 + 4;
+        assert_eq!(s.call(SEAL, &mut short), Err(CallError::BadLayout));
+        // More than MAX_DATA_LEN, in a slice with room for it
+        let big = [1; MAX_DATA_LEN + 1];
+        let mut too_big = args::<u32>(3, &big, big.len());
+        assert_eq!(s.call(SEAL, &mut too_big), Err(CallError::BadLayout));
+        let mut max = args::<u32>(3, &big[1 ..], big.len() - 1);
+        assert_eq!(s.call(SEAL, &mut max), Ok(()));
+        // Too little room to unseal into
+        let mut small = args::<u32>(3, &[], 8);
+        small[1] = 8;
+        assert_eq!(s.call(UNSEAL, &mut small), Err(CallError::BadLayout));
+        // A slot that doesn't fit in 32 bits
+        let mut wide = args::<u64>(3, SECRET, SECRET.len());
+        wide[0] = 1 << 32;
+        assert_eq!(s.call(SEAL as u64, &mut wide), Err(CallError::BadLayout));
+        assert_eq!(s.call(SEAL, &mut [3u32]), Err(CallError::BadLayout));
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let dir = env::temp_dir().join(format!("tpm-seal-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        {
+            let mut s = Sealer::new(KEY, MEASUREMENT, FileStore::new(&dir).unwrap());
+            seal::<u32, _>(&mut s, 3, SECRET);
+            seal::<u32, _>(&mut s, 3, b"retreat");
+        }
+        let mut s = Sealer::new(KEY, MEASUREMENT, FileStore::new(&dir).unwrap());
+        assert_eq!(unsealed::<u32, _>(&mut s, 3), b"retreat");
+        let (res, _) = unseal::<u32, _>(&mut s, 4);
+        assert_eq!(res, Err(CallError::Storage(StoreError::NotFound)));
+        assert!(!dir.join("slot-00000003.sealed.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
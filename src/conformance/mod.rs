@@ -0,0 +1,190 @@
+// Conformance suite for the instruction set.
+//
+// spec.rs pins down the semantics of each instruction with hand-picked cases,
+// and props.rs runs random programs. Both go through Pair, which runs State
+// (with both eval_instrs and eval_ops) and the reference interpreter in
+// reference.rs in lockstep and checks that they stop for the same reason with
+// the same pc, registers and RAM. Any rewrite of State::eval_instr can be
+// checked against the reference by running this suite; every case runs on
+// both the 32- and 64-bit VMs.
+
+mod reference;
+mod spec;
+mod props;
+
+use evaluator::*;
+use evaluator::threaded::{self, Op};
+use mem::MemFetch;
+use self::reference::{Outcome, Ref};
+use std::fmt::Debug;
+use std::vec::Vec;
+
+pub trait Vm {
+    type U : Compl<Self::S> + Debug;
+    type S : Compl<Self::U>;
+    const BITS : u32;
+}
+
+pub struct Vm32;
+pub struct Vm64;
+
+impl Vm for Vm32 {
+    type U = u32;
+    type S = i32;
+    const BITS : u32 = 32;
+}
+
+impl Vm for Vm64 {
+    type U = u64;
+    type S = i64;
+    const BITS : u32 = 64;
+}
+
+pub fn widen<U : Prim>(f : Failure<U>) -> Failure<u64> {
+    match f {
+        Failure::CallOverflow => Failure::CallOverflow,
+        Failure::CallUnderflow => Failure::CallUnderflow,
+        Failure::CodeOob{pc} => Failure::CodeOob{pc : pc.to_u64()},
+        Failure::RamOob{pc, addr, dir} => Failure::RamOob{pc : pc.to_u64(), addr : addr.to_u64(), dir},
+        Failure::StackOverflow{pc} => Failure::StackOverflow{pc : pc.to_u64()},
+        Failure::StackUnderflow{pc} => Failure::StackUnderflow{pc : pc.to_u64()},
+        Failure::BadIret{pc} => Failure::BadIret{pc : pc.to_u64()},
+        Failure::InputPending{pc} => Failure::InputPending{pc : pc.to_u64()},
+        Failure::DivByZero{pc} => Failure::DivByZero{pc : pc.to_u64()},
+        Failure::InvalidInstruction => Failure::InvalidInstruction
+    }
+}
+
+// An immediate, truncated to the word size. Negative numbers come out in
+// two's complement.
+pub fn imm<U : Prim>(v : i64) -> U {
+    U::from_u64(v as u64)
+}
+
+pub fn words<U : Prim>(xs : &[U]) -> Vec<u64> {
+    xs.iter().map(|x| x.to_u64()).collect()
+}
+
+// The result of eval_instrs or eval_ops as an Outcome, answering a Call
+// with reply: its result and words to fill the start of the slice with, or
+// None to cancel it.
+fn outcome<U : Prim>(res : Result<MutNotice<U>, Failure<U>>, reply : &Option<(u64, Vec<u64>)>) -> Outcome {
+    match res {
+        Err(f) => Outcome::Fail(widen(f)),
+        Ok(MutNotice::Thrash) => Outcome::Thrash,
+        Ok(MutNotice::Halt) => Outcome::Halt,
+        Ok(MutNotice::Out{port, out}) => Outcome::Out{port : port.to_u64(), out : out.to_u64()},
+        Ok(MutNotice::In{port}) => Outcome::In{port : port.to_u64()},
+        Ok(MutNotice::Call{major, minor, slice, reply : r}) => {
+            let got = Outcome::Call{major : major.to_u64(), minor : minor.to_u64(), slice : words(slice)};
+            match *reply {
+                None => r.cancel(),
+                Some((val, ref fill)) => {
+                    for (w, f) in slice.iter_mut().zip(fill) {
+                        *w = U::from_u64(*f);
+                    }
+                    r.resume(U::from_u64(val))
+                }
+            }
+            got
+        }
+    }
+}
+
+// state runs programs with eval_instrs and threaded with eval_ops on the
+// compiled program, each on its own RAM.
+pub struct Pair<'a, V : Vm> where V::U : 'a, V::S : 'a {
+    pub state : State<'a, V::U, V::S>,
+    pub threaded : State<'a, V::U, V::S>,
+    pub model : Ref,
+    reply : Option<(u64, Vec<u64>)>
+}
+
+impl<'a, V : Vm> Pair<'a, V> {
+    // Both RAMs must be zeroed and the same length.
+    pub fn new(ram : &'a mut [V::U], threaded_ram : &'a mut [V::U]) -> Pair<'a, V> {
+        let model = Ref::new(V::BITS, ram.len());
+        Pair {
+            state : State::new(ram), threaded : State::new(threaded_ram), model,
+            reply : Some((0, Vec::new()))
+        }
+    }
+
+    pub fn check(&self) {
+        for state in [&self.state, &self.threaded].iter() {
+            assert_eq!(state.pc().to_u64(), self.model.pc, "pc");
+            assert_eq!(&words(state.regs())[..], &self.model.regs[..], "registers");
+            assert_eq!(words(state.ram()), self.model.ram, "ram");
+        }
+    }
+
+    // Runs all three for up to fuel units and checks they agree. A Call is
+    // answered as set by reply_with or cancel_calls; by default with 0. After
+    // a failure the states are left as they are, so only the failure is
+    // compared.
+    pub fn run(&mut self, program : &[Instruction<V::U>], fuel : u64) -> Outcome {
+        let expected = self.model.run(program, fuel);
+        let got = outcome(self.state.eval_instrs(V::U::from_u64(fuel), &mut MemFetch(program)), &self.reply);
+        assert_eq!(got, expected);
+        let mut ops = vec![Op::default(); program.len()];
+        let ops = threaded::compile(program, &mut ops).unwrap();
+        let got = outcome(self.threaded.eval_ops(V::U::from_u64(fuel), ops), &self.reply);
+        assert_eq!(got, expected, "threaded");
+        if let Outcome::Call{ref slice, ..} = got {
+            match self.reply {
+                None => self.model.cancel_call(),
+                Some((val, ref fill)) => self.model.resume_call(val, &fill[..fill.len().min(slice.len())])
+            }
+        }
+        if let Outcome::Fail(_) = got {} else {self.check()}
+        got
+    }
+
+    // How run answers the Calls it stops on from now on.
+    pub fn reply_with(&mut self, val : u64, fill : &[u64]) {
+        self.reply = Some((val, fill.to_vec()));
+    }
+
+    pub fn cancel_calls(&mut self) {
+        self.reply = None;
+    }
+
+    pub fn resume_in(&mut self, val : u64) {
+        let expected = self.model.resume_in(val);
+        assert_eq!(self.state.resume_in(V::U::from_u64(val)), expected);
+        assert_eq!(self.threaded.resume_in(V::U::from_u64(val)), expected);
+        self.check();
+    }
+
+    pub fn set_stack(&mut self, base : u64, top : u64) -> Result<(), ()> {
+        let res = self.model.set_stack(base, top);
+        assert_eq!(self.state.set_stack(V::U::from_u64(base), V::U::from_u64(top)), res);
+        assert_eq!(self.threaded.set_stack(V::U::from_u64(base), V::U::from_u64(top)), res);
+        res
+    }
+
+    pub fn set_vectors(&mut self, base : u64) -> Result<(), ()> {
+        let res = self.model.set_vectors(base);
+        assert_eq!(self.state.set_vectors(V::U::from_u64(base)), res);
+        assert_eq!(self.threaded.set_vectors(V::U::from_u64(base)), res);
+        res
+    }
+
+    pub fn set_q_format(&mut self, frac : u32) -> Result<(), ()> {
+        let res = self.model.set_q_format(frac);
+        assert_eq!(self.state.set_q_format(frac), res);
+        assert_eq!(self.threaded.set_q_format(frac), res);
+        res
+    }
+
+    pub fn raise(&mut self, n : usize) -> Result<(), ()> {
+        let res = self.model.raise(n);
+        assert_eq!(self.state.raise(n), res);
+        assert_eq!(self.threaded.raise(n), res);
+        res
+    }
+
+    pub fn reg(&self, reg : Reg) -> u64 {
+        self.model.regs[reference::ix(reg)]
+    }
+}
@@ -0,0 +1,171 @@
+// Random programs run on State and the reference in lockstep. The generator
+// leans towards values that matter: small numbers that land inside RAM and
+// the program, the extremes of each width, and jump targets inside the
+// program, so most runs get well past their first instruction.
+
+use super::*;
+use super::reference::Outcome;
+use evaluator::Instruction::*;
+
+const RAM_WORDS : usize = 64;
+const PROGRAM_LEN : usize = 48;
+const PROGRAMS : usize = 3000;
+const ROUNDS : usize = 16;
+
+// xorshift64*, seeded per test so failures replay
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed : u64) -> Rng {
+        Rng(seed | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    pub fn below(&mut self, n : u64) -> u64 {
+        self.next() % n
+    }
+
+    pub fn pick<T : Copy>(&mut self, xs : &[T]) -> T {
+        xs[self.below(xs.len() as u64) as usize]
+    }
+
+    pub fn chance(&mut self, percent : u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+fn value<V : Vm>(rng : &mut Rng) -> V::U {
+    if cfg!(feature = "float") && rng.chance(15) {
+        return float::<V>(rng)
+    }
+    let v = match rng.below(6) {
+        0 | 1 => rng.below(RAM_WORDS as u64 + 4),
+        2 => rng.below(PROGRAM_LEN as u64 + 2),
+        3 => (rng.below(9) as i64 - 4) as u64,
+        4 => rng.pick(&[1 << (V::BITS - 1), (1 << (V::BITS - 1)) - 1, 1 << (V::BITS / 2)]),
+        _ => rng.next()
+    };
+    V::U::from_u64(v)
+}
+
+// Bit patterns of floats of the VM's width: ordinary, huge, tiny and special
+fn float<V : Vm>(rng : &mut Rng) -> V::U {
+    let x = rng.pick(&[0.0, -0.0, 1.0, -1.5, 0.1, 3e9, -1e19, 1e300, 1e-40, 1e-310,
+                       f64::INFINITY, f64::NEG_INFINITY, f64::NAN]);
+    let x = if rng.chance(50) {x} else {x * (rng.below(2001) as f64 - 1000.0)};
+    V::U::from_u64(if V::BITS == 32 {(x as f32).to_bits() as u64} else {x.to_bits()})
+}
+
+fn reg(rng : &mut Rng) -> Reg {
+    // R0 to R7 most of the time, so instructions share registers
+    if rng.chance(80) {Reg::ARR[rng.below(8) as usize]} else {rng.pick(Reg::ARR)}
+}
+
+fn pick<T : Fin>(rng : &mut Rng) -> T {
+    rng.pick(T::ARR)
+}
+
+fn instruction<V : Vm>(rng : &mut Rng) -> Instruction<V::U> {
+    let r = reg;
+    // The last kind only exists with floats, so other builds draw as before
+    match rng.below(if cfg!(feature = "float") {37} else {36}) {
+        0 ..= 3 => Lit{val : value::<V>(rng), reg : r(rng)},
+        4 | 5 => Um2{op : pick(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng)},
+        6 | 7 => Sm2{op : pick(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng)},
+        8 => Um2I{op : pick(rng), r1 : r(rng), imm : value::<V>(rng), r3 : r(rng)},
+        9 => Sm2I{op : pick(rng), r1 : r(rng), imm : value::<V>(rng), r3 : r(rng)},
+        10 => Um4{op : pick(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng), r4 : r(rng)},
+        11 => Sm4{op : pick(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng), r4 : r(rng)},
+        12 => UJump{cond : pick(rng), flag : r(rng), dest : r(rng)},
+        13 => SJump{cond : pick(rng), flag : r(rng), dest : r(rng)},
+        14 => UJumpI{cond : pick(rng), flag : r(rng), mode : pick(rng), target : V::U::from_u64(rng.below(PROGRAM_LEN as u64))},
+        15 => SJumpI{cond : pick(rng), flag : r(rng), mode : pick(rng), target : value::<V>(rng)},
+        16 => UJumpCmp{cond : pick(rng), r1 : r(rng), r2 : r(rng), dest : r(rng)},
+        17 => SJumpCmp{cond : pick(rng), r1 : r(rng), r2 : r(rng), dest : r(rng)},
+        18 => USelect{cond : pick(rng), flag : r(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng)},
+        19 => SSelect{cond : pick(rng), flag : r(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng)},
+        20 | 21 => Ram{dir : pick(rng), ptr : r(rng), val : r(rng)},
+        22 => RamI{dir : pick(rng), ptr : r(rng), off : value::<V>(rng), val : r(rng)},
+        23 => ULoad{width : pick(rng), ptr : r(rng), val : r(rng)},
+        24 => SLoad{width : pick(rng), ptr : r(rng), val : r(rng)},
+        25 => Store{width : pick(rng), ptr : r(rng), val : r(rng)},
+        26 => MemCopy{dst : r(rng), src : r(rng), len : r(rng)},
+        27 => MemFill{dst : r(rng), val : r(rng), len : r(rng)},
+        28 => MemCmp{a : r(rng), b : r(rng), len : r(rng), res : r(rng)},
+        29 => Push{reg : r(rng)},
+        30 => Pop{reg : r(rng)},
+        31 => Call{major : r(rng), minor : r(rng), arg : r(rng), len : r(rng), res : r(rng)},
+        32 => if rng.chance(50) {Out{reg : r(rng), port : value::<V>(rng)}} else {In{reg : r(rng), port : value::<V>(rng)}},
+        33 => IntEn{flag : r(rng)},
+        34 => Iret,
+        #[cfg(feature = "float")]
+        35 => Fm2{op : pick(rng), r1 : r(rng), r2 : r(rng), r3 : r(rng)},
+        _ => if rng.chance(80) {Halt} else {Invalid}
+    }
+}
+
+fn program<V : Vm>(rng : &mut Rng) -> Vec<Instruction<V::U>> {
+    let len = 1 + rng.below(PROGRAM_LEN as u64) as usize;
+    let mut prog : Vec<Instruction<V::U>> = (0..len).map(|_| instruction::<V>(rng)).collect();
+    // Seed most registers so the first instructions have something to work on
+    for i in 0..rng.below(12) as usize {
+        prog.insert(0, Lit{val : value::<V>(rng), reg : Reg::ARR[i]});
+    }
+    prog
+}
+
+fn lockstep<V : Vm>(seed : u64) {
+    let mut rng = Rng::new(seed);
+    for _ in 0..PROGRAMS {
+        let prog = program::<V>(&mut rng);
+        let (mut ram, mut threaded_ram) = (vec![V::U::zero(); RAM_WORDS], vec![V::U::zero(); RAM_WORDS]);
+        let mut p = Pair::<V>::new(&mut ram, &mut threaded_ram);
+        if rng.chance(70) {
+            let base = rng.below(RAM_WORDS as u64);
+            let _ = p.set_stack(base, base + rng.below(8));
+        }
+        if rng.chance(50) {
+            let _ = p.set_vectors(rng.below(RAM_WORDS as u64));
+        }
+        if rng.chance(30) {
+            let _ = p.set_q_format(rng.below(V::BITS as u64 + 1) as u32);
+        }
+        for _ in 0..ROUNDS {
+            let fuel = 1 + rng.below(60);
+            if rng.chance(20) {
+                p.cancel_calls();
+            } else {
+                let fill : Vec<u64> = (0..rng.below(4)).map(|_| value::<V>(&mut rng).to_u64()).collect();
+                let result = value::<V>(&mut rng).to_u64();
+                p.reply_with(result, &fill);
+            }
+            match p.run(&prog, fuel) {
+                Outcome::Fail(_) => break,
+                Outcome::In{..} => {
+                    let v = value::<V>(&mut rng).to_u64();
+                    p.resume_in(v);
+                },
+                Outcome::Thrash | Outcome::Halt | Outcome::Out{..} | Outcome::Call{..} => ()
+            }
+            if rng.chance(30) {
+                let _ = p.raise(rng.below(34) as usize);
+            }
+        }
+    }
+}
+
+#[test]
+fn lockstep_32() {
+    lockstep::<Vm32>(0x243F6A8885A308D3);
+}
+
+#[test]
+fn lockstep_64() {
+    lockstep::<Vm64>(0x13198A2E03707344);
+}
@@ -0,0 +1,491 @@
+// A deliberately plain interpreter for the ISA, written from the spec rather
+// than from State. Every word is a u64 holding a `bits`-wide value; signed
+// instructions sign-extend into i128 and all arithmetic happens at 128 bits
+// before being cut back to width. It is slow and allocates, and exists only
+// to check State against.
+
+use evaluator::*;
+use std::vec::Vec;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Thrash,
+    Call {major : u64, minor : u64, slice : Vec<u64>},
+    Halt,
+    Out {port : u64, out : u64},
+    In {port : u64},
+    Fail(Failure<u64>)
+}
+
+pub fn ix(reg : Reg) -> usize {
+    Reg::ARR.iter().position(|r| *r == reg).unwrap()
+}
+
+pub struct Ref {
+    pub bits : u32,
+    pub pc : u64,
+    pub regs : [u64; 16],
+    pub ram : Vec<u64>,
+    pub stack : (u64, u64),
+    pub frac : u32,
+    pub vectors : Option<u64>,
+    pub int_enabled : bool,
+    pub pending : u32,
+    pub saved : Option<(u64, [u64; 16])>,
+    pub input : Option<usize>,
+    // Result register and slice start of an unanswered Call
+    pub call : Option<(usize, usize)>
+}
+
+impl Ref {
+    pub fn new(bits : u32, ram_words : usize) -> Ref {
+        Ref {
+            bits, pc : 0, regs : [0; 16], ram : vec![0; ram_words], stack : (0, 0),
+            frac : bits / 2, vectors : None, int_enabled : false, pending : 0,
+            saved : None, input : None, call : None
+        }
+    }
+
+    fn mask(&self) -> u64 {
+        if self.bits == 64 {!0} else {(1 << self.bits) - 1}
+    }
+
+    fn cut(&self, v : i128) -> u64 {
+        (v as u64) & self.mask()
+    }
+
+    fn sx(&self, v : u64) -> i128 {
+        let sh = 128 - self.bits;
+        ((v as i128) << sh) >> sh
+    }
+
+    fn min(&self) -> i128 {
+        -(1i128 << (self.bits - 1))
+    }
+
+    fn max(&self) -> i128 {
+        (1i128 << (self.bits - 1)) - 1
+    }
+
+    pub fn set_stack(&mut self, base : u64, top : u64) -> Result<(), ()> {
+        if base > top || top > self.ram.len() as u64 {return Err(())}
+        self.stack = (base, top);
+        self.regs[15] = top;
+        Ok(())
+    }
+
+    pub fn set_vectors(&mut self, base : u64) -> Result<(), ()> {
+        if base + 32 > self.ram.len() as u64 {return Err(())}
+        self.vectors = Some(base);
+        Ok(())
+    }
+
+    pub fn set_q_format(&mut self, frac : u32) -> Result<(), ()> {
+        if frac >= self.bits {return Err(())}
+        self.frac = frac;
+        Ok(())
+    }
+
+    pub fn raise(&mut self, n : usize) -> Result<(), ()> {
+        if n >= 32 || self.vectors.is_none() {return Err(())}
+        self.pending |= 1 << n;
+        Ok(())
+    }
+
+    pub fn resume_in(&mut self, val : u64) -> Result<(), ()> {
+        match self.input.take() {
+            None => Err(()),
+            Some(r) => {
+                self.regs[r] = val;
+                Ok(())
+            }
+        }
+    }
+
+    // Answers a Call, first overwriting the start of its slice with fill.
+    pub fn resume_call(&mut self, val : u64, fill : &[u64]) {
+        let (r, arg) = self.call.take().unwrap();
+        self.ram[arg .. arg + fill.len()].copy_from_slice(fill);
+        self.regs[r] = val;
+    }
+
+    // Leaves a Call unanswered, back on the Call instruction.
+    pub fn cancel_call(&mut self) {
+        self.call.take().unwrap();
+        self.pc = self.cut(self.pc as i128 - 1);
+    }
+
+    fn fail(&self, f : Failure<u64>) -> Option<Outcome> {
+        Some(Outcome::Fail(f))
+    }
+
+    fn m2(&self, op : M2Op, a : u64, b : u64, signed : bool) -> Option<u64> {
+        let (x, y) = if signed {(self.sx(a), self.sx(b))} else {(a as i128, b as i128)};
+        let bit = |c : bool| c as u64;
+        // Unsigned products and shifted dividends need all 128 bits
+        let (u, v) = (a as u128, b as u128);
+        let narrow = |w : i128, sat : bool| {
+            if !sat {self.cut(w)}
+            else {self.cut(if w > self.max() {self.max()} else if w < self.min() {self.min()} else {w})}
+        };
+        let unarrow = |w : u128, sat : bool| {
+            if sat && w > self.mask() as u128 {self.mask()} else {(w as u64) & self.mask()}
+        };
+        Some(match op {
+            M2Op::Add => self.cut(x + y),
+            M2Op::Sub => self.cut(x - y),
+            M2Op::Mul => self.cut(x.wrapping_mul(y)),
+            M2Op::Div => if y == 0 {return None} else {self.cut(x / y)},
+            M2Op::Equ => bit(x == y),
+            M2Op::Lt => bit(x < y),
+            M2Op::Gt => bit(x > y),
+            M2Op::FxMul | M2Op::FxMulSat => {
+                let sat = op == M2Op::FxMulSat;
+                if signed {narrow((x * y) >> self.frac, sat)} else {unarrow((u * v) >> self.frac, sat)}
+            },
+            M2Op::FxDiv | M2Op::FxDivSat => {
+                let sat = op == M2Op::FxDivSat;
+                if y == 0 {return None}
+                if signed {narrow((x << self.frac) / y, sat)} else {unarrow((u << self.frac) / v, sat)}
+            },
+            M2Op::MulHi =>
+                if signed {self.cut((x * y) >> self.bits)} else {((u * v) >> self.bits) as u64}
+        })
+    }
+
+    fn m4(&self, op : M4Op, a : u64, b : u64, c : u64, signed : bool) -> Option<(u64, u64)> {
+        let (a, b, c) = (a as i128, b as i128, (c & 1) as i128);
+        Some(match op {
+            M4Op::AddC => {
+                let s = a + b + c;
+                (self.cut(s), (s >> self.bits) as u64)
+            },
+            M4Op::SubB => (self.cut(a - b - c), (a < b + c) as u64),
+            M4Op::DivRem => {
+                let (x, y) = if signed {(self.sx(a as u64), self.sx(b as u64))} else {(a, b)};
+                if y == 0 {return None}
+                (self.cut(x / y), self.cut(x % y))
+            }
+        })
+    }
+
+    fn holds(&self, cond : Cond, a : u64, b : u64, signed : bool) -> bool {
+        let (x, y) = if signed {(self.sx(a), self.sx(b))} else {(a as i128, b as i128)};
+        match cond {
+            Cond::Always => true,
+            Cond::EqZ => x == y,
+            Cond::NeZ => x != y,
+            Cond::GtZ => x > y,
+            Cond::LtZ => x < y,
+            Cond::GeZ => x >= y,
+            Cond::LeZ => x <= y
+        }
+    }
+
+    fn bytes(&self) -> u64 {
+        (self.bits / 8) as u64
+    }
+
+    fn get_byte(&self, i : u64) -> u64 {
+        let w = self.ram[(i / self.bytes()) as usize];
+        (w >> (8 * (self.bytes() - 1 - i % self.bytes()))) & 0xFF
+    }
+
+    fn set_byte(&mut self, i : u64, b : u64) {
+        let sh = 8 * (self.bytes() - 1 - i % self.bytes());
+        let at = (i / self.bytes()) as usize;
+        let w = &mut self.ram[at];
+        *w = (*w & !(0xFF << sh)) | (b << sh);
+    }
+
+    fn in_ram(&self, start : u64, len : u64) -> bool {
+        (start as u128) + (len as u128) <= self.ram.len() as u128
+    }
+
+    fn take_interrupt(&mut self) {
+        if let Some(base) = self.vectors {
+            if self.int_enabled && self.saved.is_none() && self.pending != 0 {
+                let n = self.pending.trailing_zeros();
+                self.pending &= !(1 << n);
+                self.saved = Some((self.pc, self.regs));
+                self.regs[0] = n as u64;
+                self.pc = self.ram[(base + n as u64) as usize];
+            }
+        }
+    }
+
+    fn cost<U : Prim>(&self, instr : &Instruction<U>) -> u64 {
+        match *instr {
+            Instruction::MemCopy{len,..} | Instruction::MemFill{len,..} | Instruction::MemCmp{len,..} =>
+                self.regs[ix(len)].saturating_add(1),
+            _ => 1
+        }
+    }
+
+    pub fn run<U : Prim>(&mut self, program : &[Instruction<U>], fuel : u64) -> Outcome {
+        if self.input.is_some() {
+            return Outcome::Fail(Failure::InputPending{pc : self.pc})
+        }
+        let mut fuel = fuel;
+        while fuel > 0 {
+            self.take_interrupt();
+            let instr = match program.get(self.pc as usize) {
+                Some(i) => *i,
+                None => return Outcome::Fail(Failure::CodeOob{pc : self.pc})
+            };
+            fuel = fuel.saturating_sub(self.cost(&instr));
+            if let Some(out) = self.step(&instr) {
+                return out
+            }
+        }
+        Outcome::Thrash
+    }
+
+    // Executes one instruction and moves pc on, returning why the guest
+    // stopped, if it did.
+    pub fn step<U : Prim>(&mut self, instr : &Instruction<U>) -> Option<Outcome> {
+        use evaluator::Instruction::*;
+        let pc = self.pc;
+        let r = |reg : Reg| ix(reg);
+        let mut next = self.cut(pc as i128 + 1);
+        let out = match *instr {
+            Lit{val, reg} => {self.regs[r(reg)] = val.to_u64(); None},
+            Um2{op, r1, r2, r3} | Sm2{op, r1, r2, r3} => {
+                let signed = matches!(*instr, Sm2{..});
+                match self.m2(op, self.regs[r(r1)], self.regs[r(r2)], signed) {
+                    None => self.fail(Failure::DivByZero{pc}),
+                    Some(v) => {self.regs[r(r3)] = v; None}
+                }
+            },
+            Um2I{op, r1, imm, r3} | Sm2I{op, r1, imm, r3} => {
+                let signed = matches!(*instr, Sm2I{..});
+                match self.m2(op, self.regs[r(r1)], imm.to_u64(), signed) {
+                    None => self.fail(Failure::DivByZero{pc}),
+                    Some(v) => {self.regs[r(r3)] = v; None}
+                }
+            },
+            Um4{op, r1, r2, r3, r4} | Sm4{op, r1, r2, r3, r4} => {
+                let signed = matches!(*instr, Sm4{..});
+                match self.m4(op, self.regs[r(r1)], self.regs[r(r2)], self.regs[r(r4)], signed) {
+                    None => self.fail(Failure::DivByZero{pc}),
+                    Some((v3, v4)) => {
+                        self.regs[r(r3)] = v3;
+                        self.regs[r(r4)] = v4;
+                        None
+                    }
+                }
+            },
+            #[cfg(feature = "float")]
+            Fm2{op, r1, r2, r3} => {
+                self.regs[r(r3)] = self.fm2(op, self.regs[r(r1)], self.regs[r(r2)]);
+                None
+            },
+            UJump{cond, flag, dest} | SJump{cond, flag, dest} => {
+                let signed = matches!(*instr, SJump{..});
+                if self.holds(cond, self.regs[r(flag)], 0, signed) {next = self.regs[r(dest)]}
+                None
+            },
+            UJumpI{cond, flag, mode, target} | SJumpI{cond, flag, mode, target} => {
+                let signed = matches!(*instr, SJumpI{..});
+                if self.holds(cond, self.regs[r(flag)], 0, signed) {
+                    next = match mode {
+                        Addr::Abs => target.to_u64(),
+                        Addr::Rel => self.cut(pc as i128 + target.to_u64() as i128)
+                    }
+                }
+                None
+            },
+            UJumpCmp{cond, r1, r2, dest} | SJumpCmp{cond, r1, r2, dest} => {
+                let signed = matches!(*instr, SJumpCmp{..});
+                if self.holds(cond, self.regs[r(r1)], self.regs[r(r2)], signed) {next = self.regs[r(dest)]}
+                None
+            },
+            USelect{cond, flag, r1, r2, r3} | SSelect{cond, flag, r1, r2, r3} => {
+                let signed = matches!(*instr, SSelect{..});
+                let pick = if self.holds(cond, self.regs[r(flag)], 0, signed) {r1} else {r2};
+                self.regs[r(r3)] = self.regs[r(pick)];
+                None
+            },
+            Ram{dir, ptr, val} => {
+                let addr = self.regs[r(ptr)];
+                self.word(pc, dir, addr, r(val))
+            },
+            RamI{dir, ptr, off, val} => {
+                let addr = self.cut(self.regs[r(ptr)] as i128 + off.to_u64() as i128);
+                self.word(pc, dir, addr, r(val))
+            },
+            ULoad{width, ptr, val} | SLoad{width, ptr, val} => {
+                let signed = matches!(*instr, SLoad{..});
+                let n = if width == Width::Byte {1} else {2};
+                let addr = self.regs[r(ptr)];
+                if (addr as u128) + n as u128 > (self.ram.len() as u128) * self.bytes() as u128 {
+                    self.fail(Failure::RamOob{pc, addr, dir : Dir::Read})
+                } else {
+                    let mut v = 0;
+                    for i in 0..n {
+                        v = v << 8 | self.get_byte(addr + i);
+                    }
+                    if signed && v >> (8 * n - 1) == 1 {
+                        v = self.cut(v as i128 - (1i128 << (8 * n)));
+                    }
+                    self.regs[r(val)] = v;
+                    None
+                }
+            },
+            Store{width, ptr, val} => {
+                let n = if width == Width::Byte {1} else {2};
+                let addr = self.regs[r(ptr)];
+                if (addr as u128) + n as u128 > (self.ram.len() as u128) * self.bytes() as u128 {
+                    self.fail(Failure::RamOob{pc, addr, dir : Dir::Write})
+                } else {
+                    let v = self.regs[r(val)];
+                    for i in 0..n {
+                        self.set_byte(addr + i, (v >> (8 * (n - 1 - i))) & 0xFF);
+                    }
+                    None
+                }
+            },
+            MemCopy{dst, src, len} => {
+                let (d, s, n) = (self.regs[r(dst)], self.regs[r(src)], self.regs[r(len)]);
+                if !self.in_ram(s, n) {
+                    self.fail(Failure::RamOob{pc, addr : s, dir : Dir::Read})
+                } else if !self.in_ram(d, n) {
+                    self.fail(Failure::RamOob{pc, addr : d, dir : Dir::Write})
+                } else {
+                    let words : Vec<u64> = self.ram[s as usize .. (s + n) as usize].to_vec();
+                    for (i, w) in words.into_iter().enumerate() {
+                        self.ram[d as usize + i] = w;
+                    }
+                    None
+                }
+            },
+            MemFill{dst, val, len} => {
+                let (d, v, n) = (self.regs[r(dst)], self.regs[r(val)], self.regs[r(len)]);
+                if !self.in_ram(d, n) {
+                    self.fail(Failure::RamOob{pc, addr : d, dir : Dir::Write})
+                } else {
+                    for i in 0..n {
+                        self.ram[(d + i) as usize] = v;
+                    }
+                    None
+                }
+            },
+            MemCmp{a, b, len, res} => {
+                let (a, b, n) = (self.regs[r(a)], self.regs[r(b)], self.regs[r(len)]);
+                if !self.in_ram(a, n) {
+                    self.fail(Failure::RamOob{pc, addr : a, dir : Dir::Read})
+                } else if !self.in_ram(b, n) {
+                    self.fail(Failure::RamOob{pc, addr : b, dir : Dir::Read})
+                } else {
+                    let mut v = 0;
+                    for i in 0..n {
+                        let (x, y) = (self.ram[(a + i) as usize], self.ram[(b + i) as usize]);
+                        if x != y {
+                            v = if x < y {self.mask()} else {1};
+                            break
+                        }
+                    }
+                    self.regs[r(res)] = v;
+                    None
+                }
+            },
+            Push{reg} => {
+                let sp = self.regs[15];
+                if sp <= self.stack.0 || sp > self.stack.1 {
+                    self.fail(Failure::StackOverflow{pc})
+                } else {
+                    self.ram[(sp - 1) as usize] = self.regs[r(reg)];
+                    self.regs[15] = sp - 1;
+                    None
+                }
+            },
+            Pop{reg} => {
+                let sp = self.regs[15];
+                if sp >= self.stack.1 || sp < self.stack.0 {
+                    self.fail(Failure::StackUnderflow{pc})
+                } else {
+                    self.regs[15] = sp + 1;
+                    self.regs[r(reg)] = self.ram[sp as usize];
+                    None
+                }
+            },
+            Out{reg, port} => Some(Outcome::Out{port : port.to_u64(), out : self.regs[r(reg)]}),
+            In{reg, port} => {
+                self.input = Some(r(reg));
+                Some(Outcome::In{port : port.to_u64()})
+            },
+            Call{major, minor, arg, len, res} => {
+                let (a, n) = (self.regs[r(arg)], self.regs[r(len)]);
+                if a as u128 + n as u128 > self.mask() as u128 {
+                    self.fail(Failure::CallUnderflow)
+                } else if !self.in_ram(a, n) {
+                    self.fail(Failure::CallOverflow)
+                } else {
+                    self.call = Some((r(res), a as usize));
+                    Some(Outcome::Call{
+                        major : self.regs[r(major)],
+                        minor : self.regs[r(minor)],
+                        slice : self.ram[a as usize .. (a + n) as usize].to_vec()})
+                }
+            },
+            IntEn{flag} => {
+                self.int_enabled = self.regs[r(flag)] != 0;
+                None
+            },
+            Iret => match self.saved.take() {
+                None => {next = pc; self.fail(Failure::BadIret{pc})},
+                Some((saved_pc, regs)) => {
+                    self.regs = regs;
+                    next = saved_pc;
+                    None
+                }
+            },
+            Halt => Some(Outcome::Halt),
+            Invalid => self.fail(Failure::InvalidInstruction)
+        };
+        self.pc = next;
+        out
+    }
+
+    fn word(&mut self, pc : u64, dir : Dir, addr : u64, val : usize) -> Option<Outcome> {
+        if addr >= self.ram.len() as u64 {
+            return self.fail(Failure::RamOob{pc, addr, dir})
+        }
+        match dir {
+            Dir::Read => self.regs[val] = self.ram[addr as usize],
+            Dir::Write => self.ram[addr as usize] = self.regs[val]
+        }
+        None
+    }
+
+    #[cfg(feature = "float")]
+    fn fm2(&self, op : FOp, a : u64, b : u64) -> u64 {
+        if self.bits == 32 {
+            let (x, y) = (f32::from_bits(a as u32), f32::from_bits(b as u32));
+            (match op {
+                FOp::Add => (x + y).to_bits(),
+                FOp::Sub => (x - y).to_bits(),
+                FOp::Mul => (x * y).to_bits(),
+                FOp::Div => (x / y).to_bits(),
+                FOp::Equ => (x == y) as u32,
+                FOp::Lt => (x < y) as u32,
+                FOp::Gt => (x > y) as u32,
+                FOp::FromInt => (a as u32 as i32 as f32).to_bits(),
+                FOp::ToInt => x as i32 as u32
+            }) as u64
+        } else {
+            let (x, y) = (f64::from_bits(a), f64::from_bits(b));
+            match op {
+                FOp::Add => (x + y).to_bits(),
+                FOp::Sub => (x - y).to_bits(),
+                FOp::Mul => (x * y).to_bits(),
+                FOp::Div => (x / y).to_bits(),
+                FOp::Equ => (x == y) as u64,
+                FOp::Lt => (x < y) as u64,
+                FOp::Gt => (x > y) as u64,
+                FOp::FromInt => (a as i64 as f64).to_bits(),
+                FOp::ToInt => x as i64 as u64
+            }
+        }
+    }
+}
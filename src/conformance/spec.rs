@@ -0,0 +1,406 @@
+// The semantics of each instruction, one behaviour per test. Expected values
+// are spelled out here rather than computed, and every run is also checked
+// against the reference interpreter by Pair.
+
+use super::*;
+use super::reference::Outcome;
+use evaluator::Reg::*;
+use evaluator::Instruction::*;
+
+const FUEL : u64 = 1000;
+
+fn lit<V : Vm>(v : i64, reg : Reg) -> Instruction<V::U> {
+    Lit{val : imm(v), reg}
+}
+
+fn mask<V : Vm>() -> u64 {
+    if V::BITS == 64 {!0} else {(1 << V::BITS) - 1}
+}
+
+// A word as the VM sees it, from a signed value
+fn w<V : Vm>(v : i64) -> u64 {
+    v as u64 & mask::<V>()
+}
+
+fn min<V : Vm>() -> i64 {
+    if V::BITS == 64 {i64::MIN} else {i32::MIN as i64}
+}
+
+// Runs program on a fresh pair with ram_words of RAM until it stops.
+fn exec<V : Vm, F : FnOnce(&mut Pair<V>)>(ram_words : usize, f : F) {
+    let (mut ram, mut threaded_ram) = (vec![V::U::zero(); ram_words], vec![V::U::zero(); ram_words]);
+    let mut pair = Pair::<V>::new(&mut ram, &mut threaded_ram);
+    f(&mut pair)
+}
+
+fn m2_unsigned_and_signed<V : Vm>() {
+    let big = w::<V>(-7);
+    // op, Um2 result, Sm2 result, for -7 op 2
+    let cases = [
+        (M2Op::Add, w::<V>(-5), w::<V>(-5)),
+        (M2Op::Sub, w::<V>(-9), w::<V>(-9)),
+        (M2Op::Mul, w::<V>(-14), w::<V>(-14)),
+        (M2Op::Div, big / 2, w::<V>(-3)),
+        (M2Op::Equ, 0, 0),
+        (M2Op::Lt, 0, 1),
+        (M2Op::Gt, 1, 0),
+        (M2Op::MulHi, 1, mask::<V>())
+    ];
+    for &(op, u, s) in cases.iter() {
+        exec::<V, _>(4, |p| {
+            let program = [lit::<V>(-7, R1), lit::<V>(2, R2),
+                           Um2{op, r1 : R1, r2 : R2, r3 : R3}, Sm2{op, r1 : R1, r2 : R2, r3 : R4},
+                           Um2I{op, r1 : R1, imm : imm(2), r3 : R5}, Sm2I{op, r1 : R1, imm : imm(2), r3 : R6},
+                           Halt];
+            assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+            assert_eq!((p.reg(R3), p.reg(R4)), (u, s), "{:?}", op);
+            assert_eq!((p.reg(R5), p.reg(R6)), (u, s), "{:?} immediate", op);
+        });
+    }
+}
+
+fn arithmetic_wraps<V : Vm>() {
+    exec::<V, _>(4, |p| {
+        let program = [lit::<V>(-1, R1), lit::<V>(1, R2), lit::<V>(min::<V>(), R3),
+                       Um2{op : M2Op::Add, r1 : R1, r2 : R2, r3 : R4},
+                       Um2{op : M2Op::Sub, r1 : R0, r2 : R2, r3 : R5},
+                       Sm2{op : M2Op::Mul, r1 : R3, r2 : R1, r3 : R6},
+                       Sm2{op : M2Op::Div, r1 : R3, r2 : R1, r3 : R7},
+                       Sm2{op : M2Op::Sub, r1 : R3, r2 : R2, r3 : R8},
+                       Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!(p.reg(R4), 0);
+        assert_eq!(p.reg(R5), mask::<V>());
+        assert_eq!(p.reg(R6), w::<V>(min::<V>()));
+        assert_eq!(p.reg(R7), w::<V>(min::<V>()));
+        assert_eq!(p.reg(R8), w::<V>(min::<V>()) - 1);
+    });
+}
+
+fn division_by_zero_fails<V : Vm>() {
+    let divs = [Um2{op : M2Op::Div, r1 : R1, r2 : R0, r3 : R2},
+                Sm2{op : M2Op::Div, r1 : R1, r2 : R0, r3 : R2},
+                Um2I{op : M2Op::FxDiv, r1 : R1, imm : imm(0), r3 : R2},
+                Sm2{op : M2Op::FxDivSat, r1 : R1, r2 : R0, r3 : R2},
+                Um4{op : M4Op::DivRem, r1 : R1, r2 : R0, r3 : R2, r4 : R3},
+                Sm4{op : M4Op::DivRem, r1 : R1, r2 : R0, r3 : R2, r4 : R3}];
+    for div in divs.iter() {
+        exec::<V, _>(4, |p| {
+            let program = [lit::<V>(5, R1), *div, Halt];
+            assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::DivByZero{pc : 1}));
+        });
+    }
+}
+
+fn fixed_point<V : Vm>() {
+    exec::<V, _>(4, |p| {
+        assert!(p.set_q_format(V::BITS).is_err());
+        p.set_q_format(4).unwrap();
+        // 2.5 * -1.5 = -3.75 and 2.5 / -1.5 = -1.666.. in Q.4
+        let program = [lit::<V>(40, R1), lit::<V>(-24, R2),
+                       Sm2{op : M2Op::FxMul, r1 : R1, r2 : R2, r3 : R3},
+                       Sm2{op : M2Op::FxDiv, r1 : R1, r2 : R2, r3 : R4},
+                       lit::<V>(-1, R5),
+                       Um2{op : M2Op::FxMul, r1 : R5, r2 : R5, r3 : R6},
+                       Um2{op : M2Op::FxMulSat, r1 : R5, r2 : R5, r3 : R7},
+                       Sm2{op : M2Op::FxMulSat, r1 : R3, r2 : R3, r3 : R8},
+                       Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!(p.reg(R3), w::<V>(-60));
+        assert_eq!(p.reg(R4), w::<V>(-26));
+        // (2^n - 1)^2 >> 4, truncated to n bits
+        assert_eq!(p.reg(R6), w::<V>(-(1 << (V::BITS - 3))));
+        assert_eq!(p.reg(R7), mask::<V>());
+        assert_eq!(p.reg(R8), 225);
+    });
+}
+
+fn carry_chains<V : Vm>() {
+    exec::<V, _>(4, |p| {
+        let program = [lit::<V>(-1, R1), lit::<V>(1, R2),
+                       // R1:R1 + 0:1 = 1:0, low word first
+                       Um4{op : M4Op::AddC, r1 : R1, r2 : R2, r3 : R3, r4 : R4},
+                       Um4{op : M4Op::AddC, r1 : R1, r2 : R0, r3 : R5, r4 : R4},
+                       // 0 - 1 borrows
+                       lit::<V>(0, R6),
+                       Sm4{op : M4Op::SubB, r1 : R0, r2 : R2, r3 : R7, r4 : R6},
+                       lit::<V>(-7, R8), lit::<V>(2, R9),
+                       Sm4{op : M4Op::DivRem, r1 : R8, r2 : R9, r3 : RA, r4 : RB},
+                       Um4{op : M4Op::DivRem, r1 : R8, r2 : R9, r3 : RC, r4 : RD},
+                       Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!((p.reg(R3), p.reg(R5), p.reg(R4)), (0, 0, 1));
+        assert_eq!((p.reg(R7), p.reg(R6)), (mask::<V>(), 1));
+        assert_eq!((p.reg(RA), p.reg(RB)), (w::<V>(-3), w::<V>(-1)));
+        assert_eq!((p.reg(RC), p.reg(RD)), (w::<V>(-7) / 2, 1));
+    });
+}
+
+// Whether each condition holds for a value that compares to zero (or the
+// second register) as less, equal and greater.
+const CONDS : [(Cond, [bool; 3]); 7] = [
+    (Cond::Always, [true, true, true]),
+    (Cond::EqZ, [false, true, false]),
+    (Cond::NeZ, [true, false, true]),
+    (Cond::GtZ, [false, false, true]),
+    (Cond::LtZ, [true, false, false]),
+    (Cond::GeZ, [false, true, true]),
+    (Cond::LeZ, [true, true, false])
+];
+
+// Flag values and how they compare to zero unsigned and signed.
+// 0 = less, 1 = equal, 2 = greater.
+const FLAGS : [(i64, usize, usize); 3] = [(0, 1, 1), (1, 2, 2), (-1, 2, 0)];
+
+fn conditions<V : Vm>() {
+    for &(cond, taken) in CONDS.iter() {
+        for &(flag, uord, sord) in FLAGS.iter() {
+            let forms : [(Instruction<V::U>, usize); 8] = [
+                (UJump{cond, flag : R1, dest : R2}, uord),
+                (SJump{cond, flag : R1, dest : R2}, sord),
+                (UJumpI{cond, flag : R1, mode : Addr::Abs, target : imm(6)}, uord),
+                (SJumpI{cond, flag : R1, mode : Addr::Rel, target : imm(3)}, sord),
+                (UJumpCmp{cond, r1 : R1, r2 : R0, dest : R2}, uord),
+                (SJumpCmp{cond, r1 : R1, r2 : R0, dest : R2}, sord),
+                (USelect{cond, flag : R1, r1 : R4, r2 : R0, r3 : R3}, uord),
+                (SSelect{cond, flag : R1, r1 : R4, r2 : R0, r3 : R3}, sord)
+            ];
+            for &(form, ord) in forms.iter() {
+                exec::<V, _>(4, |p| {
+                    // Jumps land on 6 and set R3; selects pick R4 = 1 over R0
+                    let select = matches!(form, USelect{..} | SSelect{..});
+                    let program = [lit::<V>(flag, R1), lit::<V>(6, R2), lit::<V>(1, R4),
+                                   form,
+                                   if select {Halt} else {lit::<V>(0, R3)}, Halt,
+                                   lit::<V>(1, R3), Halt];
+                    assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+                    assert_eq!(p.reg(R3) == 1, taken[ord], "{:?} with flag {}", form, flag);
+                });
+            }
+        }
+    }
+}
+
+fn relative_jumps<V : Vm>() {
+    exec::<V, _>(4, |p| {
+        let program = [UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Rel, target : imm(3)},
+                       Halt,
+                       Out{reg : R0, port : imm(1)},
+                       UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Rel, target : imm(-1)}];
+        assert_eq!(p.run(&program, FUEL), Outcome::Out{port : 1, out : 0});
+        assert_eq!(p.run(&program, FUEL), Outcome::Out{port : 1, out : 0});
+    });
+}
+
+// The address and direction of the access that fails, if any
+type Failing = Option<(i64, Dir)>;
+
+// A block instruction built from its three register operands, then dst or a,
+// src or b, len, and the failing access
+type BlockCase<U> = (fn(Reg, Reg, Reg) -> Instruction<U>, i64, i64, i64, Failing);
+
+fn ram_bounds<V : Vm>() {
+    let n = 16;
+    let bytes = n * V::BITS as i64 / 8;
+    let cases : [(Instruction<V::U>, i64, Failing); 12] = [
+        (Ram{dir : Dir::Read, ptr : R1, val : R2}, n - 1, None),
+        (Ram{dir : Dir::Read, ptr : R1, val : R2}, n, Some((n, Dir::Read))),
+        (Ram{dir : Dir::Write, ptr : R1, val : R2}, n, Some((n, Dir::Write))),
+        (Ram{dir : Dir::Write, ptr : R1, val : R2}, -1, Some((-1, Dir::Write))),
+        (RamI{dir : Dir::Read, ptr : R1, off : imm(1), val : R2}, -1, None),
+        (RamI{dir : Dir::Write, ptr : R1, off : imm(-2), val : R2}, n + 1, None),
+        (RamI{dir : Dir::Write, ptr : R1, off : imm(1), val : R2}, n - 1, Some((n, Dir::Write))),
+        (ULoad{width : Width::Byte, ptr : R1, val : R2}, bytes - 1, None),
+        (SLoad{width : Width::Half, ptr : R1, val : R2}, bytes - 1, Some((bytes - 1, Dir::Read))),
+        (Store{width : Width::Half, ptr : R1, val : R2}, bytes - 2, None),
+        (Store{width : Width::Byte, ptr : R1, val : R2}, bytes, Some((bytes, Dir::Write))),
+        (ULoad{width : Width::Half, ptr : R1, val : R2}, -1, Some((-1, Dir::Read)))
+    ];
+    for &(instr, addr, fail) in cases.iter() {
+        exec::<V, _>(n as usize, |p| {
+            let program = [lit::<V>(addr, R1), instr, Halt];
+            let expect = match fail {
+                None => Outcome::Halt,
+                Some((a, dir)) => Outcome::Fail(Failure::RamOob{pc : 1, addr : w::<V>(a), dir})
+            };
+            assert_eq!(p.run(&program, FUEL), expect, "{:?} at {}", instr, addr);
+        });
+    }
+}
+
+fn block_bounds<V : Vm>() {
+    let n = 16;
+    let cases : [BlockCase<V::U>; 8] = [
+        (|a, b, l| MemCopy{dst : a, src : b, len : l}, 8, 0, 8, None),
+        (|a, b, l| MemCopy{dst : a, src : b, len : l}, 8, 0, 9, Some((8, Dir::Write))),
+        (|a, b, l| MemCopy{dst : a, src : b, len : l}, 0, 8, 9, Some((8, Dir::Read))),
+        (|a, b, l| MemCopy{dst : a, src : b, len : l}, 9, 9, 9, Some((9, Dir::Read))),
+        (|a, _, l| MemFill{dst : a, val : R0, len : l}, n, 0, 0, None),
+        (|a, _, l| MemFill{dst : a, val : R0, len : l}, 1, 0, -1, Some((1, Dir::Write))),
+        (|a, b, l| MemCmp{a, b, len : l, res : R4}, 0, 15, 1, None),
+        (|a, b, l| MemCmp{a, b, len : l, res : R4}, 0, 15, 2, Some((15, Dir::Read)))
+    ];
+    for &(make, a, b, len, fail) in cases.iter() {
+        exec::<V, _>(n as usize, |p| {
+            let program = [lit::<V>(a, R1), lit::<V>(b, R2), lit::<V>(len, R3), make(R1, R2, R3), Halt];
+            let expect = match fail {
+                None => Outcome::Halt,
+                Some((at, dir)) => Outcome::Fail(Failure::RamOob{pc : 3, addr : w::<V>(at), dir})
+            };
+            assert_eq!(p.run(&program, FUEL), expect, "{:?}", make(R1, R2, R3));
+        });
+    }
+}
+
+fn blocks<V : Vm>() {
+    exec::<V, _>(8, |p| {
+        let program = [lit::<V>(7, R1), lit::<V>(0, R2), lit::<V>(4, R3),
+                       MemFill{dst : R2, val : R1, len : R3},
+                       // Overlapping copy behaves like memmove
+                       lit::<V>(1, R4), lit::<V>(5, R5), Ram{dir : Dir::Write, ptr : R0, val : R5},
+                       MemCopy{dst : R4, src : R2, len : R3},
+                       MemCmp{a : R2, b : R4, len : R3, res : R6},
+                       MemCmp{a : R4, b : R2, len : R3, res : R7},
+                       MemCmp{a : R4, b : R4, len : R3, res : R8},
+                       Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!(&p.model.ram[..], &[5, 5, 7, 7, 7, 0, 0, 0]);
+        assert_eq!((p.reg(R6), p.reg(R7), p.reg(R8)), (mask::<V>(), 1, 0));
+    });
+}
+
+fn block_fuel<V : Vm>() {
+    exec::<V, _>(64, |p| {
+        let program = [lit::<V>(50, R1), MemFill{dst : R0, val : R0, len : R1}, Halt];
+        // The fill overdraws the 5 units left, then the slice ends
+        assert_eq!(p.run(&program, 6), Outcome::Thrash);
+        assert_eq!(p.model.pc, 2);
+    });
+}
+
+fn byte_order<V : Vm>() {
+    exec::<V, _>(2, |p| {
+        let program = [lit::<V>(0x12, R1), lit::<V>(0xFF80, R2), lit::<V>(1, R3),
+                       Store{width : Width::Byte, ptr : R0, val : R1},
+                       Store{width : Width::Half, ptr : R3, val : R2},
+                       ULoad{width : Width::Half, ptr : R3, val : R4},
+                       SLoad{width : Width::Half, ptr : R3, val : R5},
+                       SLoad{width : Width::Byte, ptr : R0, val : R6},
+                       Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!(p.model.ram[0] >> (V::BITS - 24), 0x12FF80);
+        assert_eq!((p.reg(R4), p.reg(R5), p.reg(R6)), (0xFF80, w::<V>(-128), 0x12));
+    });
+}
+
+fn stack<V : Vm>() {
+    exec::<V, _>(16, |p| {
+        let program = [Push{reg : R1}, Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::StackOverflow{pc : 0}));
+    });
+    exec::<V, _>(16, |p| {
+        assert!(p.set_stack(13, 17).is_err());
+        assert!(p.set_stack(14, 13).is_err());
+        p.set_stack(13, 16).unwrap();
+        let program = [lit::<V>(1, R1), Push{reg : R1}, lit::<V>(2, R1), Push{reg : R1},
+                       lit::<V>(3, R1), Push{reg : R1}, Halt,
+                       Pop{reg : R2}, Pop{reg : R3}, Pop{reg : R4}, Halt,
+                       Push{reg : R0}, Push{reg : R0}, Push{reg : R0}, Push{reg : R0}];
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!(p.reg(SP), 13);
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!((p.reg(R2), p.reg(R3), p.reg(R4), p.reg(SP)), (3, 2, 1, 16));
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::StackOverflow{pc : 14}));
+    });
+    exec::<V, _>(16, |p| {
+        p.set_stack(13, 16).unwrap();
+        let program = [Pop{reg : R1}];
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::StackUnderflow{pc : 0}));
+    });
+}
+
+fn calls<V : Vm>() {
+    exec::<V, _>(8, |p| {
+        let program = [lit::<V>(3, R1), lit::<V>(4, R2), lit::<V>(6, R3), lit::<V>(2, R4),
+                       Call{major : R1, minor : R2, arg : R3, len : R4, res : R5},
+                       Out{reg : R5, port : imm(0)},
+                       lit::<V>(7, R4),
+                       Call{major : R1, minor : R2, arg : R3, len : R4, res : R5},
+                       lit::<V>(-1, R4),
+                       Call{major : R1, minor : R2, arg : R3, len : R4, res : R5}];
+        p.cancel_calls();
+        assert_eq!(p.run(&program, FUEL), Outcome::Call{major : 3, minor : 4, slice : vec![0, 0]});
+        assert_eq!(p.model.pc, 4);
+        p.reply_with(9, &[1, 2]);
+        assert_eq!(p.run(&program, FUEL), Outcome::Call{major : 3, minor : 4, slice : vec![0, 0]});
+        assert_eq!(p.run(&program, FUEL), Outcome::Out{port : 0, out : 9});
+        assert_eq!(&p.model.ram[6..], &[1, 2]);
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::CallOverflow));
+    });
+    exec::<V, _>(8, |p| {
+        let program = [lit::<V>(2, R3), lit::<V>(-1, R4),
+                       Call{major : R1, minor : R2, arg : R3, len : R4, res : R5}];
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::CallUnderflow));
+    });
+}
+
+fn input_output<V : Vm>() {
+    exec::<V, _>(4, |p| {
+        let program = [In{reg : R1, port : imm(7)}, Out{reg : R1, port : imm(-1)}, Halt];
+        assert_eq!(p.run(&program, FUEL), Outcome::In{port : 7});
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::InputPending{pc : 1}));
+        p.resume_in(42);
+        assert_eq!(p.run(&program, FUEL), Outcome::Out{port : mask::<V>(), out : 42});
+        assert_eq!(p.run(&program, FUEL), Outcome::Halt);
+        assert_eq!(p.run(&program, FUEL), Outcome::Fail(Failure::CodeOob{pc : 3}));
+    });
+}
+
+fn invalid<V : Vm>() {
+    exec::<V, _>(4, |p| {
+        assert_eq!(p.run(&[Invalid], FUEL), Outcome::Fail(Failure::InvalidInstruction));
+    });
+    exec::<V, _>(4, |p| {
+        assert_eq!(p.run(&[Iret], FUEL), Outcome::Fail(Failure::BadIret{pc : 0}));
+    });
+}
+
+fn interrupts<V : Vm>() {
+    exec::<V, _>(48, |p| {
+        // Handler at 6 reports the interrupt number and returns
+        let program = [lit::<V>(6, R1), lit::<V>(5, R2), Ram{dir : Dir::Write, ptr : R2, val : R1},
+                       lit::<V>(1, R3), IntEn{flag : R3},
+                       UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Rel, target : imm(0)},
+                       Out{reg : R0, port : imm(2)}, lit::<V>(99, R3), Iret];
+        assert!(p.raise(5).is_err());
+        assert!(p.set_vectors(17).is_err());
+        p.set_vectors(0).unwrap();
+        assert!(p.raise(32).is_err());
+        assert_eq!(p.run(&program, 10), Outcome::Thrash);
+        assert_eq!(p.model.pc, 5);
+        p.raise(5).unwrap();
+        p.raise(31).unwrap();
+        assert_eq!(p.run(&program, FUEL), Outcome::Out{port : 2, out : 5});
+        assert_eq!(p.model.pending, 1 << 31);
+        // Back in the loop with R3 restored; 31 has no handler and jumps to 0
+        assert_eq!(p.run(&program, 2), Outcome::Thrash);
+        assert_eq!((p.model.pc, p.reg(R3)), (5, 1));
+        assert_eq!(p.run(&program, 1), Outcome::Thrash);
+        assert_eq!((p.model.pc, p.reg(R0)), (1, 31));
+    });
+}
+
+macro_rules! both_widths {
+    ($($name:ident),*) => {
+        mod vm32 {
+            $( #[test] fn $name() { super::$name::<super::Vm32>() } )*
+        }
+        mod vm64 {
+            $( #[test] fn $name() { super::$name::<super::Vm64>() } )*
+        }
+    }
+}
+
+both_widths!(m2_unsigned_and_signed, arithmetic_wraps, division_by_zero_fails, fixed_point,
+             carry_chains, conditions, relative_jumps, ram_bounds, block_bounds, blocks,
+             block_fuel, byte_order, stack, calls, input_output, invalid, interrupts);
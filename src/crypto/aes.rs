@@ -0,0 +1,173 @@
+// FIPS 197 AES-128/256, encryption direction only (CTR and GCM never need the
+// inverse cipher), plus SP 800-38A CTR mode.
+// The S-box is a table lookup, so this is not hardened against cache-timing
+// attacks on parts with a data cache.
+
+const SBOX : [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16
+];
+
+const RCON : [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+pub const BLOCK_LEN : usize = 16;
+
+#[derive(Clone)]
+pub struct Aes {
+    rk : [u32; 60],
+    rounds : usize
+}
+
+fn sub_word(w : u32) -> u32 {
+    (SBOX[(w >> 24) as usize] as u32) << 24 | (SBOX[(w >> 16 & 0xff) as usize] as u32) << 16 |
+    (SBOX[(w >> 8 & 0xff) as usize] as u32) << 8 | SBOX[(w & 0xff) as usize] as u32
+}
+
+fn xtime(b : u8) -> u8 {
+    (b << 1) ^ (0x1b & (b >> 7).wrapping_neg())
+}
+
+impl Aes {
+    // Accepts 16- and 32-byte keys.
+    pub fn new(key : &[u8]) -> Option<Aes> {
+        let (nk, rounds) = match key.len() {
+            16 => (4, 10),
+            32 => (8, 14),
+            _ => return None
+        };
+        let mut rk = [0u32; 60];
+        for i in 0..nk {
+            rk[i] = (key[4*i] as u32) << 24 | (key[4*i+1] as u32) << 16 | (key[4*i+2] as u32) << 8 | key[4*i+3] as u32;
+        }
+        for i in nk..4 * (rounds + 1) {
+            let mut t = rk[i-1];
+            if i % nk == 0 {
+                t = sub_word(t.rotate_left(8)) ^ (RCON[i / nk - 1] as u32) << 24;
+            } else if nk > 6 && i % nk == 4 {
+                t = sub_word(t);
+            }
+            rk[i] = rk[i-nk] ^ t;
+        }
+        Some(Aes { rk, rounds })
+    }
+
+    fn add_round_key(&self, s : &mut [u8; BLOCK_LEN], round : usize) {
+        for c in 0..4 {
+            let k = self.rk[4 * round + c];
+            s[4*c]   ^= (k >> 24) as u8;
+            s[4*c+1] ^= (k >> 16) as u8;
+            s[4*c+2] ^= (k >> 8) as u8;
+            s[4*c+3] ^= k as u8;
+        }
+    }
+
+    pub fn encrypt_block(&self, s : &mut [u8; BLOCK_LEN]) {
+        self.add_round_key(s, 0);
+        for round in 1..self.rounds + 1 {
+            let mut t = [0u8; BLOCK_LEN];
+            // SubBytes + ShiftRows
+            for c in 0..4 {
+                for r in 0..4 {
+                    t[4*c+r] = SBOX[s[4*((c+r) % 4)+r] as usize];
+                }
+            }
+            if round != self.rounds {
+                for c in 0..4 {
+                    let col = [t[4*c], t[4*c+1], t[4*c+2], t[4*c+3]];
+                    let all = col[0] ^ col[1] ^ col[2] ^ col[3];
+                    for r in 0..4 {
+                        t[4*c+r] = col[r] ^ all ^ xtime(col[r] ^ col[(r+1) % 4]);
+                    }
+                }
+            }
+            *s = t;
+            self.add_round_key(s, round);
+        }
+    }
+}
+
+// Counter mode with the whole 16-byte block as a big-endian counter.
+// Encryption and decryption are the same operation.
+pub struct Ctr {
+    cipher : Aes,
+    counter : [u8; BLOCK_LEN]
+}
+
+pub fn increment(counter : &mut [u8]) {
+    for b in counter.iter_mut().rev() {
+        *b = b.wrapping_add(1);
+        if *b != 0 {
+            return
+        }
+    }
+}
+
+impl Ctr {
+    pub fn new(cipher : Aes, counter : [u8; BLOCK_LEN]) -> Ctr {
+        Ctr { cipher, counter }
+    }
+
+    // Blocks must be BLOCK_LEN long except for the last one.
+    pub fn apply_block(&mut self, block : &mut [u8]) {
+        let mut ks = self.counter;
+        self.cipher.encrypt_block(&mut ks);
+        increment(&mut self.counter);
+        for (b, k) in block.iter_mut().zip(ks.iter()) {
+            *b ^= *k;
+        }
+    }
+
+    pub fn counter(&self) -> [u8; BLOCK_LEN] {
+        self.counter
+    }
+
+    pub fn apply(&mut self, data : &mut [u8]) {
+        for chunk in data.chunks_mut(BLOCK_LEN) {
+            self.apply_block(chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::tests::hex;
+
+    // FIPS 197 appendix C
+    #[test]
+    fn fips197_vectors() {
+        let mut block = hex::<16>("00112233445566778899aabbccddeeff");
+        Aes::new(&hex::<16>("000102030405060708090a0b0c0d0e0f")).unwrap().encrypt_block(&mut block);
+        assert_eq!(block, hex::<16>("69c4e0d86a7b0430d8cdb78070b4c55a"));
+        let mut block = hex::<16>("00112233445566778899aabbccddeeff");
+        Aes::new(&hex::<32>("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")).unwrap().encrypt_block(&mut block);
+        assert_eq!(block, hex::<16>("8ea2b7ca516745bfeafc49904b496089"));
+    }
+
+    // SP 800-38A F.5.1 and F.5.5
+    #[test]
+    fn sp800_38a_ctr() {
+        let pt = hex::<64>("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710");
+        let ctr = hex::<16>("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let mut data = pt;
+        Ctr::new(Aes::new(&hex::<16>("2b7e151628aed2a6abf7158809cf4f3c")).unwrap(), ctr).apply(&mut data);
+        assert_eq!(data[..], hex::<64>("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee")[..]);
+        let mut data = pt;
+        Ctr::new(Aes::new(&hex::<32>("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4")).unwrap(), ctr).apply(&mut data);
+        assert_eq!(data[..], hex::<64>("601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c52b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6")[..]);
+    }
+}
@@ -0,0 +1,61 @@
+// RFC 8439 ChaCha20 block function.
+
+pub const KEY_LEN : usize = 32;
+pub const NONCE_LEN : usize = 12;
+pub const BLOCK_LEN : usize = 64;
+
+fn quarter(s : &mut [u32; 16], a : usize, b : usize, c : usize, d : usize) {
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+fn le(b : &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+pub fn block(key : &[u8; KEY_LEN], counter : u32, nonce : &[u8; NONCE_LEN]) -> [u8; BLOCK_LEN] {
+    let mut init = [0u32; 16];
+    init[0] = 0x61707865;
+    init[1] = 0x3320646e;
+    init[2] = 0x79622d32;
+    init[3] = 0x6b206574;
+    for i in 0..8 {
+        init[4 + i] = le(&key[4*i..]);
+    }
+    init[12] = counter;
+    for i in 0..3 {
+        init[13 + i] = le(&nonce[4*i..]);
+    }
+    let mut s = init;
+    for _ in 0..10 {
+        quarter(&mut s, 0, 4, 8, 12);
+        quarter(&mut s, 1, 5, 9, 13);
+        quarter(&mut s, 2, 6, 10, 14);
+        quarter(&mut s, 3, 7, 11, 15);
+        quarter(&mut s, 0, 5, 10, 15);
+        quarter(&mut s, 1, 6, 11, 12);
+        quarter(&mut s, 2, 7, 8, 13);
+        quarter(&mut s, 3, 4, 9, 14);
+    }
+    let mut out = [0u8; BLOCK_LEN];
+    for i in 0..16 {
+        out[4*i..4*i+4].copy_from_slice(&s[i].wrapping_add(init[i]).to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::tests::hex;
+
+    // RFC 8439 section 2.3.2
+    #[test]
+    fn rfc8439_block() {
+        let key = hex::<32>("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let nonce = hex::<12>("000000090000004a00000000");
+        assert_eq!(block(&key, 1, &nonce)[..], hex::<64>("10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e")[..]);
+    }
+}
@@ -0,0 +1,176 @@
+// SP 800-38D AES-GCM with 96-bit nonces and 128-bit tags.
+// Streaming: feed the AAD and then the text in BLOCK_LEN chunks, where only
+// the last chunk of each may be short.
+
+use crypto::aes::{self, Aes, BLOCK_LEN};
+use crypto::ct_eq;
+
+pub const NONCE_LEN : usize = 12;
+pub const TAG_LEN : usize = 16;
+
+pub struct Gcm {
+    cipher : Aes,
+    h : u128,
+    j0 : [u8; BLOCK_LEN],
+    ctr : [u8; BLOCK_LEN],
+    acc : u128,
+    aad_len : u64,
+    text_len : u64
+}
+
+fn to_u128(block : &[u8]) -> u128 {
+    let mut buf = [0u8; BLOCK_LEN];
+    buf[..block.len()].copy_from_slice(block);
+    buf.iter().fold(0, |acc, b| acc << 8 | *b as u128)
+}
+
+fn from_u128(x : u128) -> [u8; BLOCK_LEN] {
+    let mut out = [0u8; BLOCK_LEN];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = (x >> (120 - 8 * i)) as u8;
+    }
+    out
+}
+
+// Multiplication in GF(2^128) with GCM's reflected bit order. Branch-free.
+fn gf_mul(x : u128, y : u128) -> u128 {
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        z ^= v & ((x >> (127 - i)) & 1).wrapping_neg();
+        v = (v >> 1) ^ ((0xe1 << 120) & (v & 1).wrapping_neg());
+    }
+    z
+}
+
+fn inc32(block : &mut [u8; BLOCK_LEN]) {
+    aes::increment(&mut block[BLOCK_LEN - 4..]);
+}
+
+impl Gcm {
+    pub fn new(key : &[u8], nonce : &[u8; NONCE_LEN]) -> Option<Gcm> {
+        Aes::new(key).map(|cipher| {
+            let mut h = [0u8; BLOCK_LEN];
+            cipher.encrypt_block(&mut h);
+            let mut j0 = [0u8; BLOCK_LEN];
+            j0[..NONCE_LEN].copy_from_slice(nonce);
+            j0[BLOCK_LEN - 1] = 1;
+            let mut ctr = j0;
+            inc32(&mut ctr);
+            Gcm { cipher, h : to_u128(&h), j0, ctr, acc : 0, aad_len : 0, text_len : 0 }
+        })
+    }
+
+    fn absorb(&mut self, block : &[u8]) {
+        self.acc = gf_mul(self.acc ^ to_u128(block), self.h);
+    }
+
+    pub fn aad_block(&mut self, block : &[u8]) {
+        self.aad_len += block.len() as u64;
+        self.absorb(block);
+    }
+
+    fn keystream(&mut self, block : &mut [u8]) {
+        let mut ks = self.ctr;
+        self.cipher.encrypt_block(&mut ks);
+        inc32(&mut self.ctr);
+        for (b, k) in block.iter_mut().zip(ks.iter()) {
+            *b ^= *k;
+        }
+        self.text_len += block.len() as u64;
+    }
+
+    pub fn encrypt_block(&mut self, block : &mut [u8]) {
+        self.keystream(block);
+        self.absorb(block);
+    }
+
+    pub fn decrypt_block(&mut self, block : &mut [u8]) {
+        self.absorb(block);
+        self.keystream(block);
+    }
+
+    pub fn tag(mut self) -> [u8; TAG_LEN] {
+        let lens = ((self.aad_len as u128 * 8) << 64) | (self.text_len as u128 * 8);
+        self.acc = gf_mul(self.acc ^ lens, self.h);
+        let mut s = self.j0;
+        self.cipher.encrypt_block(&mut s);
+        from_u128(self.acc ^ to_u128(&s))
+    }
+}
+
+pub fn seal(key : &[u8], nonce : &[u8; NONCE_LEN], aad : &[u8], data : &mut [u8]) -> Option<[u8; TAG_LEN]> {
+    Gcm::new(key, nonce).map(|mut gcm| {
+        for chunk in aad.chunks(BLOCK_LEN) {
+            gcm.aad_block(chunk);
+        }
+        for chunk in data.chunks_mut(BLOCK_LEN) {
+            gcm.encrypt_block(chunk);
+        }
+        gcm.tag()
+    })
+}
+
+// Decrypts in place if the tag checks out. On failure the buffer is zeroed.
+pub fn open(key : &[u8], nonce : &[u8; NONCE_LEN], aad : &[u8], data : &mut [u8], tag : &[u8; TAG_LEN]) -> bool {
+    match Gcm::new(key, nonce) {
+        None => false,
+        Some(mut gcm) => {
+            for chunk in aad.chunks(BLOCK_LEN) {
+                gcm.aad_block(chunk);
+            }
+            for chunk in data.chunks_mut(BLOCK_LEN) {
+                gcm.decrypt_block(chunk);
+            }
+            if ct_eq(&gcm.tag(), tag) {
+                true
+            } else {
+                for b in data.iter_mut() {
+                    *b = 0;
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::tests::hex;
+
+    // GCM spec (McGrew & Viega) test cases 2, 4 and 16, as used in NIST CAVP gcmEncryptExtIV
+    #[test]
+    fn nist_vectors() {
+        let mut data = hex::<16>("00000000000000000000000000000000");
+        let tag = seal(&[0; 16], &[0; 12], &[], &mut data).unwrap();
+        assert_eq!(data, hex::<16>("0388dace60b6a392f328c2b971b2fe78"));
+        assert_eq!(tag, hex::<16>("ab6e47d42cec13bdf53a67b21257bddf"));
+
+        let key = hex::<16>("feffe9928665731c6d6a8f9467308308");
+        let nonce = hex::<12>("cafebabefacedbaddecaf888");
+        let aad = hex::<20>("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let pt = hex::<60>("d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39");
+        let mut data = pt;
+        let tag = seal(&key, &nonce, &aad, &mut data).unwrap();
+        assert_eq!(data[..], hex::<60>("42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091")[..]);
+        assert_eq!(tag, hex::<16>("5bc94fbc3221a5db94fae95ae7121a47"));
+        assert!(open(&key, &nonce, &aad, &mut data, &tag));
+        assert_eq!(data[..], pt[..]);
+
+        let key = hex::<32>("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308");
+        let mut data = pt;
+        let tag = seal(&key, &nonce, &aad, &mut data).unwrap();
+        assert_eq!(data[..], hex::<60>("522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662")[..]);
+        assert_eq!(tag, hex::<16>("76fc6ece0f4e1768cddf8853bb2d551b"));
+    }
+
+    #[test]
+    fn open_rejects_forgery() {
+        let mut data = [1u8; 20];
+        let mut tag = seal(&[7; 16], &[3; 12], b"hdr", &mut data).unwrap();
+        tag[0] ^= 1;
+        assert!(!open(&[7; 16], &[3; 12], b"hdr", &mut data, &tag));
+        assert_eq!(data, [0u8; 20]);
+    }
+}
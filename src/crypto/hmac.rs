@@ -0,0 +1,64 @@
+// RFC 2104 HMAC over SHA-256.
+
+use crypto::sha256::{self, Sha256, BLOCK_LEN, DIGEST_LEN};
+
+#[derive(Clone)]
+pub struct HmacSha256 {
+    inner : Sha256,
+    outer : Sha256
+}
+
+impl HmacSha256 {
+    pub fn new(key : &[u8]) -> HmacSha256 {
+        let mut block = [0u8; BLOCK_LEN];
+        if key.len() > BLOCK_LEN {
+            block[..DIGEST_LEN].copy_from_slice(&sha256::digest(key));
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+        let mut ipad = [0x36u8; BLOCK_LEN];
+        let mut opad = [0x5cu8; BLOCK_LEN];
+        for i in 0..BLOCK_LEN {
+            ipad[i] ^= block[i];
+            opad[i] ^= block[i];
+        }
+        let mut inner = Sha256::new();
+        inner.update(&ipad);
+        let mut outer = Sha256::new();
+        outer.update(&opad);
+        HmacSha256 { inner, outer }
+    }
+
+    pub fn update(&mut self, data : &[u8]) {
+        self.inner.update(data)
+    }
+
+    pub fn finish(self) -> [u8; DIGEST_LEN] {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finish());
+        outer.finish()
+    }
+}
+
+pub fn mac(key : &[u8], data : &[u8]) -> [u8; DIGEST_LEN] {
+    let mut h = HmacSha256::new(key);
+    h.update(data);
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::tests::hex;
+
+    // RFC 4231 test cases 1, 2 and 6
+    #[test]
+    fn rfc4231_vectors() {
+        assert_eq!(mac(&[0x0b; 20], b"Hi There")[..],
+                   hex::<32>("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7")[..]);
+        assert_eq!(mac(b"Jefe", b"what do ya want for nothing?")[..],
+                   hex::<32>("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")[..]);
+        assert_eq!(mac(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")[..],
+                   hex::<32>("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")[..]);
+    }
+}
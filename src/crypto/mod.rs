@@ -0,0 +1,30 @@
+// Host-side cryptographic primitives backing the call libraries.
+// Everything here works on plain byte slices without allocating.
+
+pub mod sha256;
+pub mod hmac;
+pub mod aes;
+pub mod gcm;
+pub mod chacha20;
+
+// Compares two byte strings without branching on their contents.
+// Only the lengths leak.
+pub fn ct_eq(a : &[u8], b : &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false
+    }
+    let diff = a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    diff == 0
+}
+
+#[cfg(test)]
+pub mod tests {
+    pub fn hex<const N : usize>(s : &str) -> [u8; N] {
+        assert_eq!(s.len(), 2 * N);
+        let mut out = [0u8; N];
+        for i in 0..N {
+            out[i] = u8::from_str_radix(&s[2*i..2*i+2], 16).unwrap();
+        }
+        out
+    }
+}
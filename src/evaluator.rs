@@ -1,3 +1,4 @@
+use core::cmp::Ordering;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Reg {
@@ -40,22 +41,64 @@ impl Fin for Reg {
 
 impl<T : Fin + Eq> Write for T where {
     fn write<Sink:WriteSink>(&self,sink:&mut Sink) {
+        // An element missing from ARR would be a bug in its Fin impl. Rather
+        // than panic, write an index that read() rejects.
         match T::ARR.iter().position(|x| x == self) {
-            None => panic!("Element is not present in its finite array."),
+            None => sink.write(T::ARR.len() as u8),
             Some(pos) => sink.write(pos as u8)
         }
     }
 }
 
+// The Fx ops are fixed-point multiply and divide on numbers with the number
+// of fractional bits set by State::set_q_format. Products and quotients are
+// computed at double width, then truncated (FxMul, FxDiv) or clamped to the
+// representable range (FxMulSat, FxDivSat).
+// MulHi is the upper word of the double-width product.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum M2Op {
-    Add, Sub, Mul, Div, Equ, Lt, Gt
+    Add, Sub, Mul, Div, Equ, Lt, Gt, FxMul, FxDiv, FxMulSat, FxDivSat, MulHi
 }
 
 impl Fin for M2Op {
     const ARR : &'static [M2Op] = 
         &[M2Op::Add,M2Op::Sub,M2Op::Mul,
-          M2Op::Div,M2Op::Equ,M2Op::Lt,M2Op::Gt];
+          M2Op::Div,M2Op::Equ,M2Op::Lt,M2Op::Gt,
+          M2Op::FxMul,M2Op::FxDiv,M2Op::FxMulSat,M2Op::FxDivSat,
+          M2Op::MulHi];
+}
+
+// Two-result operations for multi-word arithmetic, writing r3 and r4.
+// AddC: r3 = r1 + r2 + c, where c is the low bit of r4; r4 = carry out (0 or 1).
+// SubB: r3 = r1 - r2 - b, where b is the low bit of r4; r4 = borrow out (0 or 1).
+// DivRem: r3 = r1 / r2, r4 = r1 % r2.
+// Carries are on the bit patterns, so AddC and SubB behave the same under
+// Um4 and Sm4. DivRem under Sm4 truncates towards zero.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum M4Op {
+    AddC, SubB, DivRem
+}
+
+impl Fin for M4Op {
+    const ARR : &'static [M4Op] = &[M4Op::AddC, M4Op::SubB, M4Op::DivRem];
+}
+
+// Floating point operations, with the bits of a word read as an IEEE 754
+// binary32 on 32-bit VMs and binary64 on 64-bit ones. Equ/Lt/Gt give 1 or 0
+// as an integer, and are false if either side is NaN. FromInt converts a
+// signed integer in r1 to float; ToInt truncates the float in r1 to a signed
+// integer, saturating, with NaN going to 0. Both ignore r2.
+#[cfg(feature = "float")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FOp {
+    Add, Sub, Mul, Div, Equ, Lt, Gt, FromInt, ToInt
+}
+
+#[cfg(feature = "float")]
+impl Fin for FOp {
+    const ARR : &'static [FOp] =
+        &[FOp::Add,FOp::Sub,FOp::Mul,FOp::Div,
+          FOp::Equ,FOp::Lt,FOp::Gt,FOp::FromInt,FOp::ToInt];
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -77,13 +120,56 @@ impl Fin for Sign {
 }
 
 
+// Conditions test a flag register against zero, or for the compare forms
+// the first register against the second. Unsigned instructions compare
+// unsigned, so UJump's LtZ never fires and GeZ always does.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Cond {
-    Always, EqZ, GtZ, LtZ
+    Always, EqZ, GtZ, LtZ, NeZ, GeZ, LeZ
 }
 
 impl Fin for Cond {
-    const ARR : &'static [Cond] = &[Cond::Always, Cond::EqZ, Cond::GtZ, Cond::LtZ];
+    const ARR : &'static [Cond] =
+        &[Cond::Always, Cond::EqZ, Cond::GtZ, Cond::LtZ,
+          Cond::NeZ, Cond::GeZ, Cond::LeZ];
+}
+
+impl Cond {
+    pub fn holds(self, ord : Ordering) -> bool {
+        match self {
+            Cond::Always => true,
+            Cond::EqZ => ord == Ordering::Equal,
+            Cond::GtZ => ord == Ordering::Greater,
+            Cond::LtZ => ord == Ordering::Less,
+            Cond::NeZ => ord != Ordering::Equal,
+            Cond::GeZ => ord != Ordering::Less,
+            Cond::LeZ => ord != Ordering::Greater
+        }
+    }
+}
+
+// How an immediate jump target is interpreted: as an absolute pc, or as an
+// offset (two's complement, wrapping) from the pc of the jump itself.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Addr {
+    Abs, Rel
+}
+
+impl Fin for Addr {
+    const ARR : &'static [Addr] = &[Addr::Abs, Addr::Rel];
+}
+
+// Sub-word memory accesses. These address RAM as bytes, packed as described
+// in the bytes module: byte address a is in word a / BYTES, big-endian within
+// the word. A halfword is the two bytes at a and a+1, most significant first,
+// and need not be aligned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Width {
+    Byte, Half
+}
+
+impl Fin for Width {
+    const ARR : &'static [Width] = &[Width::Byte, Width::Half];
 }
 
 
@@ -118,8 +204,36 @@ pub enum Instruction<U> {
     UJump {cond : Cond, flag : Reg, dest : Reg},
     SJump {cond : Cond, flag : Reg, dest : Reg},
     Ram {dir : Dir, ptr : Reg, val : Reg},
-    Out {reg : Reg},
-    Call {major : Reg, minor : Reg, arg : Reg, len : Reg},
+    Out {reg : Reg, port : U},
+    // Suspends with MutNotice::In; the host answers with State::resume_in
+    In {reg : Reg, port : U},
+    // The host's answer goes in res; see Reply
+    Call {major : Reg, minor : Reg, arg : Reg, len : Reg, res : Reg},
+    Push {reg : Reg},
+    Pop {reg : Reg},
+    Um2I {op : M2Op, r1 : Reg, imm : U, r3 : Reg},
+    Sm2I {op : M2Op, r1 : Reg, imm : U, r3 : Reg},
+    UJumpI {cond : Cond, flag : Reg, mode : Addr, target : U},
+    SJumpI {cond : Cond, flag : Reg, mode : Addr, target : U},
+    RamI {dir : Dir, ptr : Reg, off : U, val : Reg},
+    ULoad {width : Width, ptr : Reg, val : Reg},
+    SLoad {width : Width, ptr : Reg, val : Reg},
+    Store {width : Width, ptr : Reg, val : Reg},
+    MemCopy {dst : Reg, src : Reg, len : Reg},
+    MemFill {dst : Reg, val : Reg, len : Reg},
+    MemCmp {a : Reg, b : Reg, len : Reg, res : Reg},
+    UJumpCmp {cond : Cond, r1 : Reg, r2 : Reg, dest : Reg},
+    SJumpCmp {cond : Cond, r1 : Reg, r2 : Reg, dest : Reg},
+    // r3 = if cond(flag) {r1} else {r2}, without branching on the data
+    USelect {cond : Cond, flag : Reg, r1 : Reg, r2 : Reg, r3 : Reg},
+    SSelect {cond : Cond, flag : Reg, r1 : Reg, r2 : Reg, r3 : Reg},
+    #[cfg(feature = "float")]
+    Fm2 {op : FOp, r1 : Reg, r2 : Reg, r3 : Reg},
+    Um4 {op : M4Op, r1 : Reg, r2 : Reg, r3 : Reg, r4 : Reg},
+    Sm4 {op : M4Op, r1 : Reg, r2 : Reg, r3 : Reg, r4 : Reg},
+    // Enables interrupts if flag is nonzero, disables them otherwise
+    IntEn {flag : Reg},
+    Iret,
     Halt,
     Invalid // If the evaluator ever hits one of these, it means the person who wrote the instruction fetcher fucked up
 }
@@ -167,15 +281,139 @@ impl<U:Read> Read for Instruction<U> {
                 Ok(Instruction::Ram{dir,ptr,val})))),
                 0x6 => 
                 Reg::read(it).and_then(|reg|
-                Ok(Instruction::Out{reg})),
+                U::read(it).and_then(|port|
+                Ok(Instruction::Out{reg,port}))),
                 0x7 => 
                 Reg::read(it).and_then(|major|
                 Reg::read(it).and_then(|minor|
                 Reg::read(it).and_then(|arg|
                 Reg::read(it).and_then(|len|
-                Ok(Instruction::Call{major,minor,arg,len}))))),
+                Reg::read(it).and_then(|res|
+                Ok(Instruction::Call{major,minor,arg,len,res})))))),
                 0x8 => Ok(Instruction::Halt),
                 0x9 => Ok(Instruction::Invalid),
+                0xA =>
+                Reg::read(it).map(|reg|
+                Instruction::Push{reg}),
+                0xB =>
+                Reg::read(it).map(|reg|
+                Instruction::Pop{reg}),
+                0xC =>
+                M2Op::read(it).and_then(
+                |op| Reg::read(it).and_then(
+                |r1| U::read(it).and_then(
+                |imm| Reg::read(it).map(
+                |r3| Instruction::Um2I{op,r1,imm,r3})))),
+                0xD =>
+                M2Op::read(it).and_then(
+                |op| Reg::read(it).and_then(
+                |r1| U::read(it).and_then(
+                |imm| Reg::read(it).map(
+                |r3| Instruction::Sm2I{op,r1,imm,r3})))),
+                0xE =>
+                Cond::read(it).and_then(
+                |cond| Reg::read(it).and_then(
+                |flag| Addr::read(it).and_then(
+                |mode| U::read(it).map(
+                |target| Instruction::UJumpI{cond,flag,mode,target})))),
+                0xF =>
+                Cond::read(it).and_then(
+                |cond| Reg::read(it).and_then(
+                |flag| Addr::read(it).and_then(
+                |mode| U::read(it).map(
+                |target| Instruction::SJumpI{cond,flag,mode,target})))),
+                0x10 =>
+                Dir::read(it).and_then(
+                |dir| Reg::read(it).and_then(
+                |ptr| U::read(it).and_then(
+                |off| Reg::read(it).map(
+                |val| Instruction::RamI{dir,ptr,off,val})))),
+                0x11 =>
+                Width::read(it).and_then(
+                |width| Reg::read(it).and_then(
+                |ptr| Reg::read(it).map(
+                |val| Instruction::ULoad{width,ptr,val}))),
+                0x12 =>
+                Width::read(it).and_then(
+                |width| Reg::read(it).and_then(
+                |ptr| Reg::read(it).map(
+                |val| Instruction::SLoad{width,ptr,val}))),
+                0x13 =>
+                Width::read(it).and_then(
+                |width| Reg::read(it).and_then(
+                |ptr| Reg::read(it).map(
+                |val| Instruction::Store{width,ptr,val}))),
+                0x14 =>
+                Reg::read(it).and_then(
+                |dst| Reg::read(it).and_then(
+                |src| Reg::read(it).map(
+                |len| Instruction::MemCopy{dst,src,len}))),
+                0x15 =>
+                Reg::read(it).and_then(
+                |dst| Reg::read(it).and_then(
+                |val| Reg::read(it).map(
+                |len| Instruction::MemFill{dst,val,len}))),
+                0x16 =>
+                Reg::read(it).and_then(
+                |a| Reg::read(it).and_then(
+                |b| Reg::read(it).and_then(
+                |len| Reg::read(it).map(
+                |res| Instruction::MemCmp{a,b,len,res})))),
+                0x17 =>
+                Cond::read(it).and_then(
+                |cond| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).map(
+                |dest| Instruction::UJumpCmp{cond,r1,r2,dest})))),
+                0x18 =>
+                Cond::read(it).and_then(
+                |cond| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).map(
+                |dest| Instruction::SJumpCmp{cond,r1,r2,dest})))),
+                0x19 =>
+                Cond::read(it).and_then(
+                |cond| Reg::read(it).and_then(
+                |flag| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).map(
+                |r3| Instruction::USelect{cond,flag,r1,r2,r3}))))),
+                0x1A =>
+                Cond::read(it).and_then(
+                |cond| Reg::read(it).and_then(
+                |flag| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).map(
+                |r3| Instruction::SSelect{cond,flag,r1,r2,r3}))))),
+                #[cfg(feature = "float")]
+                0x1B =>
+                FOp::read(it).and_then(
+                |op| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).map(
+                |r3| Instruction::Fm2{op,r1,r2,r3})))),
+                0x1C =>
+                M4Op::read(it).and_then(
+                |op| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).and_then(
+                |r3| Reg::read(it).map(
+                |r4| Instruction::Um4{op,r1,r2,r3,r4}))))),
+                0x1D =>
+                M4Op::read(it).and_then(
+                |op| Reg::read(it).and_then(
+                |r1| Reg::read(it).and_then(
+                |r2| Reg::read(it).and_then(
+                |r3| Reg::read(it).map(
+                |r4| Instruction::Sm4{op,r1,r2,r3,r4}))))),
+                0x1E =>
+                Reg::read(it).map(|flag|
+                Instruction::IntEn{flag}),
+                0x1F => Ok(Instruction::Iret),
+                0x20 =>
+                Reg::read(it).and_then(
+                |reg| U::read(it).map(
+                |port| Instruction::In{reg,port})),
                 _ => Err(())
             },
             None => Err(())
@@ -193,21 +431,75 @@ impl<U : Prim + Write> Write for Instruction<U> {
             Instruction::UJump{cond,flag,dest} => {sink.write(0x3);cond.write(sink);flag.write(sink);dest.write(sink)}
             Instruction::SJump{cond,flag,dest} => {sink.write(0x4);cond.write(sink);flag.write(sink);dest.write(sink)}
             Instruction::Ram{dir,ptr,val} => {sink.write(0x5); dir.write(sink); ptr.write(sink); val.write(sink)},
-            Instruction::Out{reg} => {sink.write(0x6); reg.write(sink)},
-            Instruction::Call{major,minor,arg,len} => {sink.write(0x7);major.write(sink);minor.write(sink);arg.write(sink);len.write(sink)},
+            Instruction::Out{reg,port} => {sink.write(0x6); reg.write(sink); port.write(sink)},
+            Instruction::Call{major,minor,arg,len,res} => {sink.write(0x7);major.write(sink);minor.write(sink);arg.write(sink);len.write(sink);res.write(sink)},
             Instruction::Halt => sink.write(0x8),
-            Instruction::Invalid => sink.write(0x9)
+            Instruction::Invalid => sink.write(0x9),
+            Instruction::Push{reg} => {sink.write(0xA); reg.write(sink)},
+            Instruction::Pop{reg} => {sink.write(0xB); reg.write(sink)},
+            Instruction::Um2I{op,r1,imm,r3} => {sink.write(0xC);op.write(sink);r1.write(sink);imm.write(sink);r3.write(sink)},
+            Instruction::Sm2I{op,r1,imm,r3} => {sink.write(0xD);op.write(sink);r1.write(sink);imm.write(sink);r3.write(sink)},
+            Instruction::UJumpI{cond,flag,mode,target} => {sink.write(0xE);cond.write(sink);flag.write(sink);mode.write(sink);target.write(sink)},
+            Instruction::SJumpI{cond,flag,mode,target} => {sink.write(0xF);cond.write(sink);flag.write(sink);mode.write(sink);target.write(sink)},
+            Instruction::RamI{dir,ptr,off,val} => {sink.write(0x10);dir.write(sink);ptr.write(sink);off.write(sink);val.write(sink)},
+            Instruction::ULoad{width,ptr,val} => {sink.write(0x11);width.write(sink);ptr.write(sink);val.write(sink)},
+            Instruction::SLoad{width,ptr,val} => {sink.write(0x12);width.write(sink);ptr.write(sink);val.write(sink)},
+            Instruction::Store{width,ptr,val} => {sink.write(0x13);width.write(sink);ptr.write(sink);val.write(sink)},
+            Instruction::MemCopy{dst,src,len} => {sink.write(0x14);dst.write(sink);src.write(sink);len.write(sink)},
+            Instruction::MemFill{dst,val,len} => {sink.write(0x15);dst.write(sink);val.write(sink);len.write(sink)},
+            Instruction::MemCmp{a,b,len,res} => {sink.write(0x16);a.write(sink);b.write(sink);len.write(sink);res.write(sink)},
+            Instruction::UJumpCmp{cond,r1,r2,dest} => {sink.write(0x17);cond.write(sink);r1.write(sink);r2.write(sink);dest.write(sink)},
+            Instruction::SJumpCmp{cond,r1,r2,dest} => {sink.write(0x18);cond.write(sink);r1.write(sink);r2.write(sink);dest.write(sink)},
+            Instruction::USelect{cond,flag,r1,r2,r3} => {sink.write(0x19);cond.write(sink);flag.write(sink);r1.write(sink);r2.write(sink);r3.write(sink)},
+            Instruction::SSelect{cond,flag,r1,r2,r3} => {sink.write(0x1A);cond.write(sink);flag.write(sink);r1.write(sink);r2.write(sink);r3.write(sink)},
+            #[cfg(feature = "float")]
+            Instruction::Fm2{op,r1,r2,r3} => {sink.write(0x1B);op.write(sink);r1.write(sink);r2.write(sink);r3.write(sink)},
+            Instruction::Um4{op,r1,r2,r3,r4} => {sink.write(0x1C);op.write(sink);r1.write(sink);r2.write(sink);r3.write(sink);r4.write(sink)},
+            Instruction::Sm4{op,r1,r2,r3,r4} => {sink.write(0x1D);op.write(sink);r1.write(sink);r2.write(sink);r3.write(sink);r4.write(sink)},
+            Instruction::IntEn{flag} => {sink.write(0x1E); flag.write(sink)},
+            Instruction::Iret => sink.write(0x1F),
+            Instruction::In{reg,port} => {sink.write(0x20); reg.write(sink); port.write(sink)}
         }
     }
 }
 
 
 use core::marker::PhantomData;
+use bytes::{get_byte, set_byte};
+
+// Pre-decoded programs, run by State::eval_ops
+pub mod threaded;
+// Programs translated to Rust ahead of time
+pub mod native;
+
+// Push and Pop use RF as the stack pointer. The stack occupies
+// ram[stack_base .. stack_top] and grows down, with SP pointing at the
+// most recently pushed word, so an empty stack has SP == stack_top.
+pub const SP : Reg = Reg::RF;
+
+// Interrupts are numbered 0 .. INTERRUPTS. The vector table is INTERRUPTS
+// words of RAM starting at the base given to State::set_vectors, each holding
+// the address of a handler. A raised interrupt stays pending until it is
+// taken, which happens before fetching the next instruction once interrupts
+// are enabled (IntEn) and no handler is running; the lowest pending number
+// goes first. Taking one saves pc and all registers, puts the interrupt
+// number in R0 and jumps to the handler. Iret restores what was saved.
+// Handlers don't nest.
+pub const INTERRUPTS : usize = 32;
 
 pub struct State<'a, U : 'a, S : 'a>  {
     pc : U,
     regs : [U;16],
     ram : &'a mut [U],
+    stack_base : U,
+    stack_top : U,
+    q_frac : u32,
+    vectors : Option<U>,
+    int_enabled : bool,
+    pending : u32,
+    saved : Option<(U, [U;16])>,
+    // Destination of an In waiting for the host
+    input : Option<Reg>,
     _phantom : PhantomData<S>
 }
 
@@ -234,34 +526,86 @@ fn reg2index(reg : Reg) -> usize {
 
 #[derive(Debug)]
 pub enum StaticNotice<U : Copy> {
-    Call{major:U, minor:U, arg:U, len:U},
+    Call{major:U, minor:U, arg:U, len:U, res:Reg},
     Halt,
-    Out{out:U}
+    Out{port:U, out:U},
+    In{port:U}
 }
 
 #[derive(Debug)]
 pub enum MutNotice<'a, U : 'a + Copy> {
     Thrash,
-    Call{major:U, minor:U, slice:&'a mut [U]},
+    Call{major:U, minor:U, slice:&'a mut [U], reply:Reply<'a, U>},
     Halt,
-    Out{out:U}
+    Out{port:U, out:U},
+    In{port:U}
 }
 
-#[derive(Copy, Clone, Debug)]
+// Handed out with MutNotice::Call. It borrows the State, so the guest
+// can't run again until the host is done with it. resume answers the call
+// and moves past it; cancel, or dropping the Reply, leaves pc on the Call
+// so the guest makes it again on the next run.
+#[must_use]
+#[derive(Debug)]
+pub struct Reply<'t, U : 't> {
+    pc : &'t mut U,
+    regs : &'t mut [U;16],
+    res : Reg,
+    next : U
+}
+
+impl<'t, U : 't + Copy> Reply<'t, U> {
+    pub fn resume(self, result : U) {
+        self.regs[reg2index(self.res)] = result;
+        *self.pc = self.next;
+    }
+
+    pub fn cancel(self) {}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Failure<U : Copy> {
     CallOverflow,
     CallUnderflow,
     CodeOob {pc : U},
     RamOob {pc : U, addr : U, dir : Dir},
+    StackOverflow {pc : U},
+    StackUnderflow {pc : U},
+    // Iret outside an interrupt handler
+    BadIret {pc : U},
+    // eval_instrs called again before an In was answered
+    InputPending {pc : U},
+    DivByZero {pc : U},
     InvalidInstruction
 }
 
 use core::ops::*;
 
 
-pub trait Prim : Copy + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> + Div<Output=Self> + Ord + Shr<Self,Output=Self> + Shl<Self,Output=Self> + Shr<u8,Output=Self> + Shl<u8,Output=Self>
+pub trait Prim : Copy + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> + Div<Output=Self> + Rem<Output=Self> + BitAnd<Output=Self> + BitOr<Output=Self> + Not<Output=Self> + Ord + Shr<Self,Output=Self> + Shl<Self,Output=Self> + Shr<u8,Output=Self> + Shl<u8,Output=Self>
      {
+    const BYTES : usize;
     fn to_usize(&self) -> usize;
+    fn to_u64(&self) -> u64;
+    fn from_u64(val : u64) -> Self;
+    fn wrapping_add(&self, other : Self) -> Self;
+    fn wrapping_sub(&self, other : Self) -> Self;
+    fn wrapping_mul(&self, other : Self) -> Self;
+    // Quotient and remainder, or None when dividing by zero. MIN / -1 wraps
+    // to MIN with remainder 0.
+    fn div_rem(&self, other : Self) -> Option<(Self, Self)>;
+    // Fixed-point product and quotient with frac fractional bits,
+    // computed at double width. The quotient is None when dividing by zero.
+    fn fx_mul(&self, other : Self, frac : u32, saturate : bool) -> Self;
+    fn fx_div(&self, other : Self, frac : u32, saturate : bool) -> Option<Self>;
+    // Upper word of the double-width product.
+    fn mul_hi(&self, other : Self) -> Self;
+    // Add and subtract on the bit patterns with carry/borrow in and out,
+    // the same for signed and unsigned types.
+    fn carrying_add(&self, other : Self, carry : bool) -> (Self, bool);
+    fn borrowing_sub(&self, other : Self, borrow : bool) -> (Self, bool);
+    #[cfg(feature = "float")]
+    fn fm2(op : FOp, r1 : Self, r2 : Self) -> Self;
     fn zero() -> Self;
     fn one() -> Self;
 }
@@ -272,6 +616,40 @@ pub trait Compl<O> : Prim where O:Compl<Self> {
 }
 
 
+// Arithmetic wraps. None means division by zero.
+fn m2<T : Prim>(op : M2Op, r1 : T, r2 : T, frac : u32) -> Option<T> {
+    Some(match op {
+        M2Op::Add => r1.wrapping_add(r2),
+        M2Op::Sub => r1.wrapping_sub(r2),
+        M2Op::Mul => r1.wrapping_mul(r2),
+        M2Op::Div => r1.div_rem(r2)?.0,
+        M2Op::Equ => if r1 == r2 {Prim::one()} else {Prim::zero()},
+        M2Op::Lt =>  if r1 <  r2 {Prim::one()} else {Prim::zero()},
+        M2Op::Gt =>  if r1 >  r2 {Prim::one()} else {Prim::zero()},
+        M2Op::FxMul => r1.fx_mul(r2, frac, false),
+        M2Op::FxDiv => r1.fx_div(r2, frac, false)?,
+        M2Op::FxMulSat => r1.fx_mul(r2, frac, true),
+        M2Op::FxDivSat => r1.fx_div(r2, frac, true)?,
+        M2Op::MulHi => r1.mul_hi(r2)
+    })
+}
+
+fn m4<T : Prim>(op : M4Op, r1 : T, r2 : T, r4 : T) -> Option<(T, T)> {
+    let bit = r4 & T::one() == T::one();
+    let flag = |b : bool| if b {T::one()} else {T::zero()};
+    Some(match op {
+        M4Op::AddC => {
+            let (val, carry) = r1.carrying_add(r2, bit);
+            (val, flag(carry))
+        },
+        M4Op::SubB => {
+            let (val, borrow) = r1.borrowing_sub(r2, bit);
+            (val, flag(borrow))
+        },
+        M4Op::DivRem => r1.div_rem(r2)?
+    })
+}
+
 impl<'a, U: 'a + Compl<S>, S: 'a + Compl<U>> State<'a,U,S> {
 
     fn set_reg(&mut self, reg : Reg, val : U) -> () {
@@ -290,136 +668,450 @@ impl<'a, U: 'a + Compl<S>, S: 'a + Compl<U>> State<'a,U,S> {
         self.ram[ptr.to_usize()]
     }
 
+    fn set_math(&mut self, reg : Reg, val : Option<U>) -> Option<Result<StaticNotice<U>, Failure<U>>> {
+        match val {
+            None => Some (Err (Failure::DivByZero {pc:self.pc})),
+            Some(val) => {
+                self.set_reg(reg, val);
+                None
+            }
+        }
+    }
+
+    fn ucond(&mut self, cond : Cond, flag : Reg) -> bool {
+        cond.holds(self.get_reg(flag).cmp(&Prim::zero()))
+    }
+
+    fn scond(&mut self, cond : Cond, flag : Reg) -> bool {
+        cond.holds(self.get_reg(flag).compl().cmp(&Prim::zero()))
+    }
+
+    fn ucmp(&mut self, cond : Cond, r1 : Reg, r2 : Reg) -> bool {
+        cond.holds(self.get_reg(r1).cmp(&self.get_reg(r2)))
+    }
+
+    fn scmp(&mut self, cond : Cond, r1 : Reg, r2 : Reg) -> bool {
+        cond.holds(self.get_reg(r1).compl().cmp(&self.get_reg(r2).compl()))
+    }
+
+    fn select(&mut self, pick_r1 : bool, r1 : Reg, r2 : Reg, r3 : Reg) {
+        let mask = U::from_u64(0u64.wrapping_sub(pick_r1 as u64));
+        let val = (self.get_reg(r1) & mask) | (self.get_reg(r2) & !mask);
+        self.set_reg(r3, val)
+    }
+
+    fn imm_target(&self, mode : Addr, target : U) -> U {
+        match mode {
+            Addr::Abs => target,
+            Addr::Rel => self.pc.wrapping_add(target)
+        }
+    }
+
+    fn ram_access(&mut self, dir : Dir, ptr : U, val : Reg) -> Option<Result<StaticNotice<U>, Failure<U>>> {
+        if ptr.to_usize() >= self.ram.len() {
+            Some (Err (Failure::RamOob {pc:self.pc, addr:ptr, dir}))
+        }
+        else {
+            match dir{
+                Dir::Read => {
+                    let word = self.get_ram(ptr);
+                    self.set_reg(val,word);
+                },
+                Dir::Write => {
+                    let word = self.get_reg(val);
+                    self.set_ram(ptr,word)
+                }
+            };
+            None
+        }
+    }
+
+    // Start and length of a sub-word access, if it lies within RAM.
+    fn byte_range(&self, width : Width, addr : U) -> Option<(usize, usize)> {
+        let n = match width {
+            Width::Byte => 1,
+            Width::Half => 2
+        };
+        let start = addr.to_usize();
+        match start.checked_add(n) {
+            Some(end) if addr.to_u64() == start as u64 && end <= self.ram.len() * U::BYTES => Some((start, n)),
+            _ => None
+        }
+    }
+
+    fn load(&mut self, width : Width, signed : bool, ptr : Reg, val : Reg) -> Option<Result<StaticNotice<U>, Failure<U>>> {
+        let addr = self.get_reg(ptr);
+        match self.byte_range(width, addr) {
+            None => Some (Err (Failure::RamOob {pc:self.pc, addr, dir:Dir::Read})),
+            Some((start, n)) => {
+                let mut word = 0u64;
+                for i in 0..n {
+                    word = word << 8 | get_byte(self.ram, start + i).unwrap_or(0) as u64;
+                }
+                if signed && word >> (8 * n - 1) == 1 {
+                    word |= !0 << (8 * n);
+                }
+                self.set_reg(val, U::from_u64(word));
+                None
+            }
+        }
+    }
+
+    // The RAM range of a block operand.
+    fn block(&self, start : U, len : U, dir : Dir) -> Result<Range<usize>, Failure<U>> {
+        let (s, n) = (start.to_usize(), len.to_usize());
+        match s.checked_add(n) {
+            Some(end) if end <= self.ram.len() => Ok(s .. end),
+            _ => Err(Failure::RamOob {pc:self.pc, addr:start, dir})
+        }
+    }
+
+    // Fuel an instruction uses: one, plus one per word for block operations.
+    fn cost(&self, instr : &Instruction<U>) -> usize {
+        match *instr {
+            Instruction::MemCopy{len,..} | Instruction::MemFill{len,..} | Instruction::MemCmp{len,..} =>
+                self.regs[reg2index(len)].to_usize().saturating_add(1),
+            _ => 1
+        }
+    }
+
     fn eval_instr(&mut self, instr: &Instruction<U>) -> Option<Result<StaticNotice<U>, Failure<U>>> {
         use self::Instruction::*;
         // println!("{:?}", instr);
         let res = match *instr {
             Lit{val,reg} => {self.set_reg(reg, val); None},
             Um2{op,r1,r2,r3} => {
-                let r1 = self.get_reg(r1);
-                let r2 = self.get_reg(r2);
-                let val = match op {
-                    M2Op::Add => r1 + r2,
-                    M2Op::Sub => r1 - r2,
-                    M2Op::Mul => r1 * r2,
-                    M2Op::Div => r1 / r2,
-                    M2Op::Equ => if r1 == r2 {Prim::one()} else {Prim::zero()},
-                    M2Op::Lt => if  r1 <  r2 {Prim::one()} else {Prim::zero()},
-                    M2Op::Gt => if  r1 >  r2 {Prim::one()} else {Prim::zero()}
-                };
+                let val = m2(op, self.get_reg(r1), self.get_reg(r2), self.q_frac);
+                self.set_math(r3, val)
+            },
+            Sm2{op,r1,r2,r3} => {
+                let val = m2(op, self.get_reg(r1).compl(), self.get_reg(r2).compl(), self.q_frac);
+                self.set_math(r3, val.map(|v| v.compl()))
+            },
+            Um2I{op,r1,imm,r3} => {
+                let val = m2(op, self.get_reg(r1), imm, self.q_frac);
+                self.set_math(r3, val)
+            },
+            Sm2I{op,r1,imm,r3} => {
+                let val = m2(op, self.get_reg(r1).compl(), imm.compl(), self.q_frac);
+                self.set_math(r3, val.map(|v| v.compl()))
+            },
+            UJump{..} | SJump{..} | UJumpI{..} | SJumpI{..} | UJumpCmp{..} | SJumpCmp{..} => None,
+            #[cfg(feature = "float")]
+            Fm2{op,r1,r2,r3} => {
+                let val = U::fm2(op, self.get_reg(r1), self.get_reg(r2));
                 self.set_reg(r3,val);
                 None
             },
-            Sm2{op,r1,r2,r3} => {
-                let r1 = self.get_reg(r1).compl();
-                let r2 = self.get_reg(r2).compl();
-                let val = match op {
-                    M2Op::Add => r1 + r2,
-                    M2Op::Sub => r1 - r2,
-                    M2Op::Mul => r1 * r2,
-                    M2Op::Div => r1 / r2,
-                    M2Op::Equ => if r1 == r2 {Prim::one()} else {Prim::zero()},
-                    M2Op::Lt =>  if r1 <  r2 {Prim::one()} else {Prim::zero()},
-                    M2Op::Gt =>  if r1 >  r2 {Prim::one()} else {Prim::zero()}
-                };
-                self.set_reg(r3,val.compl());
+            Um4{op,r1,r2,r3,r4} => {
+                match m4(op, self.get_reg(r1), self.get_reg(r2), self.get_reg(r4)) {
+                    None => Some (Err (Failure::DivByZero {pc:self.pc})),
+                    Some((v3, v4)) => {
+                        self.set_reg(r3,v3);
+                        self.set_reg(r4,v4);
+                        None
+                    }
+                }
+            },
+            Sm4{op,r1,r2,r3,r4} => {
+                match m4(op, self.get_reg(r1).compl(), self.get_reg(r2).compl(), self.get_reg(r4).compl()) {
+                    None => Some (Err (Failure::DivByZero {pc:self.pc})),
+                    Some((v3, v4)) => {
+                        self.set_reg(r3,v3.compl());
+                        self.set_reg(r4,v4.compl());
+                        None
+                    }
+                }
+            },
+            USelect{cond,flag,r1,r2,r3} => {
+                let pick = self.ucond(cond, flag);
+                self.select(pick, r1, r2, r3);
+                None
+            },
+            SSelect{cond,flag,r1,r2,r3} => {
+                let pick = self.scond(cond, flag);
+                self.select(pick, r1, r2, r3);
                 None
             },
-            UJump{..} => None,
-            SJump{..} => None,
-            Out{reg} => Some (Ok (StaticNotice::Out{out:(self.get_reg(reg))})),
+            Out{reg,port} => Some (Ok (StaticNotice::Out{port, out:(self.get_reg(reg))})),
+            In{reg,port} => {
+                self.input = Some(reg);
+                Some (Ok (StaticNotice::In{port}))
+            },
             Ram{dir,ptr,val} => {
                 let ptr = self.get_reg(ptr);
-                if ptr.to_usize() > self.ram.len() {
-                    Some (Err (Failure::RamOob {pc:self.pc, addr:ptr, dir:dir}))
-                }
-                else {
-                    match dir{
-                        Dir::Read => {
-                            let word = self.get_ram(ptr);
-                            self.set_reg(val,word);
-                        },
-                        Dir::Write => {
-                            let word = self.get_reg(val);
-                            self.set_ram(ptr,word)
-                        }
-                    };
-                    None
-                }
+                self.ram_access(dir, ptr, val)
             },
-            Instruction::Call{major,minor,arg,len} => Some ({
+            RamI{dir,ptr,off,val} => {
+                let ptr = self.get_reg(ptr).wrapping_add(off);
+                self.ram_access(dir, ptr, val)
+            },
+            Instruction::Call{major,minor,arg,len,res} => Some ({
                 Ok(StaticNotice::Call{
                     major:self.get_reg(major),
                     minor:self.get_reg(minor),
                     arg:self.get_reg(arg),
-                    len:self.get_reg(len)})
+                    len:self.get_reg(len),
+                    res})
             }),
+            ULoad{width,ptr,val} => self.load(width, false, ptr, val),
+            SLoad{width,ptr,val} => self.load(width, true, ptr, val),
+            Store{width,ptr,val} => {
+                let addr = self.get_reg(ptr);
+                match self.byte_range(width, addr) {
+                    None => Some (Err (Failure::RamOob {pc:self.pc, addr, dir:Dir::Write})),
+                    Some((start, n)) => {
+                        let word = self.get_reg(val).to_u64();
+                        for i in 0..n {
+                            set_byte(self.ram, start + i, (word >> (8 * (n - 1 - i))) as u8);
+                        }
+                        None
+                    }
+                }
+            },
+            MemCopy{dst,src,len} => {
+                let len = self.get_reg(len);
+                let dst = self.get_reg(dst);
+                let src = self.get_reg(src);
+                match (self.block(src, len, Dir::Read), self.block(dst, len, Dir::Write)) {
+                    (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+                    (Ok(src), Ok(dst)) => {
+                        self.ram.copy_within(src, dst.start);
+                        None
+                    }
+                }
+            },
+            MemFill{dst,val,len} => {
+                let len = self.get_reg(len);
+                let dst = self.get_reg(dst);
+                let word = self.get_reg(val);
+                match self.block(dst, len, Dir::Write) {
+                    Err(e) => Some(Err(e)),
+                    Ok(dst) => {
+                        for w in self.ram[dst].iter_mut() {
+                            *w = word;
+                        }
+                        None
+                    }
+                }
+            },
+            MemCmp{a,b,len,res} => {
+                let len = self.get_reg(len);
+                let a = self.get_reg(a);
+                let b = self.get_reg(b);
+                match (self.block(a, len, Dir::Read), self.block(b, len, Dir::Read)) {
+                    (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+                    (Ok(a), Ok(b)) => {
+                        let val = match self.ram[a].cmp(&self.ram[b]) {
+                            Ordering::Less => (S::zero() - S::one()).compl(),
+                            Ordering::Equal => Prim::zero(),
+                            Ordering::Greater => Prim::one()
+                        };
+                        self.set_reg(res, val);
+                        None
+                    }
+                }
+            },
+            Push{reg} => {
+                let sp = self.get_reg(SP);
+                if sp <= self.stack_base || sp > self.stack_top {
+                    Some (Err (Failure::StackOverflow {pc:self.pc}))
+                } else {
+                    let sp = sp - Prim::one();
+                    let word = self.get_reg(reg);
+                    self.set_ram(sp, word);
+                    self.set_reg(SP, sp);
+                    None
+                }
+            },
+            Pop{reg} => {
+                let sp = self.get_reg(SP);
+                if sp >= self.stack_top || sp < self.stack_base {
+                    Some (Err (Failure::StackUnderflow {pc:self.pc}))
+                } else {
+                    let word = self.get_ram(sp);
+                    self.set_reg(SP, sp + Prim::one());
+                    self.set_reg(reg, word);
+                    None
+                }
+            },
+            IntEn{flag} => {
+                self.int_enabled = self.get_reg(flag) != Prim::zero();
+                None
+            },
+            Iret => match self.saved.take() {
+                None => Some (Err (Failure::BadIret {pc:self.pc})),
+                Some((pc, regs)) => {
+                    self.regs = regs;
+                    self.pc = pc;
+                    None
+                }
+            },
             Halt => Some(Ok(StaticNotice::Halt)),
             Invalid => Some(Err(Failure::InvalidInstruction))
         };
+        let next = self.pc.wrapping_add(Prim::one());
         let pc = match *instr {
-            UJump{cond, flag, dest} => {
-                let jump = match cond {
-                    Cond::Always => true,
-                    Cond::EqZ => self.get_reg(flag) == Prim::zero(),
-                    Cond::GtZ => self.get_reg(flag) >  Prim::zero(),
-                    Cond::LtZ => false
-                };
-                if jump { self.get_reg(dest) } else { self.pc + Prim::one() }
-            },
-            SJump{cond, flag, dest} => {
-                let jump = match cond {
-                    Cond::Always => true,
-                    Cond::EqZ => self.get_reg(flag).compl() == Prim::zero(),
-                    Cond::GtZ => self.get_reg(flag).compl() >  Prim::zero(),
-                    Cond::LtZ => self.get_reg(flag).compl() <  Prim::zero(),
-                };
-                if jump { self.get_reg(dest) } else { self.pc + Prim::one() }
-            },
-            _ => self.pc + Prim::one()
+            UJump{cond, flag, dest} =>
+                if self.ucond(cond, flag) { self.get_reg(dest) } else { next },
+            SJump{cond, flag, dest} =>
+                if self.scond(cond, flag) { self.get_reg(dest) } else { next },
+            UJumpI{cond, flag, mode, target} =>
+                if self.ucond(cond, flag) { self.imm_target(mode, target) } else { next },
+            SJumpI{cond, flag, mode, target} =>
+                if self.scond(cond, flag) { self.imm_target(mode, target) } else { next },
+            UJumpCmp{cond, r1, r2, dest} =>
+                if self.ucmp(cond, r1, r2) { self.get_reg(dest) } else { next },
+            SJumpCmp{cond, r1, r2, dest} =>
+                if self.scmp(cond, r1, r2) { self.get_reg(dest) } else { next },
+            // Resumes at the saved pc, not the one after it
+            Iret => self.pc,
+            _ => next
         };
         self.pc = pc;
         res
     }
 
+    // Runs until the guest stops or thrash_cnt fuel is used up. Most
+    // instructions take one unit; see cost(). An instruction is never split,
+    // so the last one in a slice may overdraw.
     pub fn eval_instrs<'t, F:Fetcher<U,Instruction<U>>>(&'t mut self, thrash_cnt : U, instrs : &mut F) -> Result<MutNotice<'t, U>, Failure<U>>  {
-        for _ in 0..thrash_cnt.to_usize() {
+        if self.input.is_some() {
+            return Err(Failure::InputPending{pc:self.pc})
+        }
+        let mut fuel = thrash_cnt.to_usize();
+        while fuel > 0 {
+            self.take_interrupt();
             let instr = match instrs.fetch(self.pc) {
                 None => return Err(Failure::CodeOob{pc:self.pc}),
                 Some(instr) => instr
             };
+            fuel = fuel.saturating_sub(self.cost(&instr));
             // if(self.pc.to_usize() >= instrs.len()) {};
             // let instr = &instrs[self.pc.to_usize()];
             let res = self.eval_instr(&instr);
             if let Some(interruption) = res {
-                return match interruption {
-                    Ok(notice) => match notice {
-                        StaticNotice::Call {major, minor, arg, len} => {
-                            self.get_call_slice(arg,len)
-                                .map(|slice| MutNotice::Call{major,minor,slice})
-                        },
-                        StaticNotice::Halt => Ok(MutNotice::Halt),
-                        StaticNotice::Out{out} => Ok(MutNotice::Out{out})
-                    },
-                    Err(err) => Err(err)
-                }
-               
+                return self.stop(interruption)
             }
         }
         Ok(MutNotice::Thrash)
     }
 
+    // What eval_instrs returns when an instruction stops the guest.
+    fn stop<'t>(&'t mut self, interruption : Result<StaticNotice<U>, Failure<U>>) -> Result<MutNotice<'t, U>, Failure<U>> {
+        match interruption {
+            Ok(notice) => match notice {
+                StaticNotice::Call {major, minor, arg, len, res} => {
+                    match self.call_range(arg,len) {
+                        Err(e) => Err(e),
+                        Ok(range) => {
+                            // Back on the Call until the host resumes it
+                            let next = self.pc;
+                            self.pc = next.wrapping_sub(Prim::one());
+                            let reply = Reply{pc:&mut self.pc, regs:&mut self.regs, res, next};
+                            Ok(MutNotice::Call{major,minor,slice:&mut self.ram[range],reply})
+                        }
+                    }
+                },
+                StaticNotice::Halt => Ok(MutNotice::Halt),
+                StaticNotice::Out{port,out} => Ok(MutNotice::Out{port,out}),
+                StaticNotice::In{port} => Ok(MutNotice::In{port})
+            },
+            Err(err) => Err(err)
+        }
+    }
+
 
   pub fn new(ram : &'a mut [U]) -> Self {
-    State{pc:Prim::zero(), regs:[Prim::zero();16], ram:ram, _phantom:PhantomData::default()}
+    State{pc:Prim::zero(), regs:[Prim::zero();16], ram,
+          stack_base:Prim::zero(), stack_top:Prim::zero(),
+          q_frac:(U::BYTES * 4) as u32,
+          vectors:None, int_enabled:false, pending:0, saved:None, input:None,
+          _phantom:PhantomData::default()}
+  }
+
+  pub fn pc(&self) -> U {
+    self.pc
+  }
+
+  pub fn regs(&self) -> &[U;16] {
+    &self.regs
+  }
+
+  pub fn ram(&self) -> &[U] {
+    self.ram
   }
 
-  fn get_call_slice(&mut self, arg : U, len : U) -> Result<&mut [U], Failure<U>> {
-    let end = arg + len;
+  // Number of fractional bits used by the Fx ops. Defaults to half the word,
+  // i.e. Q16.16 on 32-bit VMs and Q32.32 on 64-bit ones.
+  pub fn set_q_format(&mut self, frac : u32) -> Result<(),()> {
+    if frac as usize >= U::BYTES * 8 {return Err(())};
+    self.q_frac = frac;
+    Ok(())
+  }
+
+  // Answers the In the guest stopped on. Fails if there isn't one.
+  pub fn resume_in(&mut self, val : U) -> Result<(),()> {
+    match self.input.take() {
+      None => Err(()),
+      Some(reg) => {
+        self.set_reg(reg, val);
+        Ok(())
+      }
+    }
+  }
+
+  // Sets the base of the vector table. There is none until this is called,
+  // and raise fails.
+  pub fn set_vectors(&mut self, base : U) -> Result<(),()> {
+    match base.to_usize().checked_add(INTERRUPTS) {
+      Some(end) if end <= self.ram.len() => {
+        self.vectors = Some(base);
+        Ok(())
+      },
+      _ => Err(())
+    }
+  }
+
+  // Marks interrupt n pending. Raising one that is already pending has no
+  // further effect.
+  pub fn raise(&mut self, n : usize) -> Result<(),()> {
+    if n >= INTERRUPTS || self.vectors.is_none() {return Err(())};
+    self.pending |= 1 << n;
+    Ok(())
+  }
+
+  pub fn pending(&self) -> u32 {
+    self.pending
+  }
+
+  fn take_interrupt(&mut self) {
+    let base = match self.vectors {
+      Some(base) if self.int_enabled && self.saved.is_none() && self.pending != 0 => base,
+      _ => return
+    };
+    let n = self.pending.trailing_zeros();
+    self.pending &= !(1 << n);
+    self.saved = Some((self.pc, self.regs));
+    self.regs[0] = U::from_u64(n as u64);
+    self.pc = self.ram[base.to_usize() + n as usize];
+  }
+
+  // There is no stack until this is called; Push fails with StackOverflow.
+  // Resets SP to top, i.e. empties the stack.
+  pub fn set_stack(&mut self, base : U, top : U) -> Result<(),()> {
+    if base > top || top.to_usize() > self.ram.len() {return Err(())};
+    self.stack_base = base;
+    self.stack_top = top;
+    self.set_reg(SP, top);
+    Ok(())
+  }
+
+  fn call_range(&self, arg : U, len : U) -> Result<Range<usize>, Failure<U>> {
+    let end = arg.wrapping_add(len);
     if end < arg {return Err(Failure::CallUnderflow)};
-    if end.to_usize() >= self.ram.len() {return Err(Failure::CallOverflow)};
-    let slice = &mut self.ram[arg.to_usize() .. end.to_usize()];
-    Ok(slice)
+    if end.to_usize() > self.ram.len() {return Err(Failure::CallOverflow)};
+    Ok(arg.to_usize() .. end.to_usize())
   }
 
 }
@@ -471,3 +1163,572 @@ pub trait Fetcher<Ptr, T> {
 //     fn fetch(&mut self, ptr : Ptr) -> Option<T>;
 // }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Instruction::*;
+    use mem::MemFetch;
+
+    // Runs code to its Halt and gives the registers
+    fn run<U : Compl<S>, S : Compl<U>>(code : &[Instruction<U>]) -> Result<[U; 16], Failure<U>> {
+        let mut ram = [U::zero(); 4];
+        let mut state : State<U, S> = State::new(&mut ram);
+        match state.eval_instrs(U::from_u64(100), &mut MemFetch(code)) {
+            Ok(MutNotice::Halt) => Ok(state.regs),
+            Ok(_) => panic!("stopped before the Halt"),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn div_by_zero<U : Compl<S>, S : Compl<U>>(code : &[Instruction<U>], at : U) -> bool {
+        match run::<U, S>(code) {
+            Err(Failure::DivByZero{pc}) => pc == at,
+            _ => false
+        }
+    }
+
+    #[test]
+    fn divides() {
+        let code = [
+            Lit{val : 7, reg : R1},
+            Lit{val : -7i32 as u32, reg : R2},
+            Um2I{op : M2Op::Div, r1 : R1, imm : 2, r3 : R3},
+            Sm2I{op : M2Op::Div, r1 : R2, imm : 2, r3 : R4},
+            Lit{val : 2, reg : R5},
+            Sm2{op : M2Op::Div, r1 : R2, r2 : R5, r3 : R6},
+            Halt
+        ];
+        let regs = run::<u32, i32>(&code).unwrap();
+        assert_eq!((regs[3], regs[4], regs[6]), (3, -3i32 as u32, -3i32 as u32));
+    }
+
+    #[test]
+    fn stops_on_division_by_zero() {
+        let by_reg = [Lit{val : 7, reg : R1}, Um2{op : M2Op::Div, r1 : R1, r2 : R2, r3 : R3}, Halt];
+        assert!(div_by_zero::<u32, i32>(&by_reg, 1));
+        let by_imm = [Lit{val : 7, reg : R1}, Sm2I{op : M2Op::Div, r1 : R1, imm : 0, r3 : R3}, Halt];
+        assert!(div_by_zero::<u32, i32>(&by_imm, 1));
+        let wide = [Um2I{op : M2Op::Div, r1 : R1, imm : 0u64, r3 : R3}, Halt];
+        assert!(div_by_zero::<u64, i64>(&wide, 0));
+    }
+
+    #[test]
+    fn signed_min_over_minus_one_wraps() {
+        let code = [
+            Lit{val : 0x8000_0000, reg : R1},
+            Sm2I{op : M2Op::Div, r1 : R1, imm : !0, r3 : R3},
+            Halt
+        ];
+        assert_eq!(run::<u32, i32>(&code).unwrap()[3], 0x8000_0000);
+        let code = [
+            Lit{val : 1 << 63, reg : R1},
+            Sm2I{op : M2Op::Div, r1 : R1, imm : !0, r3 : R3},
+            Halt
+        ];
+        assert_eq!(run::<u64, i64>(&code).unwrap()[3], 1 << 63);
+    }
+
+    #[test]
+    fn fixed_point_division_by_zero_stops() {
+        // Q16.16 by default
+        let code = [
+            Lit{val : 3 << 16, reg : R1},
+            Um2I{op : M2Op::FxDiv, r1 : R1, imm : 2 << 16, r3 : R3},
+            Halt
+        ];
+        assert_eq!(run::<u32, i32>(&code).unwrap()[3], 0x1_8000);
+        for &op in [M2Op::FxDiv, M2Op::FxDivSat].iter() {
+            let code = [Lit{val : 3 << 16, reg : R1}, Um2{op, r1 : R1, r2 : R2, r3 : R3}, Halt];
+            assert!(div_by_zero::<u32, i32>(&code, 1));
+            let code = [Lit{val : 3 << 16, reg : R1}, Sm2I{op, r1 : R1, imm : 0, r3 : R3}, Halt];
+            assert!(div_by_zero::<u64, i64>(&code, 1));
+        }
+    }
+
+    #[test]
+    fn div_rem() {
+        let code = [
+            Lit{val : -7i32 as u32, reg : R1},
+            Lit{val : 2, reg : R2},
+            Um4{op : M4Op::DivRem, r1 : R1, r2 : R2, r3 : R3, r4 : R4},
+            Sm4{op : M4Op::DivRem, r1 : R1, r2 : R2, r3 : R5, r4 : R6},
+            Halt
+        ];
+        let regs = run::<u32, i32>(&code).unwrap();
+        assert_eq!((regs[3], regs[4]), (0x7FFF_FFFC, 1));
+        assert_eq!((regs[5], regs[6]), (-3i32 as u32, -1i32 as u32));
+    }
+
+    #[test]
+    fn div_rem_of_signed_min_by_minus_one_wraps() {
+        let code = [
+            Lit{val : 0x8000_0000, reg : R1},
+            Lit{val : !0, reg : R2},
+            Sm4{op : M4Op::DivRem, r1 : R1, r2 : R2, r3 : R3, r4 : R4},
+            Halt
+        ];
+        let regs = run::<u32, i32>(&code).unwrap();
+        assert_eq!((regs[3], regs[4]), (0x8000_0000, 0));
+        let code = [
+            Lit{val : 1 << 63, reg : R1},
+            Lit{val : !0, reg : R2},
+            Sm4{op : M4Op::DivRem, r1 : R1, r2 : R2, r3 : R3, r4 : R4},
+            Halt
+        ];
+        let regs = run::<u64, i64>(&code).unwrap();
+        assert_eq!((regs[3], regs[4]), (1 << 63, 0));
+    }
+
+    #[test]
+    fn div_rem_by_zero_stops() {
+        let code = [Lit{val : 7, reg : R1}, Um4{op : M4Op::DivRem, r1 : R1, r2 : R2, r3 : R3, r4 : R4}, Halt];
+        assert!(div_by_zero::<u32, i32>(&code, 1));
+        let code = [Sm4{op : M4Op::DivRem, r1 : R1, r2 : R2, r3 : R3, r4 : R4}, Halt];
+        assert!(div_by_zero::<u64, i64>(&code, 0));
+    }
+
+    #[test]
+    fn calls_resume_or_repeat() {
+        // Calls major 1, minor 2 on ram[0 .. 2] and adds 1 to the result
+        let code = [
+            Lit{val : 1, reg : R1}, Lit{val : 2, reg : R2}, Lit{val : 0, reg : R3}, Lit{val : 2, reg : R4},
+            Call{major : R1, minor : R2, arg : R3, len : R4, res : R5},
+            Um2I{op : M2Op::Add, r1 : R5, imm : 1, r3 : R5},
+            UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Abs, target : 4}
+        ];
+        let mut ram = [0u32; 4];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        match state.eval_instrs(100, &mut MemFetch(&code)) {
+            Ok(MutNotice::Call{major : 1, minor : 2, slice, reply}) => {
+                assert_eq!(slice.len(), 2);
+                slice[1] = 8;
+                reply.resume(7)
+            },
+            _ => panic!("no call")
+        };
+        assert_eq!((state.pc, state.regs[5], state.ram[1]), (5, 7, 8));
+        // Cancelled: back on the Call, result register untouched
+        match state.eval_instrs(100, &mut MemFetch(&code)) {
+            Ok(MutNotice::Call{reply, ..}) => reply.cancel(),
+            _ => panic!("no call")
+        };
+        assert_eq!((state.pc, state.regs[5]), (4, 8));
+        // Dropping the reply does the same
+        match state.eval_instrs(100, &mut MemFetch(&code)) {
+            Ok(MutNotice::Call{..}) => (),
+            _ => panic!("no call")
+        };
+        assert_eq!((state.pc, state.regs[5]), (4, 8));
+        // The repeated call is answered as usual
+        match state.eval_instrs(1, &mut MemFetch(&code)) {
+            Ok(MutNotice::Call{reply, ..}) => reply.resume(1),
+            _ => panic!("no call")
+        };
+        match state.eval_instrs(100, &mut MemFetch(&code)) {
+            Ok(MutNotice::Call{reply, ..}) => reply.cancel(),
+            _ => panic!("no call")
+        };
+        assert_eq!((state.pc, state.regs[5]), (4, 2));
+    }
+
+    #[cfg(feature = "float")]
+    fn fm2<U : Compl<S>, S : Compl<U>>(op : FOp, a : U, b : U) -> U {
+        let code = [Lit{val : a, reg : R1}, Lit{val : b, reg : R2}, Fm2{op, r1 : R1, r2 : R2, r3 : R3}, Halt];
+        run::<U, S>(&code).ok().unwrap()[3]
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn floats_32() {
+        let f = |op, a : f32, b : f32| fm2::<u32, i32>(op, a.to_bits(), b.to_bits());
+        assert_eq!(f(FOp::Add, 1.5, 2.25), 3.75f32.to_bits());
+        assert_eq!(f(FOp::Sub, 1.5, 2.25), (-0.75f32).to_bits());
+        assert_eq!(f(FOp::Mul, 1.5, -2.0), (-3.0f32).to_bits());
+        assert_eq!(f(FOp::Div, 1.0, 3.0), (1.0f32 / 3.0).to_bits());
+        assert_eq!(f(FOp::Div, -1.0, 0.0), f32::NEG_INFINITY.to_bits());
+        assert_eq!((f(FOp::Equ, 2.0, 2.0), f(FOp::Lt, 1.0, 2.0), f(FOp::Gt, 1.0, 2.0)), (1, 1, 0));
+        // NaN propagates and compares false
+        for &op in [FOp::Add, FOp::Sub, FOp::Mul, FOp::Div].iter() {
+            assert!(f32::from_bits(f(op, f32::NAN, 1.0)).is_nan());
+            assert!(f32::from_bits(f(op, 1.0, f32::NAN)).is_nan());
+        }
+        assert!(f32::from_bits(f(FOp::Sub, f32::INFINITY, f32::INFINITY)).is_nan());
+        for &op in [FOp::Equ, FOp::Lt, FOp::Gt].iter() {
+            assert_eq!(f(op, f32::NAN, f32::NAN), 0);
+            assert_eq!(f(op, f32::NAN, 1.0), 0);
+        }
+        // Conversions read r1 only
+        assert_eq!(fm2::<u32, i32>(FOp::FromInt, -3i32 as u32, 9), (-3.0f32).to_bits());
+        assert_eq!(fm2::<u32, i32>(FOp::FromInt, 0x8000_0000, 0), (-2147483648.0f32).to_bits());
+        let to_int = |a : f32| fm2::<u32, i32>(FOp::ToInt, a.to_bits(), 9) as i32;
+        assert_eq!((to_int(2.9), to_int(-2.9), to_int(-0.0)), (2, -2, 0));
+        assert_eq!((to_int(1e10), to_int(-1e10)), (i32::MAX, i32::MIN));
+        assert_eq!((to_int(f32::INFINITY), to_int(f32::NEG_INFINITY)), (i32::MAX, i32::MIN));
+        assert_eq!(to_int(f32::NAN), 0);
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn floats_64() {
+        let f = |op, a : f64, b : f64| fm2::<u64, i64>(op, a.to_bits(), b.to_bits());
+        assert_eq!(f(FOp::Add, 0.1, 0.2), (0.1f64 + 0.2).to_bits());
+        assert_eq!(f(FOp::Mul, 1e200, 1e200), f64::INFINITY.to_bits());
+        assert_eq!(f(FOp::Div, 1.0, 3.0), (1.0f64 / 3.0).to_bits());
+        assert_eq!((f(FOp::Equ, 0.0, -0.0), f(FOp::Lt, -1.0, 1.0), f(FOp::Gt, -1.0, 1.0)), (1, 1, 0));
+        for &op in [FOp::Add, FOp::Sub, FOp::Mul, FOp::Div].iter() {
+            assert!(f64::from_bits(f(op, f64::NAN, 1.0)).is_nan());
+            assert!(f64::from_bits(f(op, 1.0, f64::NAN)).is_nan());
+        }
+        assert!(f64::from_bits(f(FOp::Div, 0.0, 0.0)).is_nan());
+        for &op in [FOp::Equ, FOp::Lt, FOp::Gt].iter() {
+            assert_eq!(f(op, f64::NAN, f64::NAN), 0);
+            assert_eq!(f(op, 1.0, f64::NAN), 0);
+        }
+        assert_eq!(fm2::<u64, i64>(FOp::FromInt, -3i64 as u64, 9), (-3.0f64).to_bits());
+        assert_eq!(fm2::<u64, i64>(FOp::FromInt, 1 << 53, 0), 9007199254740992.0f64.to_bits());
+        let to_int = |a : f64| fm2::<u64, i64>(FOp::ToInt, a.to_bits(), 9) as i64;
+        assert_eq!((to_int(2.9), to_int(-2.9), to_int(1e15)), (2, -2, 1_000_000_000_000_000));
+        assert_eq!((to_int(1e300), to_int(-1e300)), (i64::MAX, i64::MIN));
+        assert_eq!((to_int(f64::INFINITY), to_int(f64::NEG_INFINITY)), (i64::MAX, i64::MIN));
+        assert_eq!(to_int(f64::NAN), 0);
+        // Wider than 32 bits, unlike the u32 VM
+        assert_eq!(to_int(1e10), 10_000_000_000);
+    }
+
+    #[test]
+    fn pushes_and_pops() {
+        let code = [Lit{val : 5, reg : R1}, Lit{val : 6, reg : R2}, Push{reg : R1}, Push{reg : R2}, Pop{reg : R3}, Pop{reg : R4}, Halt];
+        let mut ram = [0u32; 8];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        state.set_stack(4, 6).unwrap();
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+        assert_eq!((state.regs[3], state.regs[4], state.get_reg(SP)), (6, 5, 6));
+        assert_eq!(&state.ram[4 .. 6], &[6, 5]);
+        // The stack must fit in RAM, and set_stack empties it
+        assert_eq!(state.set_stack(4, 9), Err(()));
+        assert_eq!(state.set_stack(5, 4), Err(()));
+        state.set_reg(SP, 0);
+        assert_eq!(state.set_stack(2, 8), Ok(()));
+        assert_eq!(state.get_reg(SP), 8);
+    }
+
+    #[test]
+    fn stack_bounds() {
+        let mut ram = [0u32; 8];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        // No stack until set_stack
+        let push = [Push{reg : R1}, Push{reg : R1}, Push{reg : R1}, Halt];
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&push)).err(), Some(Failure::StackOverflow{pc : 0}));
+        // The third push doesn't fit, and changes nothing
+        state.set_stack(4, 6).unwrap();
+        state.pc = 0;
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&push)).err(), Some(Failure::StackOverflow{pc : 2}));
+        assert_eq!((state.get_reg(SP), state.ram[3]), (4, 0));
+        let pop = [Pop{reg : R1}, Pop{reg : R1}, Pop{reg : R1}, Halt];
+        state.pc = 0;
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&pop)).err(), Some(Failure::StackUnderflow{pc : 2}));
+        assert_eq!(state.get_reg(SP), 6);
+        // SP moved out of the stack by the guest
+        for &sp in [2, 7].iter() {
+            state.set_reg(SP, sp);
+            state.pc = 0;
+            assert_eq!(state.eval_instrs(100, &mut MemFetch(&push)).err(), Some(Failure::StackOverflow{pc : 0}));
+            state.pc = 0;
+            assert_eq!(state.eval_instrs(100, &mut MemFetch(&pop)).err(), Some(Failure::StackUnderflow{pc : 0}));
+        }
+    }
+
+    #[test]
+    fn loads_bytes_and_halfwords() {
+        let code = [
+            Lit{val : 1, reg : R1}, ULoad{width : Width::Byte, ptr : R1, val : R2},
+            // Unaligned, across two words
+            Lit{val : 3, reg : R1}, ULoad{width : Width::Half, ptr : R1, val : R3},
+            Lit{val : 4, reg : R1}, ULoad{width : Width::Byte, ptr : R1, val : R4}, SLoad{width : Width::Byte, ptr : R1, val : R5},
+            ULoad{width : Width::Half, ptr : R1, val : R6}, SLoad{width : Width::Half, ptr : R1, val : R7},
+            Lit{val : 5, reg : R1}, SLoad{width : Width::Half, ptr : R1, val : R8},
+            Halt
+        ];
+        let mut ram = [0x1122_3344u32, 0x80FF_0000];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+        assert_eq!((state.regs[2], state.regs[3]), (0x22, 0x4480));
+        assert_eq!((state.regs[4], state.regs[5]), (0x80, 0xFFFF_FF80));
+        assert_eq!((state.regs[6], state.regs[7], state.regs[8]), (0x80FF, 0xFFFF_80FF, 0xFFFF_FF00));
+    }
+
+    #[test]
+    fn stores_bytes_and_halfwords() {
+        let code = [
+            Lit{val : 0xABCD, reg : R2},
+            Lit{val : 2, reg : R1}, Store{width : Width::Byte, ptr : R1, val : R2},
+            Lit{val : 7, reg : R1}, Store{width : Width::Half, ptr : R1, val : R2},
+            Halt
+        ];
+        let mut ram = [0u32; 3];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+        assert_eq!(state.ram, [0x0000_CD00, 0x0000_00AB, 0xCD00_0000]);
+        let code = [Lit{val : 0x1234_5678, reg : R2}, Store{width : Width::Half, ptr : R1, val : R2}, Halt];
+        let mut ram = [0u64; 1];
+        let mut state : State<u64, i64> = State::new(&mut ram);
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+        assert_eq!(state.ram, [0x5678_0000_0000_0000]);
+    }
+
+    #[test]
+    fn sub_word_access_bounds() {
+        // Two words of four bytes
+        let mut ram = [0u32; 2];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let code = [ULoad{width : Width::Byte, ptr : R1, val : R2}, Halt];
+        state.set_reg(R1, 8);
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&code)).err(), Some(Failure::RamOob{pc : 0, addr : 8, dir : Dir::Read}));
+        // A halfword may not hang off the end
+        let code = [SLoad{width : Width::Half, ptr : R1, val : R2}, Store{width : Width::Half, ptr : R1, val : R2}, Halt];
+        state.set_reg(R1, 7);
+        state.pc = 0;
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&code)).err(), Some(Failure::RamOob{pc : 0, addr : 7, dir : Dir::Read}));
+        state.pc = 1;
+        state.set_reg(R2, !0);
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&code)).err(), Some(Failure::RamOob{pc : 1, addr : 7, dir : Dir::Write}));
+        assert_eq!(state.ram, [0, 0]);
+        // Nor wrap around
+        state.set_reg(R1, !0);
+        state.pc = 1;
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&code)).err(), Some(Failure::RamOob{pc : 1, addr : !0, dir : Dir::Write}));
+    }
+
+    #[test]
+    fn copies_fills_and_compares_blocks() {
+        // Fill ram[1 .. 4] with 7, copy ram[0 .. 4] up by two (overlapping),
+        // then compare ram[2 .. 4] with ram[4 .. 6], and ram[3 .. 5] with
+        // ram[0 .. 2] both ways
+        let code = [
+            Lit{val : 1, reg : R1}, Lit{val : 7, reg : R2}, Lit{val : 3, reg : R3},
+            MemFill{dst : R1, val : R2, len : R3},
+            Lit{val : 2, reg : R1}, Lit{val : 0, reg : R2}, Lit{val : 4, reg : R3},
+            MemCopy{dst : R1, src : R2, len : R3},
+            Lit{val : 4, reg : R4}, Lit{val : 2, reg : R3},
+            MemCmp{a : R1, b : R4, len : R3, res : R5},
+            Lit{val : 3, reg : R1},
+            MemCmp{a : R1, b : R2, len : R3, res : R6},
+            MemCmp{a : R2, b : R1, len : R3, res : R7},
+            Lit{val : 0, reg : R3},
+            MemCmp{a : R2, b : R1, len : R3, res : R8},
+            Halt
+        ];
+        let mut ram = [0u32; 8];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+        assert_eq!(state.ram, [0, 7, 0, 7, 7, 7, 0, 0]);
+        assert_eq!((state.regs[5], state.regs[6], state.regs[7], state.regs[8]), (!0, 1, !0, 0));
+    }
+
+    #[test]
+    fn block_operations_cost_a_unit_per_word() {
+        let code = [MemFill{dst : R1, val : R2, len : R3}, Lit{val : 1, reg : R4}, Halt];
+        let mut ram = [0u32; 8];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        state.set_reg(R3, 4);
+        assert!(matches!(state.eval_instrs(6, &mut MemFetch(&code)), Ok(MutNotice::Thrash)));
+        assert_eq!(state.pc, 2);
+        // Never split: the fill overdraws a slice too small for it
+        state.pc = 0;
+        assert!(matches!(state.eval_instrs(5, &mut MemFetch(&code)), Ok(MutNotice::Thrash)));
+        assert_eq!(state.pc, 1);
+        state.pc = 0;
+        assert!(matches!(state.eval_instrs(1, &mut MemFetch(&code)), Ok(MutNotice::Thrash)));
+        assert_eq!(state.pc, 1);
+    }
+
+    #[test]
+    fn block_operation_bounds() {
+        let mut ram = [0u32; 8];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let code = [
+            MemCopy{dst : R1, src : R2, len : R3},
+            MemFill{dst : R1, val : R2, len : R3},
+            MemCmp{a : R1, b : R2, len : R3, res : R4},
+            Halt
+        ];
+        let at = |state : &mut State<u32, i32>, pc, (dst, src, len)| {
+            state.pc = pc;
+            state.regs[1 .. 4].copy_from_slice(&[dst, src, len]);
+            state.eval_instrs(100, &mut MemFetch(&code)).err()
+        };
+        assert_eq!(at(&mut state, 0, (0, 5, 4)), Some(Failure::RamOob{pc : 0, addr : 5, dir : Dir::Read}));
+        assert_eq!(at(&mut state, 0, (6, 0, 3)), Some(Failure::RamOob{pc : 0, addr : 6, dir : Dir::Write}));
+        assert_eq!(at(&mut state, 0, (1, 1, !0)), Some(Failure::RamOob{pc : 0, addr : 1, dir : Dir::Read}));
+        assert_eq!(at(&mut state, 1, (9, 5, 0)), Some(Failure::RamOob{pc : 1, addr : 9, dir : Dir::Write}));
+        assert_eq!(at(&mut state, 1, (4, 5, 5)), Some(Failure::RamOob{pc : 1, addr : 4, dir : Dir::Write}));
+        assert_eq!(at(&mut state, 2, (0, 7, 2)), Some(Failure::RamOob{pc : 2, addr : 7, dir : Dir::Read}));
+        // Empty blocks may sit at the end of RAM
+        assert_eq!(at(&mut state, 0, (8, 8, 0)), None);
+        assert_eq!(state.ram, [0; 8]);
+    }
+
+    // Runs instr alone with R1 = a, R2 = b and R3 = 9, giving the pc after it
+    // and R4
+    fn step(instr : Instruction<u32>, a : u32, b : u32) -> (u32, u32) {
+        let mut ram = [0u32; 1];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        state.regs[1 .. 4].copy_from_slice(&[a, b, 9]);
+        assert!(matches!(state.eval_instrs(1, &mut MemFetch(&[instr])), Ok(MutNotice::Thrash)));
+        (state.pc, state.regs[4])
+    }
+
+    #[test]
+    fn conditions() {
+        use core::cmp::Ordering::*;
+        let table = [
+            (Cond::Always, [true, true, true]),
+            (Cond::EqZ, [false, true, false]),
+            (Cond::GtZ, [false, false, true]),
+            (Cond::LtZ, [true, false, false]),
+            (Cond::NeZ, [true, false, true]),
+            (Cond::GeZ, [false, true, true]),
+            (Cond::LeZ, [true, true, false])
+        ];
+        for &(cond, holds) in table.iter() {
+            assert_eq!([cond.holds(Less), cond.holds(Equal), cond.holds(Greater)], holds, "{:?}", cond);
+        }
+        // Unsigned flags are never below zero
+        let minus = -1i32 as u32;
+        assert_eq!(step(UJump{cond : Cond::LtZ, flag : R1, dest : R3}, minus, 0).0, 1);
+        assert_eq!(step(SJump{cond : Cond::LtZ, flag : R1, dest : R3}, minus, 0).0, 9);
+        assert_eq!(step(UJump{cond : Cond::GeZ, flag : R1, dest : R3}, minus, 0).0, 9);
+        assert_eq!(step(SJump{cond : Cond::GeZ, flag : R1, dest : R3}, minus, 0).0, 1);
+        assert_eq!(step(SJump{cond : Cond::LeZ, flag : R1, dest : R3}, 0, 0).0, 9);
+        assert_eq!(step(SJump{cond : Cond::NeZ, flag : R1, dest : R3}, 0, 0).0, 1);
+        assert_eq!(step(UJumpI{cond : Cond::NeZ, flag : R1, mode : Addr::Rel, target : minus}, 5, 0).0, minus);
+    }
+
+    #[test]
+    fn compares_and_branches() {
+        let minus = -1i32 as u32;
+        let jump = |cond, signed, a, b| {
+            let instr = if signed {SJumpCmp{cond, r1 : R1, r2 : R2, dest : R3}} else {UJumpCmp{cond, r1 : R1, r2 : R2, dest : R3}};
+            step(instr, a, b).0 == 9
+        };
+        // -1 is the largest unsigned word
+        assert_eq!((jump(Cond::GtZ, false, minus, 1), jump(Cond::GtZ, true, minus, 1)), (true, false));
+        assert_eq!((jump(Cond::LtZ, false, minus, 1), jump(Cond::LtZ, true, minus, 1)), (false, true));
+        assert_eq!((jump(Cond::LeZ, false, 5, 5), jump(Cond::GeZ, true, 5, 5)), (true, true));
+        assert_eq!((jump(Cond::NeZ, false, 5, 5), jump(Cond::EqZ, true, 5, 5)), (false, true));
+        assert_eq!((jump(Cond::NeZ, true, 5, 6), jump(Cond::EqZ, false, 5, 6)), (true, false));
+    }
+
+    #[test]
+    fn selects() {
+        let minus = -1i32 as u32;
+        let select = |cond, signed, flag| {
+            let instr = if signed {
+                SSelect{cond, flag : R1, r1 : R2, r2 : R3, r3 : R4}
+            } else {
+                USelect{cond, flag : R1, r1 : R2, r2 : R3, r3 : R4}
+            };
+            let (pc, r4) = step(instr, flag, 7);
+            assert_eq!(pc, 1);
+            r4
+        };
+        assert_eq!((select(Cond::GtZ, false, minus), select(Cond::GtZ, true, minus)), (7, 9));
+        assert_eq!((select(Cond::LtZ, false, minus), select(Cond::LtZ, true, minus)), (9, 7));
+        assert_eq!((select(Cond::EqZ, false, 0), select(Cond::NeZ, true, 0)), (7, 9));
+        assert_eq!((select(Cond::Always, true, 0), select(Cond::LeZ, true, 0)), (7, 7));
+    }
+
+    #[test]
+    fn takes_interrupts() {
+        // The handler at 8 puts its interrupt number out on port 1 and
+        // clobbers R2, which Iret restores
+        let code = [
+            Lit{val : 1, reg : R1}, IntEn{flag : R1}, Lit{val : 5, reg : R2}, Out{reg : R2, port : 0}, Halt,
+            Invalid, Invalid, Invalid,
+            Out{reg : R0, port : 1}, Lit{val : 99, reg : R2}, Iret
+        ];
+        let mut ram = [0u32; 40];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        assert_eq!(state.raise(0), Err(()));
+        assert_eq!(state.set_vectors(9), Err(()));
+        state.set_vectors(4).unwrap();
+        state.ram[4 + 1] = 8;
+        state.ram[4 + 3] = 8;
+        assert_eq!(state.raise(INTERRUPTS), Err(()));
+        state.raise(3).unwrap();
+        state.raise(1).unwrap();
+        // Lowest number first, once interrupts are enabled
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Out{port : 1, out : 1})));
+        assert_eq!(state.pending(), 1 << 3);
+        // Not nested
+        assert!(matches!(state.eval_instrs(2, &mut MemFetch(&code)), Ok(MutNotice::Thrash)));
+        assert_eq!((state.pc, state.regs[2]), (2, 0));
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Out{port : 1, out : 3})));
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Out{port : 0, out : 5})));
+        assert_eq!(state.pending(), 0);
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+    }
+
+    #[test]
+    fn interrupts_wait_while_disabled() {
+        let code = [Lit{val : 0, reg : R1}, IntEn{flag : R1}, Lit{val : 1, reg : R1}, IntEn{flag : R1}, Out{reg : R0, port : 0}, Halt];
+        let mut ram = [0u32; 32];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        state.set_vectors(0).unwrap();
+        state.ram[31] = 5;
+        state.raise(31).unwrap();
+        state.raise(31).unwrap();
+        assert!(matches!(state.eval_instrs(4, &mut MemFetch(&code)), Ok(MutNotice::Thrash)));
+        assert_eq!((state.pc, state.pending()), (4, 1 << 31));
+        // Taken before the Out, and only once
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+        assert_eq!((state.regs[0], state.pending(), state.saved.is_some()), (31, 0, true));
+    }
+
+    #[test]
+    fn iret_outside_a_handler_fails() {
+        let mut ram = [0u32; 1];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let code = [Lit{val : 1, reg : R1}, Iret];
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&code)).err(), Some(Failure::BadIret{pc : 1}));
+        assert_eq!(state.regs[1], 1);
+    }
+
+    #[test]
+    fn waits_for_input() {
+        let code = [In{reg : R1, port : 3}, Um2I{op : M2Op::Add, r1 : R1, imm : 1, r3 : R2}, Out{reg : R2, port : 4}, Halt];
+        let mut ram = [0u32; 1];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        assert_eq!(state.resume_in(7), Err(()));
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::In{port : 3})));
+        // Not until it's answered
+        assert_eq!(state.eval_instrs(100, &mut MemFetch(&code)).err(), Some(Failure::InputPending{pc : 1}));
+        assert_eq!(state.resume_in(7), Ok(()));
+        assert_eq!(state.resume_in(8), Err(()));
+        assert_eq!(state.regs[1], 7);
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Out{port : 4, out : 8})));
+        assert!(matches!(state.eval_instrs(100, &mut MemFetch(&code)), Ok(MutNotice::Halt)));
+    }
+
+    #[test]
+    fn call_slice_bounds() {
+        let code = [Call{major : R0, minor : R0, arg : R1, len : R2, res : R3}];
+        let mut ram = [0u32; 4];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let mut call = |arg, len| {
+            state.pc = 0;
+            state.regs[1 .. 3].copy_from_slice(&[arg, len]);
+            match state.eval_instrs(100, &mut MemFetch(&code)) {
+                Ok(MutNotice::Call{slice, ..}) => Ok(slice.len()),
+                Ok(_) => panic!("no call"),
+                Err(e) => Err(e)
+            }
+        };
+        assert_eq!(call(2, 2), Ok(2));
+        assert_eq!(call(4, 0), Ok(0));
+        assert_eq!(call(3, 2), Err(Failure::CallOverflow));
+        assert_eq!(call(5, 0), Err(Failure::CallOverflow));
+        assert_eq!(call(2, !0), Err(Failure::CallUnderflow));
+    }
+}
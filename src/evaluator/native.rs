@@ -0,0 +1,338 @@
+// Ahead-of-time translation of a program into Rust source, for trusted
+// programs that should run at native speed while the bytecode stays the one
+// source of truth. translate() writes a function
+//
+//     pub fn name<'t, 'a>(state : &'t mut State<'a, u32, i32>, thrash_cnt : u32)
+//         -> Result<MutNotice<'t, u32>, Failure<u32>>
+//
+// (u64/i64 on 64-bit VMs) that behaves exactly like state.eval_instrs(thrash_cnt,
+// ..) on the program: same MutNotices, Failures, fuel use, and pc, registers
+// and RAM afterwards. The generated code keeps pc in a local and has a match
+// arm per instruction, so it can be entered or resumed anywhere, and an
+// interrupt or Iret can land anywhere. Arithmetic, RAM access and jumps are
+// written out with their operands as constants; everything else goes through
+// a Native, which hands the instruction to eval_instr.
+//
+// The usual home for the output is a build script:
+//
+//     let program : Vec<Instruction<u32>> = ..;
+//     let mut src = String::new();
+//     native::translate(&program, "firmware", "::tpm", &mut src).unwrap();
+//     fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("firmware.rs"), src).unwrap();
+//
+// and then include!(concat!(env!("OUT_DIR"), "/firmware.rs")) in the crate.
+
+use super::*;
+use core::fmt;
+
+// Everything a generated function does to the State goes through one of
+// these. It also keeps the fuel.
+pub struct Native<'t, 'a : 't, U : 'a + Compl<S>, S : 'a + Compl<U>> {
+    state : &'t mut State<'a, U, S>,
+    fuel : usize
+}
+
+impl<'t, 'a, U : 'a + Compl<S>, S : 'a + Compl<U>> Native<'t, 'a, U, S> {
+    // Refuses to start in the same cases as eval_instrs.
+    pub fn new(state : &'t mut State<'a, U, S>, thrash_cnt : U) -> Result<Self, Failure<U>> {
+        if state.input.is_some() {
+            return Err(Failure::InputPending{pc:state.pc})
+        }
+        Ok(Native {state, fuel : thrash_cnt.to_usize()})
+    }
+
+    pub fn pc(&self) -> U {
+        self.state.pc
+    }
+
+    // Before each instruction: false if the fuel is gone, with pc stored for
+    // the next run. Otherwise takes a pending interrupt, which may move pc,
+    // and uses a unit of fuel.
+    #[inline(always)]
+    pub fn tick(&mut self, pc : &mut U) -> bool {
+        if self.fuel == 0 {
+            self.state.pc = *pc;
+            return false
+        }
+        if self.state.pending != 0 {
+            self.state.pc = *pc;
+            self.state.take_interrupt();
+            *pc = self.state.pc;
+        }
+        self.fuel -= 1;
+        true
+    }
+
+    #[inline(always)]
+    pub fn get(&self, i : usize) -> U {
+        self.state.regs[i & 0xF]
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, i : usize, val : U) {
+        self.state.regs[i & 0xF] = val
+    }
+
+    // The word at addr for the instruction at pc, which moves on if it's
+    // outside RAM, as in eval_instr.
+    #[inline(always)]
+    pub fn read(&mut self, pc : U, addr : U) -> Result<U, Failure<U>> {
+        match self.state.ram.get(addr.to_usize()) {
+            Some(&word) => Ok(word),
+            None => Err(self.ram_oob(pc, addr, Dir::Read))
+        }
+    }
+
+    #[inline(always)]
+    pub fn write(&mut self, pc : U, addr : U, val : U) -> Result<(), Failure<U>> {
+        match self.state.ram.get_mut(addr.to_usize()) {
+            Some(word) => {
+                *word = val;
+                Ok(())
+            },
+            None => Err(self.ram_oob(pc, addr, Dir::Write))
+        }
+    }
+
+    fn ram_oob(&mut self, pc : U, addr : U, dir : Dir) -> Failure<U> {
+        self.state.pc = pc.wrapping_add(U::one());
+        Failure::RamOob{pc, addr, dir}
+    }
+
+    // Runs instr at *pc with eval_instr, charging the rest of its cost, and
+    // moves *pc on. Some when the guest stops; pass it to stop().
+    pub fn eval(&mut self, pc : &mut U, instr : &Instruction<U>) -> Option<Result<StaticNotice<U>, Failure<U>>> {
+        self.state.pc = *pc;
+        self.fuel = self.fuel.saturating_sub(self.state.cost(instr) - 1);
+        let res = self.state.eval_instr(instr);
+        *pc = self.state.pc;
+        res
+    }
+
+    pub fn stop(self, interruption : Result<StaticNotice<U>, Failure<U>>) -> Result<MutNotice<'t, U>, Failure<U>> {
+        let state = self.state;
+        state.stop(interruption)
+    }
+
+    // Nothing at pc
+    pub fn code_oob(self, pc : U) -> Result<MutNotice<'t, U>, Failure<U>> {
+        self.state.pc = pc;
+        Err(Failure::CodeOob{pc})
+    }
+}
+
+// Names of the word types for U
+fn types<U : Prim>() -> (&'static str, &'static str) {
+    if U::BYTES == 4 {("u32", "i32")} else {("u64", "i64")}
+}
+
+fn ix(reg : Reg) -> usize {
+    reg2index(reg)
+}
+
+// An operand in the generated source
+#[derive(Copy, Clone)]
+enum Val {
+    Reg(Reg),
+    Imm(u64, &'static str)
+}
+
+impl fmt::Display for Val {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Val::Reg(reg) => write!(f, "n.get({})", ix(reg)),
+            Val::Imm(v, u) => write!(f, "{}{}", v, u)
+        }
+    }
+}
+
+// Source for whether cond holds comparing a with b
+fn test<W : fmt::Write>(out : &mut W, cond : Cond, signed : Option<&str>, a : Val, b : Val) -> fmt::Result {
+    match signed {
+        None => write!(out, "Cond::{:?}.holds({}.cmp(&{}))", cond, a, b),
+        Some(s) => write!(out, "Cond::{:?}.holds(({} as {}).cmp(&({} as {})))", cond, a, s, b, s)
+    }
+}
+
+// Source for the result of the M2Op, if it has some
+fn m2<W : fmt::Write>(out : &mut W, op : M2Op, signed : Option<&str>, u : &str, a : Val, b : Val) -> Result<bool, fmt::Error> {
+    match (op, signed) {
+        (M2Op::Add, _) => write!(out, "{}.wrapping_add({})", a, b)?,
+        (M2Op::Sub, _) => write!(out, "{}.wrapping_sub({})", a, b)?,
+        (M2Op::Mul, _) => write!(out, "{}.wrapping_mul({})", a, b)?,
+        (M2Op::Equ, _) => write!(out, "({} == {}) as {}", a, b, u)?,
+        (M2Op::Lt, None) => write!(out, "({} < {}) as {}", a, b, u)?,
+        (M2Op::Gt, None) => write!(out, "({} > {}) as {}", a, b, u)?,
+        (M2Op::Lt, Some(s)) => write!(out, "(({} as {}) < ({} as {})) as {}", a, s, b, s, u)?,
+        (M2Op::Gt, Some(s)) => write!(out, "(({} as {}) > ({} as {})) as {}", a, s, b, s, u)?,
+        _ => return Ok(false)
+    }
+    Ok(true)
+}
+
+// An expression for instr, for the instructions left to eval_instr
+fn expr<U : Prim, W : fmt::Write>(out : &mut W, instr : &Instruction<U>, u : &'static str) -> fmt::Result {
+    use self::Instruction::*;
+    let imm = |v : U| Val::Imm(v.to_u64(), u);
+    match *instr {
+        Lit{val,reg} => write!(out, "Lit{{val:{}, reg:Reg::{:?}}}", imm(val), reg),
+        Um2{op,r1,r2,r3} => write!(out, "Um2{{op:M2Op::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}}}", op, r1, r2, r3),
+        Sm2{op,r1,r2,r3} => write!(out, "Sm2{{op:M2Op::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}}}", op, r1, r2, r3),
+        UJump{cond,flag,dest} => write!(out, "UJump{{cond:Cond::{:?}, flag:Reg::{:?}, dest:Reg::{:?}}}", cond, flag, dest),
+        SJump{cond,flag,dest} => write!(out, "SJump{{cond:Cond::{:?}, flag:Reg::{:?}, dest:Reg::{:?}}}", cond, flag, dest),
+        Ram{dir,ptr,val} => write!(out, "Ram{{dir:Dir::{:?}, ptr:Reg::{:?}, val:Reg::{:?}}}", dir, ptr, val),
+        Out{reg,port} => write!(out, "Out{{reg:Reg::{:?}, port:{}}}", reg, imm(port)),
+        In{reg,port} => write!(out, "In{{reg:Reg::{:?}, port:{}}}", reg, imm(port)),
+        Call{major,minor,arg,len,res} =>
+            write!(out, "Call{{major:Reg::{:?}, minor:Reg::{:?}, arg:Reg::{:?}, len:Reg::{:?}, res:Reg::{:?}}}", major, minor, arg, len, res),
+        Push{reg} => write!(out, "Push{{reg:Reg::{:?}}}", reg),
+        Pop{reg} => write!(out, "Pop{{reg:Reg::{:?}}}", reg),
+        Um2I{op,r1,imm:i,r3} => write!(out, "Um2I{{op:M2Op::{:?}, r1:Reg::{:?}, imm:{}, r3:Reg::{:?}}}", op, r1, imm(i), r3),
+        Sm2I{op,r1,imm:i,r3} => write!(out, "Sm2I{{op:M2Op::{:?}, r1:Reg::{:?}, imm:{}, r3:Reg::{:?}}}", op, r1, imm(i), r3),
+        UJumpI{cond,flag,mode,target} =>
+            write!(out, "UJumpI{{cond:Cond::{:?}, flag:Reg::{:?}, mode:Addr::{:?}, target:{}}}", cond, flag, mode, imm(target)),
+        SJumpI{cond,flag,mode,target} =>
+            write!(out, "SJumpI{{cond:Cond::{:?}, flag:Reg::{:?}, mode:Addr::{:?}, target:{}}}", cond, flag, mode, imm(target)),
+        RamI{dir,ptr,off,val} => write!(out, "RamI{{dir:Dir::{:?}, ptr:Reg::{:?}, off:{}, val:Reg::{:?}}}", dir, ptr, imm(off), val),
+        ULoad{width,ptr,val} => write!(out, "ULoad{{width:Width::{:?}, ptr:Reg::{:?}, val:Reg::{:?}}}", width, ptr, val),
+        SLoad{width,ptr,val} => write!(out, "SLoad{{width:Width::{:?}, ptr:Reg::{:?}, val:Reg::{:?}}}", width, ptr, val),
+        Store{width,ptr,val} => write!(out, "Store{{width:Width::{:?}, ptr:Reg::{:?}, val:Reg::{:?}}}", width, ptr, val),
+        MemCopy{dst,src,len} => write!(out, "MemCopy{{dst:Reg::{:?}, src:Reg::{:?}, len:Reg::{:?}}}", dst, src, len),
+        MemFill{dst,val,len} => write!(out, "MemFill{{dst:Reg::{:?}, val:Reg::{:?}, len:Reg::{:?}}}", dst, val, len),
+        MemCmp{a,b,len,res} => write!(out, "MemCmp{{a:Reg::{:?}, b:Reg::{:?}, len:Reg::{:?}, res:Reg::{:?}}}", a, b, len, res),
+        UJumpCmp{cond,r1,r2,dest} => write!(out, "UJumpCmp{{cond:Cond::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, dest:Reg::{:?}}}", cond, r1, r2, dest),
+        SJumpCmp{cond,r1,r2,dest} => write!(out, "SJumpCmp{{cond:Cond::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, dest:Reg::{:?}}}", cond, r1, r2, dest),
+        USelect{cond,flag,r1,r2,r3} =>
+            write!(out, "USelect{{cond:Cond::{:?}, flag:Reg::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}}}", cond, flag, r1, r2, r3),
+        SSelect{cond,flag,r1,r2,r3} =>
+            write!(out, "SSelect{{cond:Cond::{:?}, flag:Reg::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}}}", cond, flag, r1, r2, r3),
+        #[cfg(feature = "float")]
+        Fm2{op,r1,r2,r3} => write!(out, "Fm2{{op:FOp::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}}}", op, r1, r2, r3),
+        Um4{op,r1,r2,r3,r4} =>
+            write!(out, "Um4{{op:M4Op::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}, r4:Reg::{:?}}}", op, r1, r2, r3, r4),
+        Sm4{op,r1,r2,r3,r4} =>
+            write!(out, "Sm4{{op:M4Op::{:?}, r1:Reg::{:?}, r2:Reg::{:?}, r3:Reg::{:?}, r4:Reg::{:?}}}", op, r1, r2, r3, r4),
+        IntEn{flag} => write!(out, "IntEn{{flag:Reg::{:?}}}", flag),
+        Iret => write!(out, "Iret"),
+        Halt => write!(out, "Halt"),
+        Invalid => write!(out, "Invalid")
+    }
+}
+
+// The body of the match arm for instr at pc, which evaluates to the next pc.
+fn arm<U : Prim, W : fmt::Write>(out : &mut W, pc : U, instr : &Instruction<U>) -> fmt::Result {
+    use self::Instruction::*;
+    let (u, s) = types::<U>();
+    let imm = |v : U| Val::Imm(v.to_u64(), u);
+    let next = imm(pc.wrapping_add(U::one()));
+    let here = imm(pc);
+    let zero = imm(U::zero());
+    let target = |mode, t : U| imm(match mode {
+        Addr::Abs => t,
+        Addr::Rel => pc.wrapping_add(t)
+    });
+    let (math, signed) = match *instr {
+        Um2{op,r1,r2,r3} => (Some((op, Val::Reg(r1), Val::Reg(r2), r3)), None),
+        Sm2{op,r1,r2,r3} => (Some((op, Val::Reg(r1), Val::Reg(r2), r3)), Some(s)),
+        Um2I{op,r1,imm:i,r3} => (Some((op, Val::Reg(r1), imm(i), r3)), None),
+        Sm2I{op,r1,imm:i,r3} => (Some((op, Val::Reg(r1), imm(i), r3)), Some(s)),
+        _ => (None, None)
+    };
+    if let Some((op, a, b, r3)) = math {
+        let mut src = Buf::new();
+        if m2(&mut src, op, signed, u, a, b)? {
+            return write!(out, "{{let v = {}; n.set({}, v); {}}}", src.as_str(), ix(r3), next)
+        }
+    }
+    let jump = |out : &mut W, cond : Cond, signed, a, b, to : Val| {
+        if cond == Cond::Always {return write!(out, "{}", to)};
+        write!(out, "if ")?;
+        test(out, cond, signed, a, b)?;
+        write!(out, " {{{}}} else {{{}}}", to, next)
+    };
+    match *instr {
+        Lit{val,reg} => write!(out, "{{n.set({}, {}); {}}}", ix(reg), imm(val), next),
+        UJump{cond,flag,dest} => jump(out, cond, None, Val::Reg(flag), zero, Val::Reg(dest)),
+        SJump{cond,flag,dest} => jump(out, cond, Some(s), Val::Reg(flag), zero, Val::Reg(dest)),
+        UJumpI{cond,flag,mode,target:t} => jump(out, cond, None, Val::Reg(flag), zero, target(mode, t)),
+        SJumpI{cond,flag,mode,target:t} => jump(out, cond, Some(s), Val::Reg(flag), zero, target(mode, t)),
+        UJumpCmp{cond,r1,r2,dest} => jump(out, cond, None, Val::Reg(r1), Val::Reg(r2), Val::Reg(dest)),
+        SJumpCmp{cond,r1,r2,dest} => jump(out, cond, Some(s), Val::Reg(r1), Val::Reg(r2), Val::Reg(dest)),
+        Ram{dir:Dir::Read,ptr,val} =>
+            write!(out, "{{let a = {}; let v = n.read({}, a)?; n.set({}, v); {}}}", Val::Reg(ptr), here, ix(val), next),
+        Ram{dir:Dir::Write,ptr,val} =>
+            write!(out, "{{let a = {}; let v = {}; n.write({}, a, v)?; {}}}", Val::Reg(ptr), Val::Reg(val), here, next),
+        RamI{dir:Dir::Read,ptr,off,val} =>
+            write!(out, "{{let a = {}.wrapping_add({}); let v = n.read({}, a)?; n.set({}, v); {}}}",
+                   Val::Reg(ptr), imm(off), here, ix(val), next),
+        RamI{dir:Dir::Write,ptr,off,val} =>
+            write!(out, "{{let a = {}.wrapping_add({}); let v = {}; n.write({}, a, v)?; {}}}",
+                   Val::Reg(ptr), imm(off), Val::Reg(val), here, next),
+        USelect{cond,flag,r1,r2,r3} | SSelect{cond,flag,r1,r2,r3} => {
+            let signed = if let SSelect{..} = *instr {Some(s)} else {None};
+            // A mask rather than a branch, as in eval_instr
+            write!(out, "{{let m = (")?;
+            test(out, cond, signed, Val::Reg(flag), zero)?;
+            write!(out, " as {}).wrapping_neg(); let v = ({} & m) | ({} & !m); n.set({}, v); {}}}",
+                   u, Val::Reg(r1), Val::Reg(r2), ix(r3), next)
+        },
+        _ => {
+            write!(out, "match n.eval(&mut pc, &")?;
+            expr(out, instr, u)?;
+            write!(out, ") {{None => pc, Some(stop) => return n.stop(stop)}}")
+        }
+    }
+}
+
+// Room for the expression of one M2Op, so arm() can check for one before
+// writing anything
+struct Buf {
+    buf : [u8; 128],
+    len : usize
+}
+
+impl Buf {
+    fn new() -> Buf {
+        Buf {buf : [0; 128], len : 0}
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[.. self.len]).unwrap_or("")
+    }
+}
+
+impl fmt::Write for Buf {
+    fn write_str(&mut self, s : &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {return Err(fmt::Error)};
+        self.buf[self.len .. end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+// Writes a function called name that runs program; see the top of this
+// file. krate is the path to this crate from where the output is compiled,
+// e.g. "::tpm". U must be u32 or u64.
+pub fn translate<U : Prim, W : fmt::Write>(program : &[Instruction<U>], name : &str, krate : &str, out : &mut W) -> fmt::Result {
+    let (u, s) = types::<U>();
+    writeln!(out, "// {} instructions, translated by {}::evaluator::native", program.len(), krate)?;
+    writeln!(out, "pub fn {}<'t, 'a>(state : &'t mut {k}::evaluator::State<'a, {u}, {s}>, thrash_cnt : {u})", name, k = krate, u = u, s = s)?;
+    writeln!(out, "    -> Result<{k}::evaluator::MutNotice<'t, {u}>, {k}::evaluator::Failure<{u}>> {{", k = krate, u = u)?;
+    writeln!(out, "    use {}::evaluator::*;", krate)?;
+    writeln!(out, "    use {}::evaluator::Instruction::*;", krate)?;
+    writeln!(out, "    let mut n = native::Native::new(state, thrash_cnt)?;")?;
+    writeln!(out, "    let mut pc = n.pc();")?;
+    writeln!(out, "    while n.tick(&mut pc) {{")?;
+    writeln!(out, "        pc = match pc {{")?;
+    for (pc, instr) in program.iter().enumerate() {
+        write!(out, "            {} => ", pc)?;
+        arm(out, U::from_u64(pc as u64), instr)?;
+        writeln!(out, ",")?;
+    }
+    writeln!(out, "            _ => return n.code_oob(pc)")?;
+    writeln!(out, "        }};")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "    Ok(MutNotice::Thrash)")?;
+    writeln!(out, "}}")
+}
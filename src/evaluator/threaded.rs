@@ -0,0 +1,230 @@
+// A pre-decoded form of a program, for when the whole program is in memory
+// and speed matters. compile() lowers each instruction once: registers
+// become indices, relative jump targets become absolute, the common
+// arithmetic and jumps get their own ops so running them is a single match,
+// and a few common pairs are fused into one op. Everything else is kept as
+// an Instruction and handed to eval_instr.
+//
+// ops[pc] always starts at instruction pc, so jumps can land anywhere, even
+// on the second half of a fused pair. State::eval_ops gives the same
+// MutNotices, Failures and fuel use as eval_instrs on the original program.
+
+use super::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op<U> {
+    Lit {val : U, reg : u8},
+    Add {r1 : u8, r2 : u8, r3 : u8},
+    Sub {r1 : u8, r2 : u8, r3 : u8},
+    // Add or Sub of an immediate, the latter with the immediate negated
+    AddI {r1 : u8, imm : U, r3 : u8},
+    Load {ptr : u8, val : u8},
+    Store {ptr : u8, val : u8},
+    Jump {dest : u8},
+    Goto {target : U},
+    JumpIf {cond : Cond, signed : bool, flag : u8, dest : u8},
+    GotoIf {cond : Cond, signed : bool, flag : u8, target : U},
+    JumpCmp {cond : Cond, signed : bool, r1 : u8, r2 : u8, dest : u8},
+    // Lit followed by UJump or SJump
+    LitJump {val : U, reg : u8, cond : Cond, signed : bool, flag : u8, dest : u8},
+    // AddI followed by UJumpI or SJumpI; the usual loop counter
+    AddIGotoIf {r1 : u8, imm : U, r3 : u8, cond : Cond, signed : bool, flag : u8, target : U},
+    Other(Instruction<U>)
+}
+
+impl<U> Default for Op<U> {
+    fn default() -> Op<U> {
+        Op::Other(Instruction::Invalid)
+    }
+}
+
+fn ix(reg : Reg) -> u8 {
+    reg2index(reg) as u8
+}
+
+// Immediate operand of Um2I/Sm2I Add/Sub as an AddI. Both wrap, so signed
+// and unsigned are the same.
+fn add_imm<U : Prim>(instr : &Instruction<U>) -> Option<(u8, U, u8)> {
+    match *instr {
+        Instruction::Um2I{op:M2Op::Add,r1,imm,r3} | Instruction::Sm2I{op:M2Op::Add,r1,imm,r3} =>
+            Some((ix(r1), imm, ix(r3))),
+        Instruction::Um2I{op:M2Op::Sub,r1,imm,r3} | Instruction::Sm2I{op:M2Op::Sub,r1,imm,r3} =>
+            Some((ix(r1), U::zero().wrapping_sub(imm), ix(r3))),
+        _ => None
+    }
+}
+
+// Absolute target of an immediate jump at pc.
+fn target<U : Prim>(pc : usize, mode : Addr, target : U) -> U {
+    match mode {
+        Addr::Abs => target,
+        Addr::Rel => U::from_u64(pc as u64).wrapping_add(target)
+    }
+}
+
+fn lower<U : Prim>(pc : usize, instr : &Instruction<U>) -> Op<U> {
+    use self::Instruction::*;
+    if let Some((r1, imm, r3)) = add_imm(instr) {
+        return Op::AddI{r1, imm, r3}
+    }
+    match *instr {
+        Lit{val,reg} => Op::Lit{val, reg:ix(reg)},
+        Um2{op:M2Op::Add,r1,r2,r3} | Sm2{op:M2Op::Add,r1,r2,r3} => Op::Add{r1:ix(r1), r2:ix(r2), r3:ix(r3)},
+        Um2{op:M2Op::Sub,r1,r2,r3} | Sm2{op:M2Op::Sub,r1,r2,r3} => Op::Sub{r1:ix(r1), r2:ix(r2), r3:ix(r3)},
+        Ram{dir:Dir::Read,ptr,val} => Op::Load{ptr:ix(ptr), val:ix(val)},
+        Ram{dir:Dir::Write,ptr,val} => Op::Store{ptr:ix(ptr), val:ix(val)},
+        UJump{cond:Cond::Always,dest,..} | SJump{cond:Cond::Always,dest,..} => Op::Jump{dest:ix(dest)},
+        UJump{cond,flag,dest} => Op::JumpIf{cond, signed:false, flag:ix(flag), dest:ix(dest)},
+        SJump{cond,flag,dest} => Op::JumpIf{cond, signed:true, flag:ix(flag), dest:ix(dest)},
+        UJumpI{cond:Cond::Always,mode,target:t,..} | SJumpI{cond:Cond::Always,mode,target:t,..} =>
+            Op::Goto{target:target(pc, mode, t)},
+        UJumpI{cond,flag,mode,target:t} => Op::GotoIf{cond, signed:false, flag:ix(flag), target:target(pc, mode, t)},
+        SJumpI{cond,flag,mode,target:t} => Op::GotoIf{cond, signed:true, flag:ix(flag), target:target(pc, mode, t)},
+        UJumpCmp{cond,r1,r2,dest} => Op::JumpCmp{cond, signed:false, r1:ix(r1), r2:ix(r2), dest:ix(dest)},
+        SJumpCmp{cond,r1,r2,dest} => Op::JumpCmp{cond, signed:true, r1:ix(r1), r2:ix(r2), dest:ix(dest)},
+        _ => Op::Other(*instr)
+    }
+}
+
+// The fused op for the instructions at pc and pc + 1, if there is one. The
+// first half never stops the guest and doesn't touch interrupt state, so
+// no interrupt can be taken between the two.
+fn fuse<U : Prim>(pc : usize, first : &Instruction<U>, second : &Instruction<U>) -> Option<Op<U>> {
+    let (cond, signed, flag) = match *second {
+        Instruction::UJump{cond,flag,..} | Instruction::UJumpI{cond,flag,..} => (cond, false, ix(flag)),
+        Instruction::SJump{cond,flag,..} | Instruction::SJumpI{cond,flag,..} => (cond, true, ix(flag)),
+        _ => return None
+    };
+    match (*first, *second) {
+        (Instruction::Lit{val,reg}, Instruction::UJump{dest,..}) | (Instruction::Lit{val,reg}, Instruction::SJump{dest,..}) =>
+            Some(Op::LitJump{val, reg:ix(reg), cond, signed, flag, dest:ix(dest)}),
+        (_, Instruction::UJumpI{mode,target:t,..}) | (_, Instruction::SJumpI{mode,target:t,..}) =>
+            add_imm(first).map(|(r1, imm, r3)|
+                Op::AddIGotoIf{r1, imm, r3, cond, signed, flag, target:target(pc + 1, mode, t)}),
+        _ => None
+    }
+}
+
+// Lowers program into ops, which must be at least as long. Returns the
+// part of ops that holds the result, to be passed to State::eval_ops.
+pub fn compile<'o, U : Prim>(program : &[Instruction<U>], ops : &'o mut [Op<U>]) -> Result<&'o [Op<U>], ()> {
+    if ops.len() < program.len() {return Err(())};
+    for (pc, instr) in program.iter().enumerate() {
+        ops[pc] = match program.get(pc + 1).and_then(|next| fuse(pc, instr, next)) {
+            Some(op) => op,
+            None => lower(pc, instr)
+        };
+    }
+    Ok(&ops[.. program.len()])
+}
+
+impl<'a, U: 'a + Compl<S>, S: 'a + Compl<U>> State<'a,U,S> {
+
+    // Register access by index. Indices come from ix(), so the mask never
+    // changes them; it only spares the bounds check.
+    fn r(&self, i : u8) -> U {
+        self.regs[(i & 0xF) as usize]
+    }
+
+    fn set_r(&mut self, i : u8, val : U) {
+        self.regs[(i & 0xF) as usize] = val
+    }
+
+    fn holds(&self, cond : Cond, signed : bool, a : U, b : U) -> bool {
+        if signed {cond.holds(a.compl().cmp(&b.compl()))} else {cond.holds(a.cmp(&b))}
+    }
+
+    // eval_instrs, for a program lowered by compile().
+    pub fn eval_ops<'t>(&'t mut self, thrash_cnt : U, ops : &[Op<U>]) -> Result<MutNotice<'t, U>, Failure<U>> {
+        if self.input.is_some() {
+            return Err(Failure::InputPending{pc:self.pc})
+        }
+        let mut fuel = thrash_cnt.to_usize();
+        while fuel > 0 {
+            self.take_interrupt();
+            let op = match ops.get(self.pc.to_usize()) {
+                None => return Err(Failure::CodeOob{pc:self.pc}),
+                Some(op) => *op
+            };
+            let next = self.pc.wrapping_add(U::one());
+            fuel = fuel.saturating_sub(match op {
+                Op::Other(ref instr) => self.cost(instr),
+                _ => 1
+            });
+            self.pc = match op {
+                Op::Other(instr) => {
+                    if let Some(interruption) = self.eval_instr(&instr) {
+                        return self.stop(interruption)
+                    }
+                    continue
+                },
+                Op::Lit{val,reg} => {
+                    self.set_r(reg, val);
+                    next
+                },
+                Op::Add{r1,r2,r3} => {
+                    let val = self.r(r1).wrapping_add(self.r(r2));
+                    self.set_r(r3, val);
+                    next
+                },
+                Op::Sub{r1,r2,r3} => {
+                    let val = self.r(r1).wrapping_sub(self.r(r2));
+                    self.set_r(r3, val);
+                    next
+                },
+                Op::AddI{r1,imm,r3} => {
+                    let val = self.r(r1).wrapping_add(imm);
+                    self.set_r(r3, val);
+                    next
+                },
+                Op::Load{ptr,val} => {
+                    let addr = self.r(ptr);
+                    match self.ram.get(addr.to_usize()) {
+                        None => return Err(self.ram_oob(next, addr, Dir::Read)),
+                        Some(&word) => self.set_r(val, word)
+                    }
+                    next
+                },
+                Op::Store{ptr,val} => {
+                    let addr = self.r(ptr);
+                    let word = self.r(val);
+                    match self.ram.get_mut(addr.to_usize()) {
+                        None => return Err(self.ram_oob(next, addr, Dir::Write)),
+                        Some(w) => *w = word
+                    }
+                    next
+                },
+                Op::Jump{dest} => self.r(dest),
+                Op::Goto{target} => target,
+                Op::JumpIf{cond,signed,flag,dest} =>
+                    if self.holds(cond, signed, self.r(flag), U::zero()) {self.r(dest)} else {next},
+                Op::GotoIf{cond,signed,flag,target} =>
+                    if self.holds(cond, signed, self.r(flag), U::zero()) {target} else {next},
+                Op::JumpCmp{cond,signed,r1,r2,dest} =>
+                    if self.holds(cond, signed, self.r(r1), self.r(r2)) {self.r(dest)} else {next},
+                Op::LitJump{val,reg,cond,signed,flag,dest} => {
+                    self.set_r(reg, val);
+                    // Out of fuel between the two halves
+                    if fuel == 0 {self.pc = next; continue};
+                    fuel -= 1;
+                    if self.holds(cond, signed, self.r(flag), U::zero()) {self.r(dest)} else {next.wrapping_add(U::one())}
+                },
+                Op::AddIGotoIf{r1,imm,r3,cond,signed,flag,target} => {
+                    let val = self.r(r1).wrapping_add(imm);
+                    self.set_r(r3, val);
+                    if fuel == 0 {self.pc = next; continue};
+                    fuel -= 1;
+                    if self.holds(cond, signed, self.r(flag), U::zero()) {target} else {next.wrapping_add(U::one())}
+                }
+            };
+        }
+        Ok(MutNotice::Thrash)
+    }
+
+    // A failed Load or Store still moves pc on, as in eval_instr.
+    fn ram_oob(&mut self, next : U, addr : U, dir : Dir) -> Failure<U> {
+        let failure = Failure::RamOob{pc:self.pc, addr, dir};
+        self.pc = next;
+        failure
+    }
+}
@@ -0,0 +1,185 @@
+use evaluator::*;
+use mem::{MemFetch, LRU4x64};
+use calls::status;
+use core::fmt::Debug;
+
+// Entry points for the cargo-fuzz targets in fuzz/. Each takes raw fuzzer
+// input and only panics on a bug in the crate. Inputs that ever caught one
+// live in fuzz/regressions/<target>/ and are replayed by the tests below.
+
+pub const MAX_PROGRAM : usize = 256;
+pub const RAM_WORDS : usize = 256;
+pub const MAX_ROUNDS : usize = 64;
+pub const MAX_FUEL : u64 = 4096;
+
+// Decodes instructions until the input runs out or stops making sense.
+pub fn decode(data : &[u8]) {
+    decode_all::<u32>(data);
+    decode_all::<u64>(data);
+}
+
+fn decode_all<U : Read>(data : &[u8]) {
+    let mut it = data.iter().cloned();
+    while Instruction::<U>::read(&mut it).is_ok() {}
+}
+
+struct Buf {
+    bytes : [u8; 32],
+    len : usize
+}
+
+impl WriteSink for Buf {
+    fn write(&mut self, val : u8) {
+        if self.len < self.bytes.len() {
+            self.bytes[self.len] = val;
+        }
+        self.len += 1;
+    }
+}
+
+// Every instruction that decodes must encode back to the bytes it came
+// from, and those must decode to the same instruction.
+pub fn round_trip(data : &[u8]) {
+    round_trip_all::<u32>(data);
+    round_trip_all::<u64>(data);
+}
+
+fn round_trip_all<U : Prim + Read + Write + PartialEq + Debug>(data : &[u8]) {
+    let mut rest = data;
+    loop {
+        let mut it = rest.iter().cloned();
+        let instr = match Instruction::<U>::read(&mut it) {
+            Err(()) => return,
+            Ok(instr) => instr
+        };
+        let used = rest.len() - it.len();
+        let mut buf = Buf { bytes : [0; 32], len : 0 };
+        instr.write(&mut buf);
+        assert_eq!(&buf.bytes[..buf.len], &rest[..used]);
+        assert_eq!(Instruction::<U>::read(&mut buf.bytes[..buf.len].iter().cloned()), Ok(instr));
+        rest = &rest[used..];
+    }
+}
+
+// The host's side of a run, drawn from the input. Reads past the end give
+// zeros.
+struct Tape<'a>(&'a [u8]);
+
+impl<'a> Tape<'a> {
+    fn byte(&mut self) -> u8 {
+        match self.0.split_first() {
+            None => 0,
+            Some((b, rest)) => {
+                self.0 = rest;
+                *b
+            }
+        }
+    }
+
+    fn short(&mut self) -> usize {
+        (self.byte() as usize) << 8 | self.byte() as usize
+    }
+
+    fn word<U : Prim>(&mut self) -> U {
+        let mut w = 0u64;
+        for _ in 0..U::BYTES {
+            w = w << 8 | self.byte() as u64;
+        }
+        U::from_u64(w)
+    }
+}
+
+// Input layout:
+//   width:1     bit 0 picks the 64-bit VM
+//   fuel:2      per eval_instrs, taken modulo MAX_FUEL
+//   n:2         length of the program
+//   program:n   instructions, decoded until one fails
+//   tape:..     stack, vector table and Q format settings, then answers
+//               to calls and inputs, and interrupts to raise
+pub fn run(data : &[u8]) {
+    let mut tape = Tape(data);
+    let width = tape.byte();
+    let fuel = tape.short() as u64;
+    let n = tape.short();
+    let (program, rest) = tape.0.split_at(if n < tape.0.len() {n} else {tape.0.len()});
+    let tape = Tape(rest);
+    if width & 1 == 0 {
+        run_with::<u32, i32>(program, fuel % MAX_FUEL, tape)
+    } else {
+        run_with::<u64, i64>(program, fuel % MAX_FUEL, tape)
+    }
+}
+
+fn run_with<U : Compl<S> + Read + Debug, S : Compl<U>>(bytes : &[u8], fuel : u64, mut tape : Tape) {
+    let mut program = [Instruction::Invalid; MAX_PROGRAM];
+    let mut len = 0;
+    let mut it = bytes.iter().cloned();
+    while len < MAX_PROGRAM {
+        match Instruction::<U>::read(&mut it) {
+            Err(()) => break,
+            Ok(instr) => program[len] = instr
+        }
+        len += 1;
+    }
+    let mut code = LRU4x64::new(MemFetch(&program[..len]));
+    let mut ram = [U::zero(); RAM_WORDS];
+    let mut state : State<U, S> = State::new(&mut ram);
+    let _ = state.set_stack(tape.word(), tape.word());
+    let _ = state.set_vectors(tape.word());
+    let _ = state.set_q_format(tape.byte() as u32);
+    for _ in 0..MAX_ROUNDS {
+        match state.eval_instrs(U::from_u64(fuel), &mut code) {
+            Err(_) | Ok(MutNotice::Halt) => return,
+            Ok(MutNotice::Thrash) | Ok(MutNotice::Out{..}) => (),
+            Ok(MutNotice::In{..}) => {
+                let _ = state.resume_in(tape.word());
+            },
+            Ok(MutNotice::Call{slice, reply, ..}) => {
+                for w in slice.iter_mut() {
+                    *w = tape.word();
+                }
+                match tape.byte() {
+                    0 => reply.resume(status(Ok(()))),
+                    // Makes the guest call again
+                    0xFF => reply.cancel(),
+                    code => reply.resume(tape.word::<U>() | U::from_u64(code as u64))
+                }
+            }
+        };
+        let _ = state.raise(tape.byte() as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use self::std::fs;
+    use self::std::path::Path;
+
+    fn replay(target : &str, f : fn(&[u8])) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/regressions").join(target);
+        let mut count = 0;
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            f(&fs::read(&path).unwrap());
+            count += 1;
+        }
+        assert!(count > 0, "no regression inputs in {:?}", dir);
+    }
+
+    #[test]
+    fn decode_regressions() {
+        replay("decode", decode);
+    }
+
+    #[test]
+    fn round_trip_regressions() {
+        replay("round_trip", round_trip);
+    }
+
+    #[test]
+    fn run_regressions() {
+        replay("run", run);
+    }
+}
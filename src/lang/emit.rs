@@ -0,0 +1,267 @@
+// Allocated functions to Instructions.
+//
+// A call pushes the registers it must save (see regalloc.rs), then the
+// arguments in order, puts the return address in RE and jumps. The callee
+// pushes RE and then one word per spill slot, so its frame, from SP up, is
+//
+//     slot 0, slot 1, .. | saved RE | last argument, .., first argument
+//
+// and it returns by dropping the slots, popping RE and jumping through it,
+// with the result in RC. The caller drops the arguments and pops what it
+// saved. Only Push grows the stack, so running out of it is a
+// StackOverflow rather than a write over the globals.
+//
+// Frame offsets are from SP, which moves while a call is being set up, so
+// the emitter keeps count of the words pushed since the function's body
+// started.
+
+use super::ir::*;
+use super::regalloc::{Alloc, Loc};
+use evaluator::*;
+use evaluator::Instruction::*;
+use evaluator::Reg::*;
+use std::vec::Vec;
+
+// Where a spilled operand is loaded to
+const SCRATCH : [Reg; 2] = [RB, RC];
+const RESULT : Reg = RC;
+const TEMP : Reg = RD;
+const LINK : Reg = RE;
+
+struct Emit<'f, U> {
+    code : Vec<Instruction<U>>,
+    // Code that needs the address of a function or label filled in
+    func_fixups : Vec<(usize, usize)>,
+    label_fixups : Vec<(usize, Label)>,
+    f : &'f Func,
+    alloc : &'f Alloc,
+    pushed : u64
+}
+
+impl<'f, U : Prim> Emit<'f, U> {
+    fn op(&mut self, instr : Instruction<U>) {
+        self.code.push(instr)
+    }
+
+    fn here(&self) -> U {
+        U::from_u64(self.code.len() as u64)
+    }
+
+    fn imm(&self, v : u64) -> U {
+        U::from_u64(v)
+    }
+
+    // Offset from SP of a spilled vreg
+    fn offset(&self, loc : Loc) -> u64 {
+        self.pushed + match loc {
+            Loc::Slot(s) => s as u64,
+            Loc::Arg(i) => (self.alloc.slots + 1 + (self.f.params - 1 - i)) as u64,
+            Loc::Reg(_) | Loc::Nowhere => 0
+        }
+    }
+
+    fn spill_access(&mut self, dir : Dir, loc : Loc, val : Reg) {
+        let off = self.imm(self.offset(loc));
+        self.op(RamI{dir, ptr : SP, off, val});
+    }
+
+    // The register holding v, loading it into scratch if it's spilled
+    fn read(&mut self, v : V, scratch : Reg) -> Reg {
+        match self.alloc.loc[v] {
+            Loc::Reg(r) => r,
+            loc => {
+                self.spill_access(Dir::Read, loc, scratch);
+                scratch
+            }
+        }
+    }
+
+    // The register to compute v in; pass it to written() afterwards
+    fn target(&self, v : V) -> Reg {
+        match self.alloc.loc[v] {
+            Loc::Reg(r) => r,
+            _ => SCRATCH[0]
+        }
+    }
+
+    fn written(&mut self, v : V, from : Reg) {
+        match self.alloc.loc[v] {
+            Loc::Reg(r) => self.mov(r, from),
+            Loc::Nowhere => (),
+            loc => self.spill_access(Dir::Write, loc, from)
+        }
+    }
+
+    fn mov(&mut self, dst : Reg, src : Reg) {
+        if dst != src {
+            self.op(Sm2I{op : M2Op::Add, r1 : src, imm : U::zero(), r3 : dst});
+        }
+    }
+
+    fn jump(&mut self, cond : Cond, flag : Reg, to : Label) {
+        self.label_fixups.push((self.code.len(), to));
+        self.op(SJumpI{cond, flag, mode : Addr::Abs, target : U::zero()});
+    }
+
+    fn instr(&mut self, i : usize, op : &Op, epilogue : Label) {
+        match *op {
+            Op::Param{dst, index} => if let Loc::Reg(r) = self.alloc.loc[dst] {
+                self.spill_access(Dir::Read, Loc::Arg(index), r);
+            },
+            Op::Const{dst, val} => {
+                let r = self.target(dst);
+                let val = self.imm(val);
+                self.op(Lit{val, reg : r});
+                self.written(dst, r);
+            },
+            Op::Mov{dst, src} => {
+                let r = self.read(src, SCRATCH[0]);
+                self.written(dst, r);
+            },
+            Op::Bin{op, dst, a, b} => {
+                let (r1, r2) = (self.read(a, SCRATCH[0]), self.read(b, SCRATCH[1]));
+                let r3 = self.target(dst);
+                self.op(Sm2{op, r1, r2, r3});
+                self.written(dst, r3);
+            },
+            Op::BinI{op, dst, a, imm} => {
+                let r1 = self.read(a, SCRATCH[0]);
+                let r3 = self.target(dst);
+                let imm = self.imm(imm);
+                self.op(Sm2I{op, r1, imm, r3});
+                self.written(dst, r3);
+            },
+            Op::Rem{dst, a, b} => {
+                let (r1, r2) = (self.read(a, SCRATCH[0]), self.read(b, SCRATCH[1]));
+                let r4 = self.target(dst);
+                self.op(Sm4{op : M4Op::DivRem, r1, r2, r3 : TEMP, r4});
+                self.written(dst, r4);
+            },
+            Op::Load{dst, addr, off} => {
+                let ptr = self.read(addr, SCRATCH[0]);
+                let val = self.target(dst);
+                let off = self.imm(off);
+                self.op(RamI{dir : Dir::Read, ptr, off, val});
+                self.written(dst, val);
+            },
+            Op::Store{addr, off, val} => {
+                let (ptr, val) = (self.read(addr, SCRATCH[0]), self.read(val, SCRATCH[1]));
+                let off = self.imm(off);
+                self.op(RamI{dir : Dir::Write, ptr, off, val});
+            },
+            Op::LoadAbs{dst, addr} => {
+                let val = self.target(dst);
+                let addr = self.imm(addr);
+                self.op(Lit{val : addr, reg : TEMP});
+                self.op(Ram{dir : Dir::Read, ptr : TEMP, val});
+                self.written(dst, val);
+            },
+            Op::StoreAbs{addr, val} => {
+                let val = self.read(val, SCRATCH[0]);
+                let addr = self.imm(addr);
+                self.op(Lit{val : addr, reg : TEMP});
+                self.op(Ram{dir : Dir::Write, ptr : TEMP, val});
+            },
+            Op::Label(_) => (),
+            Op::Jump(to) => self.jump(Cond::Always, R0, to),
+            Op::Branch{cond, flag, to} => {
+                let flag = self.read(flag, SCRATCH[0]);
+                self.jump(cond, flag, to);
+            },
+            Op::Call{dst, func, ref args} => {
+                let saves = &self.alloc.saves[i];
+                for &r in saves.iter() {
+                    self.op(Push{reg : r});
+                    self.pushed += 1;
+                }
+                for &a in args.iter() {
+                    let r = self.read(a, SCRATCH[0]);
+                    self.op(Push{reg : r});
+                    self.pushed += 1;
+                }
+                let ret = self.here().wrapping_add(self.imm(3));
+                self.op(Lit{val : ret, reg : LINK});
+                self.func_fixups.push((self.code.len(), func));
+                self.op(Lit{val : U::zero(), reg : TEMP});
+                self.op(UJump{cond : Cond::Always, flag : R0, dest : TEMP});
+                if !args.is_empty() {
+                    let n = self.imm(args.len() as u64);
+                    self.op(Sm2I{op : M2Op::Add, r1 : SP, imm : n, r3 : SP});
+                    self.pushed -= args.len() as u64;
+                }
+                for &r in saves.iter().rev() {
+                    self.op(Pop{reg : r});
+                    self.pushed -= 1;
+                }
+                self.written(dst, RESULT);
+            },
+            Op::Ret(val) => {
+                let r = self.read(val, RESULT);
+                self.mov(RESULT, r);
+                // The epilogue comes straight after the last operation
+                if i + 1 < self.f.ops.len() {
+                    self.jump(Cond::Always, R0, epilogue);
+                }
+            },
+            Op::Out{port, val} => {
+                let reg = self.read(val, SCRATCH[0]);
+                let port = self.imm(port);
+                self.op(Out{reg, port});
+            },
+            Op::In{dst, port} => {
+                let reg = self.target(dst);
+                let port = self.imm(port);
+                self.op(In{reg, port});
+                self.written(dst, reg);
+            }
+        }
+    }
+}
+
+// The whole program: a call to main and a Halt, then each function.
+pub fn emit<U : Prim>(unit : &Unit, allocs : &[Alloc]) -> Vec<Instruction<U>> {
+    let mut code = vec![
+        Lit{val : U::from_u64(3), reg : LINK},
+        Lit{val : U::zero(), reg : TEMP},
+        UJump{cond : Cond::Always, flag : R0, dest : TEMP},
+        Halt
+    ];
+    let mut func_fixups = vec![(1, unit.main)];
+    let mut addrs = Vec::new();
+    for (f, alloc) in unit.funcs.iter().zip(allocs) {
+        addrs.push(code.len());
+        let mut e = Emit {code, func_fixups, label_fixups : Vec::new(), f, alloc, pushed : 0};
+        e.op(Push{reg : LINK});
+        for _ in 0..alloc.slots {
+            e.op(Push{reg : TEMP});
+        }
+        let mut labels = vec![0; f.labels + 1];
+        let epilogue = f.labels;
+        for (i, op) in f.ops.iter().enumerate() {
+            if let Op::Label(l) = *op {
+                labels[l] = e.code.len();
+            }
+            e.instr(i, op, epilogue);
+        }
+        labels[epilogue] = e.code.len();
+        if alloc.slots > 0 {
+            let n = e.imm(alloc.slots as u64);
+            e.op(Sm2I{op : M2Op::Add, r1 : SP, imm : n, r3 : SP});
+        }
+        e.op(Pop{reg : LINK});
+        e.op(UJump{cond : Cond::Always, flag : R0, dest : LINK});
+        for &(pc, l) in e.label_fixups.iter() {
+            if let SJumpI{ref mut target, ..} = e.code[pc] {
+                *target = U::from_u64(labels[l] as u64);
+            }
+        }
+        code = e.code;
+        func_fixups = e.func_fixups;
+    }
+    for (pc, func) in func_fixups {
+        if let Lit{ref mut val, ..} = code[pc] {
+            *val = U::from_u64(addrs[func] as u64);
+        }
+    }
+    code
+}
@@ -0,0 +1,446 @@
+// The syntax tree lowered to a list of operations per function, on virtual
+// registers: one per variable, plus one per intermediate value. Variables
+// are assigned to more than once, so this isn't SSA; regalloc.rs works out
+// where each is live. Control flow is by labels, local to the function.
+
+use super::{Error, Span};
+use super::parse::*;
+use evaluator::{Cond, M2Op};
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+pub type V = usize;
+pub type Label = usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    // Parameter index, from the caller's frame
+    Param {dst : V, index : usize},
+    Const {dst : V, val : u64},
+    Mov {dst : V, src : V},
+    // Signed, as in Sm2 and Sm2I
+    Bin {op : M2Op, dst : V, a : V, b : V},
+    BinI {op : M2Op, dst : V, a : V, imm : u64},
+    Rem {dst : V, a : V, b : V},
+    // ram[addr + off]
+    Load {dst : V, addr : V, off : u64},
+    Store {addr : V, off : u64, val : V},
+    // ram[addr], for globals
+    LoadAbs {dst : V, addr : u64},
+    StoreAbs {addr : u64, val : V},
+    Label(Label),
+    Jump(Label),
+    // Jumps if cond holds for flag, signed
+    Branch {cond : Cond, flag : V, to : Label},
+    Call {dst : V, func : usize, args : Vec<V>},
+    // Returns val from the function
+    Ret(V),
+    Out {port : u64, val : V},
+    In {dst : V, port : u64}
+}
+
+impl Op {
+    pub fn def(&self) -> Option<V> {
+        match *self {
+            Op::Param{dst,..} | Op::Const{dst,..} | Op::Mov{dst,..} | Op::Bin{dst,..} | Op::BinI{dst,..} |
+            Op::Rem{dst,..} | Op::Load{dst,..} | Op::LoadAbs{dst,..} | Op::Call{dst,..} | Op::In{dst,..} => Some(dst),
+            _ => None
+        }
+    }
+
+    pub fn uses(&self) -> Vec<V> {
+        match *self {
+            Op::Mov{src,..} => vec![src],
+            Op::Bin{a,b,..} | Op::Rem{a,b,..} => vec![a, b],
+            Op::BinI{a,..} => vec![a],
+            Op::Load{addr,..} => vec![addr],
+            Op::Store{addr,val,..} => vec![addr, val],
+            Op::StoreAbs{val,..} | Op::Out{val,..} | Op::Ret(val) => vec![val],
+            Op::Branch{flag,..} => vec![flag],
+            Op::Call{ref args,..} => args.clone(),
+            _ => Vec::new()
+        }
+    }
+}
+
+pub struct Func {
+    pub params : usize,
+    pub vregs : usize,
+    pub labels : usize,
+    pub ops : Vec<Op>
+}
+
+pub struct Unit {
+    pub funcs : Vec<Func>,
+    // Index of main in funcs
+    pub main : usize,
+    pub globals : u64
+}
+
+const BUILTINS : &[&str] = &["in", "out"];
+
+fn err<T>(span : Span, msg : String) -> Result<T, Error> {
+    Err(Error::new(span, msg))
+}
+
+// A global's address, and its length if it's an array
+#[derive(Copy, Clone)]
+struct Global {
+    addr : u64,
+    len : Option<u64>
+}
+
+struct Lower<'a, 's : 'a> {
+    globals : &'a [(&'s str, Global)],
+    // Name and parameter count of every function
+    funcs : &'a [(&'s str, usize)],
+    // The largest number that fits in a word
+    max : u64,
+    // Innermost last
+    scopes : Vec<Vec<(&'s str, V)>>,
+    ops : Vec<Op>,
+    vregs : usize,
+    labels : usize
+}
+
+impl<'a, 's> Lower<'a, 's> {
+    fn vreg(&mut self) -> V {
+        self.vregs += 1;
+        self.vregs - 1
+    }
+
+    fn label(&mut self) -> Label {
+        self.labels += 1;
+        self.labels - 1
+    }
+
+    fn local(&self, name : &str) -> Option<V> {
+        self.scopes.iter().rev().flat_map(|s| s.iter().rev()).find(|&&(n, _)| n == name).map(|&(_, v)| v)
+    }
+
+    fn global(&self, name : &str) -> Option<Global> {
+        self.globals.iter().find(|&&(n, _)| n == name).map(|&(_, g)| g)
+    }
+
+    fn func(&self, name : &str) -> Option<(usize, usize)> {
+        self.funcs.iter().position(|&(n, _)| n == name).map(|i| (i, self.funcs[i].1))
+    }
+
+    fn num(&self, n : u64, span : Span) -> Result<u64, Error> {
+        if n > self.max {return err(span, format!("{} doesn't fit in a word", n))};
+        Ok(n)
+    }
+
+    // The value of a name on its own
+    fn var(&mut self, name : &str, span : Span) -> Result<V, Error> {
+        if let Some(v) = self.local(name) {
+            return Ok(v)
+        }
+        match self.global(name) {
+            Some(Global{addr, len : None}) => {
+                let dst = self.vreg();
+                self.ops.push(Op::LoadAbs{dst, addr});
+                Ok(dst)
+            },
+            Some(Global{addr, len : Some(_)}) => {
+                let dst = self.vreg();
+                self.ops.push(Op::Const{dst, val : addr});
+                Ok(dst)
+            },
+            None if self.func(name).is_some() || BUILTINS.contains(&name) =>
+                err(span, format!("`{}` is a function; call it", name)),
+            None => err(span, format!("no variable `{}`", name))
+        }
+    }
+
+    // The address of name[index], as a register and an offset
+    fn element(&mut self, name : &str, index : &Expr<'s>, span : Span) -> Result<(V, u64), Error> {
+        if self.local(name).is_none() {
+            if let Some(Global{addr, len : Some(_)}) = self.global(name) {
+                return Ok((self.expr(index)?, addr))
+            }
+        }
+        let base = self.var(name, span)?;
+        let i = self.expr(index)?;
+        let dst = self.vreg();
+        self.ops.push(Op::Bin{op : M2Op::Add, dst, a : base, b : i});
+        Ok((dst, 0))
+    }
+
+    // 0 or 1: whether v is nonzero
+    fn truth(&mut self, v : V) -> V {
+        let zero = self.vreg();
+        self.ops.push(Op::BinI{op : M2Op::Equ, dst : zero, a : v, imm : 0});
+        let dst = self.vreg();
+        self.ops.push(Op::BinI{op : M2Op::Equ, dst, a : zero, imm : 0});
+        dst
+    }
+
+    fn not(&mut self, v : V) -> V {
+        let dst = self.vreg();
+        self.ops.push(Op::BinI{op : M2Op::Equ, dst, a : v, imm : 0});
+        dst
+    }
+
+    fn expr(&mut self, e : &Expr<'s>) -> Result<V, Error> {
+        Ok(match e.kind {
+            ExprKind::Num(n) => {
+                let val = self.num(n, e.span)?;
+                let dst = self.vreg();
+                self.ops.push(Op::Const{dst, val});
+                dst
+            },
+            ExprKind::Var(name) => self.var(name, e.span)?,
+            ExprKind::Index(name, ref index) => {
+                let (addr, off) = self.element(name, index, e.span)?;
+                let dst = self.vreg();
+                self.ops.push(Op::Load{dst, addr, off});
+                dst
+            },
+            ExprKind::Call(name, ref args) => self.call(name, args, e.span)?,
+            ExprKind::Neg(ref a) => {
+                let a = self.expr(a)?;
+                let dst = self.vreg();
+                self.ops.push(Op::BinI{op : M2Op::Mul, dst, a, imm : self.max});
+                dst
+            },
+            ExprKind::Not(ref a) => {
+                let a = self.expr(a)?;
+                self.not(a)
+            },
+            ExprKind::Bin(op, ref a, ref b) => self.binary(op, a, b)?
+        })
+    }
+
+    fn binary(&mut self, op : BinOp, a : &Expr<'s>, b : &Expr<'s>) -> Result<V, Error> {
+        if op == BinOp::And || op == BinOp::Or {
+            // dst = a != 0, and then b != 0 unless that settles it
+            let end = self.label();
+            let a = self.expr(a)?;
+            let dst = self.truth(a);
+            let cond = if op == BinOp::And {Cond::EqZ} else {Cond::NeZ};
+            self.ops.push(Op::Branch{cond, flag : dst, to : end});
+            let b = self.expr(b)?;
+            let t = self.truth(b);
+            self.ops.push(Op::Mov{dst, src : t});
+            self.ops.push(Op::Label(end));
+            return Ok(dst)
+        }
+        let a = self.expr(a)?;
+        let (m2, negate) = match op {
+            BinOp::Add => (M2Op::Add, false),
+            BinOp::Sub => (M2Op::Sub, false),
+            BinOp::Mul => (M2Op::Mul, false),
+            BinOp::Div => (M2Op::Div, false),
+            BinOp::Eq => (M2Op::Equ, false),
+            BinOp::Ne => (M2Op::Equ, true),
+            BinOp::Lt => (M2Op::Lt, false),
+            BinOp::Ge => (M2Op::Lt, true),
+            BinOp::Gt => (M2Op::Gt, false),
+            BinOp::Le => (M2Op::Gt, true),
+            BinOp::Rem => {
+                let b = self.expr(b)?;
+                let dst = self.vreg();
+                self.ops.push(Op::Rem{dst, a, b});
+                return Ok(dst)
+            },
+            BinOp::And | BinOp::Or => unreachable!()
+        };
+        let dst = self.vreg();
+        match b.kind {
+            ExprKind::Num(n) => {
+                let imm = self.num(n, b.span)?;
+                self.ops.push(Op::BinI{op : m2, dst, a, imm});
+            },
+            _ => {
+                let b = self.expr(b)?;
+                self.ops.push(Op::Bin{op : m2, dst, a, b});
+            }
+        }
+        Ok(if negate {self.not(dst)} else {dst})
+    }
+
+    fn port(&self, e : &Expr<'s>) -> Result<u64, Error> {
+        match e.kind {
+            ExprKind::Num(n) => self.num(n, e.span),
+            _ => err(e.span, "ports are numbers".to_string())
+        }
+    }
+
+    fn call(&mut self, name : &str, args : &[Expr<'s>], span : Span) -> Result<V, Error> {
+        let arity = match (name, self.func(name)) {
+            ("in", _) => 1,
+            ("out", _) => 2,
+            (_, Some((_, n))) => n,
+            (_, None) => return err(span, format!("no function `{}`", name))
+        };
+        if args.len() != arity {
+            return err(span, format!("`{}` takes {} argument{}, not {}", name, arity, if arity == 1 {""} else {"s"}, args.len()))
+        }
+        match name {
+            "in" => {
+                let port = self.port(&args[0])?;
+                let dst = self.vreg();
+                self.ops.push(Op::In{dst, port});
+                Ok(dst)
+            },
+            "out" => {
+                let port = self.port(&args[0])?;
+                let val = self.expr(&args[1])?;
+                self.ops.push(Op::Out{port, val});
+                Ok(val)
+            },
+            _ => {
+                let func = self.func(name).map_or(0, |(i, _)| i);
+                let mut vals = Vec::new();
+                for a in args {
+                    vals.push(self.expr(a)?);
+                }
+                let dst = self.vreg();
+                self.ops.push(Op::Call{dst, func, args : vals});
+                Ok(dst)
+            }
+        }
+    }
+
+    fn block(&mut self, stmts : &[Stmt<'s>]) -> Result<(), Error> {
+        self.scopes.push(Vec::new());
+        for s in stmts {
+            self.stmt(s)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn stmt(&mut self, s : &Stmt<'s>) -> Result<(), Error> {
+        match *s {
+            Stmt::Var{name, span, ref init} => {
+                if self.scopes.last().is_some_and(|s| s.iter().any(|&(n, _)| n == name)) {
+                    return err(span, format!("`{}` is already declared in this block", name))
+                }
+                let src = self.expr(init)?;
+                let dst = self.vreg();
+                self.ops.push(Op::Mov{dst, src});
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push((name, dst));
+                }
+            },
+            Stmt::Assign{ref place, ref val} => match place.kind {
+                ExprKind::Index(name, ref index) => {
+                    let (addr, off) = self.element(name, index, place.span)?;
+                    let val = self.expr(val)?;
+                    self.ops.push(Op::Store{addr, off, val});
+                },
+                ExprKind::Var(name) => {
+                    if let Some(dst) = self.local(name) {
+                        let src = self.expr(val)?;
+                        self.ops.push(Op::Mov{dst, src});
+                    } else {
+                        match self.global(name) {
+                            Some(Global{addr, len : None}) => {
+                                let val = self.expr(val)?;
+                                self.ops.push(Op::StoreAbs{addr, val});
+                            },
+                            Some(_) => return err(place.span, format!("`{}` is an array; assign to its elements", name)),
+                            None => return err(place.span, format!("no variable `{}`", name))
+                        }
+                    }
+                },
+                _ => return err(place.span, "can only assign to a variable or an element".to_string())
+            },
+            Stmt::If{ref cond, ref then, ref els} => {
+                let c = self.expr(cond)?;
+                let (other, end) = (self.label(), self.label());
+                self.ops.push(Op::Branch{cond : Cond::EqZ, flag : c, to : other});
+                self.block(then)?;
+                if !els.is_empty() {
+                    self.ops.push(Op::Jump(end));
+                }
+                self.ops.push(Op::Label(other));
+                self.block(els)?;
+                self.ops.push(Op::Label(end));
+            },
+            Stmt::While{ref cond, ref body} => {
+                let (top, end) = (self.label(), self.label());
+                self.ops.push(Op::Label(top));
+                let c = self.expr(cond)?;
+                self.ops.push(Op::Branch{cond : Cond::EqZ, flag : c, to : end});
+                self.block(body)?;
+                self.ops.push(Op::Jump(top));
+                self.ops.push(Op::Label(end));
+            },
+            Stmt::Return(ref val) => {
+                let v = match *val {
+                    Some(ref e) => self.expr(e)?,
+                    None => {
+                        let dst = self.vreg();
+                        self.ops.push(Op::Const{dst, val : 0});
+                        dst
+                    }
+                };
+                self.ops.push(Op::Ret(v));
+            },
+            Stmt::Expr(ref e) => {
+                self.expr(e)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Checks names and lays out the globals, then lowers each function.
+// max is the largest number that fits in a word.
+pub fn lower(ast : &Ast, max : u64) -> Result<Unit, Error> {
+    let mut globals = Vec::new();
+    let mut next = 0u64;
+    for g in ast.globals.iter() {
+        if globals.iter().any(|&(n, _)| n == g.name) {
+            return err(g.span, format!("`{}` is already declared", g.name))
+        }
+        let words = g.len.unwrap_or(1);
+        globals.push((g.name, Global{addr : next, len : g.len}));
+        next = match next.checked_add(words) {
+            Some(end) if end <= max => end,
+            _ => return err(g.span, format!("`{}` doesn't fit in RAM", g.name))
+        };
+    }
+    let mut funcs : Vec<(&str, usize)> = Vec::new();
+    for f in ast.funcs.iter() {
+        if funcs.iter().any(|&(n, _)| n == f.name) || BUILTINS.contains(&f.name) {
+            return err(f.span, format!("`{}` is already a function", f.name))
+        }
+        if globals.iter().any(|&(n, _)| n == f.name) {
+            return err(f.span, format!("`{}` is already a global", f.name))
+        }
+        funcs.push((f.name, f.params.len()));
+    }
+    let main = match ast.funcs.iter().position(|f| f.name == "main") {
+        Some(i) => i,
+        None => return err(Span {start : 0, end : 0}, "no `main` function".to_string())
+    };
+    if !ast.funcs[main].params.is_empty() {
+        return err(ast.funcs[main].span, "`main` takes no arguments".to_string())
+    }
+    let mut out = Vec::new();
+    for f in ast.funcs.iter() {
+        let mut l = Lower {
+            globals : &globals, funcs : &funcs, max,
+            scopes : vec![Vec::new()], ops : Vec::new(), vregs : 0, labels : 0
+        };
+        // Parameters are the first vregs
+        for (index, &(name, span)) in f.params.iter().enumerate() {
+            if f.params[.. index].iter().any(|&(n, _)| n == name) {
+                return err(span, format!("`{}` is already a parameter", name))
+            }
+            let dst = l.vreg();
+            l.ops.push(Op::Param{dst, index});
+            l.scopes[0].push((name, dst));
+        }
+        l.block(&f.body)?;
+        let zero = l.vreg();
+        l.ops.push(Op::Const{dst : zero, val : 0});
+        l.ops.push(Op::Ret(zero));
+        out.push(Func {params : f.params.len(), vregs : l.vregs, labels : l.labels, ops : l.ops});
+    }
+    Ok(Unit {funcs : out, main, globals : next})
+}
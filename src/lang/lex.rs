@@ -0,0 +1,73 @@
+// Source text to tokens, each with its span. // comments run to the end of
+// the line.
+
+use super::{Error, Span};
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tok<'s> {
+    Num(u64),
+    // Names and keywords
+    Ident(&'s str),
+    Sym(&'static str),
+    Eof
+}
+
+// Longest first, so "<=" isn't read as "<" "="
+const SYMS : &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||",
+    "+", "-", "*", "/", "%", "<", ">", "=", "!",
+    "(", ")", "{", "}", "[", "]", ",", ";"
+];
+
+pub fn lex<'s>(src : &'s str) -> Result<Vec<(Tok<'s>, Span)>, Error> {
+    let bytes = src.as_bytes();
+    let mut toks = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if src[i ..].starts_with("//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_digit() {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let text = &src[start .. i];
+            let digits : String = text.chars().filter(|&c| c != '_').collect();
+            let val = match digits.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => digits.parse()
+            };
+            let span = Span {start, end : i};
+            match val {
+                Ok(v) => toks.push((Tok::Num(v), span)),
+                Err(_) => return Err(Error::new(span, format!("bad number `{}`", text)))
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            toks.push((Tok::Ident(&src[start .. i]), Span {start, end : i}));
+        } else {
+            match SYMS.iter().find(|s| src[i ..].starts_with(**s)) {
+                Some(s) => {
+                    i += s.len();
+                    toks.push((Tok::Sym(s), Span {start, end : i}));
+                },
+                None => {
+                    let ch = src[i ..].chars().next().unwrap_or('?');
+                    let span = Span {start, end : i + ch.len_utf8()};
+                    return Err(Error::new(span, format!("unexpected `{}`", ch)))
+                }
+            }
+        }
+    }
+    toks.push((Tok::Eof, Span {start : src.len(), end : src.len()}));
+    Ok(toks)
+}
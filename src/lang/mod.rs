@@ -0,0 +1,140 @@
+// A small language for guest programs, compiled to Instructions.
+//
+//     var table[16];              // global array, in RAM
+//     var count;                  // global word
+//
+//     fn fib(n) {
+//         if n < 2 { return n; }
+//         return fib(n - 1) + fib(n - 2);
+//     }
+//
+//     fn main() {
+//         var i = 0;
+//         while i < 16 {
+//             table[i] = fib(i);
+//             i = i + 1;
+//         }
+//         out(1, table[15]);
+//     }
+//
+// Every value is a word, read as signed by / % < <= > >=. Arithmetic wraps
+// and dividing by zero stops the guest with DivByZero. && and || short
+// circuit and give 0 or 1, as do the comparisons and !. There are no bitwise
+// operators, as the VM has none.
+//
+// Globals live at the bottom of RAM in the order they're declared, and a
+// global array's name on its own is its address. x[i] reads the word at
+// x + i, where x is a global array or any word holding an address, so
+// arrays can be passed to functions; indices aren't checked beyond RAM.
+// Locals are declared with var and scoped to their block; they are single
+// words, as only globals can be arrays. Expressions and statements may nest
+// about a hundred deep (see parse.rs). out(port, x) stops the guest with Out
+// and gives x; in(port) stops with In and gives the host's answer. Ports are
+// numbers.
+//
+// The program starts by calling main, and halts when it returns, with the
+// return value in RC. Calls need a stack: the host must give one with
+// State::set_stack, above the globals; see Program::globals.
+//
+// Register use: R0 to RA hold values, allocated by linear scan over each
+// function (see regalloc.rs) and spilled to the function's stack frame when
+// there are too many live at once. RB and RC are scratch for spilled values,
+// and RC also carries return values. RD is scratch for addresses and call
+// targets, RE holds the return address and RF is SP.
+
+mod lex;
+mod parse;
+mod ir;
+mod regalloc;
+mod emit;
+#[cfg(test)]
+mod tests;
+
+use evaluator::*;
+use std::fmt;
+use std::string::String;
+use std::vec::Vec;
+
+// Byte offsets into the source
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start : usize,
+    pub end : usize
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub span : Span,
+    pub msg : String
+}
+
+impl Error {
+    fn new(span : Span, msg : String) -> Error {
+        Error {span, msg}
+    }
+
+    // 1-based line and column of the start of the span
+    pub fn line_col(&self, src : &str) -> (usize, usize) {
+        let before = &src[.. self.span.start.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let col = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        (line, col)
+    }
+
+    // The message with its position, the line it's on, and the span
+    // underlined, e.g.
+    //
+    //     3:9: no variable `y`
+    //         x = y + 1;
+    //             ^
+    pub fn render(&self, src : &str) -> String {
+        let (line, col) = self.line_col(src);
+        let text = src.lines().nth(line - 1).unwrap_or("");
+        let width = src[self.span.start.min(src.len()) .. self.span.end.min(src.len())]
+            .chars().take_while(|&c| c != '\n').count().max(1);
+        format!("{}:{}: {}\n    {}\n    {}{}", line, col, self.msg, text,
+                " ".repeat(col - 1), "^".repeat(width))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}: {}", self.span.start, self.span.end, self.msg)
+    }
+}
+
+pub struct Program<U> {
+    pub code : Vec<Instruction<U>>,
+    // Words of RAM the globals take, from 0
+    pub globals : u64
+}
+
+impl<U : Prim + Write> Program<U> {
+    // The code in the usual binary encoding of Instructions, one after another
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Bytes(Vec::new());
+        for instr in self.code.iter() {
+            instr.write(&mut bytes);
+        }
+        bytes.0
+    }
+}
+
+struct Bytes(Vec<u8>);
+
+impl WriteSink for Bytes {
+    fn write(&mut self, val : u8) {
+        self.0.push(val)
+    }
+}
+
+// Compiles a whole program for a VM with words of U. Stops at the first
+// error.
+pub fn compile<U : Prim>(src : &str) -> Result<Program<U>, Error> {
+    let toks = lex::lex(src)?;
+    let ast = parse::parse(&toks)?;
+    let max = if U::BYTES >= 8 {!0} else {(1u64 << (8 * U::BYTES)) - 1};
+    let unit = ir::lower(&ast, max)?;
+    let allocs : Vec<regalloc::Alloc> = unit.funcs.iter().map(regalloc::alloc).collect();
+    Ok(Program {code : emit::emit(&unit, &allocs), globals : unit.globals})
+}
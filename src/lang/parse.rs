@@ -0,0 +1,384 @@
+// Tokens to a syntax tree, by recursive descent.
+//
+//     program := (global | func)*
+//     global  := "var" name ("[" num "]")? ";"
+//     func    := "fn" name "(" (name ("," name)*)? ")" block
+//     block   := "{" stmt* "}"
+//     stmt    := "var" name "=" expr ";" | expr "=" expr ";" | expr ";"
+//              | "if" expr block ("else" (block | if))? | "while" expr block
+//              | "return" expr? ";"
+//
+// Binary operators from loosest to tightest: ||, &&, == !=, < <= > >=, + -,
+// * / %, all left associative. Then unary - and !, then calls f(..),
+// indexing x[..] and parentheses.
+//
+// Only globals can be arrays. Expressions, operator chains, statements and
+// else-if chains may nest MAX_DEPTH deep, so that no source can overflow the
+// stack, here or in the later passes.
+
+use super::{Error, Span};
+use super::lex::Tok;
+use std::boxed::Box;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Or, And, Eq, Ne, Lt, Le, Gt, Ge, Add, Sub, Mul, Div, Rem
+}
+
+impl BinOp {
+    fn of(sym : &str) -> Option<(BinOp, u8)> {
+        Some(match sym {
+            "||" => (BinOp::Or, 1),
+            "&&" => (BinOp::And, 2),
+            "==" => (BinOp::Eq, 3),
+            "!=" => (BinOp::Ne, 3),
+            "<" => (BinOp::Lt, 4),
+            "<=" => (BinOp::Le, 4),
+            ">" => (BinOp::Gt, 4),
+            ">=" => (BinOp::Ge, 4),
+            "+" => (BinOp::Add, 5),
+            "-" => (BinOp::Sub, 5),
+            "*" => (BinOp::Mul, 6),
+            "/" => (BinOp::Div, 6),
+            "%" => (BinOp::Rem, 6),
+            _ => return None
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Expr<'s> {
+    pub kind : ExprKind<'s>,
+    pub span : Span
+}
+
+#[derive(Debug)]
+pub enum ExprKind<'s> {
+    Num(u64),
+    Var(&'s str),
+    Index(&'s str, Box<Expr<'s>>),
+    Call(&'s str, Vec<Expr<'s>>),
+    Neg(Box<Expr<'s>>),
+    Not(Box<Expr<'s>>),
+    Bin(BinOp, Box<Expr<'s>>, Box<Expr<'s>>)
+}
+
+#[derive(Debug)]
+pub enum Stmt<'s> {
+    Var {name : &'s str, span : Span, init : Expr<'s>},
+    // place is a Var or an Index
+    Assign {place : Expr<'s>, val : Expr<'s>},
+    If {cond : Expr<'s>, then : Vec<Stmt<'s>>, els : Vec<Stmt<'s>>},
+    While {cond : Expr<'s>, body : Vec<Stmt<'s>>},
+    Return(Option<Expr<'s>>),
+    Expr(Expr<'s>)
+}
+
+#[derive(Debug)]
+pub struct Global<'s> {
+    pub name : &'s str,
+    pub span : Span,
+    // Some for arrays
+    pub len : Option<u64>
+}
+
+#[derive(Debug)]
+pub struct Func<'s> {
+    pub name : &'s str,
+    pub span : Span,
+    pub params : Vec<(&'s str, Span)>,
+    pub body : Vec<Stmt<'s>>
+}
+
+#[derive(Debug)]
+pub struct Ast<'s> {
+    pub globals : Vec<Global<'s>>,
+    pub funcs : Vec<Func<'s>>
+}
+
+struct Parser<'t, 's : 't> {
+    toks : &'t [(Tok<'s>, Span)],
+    pos : usize,
+    depth : usize
+}
+
+const MAX_DEPTH : usize = 100;
+
+const KEYWORDS : &[&str] = &["var", "fn", "if", "else", "while", "return"];
+
+fn describe(tok : Tok) -> String {
+    match tok {
+        Tok::Num(n) => format!("`{}`", n),
+        Tok::Ident(s) => format!("`{}`", s),
+        Tok::Sym(s) => format!("`{}`", s),
+        Tok::Eof => "the end".to_string()
+    }
+}
+
+impl<'t, 's> Parser<'t, 's> {
+    fn peek(&self) -> Tok<'s> {
+        self.toks[self.pos].0
+    }
+
+    fn span(&self) -> Span {
+        self.toks[self.pos].1
+    }
+
+    // The span of the last token taken
+    fn last(&self) -> Span {
+        self.toks[self.pos.saturating_sub(1)].1
+    }
+
+    fn bump(&mut self) -> (Tok<'s>, Span) {
+        let tok = self.toks[self.pos];
+        if tok.0 != Tok::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn error<T>(&self, what : &str) -> Result<T, Error> {
+        Err(Error::new(self.span(), format!("expected {}, found {}", what, describe(self.peek()))))
+    }
+
+    // Goes one level deeper, failing at the next token past MAX_DEPTH
+    fn deeper(&mut self) -> Result<(), Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(Error::new(self.span(), "nested too deeply".to_string()))
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn nested<T>(&mut self, f : fn(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        self.deeper()?;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
+    fn is(&self, sym : &str) -> bool {
+        match self.peek() {
+            Tok::Sym(s) | Tok::Ident(s) => s == sym,
+            _ => false
+        }
+    }
+
+    fn eat(&mut self, sym : &str) -> bool {
+        if self.is(sym) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, sym : &str) -> Result<Span, Error> {
+        if self.is(sym) {Ok(self.bump().1)} else {self.error(&format!("`{}`", sym))}
+    }
+
+    fn name(&mut self) -> Result<(&'s str, Span), Error> {
+        match self.peek() {
+            Tok::Ident(s) if !KEYWORDS.contains(&s) => {
+                let span = self.bump().1;
+                Ok((s, span))
+            },
+            _ => self.error("a name")
+        }
+    }
+
+    fn num(&mut self) -> Result<u64, Error> {
+        match self.peek() {
+            Tok::Num(n) => {
+                self.bump();
+                Ok(n)
+            },
+            _ => self.error("a number")
+        }
+    }
+
+    fn program(&mut self) -> Result<Ast<'s>, Error> {
+        let mut ast = Ast {globals : Vec::new(), funcs : Vec::new()};
+        loop {
+            if self.eat("var") {
+                let (name, span) = self.name()?;
+                let len = if self.eat("[") {
+                    let n = self.num()?;
+                    self.expect("]")?;
+                    Some(n)
+                } else {
+                    None
+                };
+                self.expect(";")?;
+                ast.globals.push(Global {name, span, len});
+            } else if self.eat("fn") {
+                let (name, span) = self.name()?;
+                self.expect("(")?;
+                let mut params = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        params.push(self.name()?);
+                        if self.eat(")") {break};
+                        self.expect(",")?;
+                    }
+                }
+                let body = self.block()?;
+                ast.funcs.push(Func {name, span, params, body});
+            } else if self.peek() == Tok::Eof {
+                return Ok(ast)
+            } else {
+                return self.error("`var` or `fn`")
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt<'s>>, Error> {
+        self.expect("{")?;
+        let mut stmts = Vec::new();
+        while !self.eat("}") {
+            if self.peek() == Tok::Eof {
+                return self.error("`}`")
+            }
+            stmts.push(self.nested(Self::stmt)?);
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt<'s>, Error> {
+        if self.eat("var") {
+            let (name, span) = self.name()?;
+            if self.is("[") {
+                return Err(Error::new(self.span(), format!("`{}` can't be an array; only globals can", name)))
+            }
+            self.expect("=")?;
+            let init = self.expr()?;
+            self.expect(";")?;
+            Ok(Stmt::Var {name, span, init})
+        } else if self.eat("if") {
+            self.if_rest()
+        } else if self.eat("while") {
+            let cond = self.expr()?;
+            let body = self.block()?;
+            Ok(Stmt::While {cond, body})
+        } else if self.eat("return") {
+            let val = if self.is(";") {None} else {Some(self.expr()?)};
+            self.expect(";")?;
+            Ok(Stmt::Return(val))
+        } else {
+            let e = self.expr()?;
+            if self.eat("=") {
+                match e.kind {
+                    ExprKind::Var(_) | ExprKind::Index(..) => (),
+                    _ => return Err(Error::new(e.span, "can only assign to a variable or an element".to_string()))
+                }
+                let val = self.expr()?;
+                self.expect(";")?;
+                Ok(Stmt::Assign {place : e, val})
+            } else {
+                self.expect(";")?;
+                Ok(Stmt::Expr(e))
+            }
+        }
+    }
+
+    // After "if"
+    fn if_rest(&mut self) -> Result<Stmt<'s>, Error> {
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let els = if !self.eat("else") {
+            Vec::new()
+        } else if self.eat("if") {
+            vec![self.nested(Self::if_rest)?]
+        } else {
+            self.block()?
+        };
+        Ok(Stmt::If {cond, then, els})
+    }
+
+    fn expr(&mut self) -> Result<Expr<'s>, Error> {
+        self.nested(|p| p.binary(1))
+    }
+
+    // Operators binding at least as tightly as prec. Each one folded into lhs
+    // nests it a level deeper.
+    fn binary(&mut self, prec : u8) -> Result<Expr<'s>, Error> {
+        let depth = self.depth;
+        let res = self.chain(prec);
+        self.depth = depth;
+        res
+    }
+
+    fn chain(&mut self, prec : u8) -> Result<Expr<'s>, Error> {
+        let mut lhs = self.unary()?;
+        loop {
+            let (op, p) = match self.peek() {
+                Tok::Sym(s) => match BinOp::of(s) {
+                    Some((op, p)) if p >= prec => (op, p),
+                    _ => return Ok(lhs)
+                },
+                _ => return Ok(lhs)
+            };
+            self.deeper()?;
+            self.bump();
+            let rhs = self.binary(p + 1)?;
+            let span = Span {start : lhs.span.start, end : rhs.span.end};
+            lhs = Expr {kind : ExprKind::Bin(op, Box::new(lhs), Box::new(rhs)), span};
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr<'s>, Error> {
+        let start = self.span().start;
+        if self.eat("-") {
+            let e = self.nested(Self::unary)?;
+            let span = Span {start, end : e.span.end};
+            Ok(Expr {kind : ExprKind::Neg(Box::new(e)), span})
+        } else if self.eat("!") {
+            let e = self.nested(Self::unary)?;
+            let span = Span {start, end : e.span.end};
+            Ok(Expr {kind : ExprKind::Not(Box::new(e)), span})
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr<'s>, Error> {
+        let start = self.span().start;
+        if let Tok::Num(n) = self.peek() {
+            let span = self.bump().1;
+            return Ok(Expr {kind : ExprKind::Num(n), span})
+        }
+        if self.eat("(") {
+            let e = self.expr()?;
+            self.expect(")")?;
+            return Ok(e)
+        }
+        let (name, span) = match self.peek() {
+            Tok::Ident(_) => self.name()?,
+            _ => return self.error("an expression")
+        };
+        let kind = if self.eat("(") {
+            let mut args = Vec::new();
+            if !self.eat(")") {
+                loop {
+                    args.push(self.expr()?);
+                    if self.eat(")") {break};
+                    self.expect(",")?;
+                }
+            }
+            ExprKind::Call(name, args)
+        } else if self.eat("[") {
+            let index = self.expr()?;
+            self.expect("]")?;
+            ExprKind::Index(name, Box::new(index))
+        } else {
+            return Ok(Expr {kind : ExprKind::Var(name), span})
+        };
+        Ok(Expr {kind, span : Span {start, end : self.last().end}})
+    }
+}
+
+pub fn parse<'s>(toks : &[(Tok<'s>, Span)]) -> Result<Ast<'s>, Error> {
+    Parser {toks, pos : 0, depth : 0}.program()
+}
@@ -0,0 +1,181 @@
+// Register allocation by linear scan. Liveness is worked out per operation
+// by the usual backward dataflow; each vreg's interval is then everything
+// from the first operation it's live at to the last, holes and all. The
+// intervals are handed R0 to RA in order of start, and when all are taken
+// the one that ends last is spilled to a slot in the stack frame. A spilled
+// parameter stays where the caller pushed it.
+//
+// Values live across a Call are pushed by the caller around it (see
+// Alloc::saves), since the callee is free to use every register.
+
+use super::ir::*;
+use evaluator::Reg;
+use std::vec::Vec;
+
+pub const REGS : &[Reg] = &[
+    Reg::R0, Reg::R1, Reg::R2, Reg::R3, Reg::R4, Reg::R5,
+    Reg::R6, Reg::R7, Reg::R8, Reg::R9, Reg::RA
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Loc {
+    Reg(Reg),
+    // Spill slot in the frame
+    Slot(usize),
+    // The parameter with this index, in the caller's frame
+    Arg(usize),
+    // Never live, so never read or written
+    Nowhere
+}
+
+pub struct Alloc {
+    pub loc : Vec<Loc>,
+    pub slots : usize,
+    // For each Call, by operation index, the registers to save around it
+    pub saves : Vec<Vec<Reg>>
+}
+
+// A set of vregs
+#[derive(Clone, PartialEq, Eq)]
+struct Set(Vec<u64>);
+
+impl Set {
+    fn new(n : usize) -> Set {
+        Set(vec![0; n.div_ceil(64)])
+    }
+
+    fn insert(&mut self, v : V) {
+        self.0[v / 64] |= 1 << (v % 64)
+    }
+
+    fn remove(&mut self, v : V) {
+        self.0[v / 64] &= !(1 << (v % 64))
+    }
+
+    fn union(&mut self, other : &Set) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a |= *b
+        }
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = V> + 'a {
+        self.0.iter().enumerate().flat_map(|(i, &word)|
+            (0..64).filter(move |b| word >> b & 1 == 1).map(move |b| i * 64 + b))
+    }
+}
+
+// Operations that can run after each one
+fn successors(f : &Func) -> Vec<Vec<usize>> {
+    let mut at = vec![0; f.labels];
+    for (i, op) in f.ops.iter().enumerate() {
+        if let Op::Label(l) = *op {
+            at[l] = i;
+        }
+    }
+    let n = f.ops.len();
+    f.ops.iter().enumerate().map(|(i, op)| {
+        let next = if i + 1 < n {vec![i + 1]} else {Vec::new()};
+        match *op {
+            Op::Jump(l) => vec![at[l]],
+            Op::Branch{to,..} => next.into_iter().chain(Some(at[to])).collect(),
+            Op::Ret(_) => Vec::new(),
+            _ => next
+        }
+    }).collect()
+}
+
+// The vregs live after each operation
+fn live_out(f : &Func) -> Vec<Set> {
+    let succ = successors(f);
+    let n = f.ops.len();
+    let mut live_in = vec![Set::new(f.vregs); n];
+    let mut live_out = vec![Set::new(f.vregs); n];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..n).rev() {
+            let mut out = Set::new(f.vregs);
+            for &s in succ[i].iter() {
+                out.union(&live_in[s]);
+            }
+            let mut inn = out.clone();
+            if let Some(d) = f.ops[i].def() {
+                inn.remove(d);
+            }
+            for u in f.ops[i].uses() {
+                inn.insert(u);
+            }
+            if inn != live_in[i] || out != live_out[i] {
+                live_in[i] = inn;
+                live_out[i] = out;
+                changed = true;
+            }
+        }
+    }
+    live_out
+}
+
+pub fn alloc(f : &Func) -> Alloc {
+    let live = live_out(f);
+    // First and last operation each vreg is live at
+    let mut start = vec![usize::MAX; f.vregs];
+    let mut end = vec![0; f.vregs];
+    for (i, op) in f.ops.iter().enumerate() {
+        for v in op.def().into_iter().chain(op.uses()).chain(live[i].iter()) {
+            start[v] = start[v].min(i);
+            end[v] = end[v].max(i);
+        }
+    }
+    let mut order : Vec<V> = (0..f.vregs).filter(|&v| start[v] != usize::MAX).collect();
+    order.sort_by_key(|&v| (start[v], v));
+    let mut loc = vec![Loc::Nowhere; f.vregs];
+    let mut slots = 0;
+    let mut spill = |v : V, loc : &mut Vec<Loc>| {
+        loc[v] = if v < f.params {
+            Loc::Arg(v)
+        } else {
+            slots += 1;
+            Loc::Slot(slots - 1)
+        }
+    };
+    let mut free : Vec<Reg> = REGS.iter().rev().cloned().collect();
+    // Sorted by end
+    let mut active : Vec<V> = Vec::new();
+    for v in order {
+        // An interval ending where this one starts can share its register:
+        // every operation reads its operands before writing its result.
+        while !active.is_empty() && end[active[0]] <= start[v] {
+            if let Loc::Reg(r) = loc[active.remove(0)] {
+                free.push(r);
+            }
+        }
+        match free.pop() {
+            Some(r) => loc[v] = Loc::Reg(r),
+            None => {
+                let last = active[active.len() - 1];
+                if end[last] > end[v] {
+                    loc[v] = loc[last];
+                    spill(last, &mut loc);
+                    active.pop();
+                } else {
+                    spill(v, &mut loc);
+                    continue
+                }
+            }
+        }
+        let at = active.iter().position(|&a| end[a] > end[v]).unwrap_or(active.len());
+        active.insert(at, v);
+    }
+    let saves = f.ops.iter().enumerate().map(|(i, op)| match *op {
+        Op::Call{dst,..} => {
+            let mut regs : Vec<Reg> = live[i].iter().filter(|&v| v != dst).filter_map(|v| match loc[v] {
+                Loc::Reg(r) => Some(r),
+                _ => None
+            }).collect();
+            regs.sort_by_key(|&r| REGS.iter().position(|&x| x == r));
+            regs
+        },
+        _ => Vec::new()
+    }).collect();
+    Alloc {loc, slots, saves}
+}
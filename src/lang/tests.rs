@@ -0,0 +1,345 @@
+use super::*;
+use mem::MemFetch;
+use std::string::ToString;
+
+const RAM : usize = 1024;
+
+// Runs a program to its Halt, answering each in(port) with port * 10, and
+// gives the outs and the return value of main
+fn run(src : &str) -> (Vec<(u32, u32)>, u32) {
+    let program = compile::<u32>(src).unwrap_or_else(|e| panic!("{}", e.render(src)));
+    let mut ram = vec![0u32; RAM];
+    let mut state : State<u32, i32> = State::new(&mut ram);
+    state.set_stack(program.globals as u32, RAM as u32).unwrap();
+    let mut outs = Vec::new();
+    for _ in 0..10_000 {
+        let answer = match state.eval_instrs(1000, &mut MemFetch(&program.code)) {
+            Ok(MutNotice::Thrash) => None,
+            Ok(MutNotice::Halt) => return (outs, state.regs()[Reg::RC as usize]),
+            Ok(MutNotice::Out{port, out}) => {
+                outs.push((port, out));
+                None
+            },
+            Ok(MutNotice::In{port}) => Some(port * 10),
+            res => panic!("{:?}", res.err())
+        };
+        if let Some(answer) = answer {
+            state.resume_in(answer).unwrap();
+        }
+    }
+    panic!("didn't halt")
+}
+
+fn outs(src : &str) -> Vec<u32> {
+    run(src).0.into_iter().map(|(_, v)| v).collect()
+}
+
+fn error(src : &str) -> Error {
+    match compile::<u32>(src) {
+        Ok(_) => panic!("compiled: {}", src),
+        Err(e) => e
+    }
+}
+
+#[test]
+fn returns_from_main() {
+    assert_eq!(run("fn main() { return 6 * 7; }"), (vec![], 42));
+    assert_eq!(run("fn main() { }"), (vec![], 0));
+}
+
+#[test]
+fn recursion() {
+    let src = "
+        fn fib(n) {
+            if n < 2 { return n; }
+            return fib(n - 1) + fib(n - 2);
+        }
+        fn main() { out(1, fib(15)); }";
+    assert_eq!(run(src).0, vec![(1, 610)]);
+}
+
+#[test]
+fn control_flow() {
+    let src = "
+        fn sign(x) {
+            if x < 0 { return 0 - 1; } else if x == 0 { return 0; } else { return 1; }
+        }
+        fn main() {
+            var i = 0;
+            var sum = 0;
+            while i < 10 {
+                if i % 2 == 0 { sum = sum + i; }
+                i = i + 1;
+            }
+            out(0, sum);
+            out(0, sign(0 - 5));
+            out(0, sign(0));
+            out(0, sign(9));
+        }";
+    assert_eq!(outs(src), vec![20, !0, 0, 1]);
+}
+
+#[test]
+fn scopes() {
+    let src = "
+        fn main() {
+            var x = 1;
+            if 1 { var x = 2; out(0, x); x = 3; out(0, x); }
+            out(0, x);
+        }";
+    assert_eq!(outs(src), vec![2, 3, 1]);
+}
+
+#[test]
+fn signed_arithmetic() {
+    let src = "
+        fn main() {
+            out(0, -7 / 2);
+            out(0, -7 % 2);
+            out(0, 7 % -2);
+            out(0, -1 < 1);
+            out(0, -1 >= 1);
+            out(0, 3 <= 3);
+            out(0, 3 > 3);
+            out(0, 3 != 4);
+            out(0, !5);
+            out(0, 0xFFFF_FFFF + 2);
+        }";
+    assert_eq!(outs(src), vec![-3i32 as u32, -1i32 as u32, 1, 1, 0, 1, 0, 1, 0, 1]);
+}
+
+#[test]
+fn short_circuit() {
+    let src = "
+        var calls;
+        fn side(x) { calls = calls + 1; return x; }
+        fn main() {
+            out(0, side(0) && side(1));
+            out(0, side(1) || side(0));
+            out(0, side(2) && side(3));
+            out(0, side(0) || side(0));
+            out(1, calls);
+        }";
+    assert_eq!(outs(src), vec![0, 1, 1, 0, 6]);
+}
+
+#[test]
+fn arrays() {
+    let src = "
+        var xs[8];
+        var n;
+        fn sort(a, len) {
+            var i = 1;
+            while i < len {
+                var j = i;
+                while j > 0 && a[j - 1] > a[j] {
+                    var t = a[j];
+                    a[j] = a[j - 1];
+                    a[j - 1] = t;
+                    j = j - 1;
+                }
+                i = i + 1;
+            }
+        }
+        fn main() {
+            n = 8;
+            var i = 0;
+            while i < n { xs[i] = (i * 5 + 3) % 8 - 4; i = i + 1; }
+            sort(xs, n);
+            i = 0;
+            while i < n { out(2, xs[i]); i = i + 1; }
+            return xs;
+        }";
+    let (outs, ret) = run(src);
+    let sorted : Vec<u32> = (-4..4).map(|x : i32| x as u32).collect();
+    assert_eq!(outs.into_iter().map(|(_, v)| v).collect::<Vec<_>>(), sorted);
+    // xs is at 0, then n
+    assert_eq!(ret, 0);
+    assert_eq!(compile::<u32>(src).unwrap().globals, 9);
+}
+
+#[test]
+fn input() {
+    assert_eq!(outs("fn main() { out(0, in(4) + in(5)); }"), vec![90]);
+}
+
+#[test]
+fn live_across_calls() {
+    let src = "
+        fn id(x) { var a = 100; var b = 200; return x + a + b - 300; }
+        fn main() {
+            var a = 1; var b = 2; var c = 3;
+            var d = id(4);
+            out(0, a + b + c + d + id(5));
+        }";
+    assert_eq!(outs(src), vec![15]);
+}
+
+#[test]
+fn spills() {
+    // More values live at once than there are registers
+    let mut src = "fn f(".to_string();
+    src += &(0..14).map(|i| format!("p{}", i)).collect::<Vec<_>>().join(", ");
+    src += ") {\n";
+    for i in 0..14 {
+        src += &format!("var v{} = p{} * {};\n", i, i, i + 1);
+    }
+    src += "return ";
+    src += &(0..14).map(|i| format!("v{} + p{}", i, i)).collect::<Vec<_>>().join(" + ");
+    src += ";\n}\nfn main() { out(0, f(";
+    src += &(0..14).map(|i| (i + 1).to_string()).collect::<Vec<_>>().join(", ");
+    src += ")); }";
+    let expected : u32 = (0..14).map(|i| (i + 1) * (i + 1) + (i + 1)).sum();
+    assert_eq!(outs(&src), vec![expected]);
+    let code = compile::<u32>(&src).unwrap().code;
+    assert!(code.iter().any(|i| matches!(*i, Instruction::RamI{dir : Dir::Write, ptr : Reg::RF, ..})));
+}
+
+#[test]
+fn stack_overflow() {
+    let program = compile::<u32>("fn f(n) { return f(n + 1); } fn main() { f(0); }").unwrap();
+    let mut ram = vec![0u32; 64];
+    let mut state : State<u32, i32> = State::new(&mut ram);
+    state.set_stack(0, 64).unwrap();
+    match state.eval_instrs(10_000, &mut MemFetch(&program.code)) {
+        Err(Failure::StackOverflow{..}) => (),
+        res => panic!("{:?}", res.err())
+    }
+}
+
+#[test]
+fn words_of_64_bits() {
+    let src = "fn main() { return 0xFFFF_FFFF * 4 / -2; }";
+    let program = compile::<u64>(src).unwrap();
+    let mut ram = vec![0u64; 64];
+    let mut state : State<u64, i64> = State::new(&mut ram);
+    state.set_stack(0, 64).unwrap();
+    match state.eval_instrs(1000, &mut MemFetch(&program.code)) {
+        Ok(MutNotice::Halt) => (),
+        res => panic!("{:?}", res.err())
+    }
+    assert_eq!(state.regs()[Reg::RC as usize], (0xFFFF_FFFFi64 * -2) as u64);
+    assert!(compile::<u64>("fn main() { return 0x1_0000_0000; }").is_ok());
+    assert!(compile::<u32>("fn main() { return 0x1_0000_0000; }").is_err());
+}
+
+#[test]
+fn bytes_decode_to_the_code() {
+    let program = compile::<u32>("var a[3]; fn main() { a[1] = in(2); out(3, a[1] * 2); }").unwrap();
+    let bytes = program.to_bytes();
+    let mut it = bytes.iter().cloned().peekable();
+    let mut decoded = Vec::new();
+    while it.peek().is_some() {
+        decoded.push(Instruction::<u32>::read(&mut it).unwrap());
+    }
+    assert_eq!(decoded, program.code);
+}
+
+#[test]
+fn errors() {
+    let cases : &[(&str, &str, &str)] = &[
+        ("fn main() {\n    x = y + 1;\n}", "2:5: no variable `x`", "    ^"),
+        ("fn f(a) { }\nfn main() { f(1, 2); }", "2:13: `f` takes 1 argument, not 2", "            ^^^^^^^"),
+        ("fn f() { }", "1:1: no `main` function", "^"),
+        ("fn main() { $ }", "1:13: unexpected `$`", "            ^"),
+        ("fn main() {\n  out(0, 1);", "2:13: expected `}`, found the end", "            ^"),
+        ("fn main() { 1 = 2; }", "1:13: can only assign to a variable or an element", "            ^"),
+        ("var a[2]; fn main() { a = 1; }", "1:23: `a` is an array; assign to its elements", "                      ^"),
+        ("fn main() { out(x, 1); }", "1:17: ports are numbers", "                ^"),
+        ("fn main() { var a = 1; var a = 2; }", "1:28: `a` is already declared in this block", "                           ^"),
+        ("fn main() { var a[4]; }", "1:18: `a` can't be an array; only globals can", "                 ^"),
+        ("fn main() { return 1 +; }", "1:23: expected an expression, found `;`", "                      ^"),
+        ("fn main() { return 99999999999; }", "1:20: 99999999999 doesn't fit in a word", "                   ^^^^^^^^^^^")
+    ];
+    for &(src, first, caret) in cases {
+        let rendered = error(src).render(src);
+        let lines : Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], first, "{}", rendered);
+        assert_eq!(&lines[2][4 ..], caret, "{}", rendered);
+    }
+}
+
+#[test]
+fn nesting_limit() {
+    // A hundred thousand deep, failing at the token past the limit
+    let cases = [
+        (format!("fn main() {{ return {}1; }}", "(".repeat(100_000)), "("),
+        (format!("fn main() {{ return {}1; }}", "-".repeat(100_000)), "-"),
+        (format!("fn main() {{ return {}1; }}", "1 + ".repeat(100_000)), "+"),
+        (format!("fn main() {{ {} }}", "while 1 { ".repeat(100_000)), "1"),
+        (format!("fn main() {{ if 0 {{ }}{} }}", " else if 0 { }".repeat(100_000)), "0")
+    ];
+    for &(ref src, tok) in cases.iter() {
+        let e = error(src);
+        assert_eq!((&e.msg[..], &src[e.span.start .. e.span.end]), ("nested too deeply", tok));
+    }
+    // Not far short of the limit
+    let src = format!("fn main() {{ return {}1{}; }}", "(1 + ".repeat(45), ")".repeat(45));
+    assert_eq!(run(&src).1, 46);
+}
+
+// Random expressions against the same sums in Rust
+
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n : u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+// Gives the source and its value, given the values of x0..x15
+fn expr(rng : &mut Rng, vars : &[i32], depth : u32) -> (String, i32) {
+    if depth == 0 || rng.below(4) == 0 {
+        return if rng.below(2) == 0 {
+            let i = rng.below(vars.len() as u64) as usize;
+            (format!("x{}", i), vars[i])
+        } else {
+            let n = rng.below(100) as i32;
+            (n.to_string(), n)
+        }
+    }
+    let (a, x) = expr(rng, vars, depth - 1);
+    let (b, y) = expr(rng, vars, depth - 1);
+    let (op, val) = match rng.below(11) {
+        0 => ("+", x.wrapping_add(y)),
+        1 => ("-", x.wrapping_sub(y)),
+        2 => ("*", x.wrapping_mul(y)),
+        3 if y != 0 => ("/", x.wrapping_div(y)),
+        4 if y != 0 => ("%", x.wrapping_rem(y)),
+        5 => ("<", (x < y) as i32),
+        6 => (">=", (x >= y) as i32),
+        7 => ("==", (x == y) as i32),
+        8 => ("!=", (x != y) as i32),
+        9 => ("&&", (x != 0 && y != 0) as i32),
+        _ => ("||", (x != 0 || y != 0) as i32)
+    };
+    (format!("({} {} {})", a, op, b), val)
+}
+
+#[test]
+fn random_expressions() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    for _ in 0..40 {
+        let vars : Vec<i32> = (0..16).map(|_| rng.below(2000) as i32 - 1000).collect();
+        let mut src = "fn main() {\n".to_string();
+        for (i, v) in vars.iter().enumerate() {
+            src += &format!("var x{} = {};\n", i, v);
+        }
+        let mut expected = Vec::new();
+        for _ in 0..4 {
+            let (e, val) = expr(&mut rng, &vars, 5);
+            src += &format!("out(0, {});\n", e);
+            expected.push(val as u32);
+        }
+        // Keeps every variable live to the end
+        src += "out(1, ";
+        src += &(0..16).map(|i| format!("x{}", i)).collect::<Vec<_>>().join(" + ");
+        src += ");\n}";
+        expected.push(vars.iter().fold(0i32, |a, &v| a.wrapping_add(v)) as u32);
+        assert_eq!(outs(&src), expected, "{}", src);
+    }
+}
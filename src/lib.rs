@@ -1,9 +1,28 @@
 #![no_std]
 #![no_builtins]
 
+// Tests always have std
+#[cfg(any(test, feature = "std"))]
+#[macro_use]
+extern crate std;
+
 pub mod evaluator;
 pub mod prim;
 pub mod mem;
+pub mod bytes;
+pub mod crypto;
+pub mod calls;
+pub mod runner;
+#[cfg(any(test, feature = "std"))]
+pub mod lang;
+#[cfg(any(test, feature = "std"))]
+pub mod peephole;
+#[cfg(any(test, feature = "std"))]
+pub mod link;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzzing;
+#[cfg(test)]
+mod conformance;
 
 
 
@@ -0,0 +1,590 @@
+// Relocatable objects, and a linker that puts them together into one
+// program.
+//
+// Code addresses reach the VM as plain words: the value of a Lit that's
+// jumped through, or the target of an absolute UJumpI or SJumpI. So do RAM
+// addresses. An object's RAM is its data, words with initial values, and
+// its BSS, words that start as 0. A piece of code can only run where it was
+// laid out for, and an Object says which of its words are addresses, so the
+// linker can move it. Each Reloc marks one instruction's word as either
+//
+//  - an offset into the object's own code, data or BSS, to which the linker
+//    adds where that ended up, or
+//  - an offset from a symbol, which may be defined in any of the objects,
+//    to which the linker adds the symbol's address.
+//
+// Words of data are copied as they are; only instructions are relocated.
+//
+// link lays out the code of each object one after another from 0, in the
+// order given. RAM has the data of each object from 0, in the same order,
+// and then the BSS of each. The program starts at 0, i.e. at the first
+// instruction of the first object. Image::load puts the data and BSS into
+// the RAM the host gives the VM, and the rest of it (e.g. the stack) is up
+// to the host; see Image::ram.
+//
+// Objects and images have binary encodings (to_bytes), so libraries and
+// programs can be kept in files:
+//
+//     "TPMO" or "TPMI", U::BYTES
+//     code : count, then each Instruction in its usual encoding
+//     data : count, then each word
+//     bss : u64
+//     symbols : count, then each as name, section, offset : u64
+//     relocs, objects only : count, then each as at, then a section or 3
+//              and a name for a symbol
+//
+// with counts, lengths and at as u32, sections as 0 for code, 1 for data
+// and 2 for BSS, and names as their length and UTF-8 bytes.
+
+use evaluator::*;
+use core::{iter, slice};
+use std::collections::BTreeMap;
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Section {
+    Code,
+    Data,
+    Bss
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name : String,
+    pub section : Section,
+    // From the start of the section; up to and including its end
+    pub offset : u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    // The start of this object's code, data or BSS
+    Local(Section),
+    Symbol(String)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reloc {
+    // A Lit, or a UJumpI or SJumpI with Addr::Abs, whose word is an offset
+    // from the target
+    pub at : usize,
+    pub target : Target
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object<U> {
+    pub code : Vec<Instruction<U>>,
+    pub data : Vec<U>,
+    // Words of BSS
+    pub bss : u64,
+    pub symbols : Vec<Symbol>,
+    pub relocs : Vec<Reloc>
+}
+
+// Objects are numbered by where they are in the list given to link
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    // Defined twice, in first and then in second
+    Duplicate {name : String, first : usize, second : usize},
+    // Used by object but not defined anywhere
+    Undefined {name : String, object : usize},
+    // Past the end of its section
+    Outside {name : String, object : usize},
+    // A Reloc that's past the end of the code or isn't on a Lit or an
+    // absolute jump
+    BadReloc {object : usize, at : usize},
+    // More code or RAM than a word can address
+    TooBig
+}
+
+// Bytes that aren't what to_bytes gives
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DecodeError;
+
+// An image's data and BSS are more than the RAM given to Image::load
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DoesntFit;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image<U> {
+    pub code : Vec<Instruction<U>>,
+    // From 0 in RAM, and then the BSS
+    pub data : Vec<U>,
+    pub bss : u64,
+    // Every object's symbols, with offsets from 0 in the code or RAM
+    pub symbols : Vec<Symbol>
+}
+
+impl<U : Prim> Image<U> {
+    pub fn address(&self, name : &str) -> Option<U> {
+        self.symbols.iter().find(|s| s.name == name).map(|s| U::from_u64(s.offset))
+    }
+
+    // Words of RAM the data and BSS take, from 0. What's above is free for
+    // the host to use, e.g. as the stack. Saturates, for an Image made by
+    // hand with more BSS than any RAM.
+    pub fn ram(&self) -> u64 {
+        (self.data.len() as u64).saturating_add(self.bss)
+    }
+
+    // Copies the data to the start of ram and zeroes the BSS, before the
+    // VM is given ram. Fails, changing nothing, if they don't fit.
+    pub fn load(&self, ram : &mut [U]) -> Result<(), DoesntFit> {
+        if self.ram() > ram.len() as u64 {
+            return Err(DoesntFit)
+        }
+        let (data, rest) = ram.split_at_mut(self.data.len());
+        data.copy_from_slice(&self.data);
+        for w in rest[.. self.bss as usize].iter_mut() {
+            *w = U::zero();
+        }
+        Ok(())
+    }
+}
+
+// The word a Reloc at instr changes
+fn word<U>(instr : &mut Instruction<U>) -> Option<&mut U> {
+    match *instr {
+        Instruction::Lit{ref mut val, ..} => Some(val),
+        Instruction::UJumpI{mode : Addr::Abs, ref mut target, ..}
+        | Instruction::SJumpI{mode : Addr::Abs, ref mut target, ..} => Some(target),
+        _ => None
+    }
+}
+
+// The most code or RAM a word of U can address
+fn max<U : Prim>() -> u64 {
+    if U::BYTES >= 8 {!0} else {(1u64 << (8 * U::BYTES)) - 1}
+}
+
+pub fn link<U : Prim>(objects : &[Object<U>]) -> Result<Image<U>, Error> {
+    let max = max::<U>();
+    // Where each object's code, data and BSS start, indexed by Section
+    let mut bases = Vec::new();
+    let (mut code_len, mut data_len, mut bss) = (0u64, 0u64, 0u64);
+    for o in objects.iter() {
+        bases.push([code_len, data_len, bss]);
+        code_len += o.code.len() as u64;
+        data_len += o.data.len() as u64;
+        bss = bss.checked_add(o.bss).ok_or(Error::TooBig)?;
+    }
+    if code_len > max || data_len.checked_add(bss).is_none_or(|ram| ram > max) {
+        return Err(Error::TooBig)
+    }
+    for b in bases.iter_mut() {
+        b[2] += data_len;
+    }
+    let mut defined : BTreeMap<&str, (usize, Section, u64)> = BTreeMap::new();
+    for (i, o) in objects.iter().enumerate() {
+        for s in o.symbols.iter() {
+            let len = match s.section {
+                Section::Code => o.code.len() as u64,
+                Section::Data => o.data.len() as u64,
+                Section::Bss => o.bss
+            };
+            if s.offset > len {
+                return Err(Error::Outside{name : s.name.clone(), object : i})
+            }
+            if let Some(&(first, _, _)) = defined.get(s.name.as_str()) {
+                return Err(Error::Duplicate{name : s.name.clone(), first, second : i})
+            }
+            defined.insert(&s.name, (i, s.section, bases[i][s.section as usize] + s.offset));
+        }
+    }
+    let mut code = Vec::with_capacity(code_len as usize);
+    let mut data = Vec::with_capacity(data_len as usize);
+    for (i, o) in objects.iter().enumerate() {
+        let start = code.len();
+        code.extend_from_slice(&o.code);
+        data.extend_from_slice(&o.data);
+        for r in o.relocs.iter() {
+            let base = match r.target {
+                Target::Local(section) => bases[i][section as usize],
+                Target::Symbol(ref name) => match defined.get(name.as_str()) {
+                    Some(&(_, _, addr)) => addr,
+                    None => return Err(Error::Undefined{name : name.clone(), object : i})
+                }
+            };
+            let bad = Error::BadReloc{object : i, at : r.at};
+            if r.at >= o.code.len() {
+                return Err(bad)
+            }
+            match word(&mut code[start + r.at]) {
+                Some(w) => *w = w.wrapping_add(U::from_u64(base)),
+                None => return Err(bad)
+            }
+        }
+    }
+    let symbols = defined.into_iter().map(|(name, (_, section, offset))| {
+        Symbol {name : name.into(), section, offset}
+    }).collect();
+    Ok(Image {code, data, bss, symbols})
+}
+
+const OBJECT : &[u8; 4] = b"TPMO";
+const IMAGE : &[u8; 4] = b"TPMI";
+
+struct Bytes(Vec<u8>);
+
+impl WriteSink for Bytes {
+    fn write(&mut self, val : u8) {
+        self.0.push(val)
+    }
+}
+
+impl Bytes {
+    // Everything up to an object's relocs
+    fn new<U : Prim + Write>(magic : &[u8; 4], code : &[Instruction<U>], data : &[U], bss : u64,
+                             symbols : &[Symbol]) -> Bytes {
+        let mut b = Bytes(magic.to_vec());
+        b.0.push(U::BYTES as u8);
+        b.len(code.len());
+        for instr in code.iter() {
+            instr.write(&mut b);
+        }
+        b.len(data.len());
+        for w in data.iter() {
+            w.write(&mut b);
+        }
+        bss.write(&mut b);
+        b.len(symbols.len());
+        for s in symbols.iter() {
+            b.name(&s.name);
+            b.0.push(s.section as u8);
+            s.offset.write(&mut b);
+        }
+        b
+    }
+
+    fn len(&mut self, n : usize) {
+        (n as u32).write(self)
+    }
+
+    fn name(&mut self, name : &str) {
+        self.len(name.len());
+        self.0.extend_from_slice(name.as_bytes());
+    }
+}
+
+type Iter<'b> = iter::Cloned<slice::Iter<'b, u8>>;
+
+// What Bytes::new wrote
+struct Parts<U> {
+    code : Vec<Instruction<U>>,
+    data : Vec<U>,
+    bss : u64,
+    symbols : Vec<Symbol>
+}
+
+fn read_parts<'b, U : Prim + Read>(magic : &[u8; 4], bytes : &'b [u8]) -> Result<(Parts<U>, Iter<'b>), DecodeError> {
+    if bytes.len() < 5 || &bytes[.. 4] != magic || bytes[4] as usize != U::BYTES {
+        return Err(DecodeError)
+    }
+    let mut it = bytes[5 ..].iter().cloned();
+    let n = read_len(&mut it)?;
+    let mut code = Vec::new();
+    for _ in 0..n {
+        code.push(read(&mut it)?);
+    }
+    let n = read_len(&mut it)?;
+    let mut data = Vec::new();
+    for _ in 0..n {
+        data.push(read(&mut it)?);
+    }
+    let bss = read(&mut it)?;
+    let n = read_len(&mut it)?;
+    let mut symbols = Vec::new();
+    for _ in 0..n {
+        let name = read_name(&mut it)?;
+        let section = match it.next() {
+            Some(n) => read_section(n)?,
+            None => return Err(DecodeError)
+        };
+        let offset = read(&mut it)?;
+        symbols.push(Symbol {name, section, offset});
+    }
+    Ok((Parts {code, data, bss, symbols}, it))
+}
+
+fn read<T : Read>(it : &mut Iter) -> Result<T, DecodeError> {
+    T::read(it).map_err(|()| DecodeError)
+}
+
+fn read_len(it : &mut Iter) -> Result<usize, DecodeError> {
+    read::<u32>(it).map(|n| n as usize)
+}
+
+fn read_name(it : &mut Iter) -> Result<String, DecodeError> {
+    let n = read_len(it)?;
+    let bytes : Vec<u8> = it.take(n).collect();
+    if bytes.len() < n {
+        return Err(DecodeError)
+    }
+    String::from_utf8(bytes).map_err(|_| DecodeError)
+}
+
+fn read_section(n : u8) -> Result<Section, DecodeError> {
+    match n {
+        0 => Ok(Section::Code),
+        1 => Ok(Section::Data),
+        2 => Ok(Section::Bss),
+        _ => Err(DecodeError)
+    }
+}
+
+impl<U : Prim + Read + Write> Object<U> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Bytes::new(OBJECT, &self.code, &self.data, self.bss, &self.symbols);
+        b.len(self.relocs.len());
+        for r in self.relocs.iter() {
+            b.len(r.at);
+            match r.target {
+                Target::Local(section) => b.0.push(section as u8),
+                Target::Symbol(ref name) => {
+                    b.0.push(3);
+                    b.name(name);
+                }
+            }
+        }
+        b.0
+    }
+
+    // Fails on anything but exactly what to_bytes gives for a VM with words
+    // of U
+    pub fn from_bytes(bytes : &[u8]) -> Result<Object<U>, DecodeError> {
+        let (Parts {code, data, bss, symbols}, mut it) = read_parts(OBJECT, bytes)?;
+        let n = read_len(&mut it)?;
+        let mut relocs = Vec::new();
+        for _ in 0..n {
+            let at = read_len(&mut it)?;
+            let target = match it.next() {
+                Some(3) => Target::Symbol(read_name(&mut it)?),
+                Some(n) => Target::Local(read_section(n)?),
+                None => return Err(DecodeError)
+            };
+            relocs.push(Reloc {at, target});
+        }
+        if it.next().is_some() {
+            return Err(DecodeError)
+        }
+        Ok(Object {code, data, bss, symbols, relocs})
+    }
+}
+
+impl<U : Prim + Read + Write> Image<U> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Bytes::new(IMAGE, &self.code, &self.data, self.bss, &self.symbols).0
+    }
+
+    // As Object::from_bytes. Also fails on an image link wouldn't give, with
+    // more code or RAM than a word can address.
+    pub fn from_bytes(bytes : &[u8]) -> Result<Image<U>, DecodeError> {
+        let (Parts {code, data, bss, symbols}, mut it) = read_parts(IMAGE, bytes)?;
+        if it.next().is_some() {
+            return Err(DecodeError)
+        }
+        let image = Image {code, data, bss, symbols};
+        if image.code.len() as u64 > max::<U>() || image.ram() > max::<U>() {
+            return Err(DecodeError)
+        }
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evaluator::Instruction::*;
+    use evaluator::Reg::*;
+    use mem::MemFetch;
+    use std::string::ToString;
+
+    fn sym(name : &str, section : Section, offset : u64) -> Symbol {
+        Symbol {name : name.to_string(), section, offset}
+    }
+
+    fn local(at : usize, section : Section) -> Reloc {
+        Reloc {at, target : Target::Local(section)}
+    }
+
+    fn extern_(at : usize, name : &str) -> Reloc {
+        Reloc {at, target : Target::Symbol(name.to_string())}
+    }
+
+    // Keeps a count in its BSS. bump adds R1 to it, or its step if R1 is 0,
+    // and returns through RE.
+    fn counter() -> Object<u32> {
+        Object {
+            code : vec![
+                Invalid,
+                // bump
+                UJumpI{cond : Cond::NeZ, flag : R1, mode : Addr::Abs, target : 4},
+                Lit{val : 0, reg : R2},
+                Ram{dir : Dir::Read, ptr : R2, val : R1},
+                Lit{val : 1, reg : R2},
+                Ram{dir : Dir::Read, ptr : R2, val : R3},
+                Um2{op : M2Op::Add, r1 : R3, r2 : R1, r3 : R3},
+                Ram{dir : Dir::Write, ptr : R2, val : R3},
+                UJump{cond : Cond::Always, flag : R0, dest : RE}
+            ],
+            data : vec![2],
+            bss : 2,
+            symbols : vec![
+                sym("bump", Section::Code, 1),
+                sym("step", Section::Data, 0),
+                sym("count", Section::Bss, 1)
+            ],
+            relocs : vec![local(1, Section::Code), local(2, Section::Data), local(4, Section::Bss)]
+        }
+    }
+
+    // Bumps the count by its step and then 5, keeping a copy of each in
+    // its data over what was there, and gives the count and its data
+    fn main() -> Object<u32> {
+        let mut code = Vec::new();
+        let mut relocs = Vec::new();
+        for (i, &n) in [0u32, 5].iter().enumerate() {
+            let at = code.len();
+            code.extend_from_slice(&[
+                Lit{val : n, reg : R1},
+                Lit{val : at as u32 + 4, reg : RE},
+                Lit{val : 0, reg : RD},
+                UJump{cond : Cond::Always, flag : R0, dest : RD},
+                // Read through the count's address plus one, less one
+                Lit{val : 1, reg : RD},
+                RamI{dir : Dir::Read, ptr : RD, off : !0, val : R3},
+                Lit{val : i as u32, reg : RD},
+                Ram{dir : Dir::Write, ptr : RD, val : R3}
+            ]);
+            relocs.extend(vec![
+                local(at + 1, Section::Code),
+                extern_(at + 2, "bump"),
+                extern_(at + 4, "count"),
+                local(at + 6, Section::Data)
+            ]);
+        }
+        code.push(Out{reg : R3, port : 0});
+        for i in 0..3 {
+            relocs.push(local(code.len(), Section::Data));
+            code.extend_from_slice(&[
+                Lit{val : i, reg : RD},
+                Ram{dir : Dir::Read, ptr : RD, val : R3},
+                Out{reg : R3, port : 1}
+            ]);
+        }
+        code.push(Halt);
+        let symbols = vec![sym("main", Section::Code, 0)];
+        Object {code, data : vec![100, 200, 300], bss : 0, symbols, relocs}
+    }
+
+    const JUNK : u32 = 0xDEAD_BEEF;
+
+    // Loads image into more RAM than it needs, full of JUNK
+    fn run(image : &Image<u32>) -> Vec<(u32, u32)> {
+        let mut ram = vec![JUNK; image.ram() as usize + 4];
+        image.load(&mut ram).unwrap();
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let mut outs = Vec::new();
+        loop {
+            match state.eval_instrs(1000, &mut MemFetch(&image.code)) {
+                Ok(MutNotice::Halt) => return outs,
+                Ok(MutNotice::Out{port, out}) => outs.push((port, out)),
+                res => panic!("{:?}", res.err())
+            }
+        }
+    }
+
+    const OUTS : &[(u32, u32)] = &[(0, 7), (1, 2), (1, 7), (1, 300)];
+
+    #[test]
+    fn links_and_runs() {
+        let image = link(&[main(), counter()]).unwrap();
+        assert_eq!(image.data, vec![100, 200, 300, 2]);
+        assert_eq!((image.bss, image.ram()), (2, 6));
+        assert_eq!(image.address("main"), Some(0));
+        assert_eq!(image.address("bump"), Some(28));
+        assert_eq!(image.address("step"), Some(3));
+        assert_eq!(image.address("count"), Some(5));
+        assert_eq!(image.address("nothing"), None);
+        assert_eq!(run(&image), OUTS);
+    }
+
+    #[test]
+    fn loads() {
+        let image = link(&[main(), counter()]).unwrap();
+        let mut ram = vec![JUNK; 7];
+        image.load(&mut ram).unwrap();
+        assert_eq!(ram, vec![100, 200, 300, 2, 0, 0, JUNK]);
+        let mut ram = vec![JUNK; 6];
+        image.load(&mut ram).unwrap();
+        assert_eq!(ram, vec![100, 200, 300, 2, 0, 0]);
+        let mut ram = vec![JUNK; 5];
+        assert_eq!(image.load(&mut ram), Err(DoesntFit));
+        assert_eq!(ram, vec![JUNK; 5]);
+        // BSS that no RAM could hold
+        for &bss in [u64::MAX - 3, u64::MAX].iter() {
+            let huge = Image {bss, ..image.clone()};
+            assert_eq!(huge.ram(), u64::MAX);
+            assert_eq!(huge.load(&mut ram), Err(DoesntFit));
+            assert_eq!(ram, vec![JUNK; 5]);
+        }
+    }
+
+    #[test]
+    fn fails() {
+        let mut twice = counter();
+        twice.symbols = vec![sym("main", Section::Code, 2)];
+        assert_eq!(link(&[main(), twice]).err(),
+                   Some(Error::Duplicate{name : "main".to_string(), first : 0, second : 1}));
+        assert_eq!(link(&[main()]).err(),
+                   Some(Error::Undefined{name : "bump".to_string(), object : 0}));
+        let mut outside = counter();
+        outside.symbols[2].offset = 3;
+        assert_eq!(link(&[main(), outside]).err(),
+                   Some(Error::Outside{name : "count".to_string(), object : 1}));
+        let mut bad = counter();
+        bad.relocs.push(local(5, Section::Bss));
+        assert_eq!(link(&[main(), bad]).err(), Some(Error::BadReloc{object : 1, at : 5}));
+        let mut past = counter();
+        past.relocs.push(local(9, Section::Bss));
+        assert_eq!(link(&[main(), past]).err(), Some(Error::BadReloc{object : 1, at : 9}));
+        let mut relative = counter();
+        relative.code[1] = UJumpI{cond : Cond::NeZ, flag : R1, mode : Addr::Rel, target : 3};
+        assert_eq!(link(&[main(), relative]).err(), Some(Error::BadReloc{object : 1, at : 1}));
+        let mut huge = counter();
+        huge.bss = (1 << 32) - 4;
+        assert_eq!(link(&[main(), huge]).err(), Some(Error::TooBig));
+    }
+
+    #[test]
+    fn bytes() {
+        for o in [main(), counter()].iter() {
+            let bytes = o.to_bytes();
+            assert_eq!(Object::<u32>::from_bytes(&bytes).as_ref(), Ok(o));
+            for n in 0..bytes.len() {
+                assert_eq!(Object::<u32>::from_bytes(&bytes[.. n]), Err(DecodeError));
+            }
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert_eq!(Object::<u32>::from_bytes(&longer), Err(DecodeError));
+            assert_eq!(Object::<u64>::from_bytes(&bytes), Err(DecodeError));
+            assert_eq!(Image::<u32>::from_bytes(&bytes), Err(DecodeError));
+        }
+        let image = link(&[Object::from_bytes(&main().to_bytes()).unwrap(), counter()]).unwrap();
+        let bytes = image.to_bytes();
+        assert_eq!(Object::<u32>::from_bytes(&bytes), Err(DecodeError));
+        assert_eq!(Image::<u32>::from_bytes(&bytes).as_ref(), Ok(&image));
+        for n in 0..bytes.len() {
+            assert_eq!(Image::<u32>::from_bytes(&bytes[.. n]), Err(DecodeError));
+        }
+        assert_eq!(run(&Image::from_bytes(&bytes).unwrap()), OUTS);
+        // RAM past what a word can address, overflowing u64 or not
+        let fits = Image {bss : (1 << 32) - 5, ..image.clone()};
+        assert_eq!(Image::<u32>::from_bytes(&fits.to_bytes()), Ok(fits));
+        for &bss in [(1 << 32) - 4, u64::MAX - 3, u64::MAX].iter() {
+            let huge = Image {bss, ..image.clone()};
+            assert_eq!(Image::<u32>::from_bytes(&huge.to_bytes()), Err(DecodeError));
+        }
+    }
+}
@@ -4,7 +4,7 @@ extern crate core;
 // mod lib;
 mod mem;
 mod prim;
-mod multi;
+mod bytes;
 mod evaluator;
 
 use mem::*;
@@ -13,15 +13,13 @@ use evaluator::Reg::*;
 use evaluator::Instruction::*;
 use evaluator::Cond::*;
 use evaluator::M2Op::*;
+use evaluator::Addr::*;
 
 const PROGRAM : &[evaluator::Instruction<u32>] = &[
-    Lit{val:4, reg:RA},
     Lit{val:0, reg:R0},
-    Lit{val:1, reg:R1},
-    Lit{val:100_000_000, reg:R2},
-    Um2{op:Add,r1:R0,r2:R1,r3:R0},
-    Um2{op:Sub,r1:R0,r2:R2,r3:R3},
-    UJump{cond:GtZ,flag:R3,dest:RA},
+    Um2I{op:Add,r1:R0,imm:1,r3:R0},
+    Um2I{op:Sub,r1:R0,imm:100_000_000,r3:R3},
+    UJumpI{cond:GtZ,flag:R3,mode:Rel,target:(-2i32) as u32},
     Halt
     ];
 
@@ -44,7 +42,7 @@ fn main() {
     }
     // println!("{:?}", buf);
 
-    let mut read_instrs : [evaluator::Instruction<u32>; 8] = [Halt; 8];
+    let mut read_instrs : [evaluator::Instruction<u32>; 5] = [Halt; 5];
     let res = prim::read_buf(read_instrs.iter_mut(), &mut buf.iter().map(|x| *x));
     println!("{:?}", res);
     println!("{:?}", read_instrs);
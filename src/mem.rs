@@ -45,11 +45,9 @@ struct Line64<T> {
 
 impl<T : Copy> Line64<T> {
     fn get(&self, ptr : usize) -> Option<T> {
-        let offset = ptr - self.loc;
-        if ptr >= self.loc && offset < self.length {
-            Some(self.values[offset])
-        } else {
-            None
+        match ptr.checked_sub(self.loc) {
+            Some(offset) if offset < self.length => Some(self.values[offset]),
+            _ => None
         }
     }
     fn get_and_set(&mut self, ptr : usize, counter : usize) -> Option<T> {
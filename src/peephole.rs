@@ -0,0 +1,920 @@
+// A peephole optimizer for whole programs. It deletes instructions that
+// can't do anything where they are:
+//
+//  - a Lit of the value its register already holds,
+//  - a jump of any kind to the next instruction,
+//  - an Add or Sub of a register known to hold 0, or of an immediate 0,
+//    back into the register it reads,
+//
+// until there are none left, and closes up the gaps. Everything after a
+// deleted instruction moves down, so every code address has to move with
+// it: the targets of UJumpI and SJumpI, and the value of each Lit that ends
+// up as the destination of a UJump, SJump, UJumpCmp or SJumpCmp.
+//
+// Both need the program analysed. Each register, and each word near the
+// top of the stack, is either a copy of what one of a set of Lits loaded or
+// something else, and a Lit is a code address if its value can reach a
+// jump's destination. A jump made while a register holds its own address
+// plus one is taken to be a call: the callee is analysed separately for
+// each call, and what it returns with is put together with what the caller
+// had on its stack, so return addresses survive recursion.
+//
+// optimize refuses (see Refusal) rather than guess, when a jump's
+// destination could be anything but the value of a Lit, when a Lit is used
+// both as a code address and as data, and when a jump leaves the code.
+// It assumes that:
+//
+//  - the guest reaches its stack only through SP (by Push, Pop, moving SP
+//    by an immediate and RamI with ptr SP) or through addresses computed
+//    from SP, which are taken to be able to write anywhere in it,
+//  - the host changes registers only by answering In and Call, and
+//  - the code is only entered at 0 and the given entries, e.g. interrupt
+//    handlers.
+
+use evaluator::*;
+use evaluator::Instruction::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::vec::Vec;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Refusal<U> {
+    // A jump through a register that might hold something other than the
+    // value of a Lit
+    Indirect {pc : U},
+    // A jump to outside the code
+    Target {pc : U, target : U},
+    // A Lit that's jumped to and used as data
+    Mixed {pc : U},
+    // An entry outside the code
+    Entry {addr : U}
+}
+
+pub struct Optimized<U> {
+    pub code : Vec<Instruction<U>>,
+    // The new address of each old instruction, with one more for the end. A
+    // deleted instruction's is that of the next one kept.
+    pub addrs : Vec<usize>
+}
+
+// Optimizes code that starts at 0 and may also be entered at entries.
+pub fn optimize<U : Prim>(code : &[Instruction<U>], entries : &[U]) -> Result<Optimized<U>, Refusal<U>> {
+    let mut code = code.to_vec();
+    let mut entries = entries.to_vec();
+    let mut addrs : Vec<usize> = (0 ..= code.len()).collect();
+    loop {
+        let pass = pass(&code, &entries)?;
+        if pass.addrs[code.len()] == code.len() {
+            return Ok(Optimized {code, addrs})
+        }
+        for a in addrs.iter_mut() {
+            *a = pass.addrs[*a];
+        }
+        for e in entries.iter_mut() {
+            *e = U::from_u64(pass.addrs[e.to_usize()] as u64);
+        }
+        code = pass.code;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Val {
+    // The value one of these Lits loaded, sorted
+    Lit(Vec<usize>),
+    // Anything, but not an address in the stack
+    Any,
+    // Anything, maybe an address in the stack
+    Stack
+}
+
+impl Val {
+    fn join(&self, other : &Val) -> Val {
+        match (self, other) {
+            (Val::Lit(a), Val::Lit(b)) => {
+                let mut sites = a.clone();
+                sites.extend(b.iter().filter(|s| !a.contains(s)));
+                sites.sort();
+                Val::Lit(sites)
+            },
+            (Val::Stack, _) | (_, Val::Stack) => Val::Stack,
+            _ => Val::Any
+        }
+    }
+}
+
+// What's known before an instruction, in one context. A context is entered
+// at a call, or at an entry; depths are relative to SP when it was entered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Frame {
+    regs : Vec<Val>,
+    // Words from the top of the stack down, top last. Those below are
+    // unknown, i.e. Stack.
+    stack : Vec<Val>,
+    // Words pushed since the context was entered
+    depth : Option<i64>,
+    // The lowest depth written since: 0 if nothing that was on the stack,
+    // -1 if the word that was on top, and so on. i64::MIN for maybe anything.
+    low : i64
+}
+
+impl Frame {
+    fn entry() -> Frame {
+        Frame {regs : vec![Val::Any; 16], stack : Vec::new(), depth : Some(0), low : 0}
+    }
+
+    fn get(&self, reg : Reg) -> Val {
+        if reg == SP {Val::Stack} else {self.regs[reg as usize].clone()}
+    }
+
+    fn set(&mut self, reg : Reg, val : Val) {
+        if reg == SP {
+            // SP is now somewhere unknown
+            self.clobber();
+            self.depth = None;
+        } else {
+            self.regs[reg as usize] = val;
+        }
+    }
+
+    // Something may have written anywhere in the stack
+    fn clobber(&mut self) {
+        self.stack.clear();
+        self.low = i64::MIN;
+    }
+
+    fn wrote(&mut self, depth : Option<i64>) {
+        self.low = match depth {
+            Some(d) => self.low.min(d),
+            None => i64::MIN
+        }
+    }
+
+    fn push(&mut self, val : Val) {
+        let d = self.depth;
+        self.wrote(d);
+        self.stack.push(val);
+        self.depth = d.map(|d| d + 1);
+    }
+
+    fn pop(&mut self) -> Val {
+        self.depth = self.depth.map(|d| d - 1);
+        self.stack.pop().unwrap_or(Val::Stack)
+    }
+
+    // SP += off, off signed
+    fn adjust(&mut self, off : i64) {
+        if off >= 0 {
+            let keep = self.stack.len().saturating_sub(off as usize);
+            self.stack.truncate(keep);
+        } else if off < -1024 {
+            self.set(SP, Val::Stack);
+            return
+        } else {
+            // Uncovers whatever was there before
+            for _ in off .. 0 {
+                self.stack.push(Val::Stack);
+            }
+        }
+        self.depth = self.depth.map(|d| d - off);
+    }
+
+    // RamI with ptr SP and off signed
+    fn read(&self, off : i64) -> Val {
+        if off >= 0 && (off as usize) < self.stack.len() {
+            self.stack[self.stack.len() - 1 - off as usize].clone()
+        } else {
+            Val::Stack
+        }
+    }
+
+    fn write(&mut self, off : i64, val : Val) {
+        // Negative offsets are below SP, in no one's frame
+        if off < 0 {return};
+        if (off as usize) < self.stack.len() {
+            let i = self.stack.len() - 1 - off as usize;
+            self.stack[i] = val;
+        }
+        let d = self.depth.map(|d| d - 1 - off);
+        self.wrote(d);
+    }
+
+    fn join(&self, other : &Frame) -> Frame {
+        let n = self.stack.len().min(other.stack.len());
+        Frame {
+            regs : self.regs.iter().zip(other.regs.iter()).map(|(a, b)| a.join(b)).collect(),
+            stack : self.stack[self.stack.len() - n ..].iter().zip(other.stack[other.stack.len() - n ..].iter())
+                .map(|(a, b)| a.join(b)).collect(),
+            depth : if self.depth == other.depth {self.depth} else {None},
+            low : self.low.min(other.low)
+        }
+    }
+
+    // Where a call returns to, given the caller's frame at the call and the
+    // callee's at its return, which is balanced (depth 0).
+    fn returned(&self, callee : &Frame) -> Frame {
+        let mut stack = self.stack.clone();
+        if callee.low == i64::MIN {
+            stack.clear();
+        } else {
+            // The callee wrote the top -low words, and its stack shows them
+            let n = stack.len();
+            for i in 0 .. ((-callee.low) as usize).min(n) {
+                stack[n - 1 - i] = callee.read(i as i64);
+            }
+        }
+        let low = match (self.depth, callee.low) {
+            (_, 0) => self.low,
+            (Some(d), l) if l != i64::MIN => self.low.min(d + l),
+            _ => i64::MIN
+        };
+        Frame {regs : callee.regs.clone(), stack, depth : self.depth, low}
+    }
+}
+
+// The pc of the call that started a context, or None for an entry
+type Ctx = Option<usize>;
+
+struct Analysis<'c, U : 'c> {
+    code : &'c [Instruction<U>],
+    frames : BTreeMap<(usize, Ctx), Frame>,
+    work : Vec<(usize, Ctx)>,
+    // By the pc of each call: the contexts it was made in, and the joined
+    // frames it returned with
+    callers : BTreeMap<usize, BTreeSet<Ctx>>,
+    returns : BTreeMap<usize, Frame>,
+    // Lits whose values reach a jump's destination, and those used as data
+    jumped : Vec<bool>,
+    used : Vec<bool>
+}
+
+fn signed<U : Prim>(off : U) -> i64 {
+    let neg = U::zero().wrapping_sub(off).to_u64();
+    if off.to_u64() <= neg {off.to_u64() as i64} else {-(neg as i64)}
+}
+
+fn is_move<U : Prim>(op : M2Op, imm : U) -> bool {
+    (op == M2Op::Add || op == M2Op::Sub) && imm == U::zero()
+}
+
+impl<'c, U : Prim> Analysis<'c, U> {
+    fn lit(&self, site : usize) -> U {
+        match self.code[site] {
+            Lit{val, ..} => val,
+            _ => U::zero()
+        }
+    }
+
+    fn flow(&mut self, pc : usize, ctx : Ctx, frame : Frame) {
+        if pc >= self.code.len() {return};
+        let joined = match self.frames.get(&(pc, ctx)) {
+            Some(old) => {
+                let new = old.join(&frame);
+                if new == *old {return};
+                new
+            },
+            None => frame
+        };
+        self.frames.insert((pc, ctx), joined);
+        self.work.push((pc, ctx));
+    }
+
+    // Registers read as data: any Lits in them aren't code addresses. Gives
+    // Stack if any of them might point into the stack, Any otherwise.
+    fn data(&mut self, frame : &Frame, regs : &[Reg]) -> Val {
+        let mut val = Val::Any;
+        for &r in regs {
+            match frame.get(r) {
+                Val::Lit(sites) => for s in sites {
+                    self.used[s] = true;
+                },
+                Val::Stack => val = Val::Stack,
+                Val::Any => ()
+            }
+        }
+        val
+    }
+
+    // A write to RAM at ptr, reading regs
+    fn store(&mut self, frame : &mut Frame, ptr : Reg, regs : &[Reg]) {
+        self.data(frame, regs);
+        if frame.get(ptr) == Val::Stack {
+            frame.clobber();
+        }
+    }
+
+    // Where a jump at pc through dest can go
+    fn dests(&mut self, pc : usize, frame : &Frame, dest : Reg) -> Result<Vec<usize>, Refusal<U>> {
+        match frame.get(dest) {
+            Val::Lit(sites) => {
+                let mut targets = Vec::new();
+                for s in sites {
+                    self.jumped[s] = true;
+                    targets.push(self.target(pc, self.lit(s))?);
+                }
+                Ok(targets)
+            },
+            _ => Err(Refusal::Indirect {pc : U::from_u64(pc as u64)})
+        }
+    }
+
+    fn target(&self, pc : usize, target : U) -> Result<usize, Refusal<U>> {
+        if target.to_u64() < self.code.len() as u64 {
+            Ok(target.to_usize())
+        } else {
+            Err(Refusal::Target {pc : U::from_u64(pc as u64), target})
+        }
+    }
+
+    fn jump(&mut self, pc : usize, ctx : Ctx, frame : &Frame, to : usize) {
+        let call = frame.regs.iter().any(|v| match *v {
+            Val::Lit(ref sites) => sites.iter().all(|&s| self.lit(s).to_u64() == pc as u64 + 1),
+            _ => false
+        });
+        if call {
+            let mut entered = frame.clone();
+            entered.depth = Some(0);
+            entered.low = 0;
+            self.flow(to, Some(pc), entered);
+            self.callers.entry(pc).or_default().insert(ctx);
+            if let Some(ret) = self.returns.get(&pc).cloned() {
+                self.flow(pc + 1, ctx, frame.returned(&ret));
+            }
+        } else if ctx.map(|c| c + 1) == Some(to) && frame.depth == Some(0) {
+            let call = to - 1;
+            let ret = match self.returns.get(&call) {
+                Some(old) => old.join(frame),
+                None => frame.clone()
+            };
+            if self.returns.get(&call) == Some(&ret) {return};
+            self.returns.insert(call, ret.clone());
+            let callers : Vec<Ctx> = self.callers.get(&call).map_or(Vec::new(), |c| c.iter().cloned().collect());
+            for c in callers {
+                if let Some(at) = self.frames.get(&(call, c)).cloned() {
+                    self.flow(to, c, at.returned(&ret));
+                }
+            }
+        } else {
+            self.flow(to, ctx, frame.clone());
+        }
+    }
+
+    fn step(&mut self, pc : usize, ctx : Ctx) -> Result<(), Refusal<U>> {
+        let mut f = self.frames[&(pc, ctx)].clone();
+        let at = f.clone();
+        // Whether it can go on to pc + 1, and where else it can go
+        let (mut next, mut targets) = (true, Vec::new());
+        match self.code[pc] {
+            Lit{reg, ..} => f.set(reg, Val::Lit(vec![pc])),
+            Um2I{op, r1, imm, r3} | Sm2I{op, r1, imm, r3} if (op == M2Op::Add || op == M2Op::Sub) && r1 == SP && r3 == SP => {
+                let off = signed(imm);
+                f.adjust(if op == M2Op::Add {off} else {-off});
+            },
+            Um2I{op, r1, imm, r3} | Sm2I{op, r1, imm, r3} if is_move(op, imm) => {
+                let val = f.get(r1);
+                f.set(r3, val);
+            },
+            Um2{r1, r2, r3, ..} | Sm2{r1, r2, r3, ..} => {
+                let val = self.data(&f, &[r1, r2]);
+                f.set(r3, val);
+            },
+            #[cfg(feature = "float")]
+            Fm2{r1, r2, r3, ..} => {
+                let val = self.data(&f, &[r1, r2]);
+                f.set(r3, val);
+            },
+            Um2I{r1, r3, ..} | Sm2I{r1, r3, ..} => {
+                let val = self.data(&f, &[r1]);
+                f.set(r3, val);
+            },
+            Um4{r1, r2, r3, r4, ..} | Sm4{r1, r2, r3, r4, ..} => {
+                let val = self.data(&f, &[r1, r2, r4]);
+                f.set(r3, val.clone());
+                f.set(r4, val);
+            },
+            USelect{flag, r1, r2, r3, ..} | SSelect{flag, r1, r2, r3, ..} => {
+                self.data(&f, &[flag]);
+                let val = f.get(r1).join(&f.get(r2));
+                f.set(r3, val);
+            },
+            RamI{dir, ptr, off, val} if ptr == SP => match dir {
+                Dir::Read => {
+                    let word = f.read(signed(off));
+                    f.set(val, word);
+                },
+                Dir::Write => {
+                    let word = f.get(val);
+                    f.write(signed(off), word);
+                }
+            },
+            Ram{dir, ptr, val} | RamI{dir, ptr, val, ..} => match dir {
+                Dir::Read => {
+                    self.data(&f, &[ptr]);
+                    f.set(val, Val::Stack);
+                },
+                Dir::Write => self.store(&mut f, ptr, &[ptr, val])
+            },
+            ULoad{ptr, val, ..} | SLoad{ptr, val, ..} => {
+                self.data(&f, &[ptr]);
+                f.set(val, Val::Stack);
+            },
+            Store{ptr, val, ..} => self.store(&mut f, ptr, &[ptr, val]),
+            MemCopy{dst, src, len} => self.store(&mut f, dst, &[dst, src, len]),
+            MemFill{dst, val, len} => self.store(&mut f, dst, &[dst, val, len]),
+            MemCmp{a, b, len, res} => {
+                self.data(&f, &[a, b, len]);
+                f.set(res, Val::Any);
+            },
+            Push{reg} => {
+                let val = f.get(reg);
+                f.push(val);
+            },
+            Pop{reg} => {
+                let val = f.pop();
+                f.set(reg, val);
+            },
+            Out{reg, ..} => {
+                self.data(&f, &[reg]);
+            },
+            In{reg, ..} => f.set(reg, Val::Any),
+            Instruction::Call{major, minor, arg, len, res} => {
+                // The host writes the slice at arg
+                self.store(&mut f, arg, &[major, minor, arg, len]);
+                f.set(res, Val::Any);
+            },
+            IntEn{flag} => {
+                self.data(&f, &[flag]);
+            },
+            UJumpI{cond, flag, mode, target} | SJumpI{cond, flag, mode, target} => {
+                if cond != Cond::Always {
+                    self.data(&f, &[flag]);
+                }
+                next = cond != Cond::Always;
+                let target = match mode {
+                    Addr::Abs => target,
+                    Addr::Rel => U::from_u64(pc as u64).wrapping_add(target)
+                };
+                targets.push(self.target(pc, target)?);
+            },
+            UJump{cond, flag, dest} | SJump{cond, flag, dest} => {
+                if cond != Cond::Always {
+                    self.data(&f, &[flag]);
+                }
+                next = cond != Cond::Always;
+                targets = self.dests(pc, &f, dest)?;
+            },
+            UJumpCmp{cond, r1, r2, dest} | SJumpCmp{cond, r1, r2, dest} => {
+                if cond != Cond::Always {
+                    self.data(&f, &[r1, r2]);
+                }
+                next = cond != Cond::Always;
+                targets = self.dests(pc, &f, dest)?;
+            },
+            // Iret goes back to wherever the interrupt came
+            Iret | Halt | Invalid => next = false
+        }
+        for t in targets {
+            self.jump(pc, ctx, &at, t);
+        }
+        if next {
+            self.flow(pc + 1, ctx, f);
+        }
+        Ok(())
+    }
+
+    // Whether the instruction at pc does nothing, given what's known there
+    fn removable(&self, pc : usize, f : &Frame) -> bool {
+        // The register holds val, and would be moved along with it
+        let holds = |reg : Reg, val : U, code : bool| match f.get(reg) {
+            Val::Lit(ref sites) => sites.iter().all(|&s| self.lit(s) == val && self.jumped[s] == code),
+            _ => false
+        };
+        let zero = |reg : Reg| holds(reg, U::zero(), false) || holds(reg, U::zero(), true);
+        let next = U::from_u64(pc as u64 + 1);
+        match self.code[pc] {
+            Lit{val, reg} => holds(reg, val, self.jumped[pc]),
+            UJumpI{mode, target, ..} | SJumpI{mode, target, ..} => match mode {
+                Addr::Abs => target == next,
+                Addr::Rel => target == U::one()
+            },
+            UJump{dest, ..} | SJump{dest, ..} | UJumpCmp{dest, ..} | SJumpCmp{dest, ..} =>
+                holds(dest, next, true),
+            Um2{op : M2Op::Add, r1, r2, r3} | Sm2{op : M2Op::Add, r1, r2, r3} =>
+                (r1 == r3 && zero(r2)) || (r2 == r3 && zero(r1)),
+            Um2{op : M2Op::Sub, r1, r2, r3} | Sm2{op : M2Op::Sub, r1, r2, r3} =>
+                r1 == r3 && zero(r2),
+            Um2I{op, r1, imm, r3} | Sm2I{op, r1, imm, r3} => r1 == r3 && is_move(op, imm),
+            _ => false
+        }
+    }
+}
+
+// One round of deletions
+fn pass<U : Prim>(code : &[Instruction<U>], entries : &[U]) -> Result<Optimized<U>, Refusal<U>> {
+    let n = code.len();
+    let mut a = Analysis {
+        code, frames : BTreeMap::new(), work : Vec::new(),
+        callers : BTreeMap::new(), returns : BTreeMap::new(),
+        jumped : vec![false; n], used : vec![false; n]
+    };
+    for &e in [U::zero()].iter().chain(entries) {
+        if e.to_u64() >= n as u64 && n > 0 {
+            return Err(Refusal::Entry {addr : e})
+        }
+        a.flow(e.to_usize(), None, Frame::entry());
+    }
+    while let Some((pc, ctx)) = a.work.pop() {
+        a.step(pc, ctx)?;
+    }
+    if let Some(pc) = (0 .. n).find(|&pc| a.jumped[pc] && a.used[pc]) {
+        return Err(Refusal::Mixed {pc : U::from_u64(pc as u64)})
+    }
+    // What's known at each reachable instruction, in any context
+    let mut known : Vec<Option<Frame>> = vec![None; n];
+    for (&(pc, _), f) in a.frames.iter() {
+        known[pc] = Some(match known[pc].take() {
+            Some(k) => k.join(f),
+            None => f.clone()
+        });
+    }
+    let keep : Vec<bool> = (0 .. n).map(|pc| match known[pc] {
+        Some(ref f) => !a.removable(pc, f),
+        None => true
+    }).collect();
+    let mut addrs = Vec::with_capacity(n + 1);
+    let mut kept = 0;
+    for &k in keep.iter() {
+        addrs.push(kept);
+        kept += k as usize;
+    }
+    addrs.push(kept);
+    let moved = |addr : U| U::from_u64(addrs[addr.to_usize()] as u64);
+    let mut out = Vec::with_capacity(kept);
+    for (pc, &instr) in code.iter().enumerate() {
+        if !keep[pc] {continue};
+        let here = U::from_u64(out.len() as u64);
+        out.push(match instr {
+            Lit{val, reg} if a.jumped[pc] => Lit{val : moved(val), reg},
+            UJumpI{cond, flag, mode, target} | SJumpI{cond, flag, mode, target} => {
+                let old = match mode {
+                    Addr::Abs => target,
+                    Addr::Rel => U::from_u64(pc as u64).wrapping_add(target)
+                };
+                // Unreachable jumps out of the code are left alone
+                let target = if old.to_u64() > n as u64 {
+                    target
+                } else if mode == Addr::Abs {
+                    moved(old)
+                } else {
+                    moved(old).wrapping_sub(here)
+                };
+                match instr {
+                    UJumpI{..} => UJumpI{cond, flag, mode, target},
+                    _ => SJumpI{cond, flag, mode, target}
+                }
+            },
+            instr => instr
+        });
+    }
+    Ok(Optimized {code : out, addrs})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evaluator::Reg::*;
+    use lang;
+    use mem::MemFetch;
+
+    // Runs code until it stops other than by Out or Thrash, or for steps
+    // instructions, answering In with 7. Gives the outs and how it stopped.
+    fn run(code : &[Instruction<u32>], steps : u32) -> (Vec<u32>, Result<u32, Failure<u32>>, u32) {
+        let mut ram = vec![0u32; 256];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        state.set_stack(128, 256).unwrap();
+        let mut outs = Vec::new();
+        let mut used = 0;
+        while used < steps {
+            used += 1;
+            match state.eval_instrs(1, &mut MemFetch(code)) {
+                Ok(MutNotice::Thrash) => (),
+                Ok(MutNotice::Out{out, ..}) => outs.push(out),
+                Ok(MutNotice::In{..}) => state.resume_in(7).unwrap(),
+                Ok(MutNotice::Halt) => return (outs, Ok(state.regs()[RC as usize]), used),
+                Ok(_) => panic!("call"),
+                Err(e) => return (outs, Err(e), used)
+            }
+        }
+        (outs, Ok(!0), used)
+    }
+
+    fn opt(code : &[Instruction<u32>]) -> Vec<Instruction<u32>> {
+        optimize(code, &[]).unwrap().code
+    }
+
+    #[test]
+    fn deletes_repeated_lits() {
+        let code = vec![
+            Lit{val : 5, reg : R1},
+            Lit{val : 5, reg : R1},
+            Out{reg : R1, port : 0},
+            Lit{val : 5, reg : R1},
+            Lit{val : 6, reg : R1},
+            Out{reg : R1, port : 0},
+            Halt
+        ];
+        assert_eq!(opt(&code), vec![
+            Lit{val : 5, reg : R1},
+            Out{reg : R1, port : 0},
+            Lit{val : 6, reg : R1},
+            Out{reg : R1, port : 0},
+            Halt
+        ]);
+    }
+
+    #[test]
+    fn keeps_lits_that_differ_on_some_path() {
+        let code = vec![
+            Lit{val : 5, reg : R1},
+            In{reg : R2, port : 0},
+            UJumpI{cond : Cond::EqZ, flag : R2, mode : Addr::Abs, target : 4},
+            Lit{val : 6, reg : R1},
+            Lit{val : 5, reg : R1},
+            Out{reg : R1, port : 0},
+            Halt
+        ];
+        assert_eq!(opt(&code), code);
+    }
+
+    #[test]
+    fn keeps_data_lits_equal_to_code_addresses() {
+        let code = vec![
+            Lit{val : 1, reg : R2},
+            Lit{val : 1, reg : R2},
+            Lit{val : 5, reg : R1},
+            UJump{cond : Cond::Always, flag : R0, dest : R1},
+            Halt,
+            // R1 holds 5 here, but as an address, which moves to 4
+            Lit{val : 5, reg : R1},
+            Out{reg : R1, port : 0},
+            Halt
+        ];
+        let out = opt(&code);
+        assert_eq!(out[1], Lit{val : 4, reg : R1});
+        assert_eq!(out[4], Lit{val : 5, reg : R1});
+        assert_eq!(run(&out, 100).0, vec![5]);
+    }
+
+    #[test]
+    fn deletes_jumps_to_the_next_instruction() {
+        let code = vec![
+            In{reg : R2, port : 0},
+            SJumpI{cond : Cond::NeZ, flag : R2, mode : Addr::Abs, target : 2},
+            UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Rel, target : 1},
+            Lit{val : 5, reg : RD},
+            SJump{cond : Cond::GtZ, flag : R2, dest : RD},
+            Lit{val : 7, reg : RE},
+            UJumpCmp{cond : Cond::Always, r1 : R0, r2 : R0, dest : RE},
+            Halt
+        ];
+        // The Lits stay, moved along with what they point at
+        assert_eq!(opt(&code), vec![
+            In{reg : R2, port : 0},
+            Lit{val : 2, reg : RD},
+            Lit{val : 3, reg : RE},
+            Halt
+        ]);
+    }
+
+    #[test]
+    fn deletes_adds_of_zero() {
+        let code = vec![
+            In{reg : R1, port : 0},
+            Lit{val : 0, reg : R2},
+            Um2{op : M2Op::Add, r1 : R1, r2 : R2, r3 : R1},
+            Sm2{op : M2Op::Add, r1 : R2, r2 : R1, r3 : R1},
+            Um2{op : M2Op::Sub, r1 : R1, r2 : R2, r3 : R1},
+            Um2I{op : M2Op::Add, r1 : R1, imm : 0, r3 : R1},
+            Sm2I{op : M2Op::Sub, r1 : R1, imm : 0, r3 : R1},
+            // Not the same: a copy, a negation, and a register that isn't 0
+            Um2{op : M2Op::Add, r1 : R1, r2 : R2, r3 : R3},
+            Um2{op : M2Op::Sub, r1 : R2, r2 : R1, r3 : R1},
+            Um2{op : M2Op::Add, r1 : R1, r2 : R3, r3 : R1},
+            Out{reg : R1, port : 0},
+            Halt
+        ];
+        assert_eq!(opt(&code), vec![
+            In{reg : R1, port : 0},
+            Lit{val : 0, reg : R2},
+            Um2{op : M2Op::Add, r1 : R1, r2 : R2, r3 : R3},
+            Um2{op : M2Op::Sub, r1 : R2, r2 : R1, r3 : R1},
+            Um2{op : M2Op::Add, r1 : R1, r2 : R3, r3 : R1},
+            Out{reg : R1, port : 0},
+            Halt
+        ]);
+    }
+
+    #[test]
+    fn moves_jump_targets() {
+        let code = vec![
+            Lit{val : 1, reg : R1},
+            Lit{val : 1, reg : R1},
+            Lit{val : 10, reg : RD},
+            Lit{val : 1, reg : R1},
+            // A loop counting R2 down from 3, jumping back by Rel
+            Lit{val : 3, reg : R2},
+            Um2{op : M2Op::Sub, r1 : R2, r2 : R1, r3 : R2},
+            Out{reg : R2, port : 0},
+            UJumpI{cond : Cond::NeZ, flag : R2, mode : Addr::Rel, target : !1},
+            // Through RD, then to the Halt by Abs
+            UJump{cond : Cond::Always, flag : R0, dest : RD},
+            Out{reg : R1, port : 1},
+            UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Abs, target : 12},
+            Out{reg : R1, port : 2},
+            Halt
+        ];
+        let optimized = optimize(&code, &[]).unwrap();
+        assert_eq!(optimized.code, vec![
+            Lit{val : 1, reg : R1},
+            Lit{val : 8, reg : RD},
+            Lit{val : 3, reg : R2},
+            Um2{op : M2Op::Sub, r1 : R2, r2 : R1, r3 : R2},
+            Out{reg : R2, port : 0},
+            UJumpI{cond : Cond::NeZ, flag : R2, mode : Addr::Rel, target : !1},
+            UJump{cond : Cond::Always, flag : R0, dest : RD},
+            Out{reg : R1, port : 1},
+            UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Abs, target : 10},
+            Out{reg : R1, port : 2},
+            Halt
+        ]);
+        assert_eq!(optimized.addrs, vec![0, 1, 1, 2, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(run(&optimized.code, 100).0, run(&code, 100).0);
+    }
+
+    #[test]
+    fn entries() {
+        // A handler at 3 that nothing else reaches
+        let code = vec![
+            Lit{val : 1, reg : R1},
+            Lit{val : 1, reg : R1},
+            Halt,
+            Lit{val : 2, reg : R2},
+            Lit{val : 2, reg : R2},
+            Iret
+        ];
+        let optimized = optimize(&code, &[3]).unwrap();
+        assert_eq!(optimized.code, vec![Lit{val : 1, reg : R1}, Halt, Lit{val : 2, reg : R2}, Iret]);
+        assert_eq!(optimized.addrs[3], 2);
+        // Without the entry, it isn't known to be reached, so is left alone
+        assert_eq!(opt(&code).len(), 5);
+        assert_eq!(optimize(&code, &[6]).err(), Some(Refusal::Entry {addr : 6}));
+    }
+
+    #[test]
+    fn refuses() {
+        let indirect = vec![
+            In{reg : RD, port : 0},
+            UJump{cond : Cond::Always, flag : R0, dest : RD}
+        ];
+        assert_eq!(optimize(&indirect, &[]).err(), Some(Refusal::Indirect {pc : 1}));
+        let through_ram = vec![
+            Lit{val : 3, reg : R1},
+            Ram{dir : Dir::Write, ptr : R0, val : R1},
+            Ram{dir : Dir::Read, ptr : R0, val : RD},
+            UJump{cond : Cond::Always, flag : R0, dest : RD},
+            Halt
+        ];
+        assert_eq!(optimize(&through_ram, &[]).err(), Some(Refusal::Indirect {pc : 3}));
+        let out_of_code = vec![
+            Lit{val : 9, reg : RD},
+            SJump{cond : Cond::Always, flag : R0, dest : RD}
+        ];
+        assert_eq!(optimize(&out_of_code, &[]).err(), Some(Refusal::Target {pc : 1, target : 9}));
+        let rel = vec![UJumpI{cond : Cond::Always, flag : R0, mode : Addr::Rel, target : !0}];
+        assert_eq!(optimize(&rel, &[]).err(), Some(Refusal::Target {pc : 0, target : !0}));
+        let mixed = vec![
+            Lit{val : 3, reg : RD},
+            Out{reg : RD, port : 0},
+            UJump{cond : Cond::Always, flag : R0, dest : RD},
+            Halt
+        ];
+        assert_eq!(optimize(&mixed, &[]).err(), Some(Refusal::Mixed {pc : 0}));
+        // A return address on the stack, overwritten through a pointer
+        // computed from SP
+        let clobbered = vec![
+            Lit{val : 6, reg : RE},
+            Push{reg : RE},
+            Um2I{op : M2Op::Add, r1 : SP, imm : 0, r3 : R1},
+            Ram{dir : Dir::Write, ptr : R1, val : R2},
+            Pop{reg : RE},
+            UJump{cond : Cond::Always, flag : R0, dest : RE},
+            Halt
+        ];
+        assert_eq!(optimize(&clobbered, &[]).err(), Some(Refusal::Indirect {pc : 5}));
+    }
+
+    #[test]
+    fn follows_return_addresses_through_the_stack() {
+        // A recursive countdown, called from two places with different
+        // amounts on the stack, spilling its argument
+        let code = vec![
+            /* 0 */ Lit{val : 5, reg : R1},
+            /* 1 */ Push{reg : R1},
+            /* 2 */ Push{reg : R1},
+            /* 3 */ Lit{val : 6, reg : RE},
+            /* 4 */ Lit{val : 12, reg : RD},
+            /* 5 */ UJump{cond : Cond::Always, flag : R0, dest : RD},
+            /* 6 */ Pop{reg : R1},
+            /* 7 */ Lit{val : 10, reg : RE},
+            /* 8 */ Lit{val : 12, reg : RD},
+            /* 9 */ UJump{cond : Cond::Always, flag : R0, dest : RD},
+            /* 10 */ Pop{reg : R1},
+            /* 11 */ Halt,
+            // countdown(n): out(n); if n != 0 { countdown(n - 1) }
+            /* 12 */ Push{reg : RE},
+            /* 13 */ RamI{dir : Dir::Read, ptr : SP, off : 1, val : R2},
+            /* 14 */ Out{reg : R2, port : 0},
+            /* 15 */ UJumpI{cond : Cond::EqZ, flag : R2, mode : Addr::Abs, target : 23},
+            /* 16 */ Um2I{op : M2Op::Sub, r1 : R2, imm : 1, r3 : R2},
+            /* 17 */ RamI{dir : Dir::Write, ptr : SP, off : 1, val : R2},
+            /* 18 */ Push{reg : R2},
+            /* 19 */ Lit{val : 22, reg : RE},
+            /* 20 */ Lit{val : 12, reg : RD},
+            /* 21 */ UJump{cond : Cond::Always, flag : R0, dest : RD},
+            /* 22 */ Um2I{op : M2Op::Add, r1 : SP, imm : 1, r3 : SP},
+            /* 23 */ Pop{reg : RE},
+            /* 24 */ Um2I{op : M2Op::Add, r1 : RE, imm : 0, r3 : RE},
+            /* 25 */ UJump{cond : Cond::Always, flag : R0, dest : RE}
+        ];
+        let optimized = optimize(&code, &[]).unwrap();
+        // The Lits of 12 after the first, the Add of 0 and nothing else
+        assert_eq!(optimized.code.len(), code.len() - 3);
+        let (outs, res, _) = run(&code, 1000);
+        assert_eq!(outs, vec![5, 4, 3, 2, 1, 0, 5, 4, 3, 2, 1, 0]);
+        assert_eq!(run(&optimized.code, 1000).0, outs);
+        assert_eq!(res, Ok(0));
+    }
+
+    #[test]
+    fn sees_callees_write_to_the_callers_stack() {
+        let code = vec![
+            Lit{val : 5, reg : R1},
+            Push{reg : R1},
+            Lit{val : 5, reg : RE},
+            Lit{val : 9, reg : RD},
+            UJump{cond : Cond::Always, flag : R0, dest : RD},
+            // R1 is 6, so this stays
+            Pop{reg : R1},
+            Lit{val : 5, reg : R1},
+            Out{reg : R1, port : 0},
+            Halt,
+            // Overwrites its argument
+            Lit{val : 6, reg : R3},
+            RamI{dir : Dir::Write, ptr : SP, off : 0, val : R3},
+            UJump{cond : Cond::Always, flag : R0, dest : RE}
+        ];
+        assert_eq!(opt(&code), code);
+    }
+
+    const PROGRAMS : &[&str] = &[
+        "fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }
+         fn main() { out(0, fib(10)); }",
+        "var xs[8];
+         fn sort(a, len) {
+             var i = 1;
+             while i < len {
+                 var j = i;
+                 while j > 0 && a[j - 1] > a[j] { var t = a[j]; a[j] = a[j - 1]; a[j - 1] = t; j = j - 1; }
+                 i = i + 1;
+             }
+         }
+         fn main() {
+             var i = 0;
+             while i < 8 { xs[i] = (i * 5 + 3) % 8; i = i + 1; }
+             sort(xs, 8);
+             i = 0;
+             while i < 8 { out(0, xs[i]); i = i + 1; }
+         }",
+        "var calls;
+         fn side(x) { calls = calls + 1; return x; }
+         fn main() { out(0, side(0) && side(1)); out(0, side(1) || side(in(0))); out(0, calls); }",
+        "fn f(a, b, c, d, e, f, g, h, i, j, k, l, m) {
+             var x = a * b + c * d + e * f + g * h + i * j + k * l + m;
+             if m > 0 { x = x + f(a, b, c, d, e, f, g, h, i, j, k, l, m - 1); }
+             return x;
+         }
+         fn main() { return f(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 3); }"
+    ];
+
+    #[test]
+    fn compiled_programs() {
+        for src in PROGRAMS {
+            let code = lang::compile::<u32>(src).unwrap().code;
+            let optimized = optimize(&code, &[]).unwrap_or_else(|r| panic!("{:?} in {}", r, src)).code;
+            let (before, after) = (run(&code, 100_000), run(&optimized, 100_000));
+            assert_eq!(after.0, before.0, "{}", src);
+            assert_eq!(after.1, before.1, "{}", src);
+            assert!(before.1.is_ok() && before.1 != Ok(!0), "{}", src);
+            assert!(optimized.len() < code.len(), "{}", src);
+            assert!(after.2 < before.2, "{}", src);
+        }
+    }
+}
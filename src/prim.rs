@@ -2,26 +2,142 @@ use evaluator::*;
 
 
 impl Prim for u64 {
+    const BYTES : usize = 8;
     fn to_usize(&self) -> usize {*self as usize}
+    fn to_u64(&self) -> u64 {*self}
+    fn from_u64(val : u64) -> u64 {val}
+    fn wrapping_add(&self, other : u64) -> u64 {u64::wrapping_add(*self, other)}
+    fn wrapping_sub(&self, other : u64) -> u64 {u64::wrapping_sub(*self, other)}
+    fn wrapping_mul(&self, other : u64) -> u64 {u64::wrapping_mul(*self, other)}
+    fn div_rem(&self, other : u64) -> Option<(u64, u64)> {
+        if other == 0 {None} else {Some((self.wrapping_div(other), self.wrapping_rem(other)))}
+    }
+    fn fx_mul(&self, other : u64, frac : u32, saturate : bool) -> u64 {
+        narrow_u64((*self as u128 * other as u128) >> frac, saturate)
+    }
+    fn fx_div(&self, other : u64, frac : u32, saturate : bool) -> Option<u64> {
+        if other == 0 {return None};
+        Some(narrow_u64(((*self as u128) << frac) / other as u128, saturate))
+    }
+    fn mul_hi(&self, other : u64) -> u64 {((*self as u128 * other as u128) >> 64) as u64}
+    fn carrying_add(&self, other : u64, carry : bool) -> (u64, bool) {
+        let (a, c1) = self.overflowing_add(other);
+        let (b, c2) = a.overflowing_add(carry as u64);
+        (b, c1 | c2)
+    }
+    fn borrowing_sub(&self, other : u64, borrow : bool) -> (u64, bool) {
+        let (a, b1) = self.overflowing_sub(other);
+        let (b, b2) = a.overflowing_sub(borrow as u64);
+        (b, b1 | b2)
+    }
+    #[cfg(feature = "float")]
+    fn fm2(op : FOp, r1 : u64, r2 : u64) -> u64 {f64_m2(op, r1, r2)}
     fn zero() -> u64 {0}
     fn one() -> u64 {1}
 }
 
 impl Prim for i64 {
+    const BYTES : usize = 8;
     fn to_usize(&self) -> usize {*self as usize}
+    fn to_u64(&self) -> u64 {*self as u64}
+    fn from_u64(val : u64) -> i64 {val as i64}
+    fn wrapping_add(&self, other : i64) -> i64 {i64::wrapping_add(*self, other)}
+    fn wrapping_sub(&self, other : i64) -> i64 {i64::wrapping_sub(*self, other)}
+    fn wrapping_mul(&self, other : i64) -> i64 {i64::wrapping_mul(*self, other)}
+    fn div_rem(&self, other : i64) -> Option<(i64, i64)> {
+        if other == 0 {None} else {Some((self.wrapping_div(other), self.wrapping_rem(other)))}
+    }
+    fn fx_mul(&self, other : i64, frac : u32, saturate : bool) -> i64 {
+        narrow_i64((*self as i128 * other as i128) >> frac, saturate)
+    }
+    fn fx_div(&self, other : i64, frac : u32, saturate : bool) -> Option<i64> {
+        if other == 0 {return None};
+        Some(narrow_i64(((*self as i128) << frac) / other as i128, saturate))
+    }
+    fn mul_hi(&self, other : i64) -> i64 {((*self as i128 * other as i128) >> 64) as i64}
+    fn carrying_add(&self, other : i64, carry : bool) -> (i64, bool) {
+        let (a, c1) = (*self as u64).overflowing_add(other as u64);
+        let (b, c2) = a.overflowing_add(carry as u64);
+        (b as i64, c1 | c2)
+    }
+    fn borrowing_sub(&self, other : i64, borrow : bool) -> (i64, bool) {
+        let (a, b1) = (*self as u64).overflowing_sub(other as u64);
+        let (b, b2) = a.overflowing_sub(borrow as u64);
+        (b as i64, b1 | b2)
+    }
+    #[cfg(feature = "float")]
+    fn fm2(op : FOp, r1 : i64, r2 : i64) -> i64 {f64_m2(op, r1 as u64, r2 as u64) as i64}
     fn zero() -> i64 {0}
     fn one() -> i64 {1}
 }
 
 
 impl Prim for u32 {
+    const BYTES : usize = 4;
     fn to_usize(&self) -> usize {*self as usize}
+    fn to_u64(&self) -> u64 {*self as u64}
+    fn from_u64(val : u64) -> u32 {val as u32}
+    fn wrapping_add(&self, other : u32) -> u32 {u32::wrapping_add(*self, other)}
+    fn wrapping_sub(&self, other : u32) -> u32 {u32::wrapping_sub(*self, other)}
+    fn wrapping_mul(&self, other : u32) -> u32 {u32::wrapping_mul(*self, other)}
+    fn div_rem(&self, other : u32) -> Option<(u32, u32)> {
+        if other == 0 {None} else {Some((self.wrapping_div(other), self.wrapping_rem(other)))}
+    }
+    fn fx_mul(&self, other : u32, frac : u32, saturate : bool) -> u32 {
+        narrow_u32((*self as u64 * other as u64) >> frac, saturate)
+    }
+    fn fx_div(&self, other : u32, frac : u32, saturate : bool) -> Option<u32> {
+        if other == 0 {return None};
+        Some(narrow_u32(((*self as u64) << frac) / other as u64, saturate))
+    }
+    fn mul_hi(&self, other : u32) -> u32 {((*self as u64 * other as u64) >> 32) as u32}
+    fn carrying_add(&self, other : u32, carry : bool) -> (u32, bool) {
+        let (a, c1) = self.overflowing_add(other);
+        let (b, c2) = a.overflowing_add(carry as u32);
+        (b, c1 | c2)
+    }
+    fn borrowing_sub(&self, other : u32, borrow : bool) -> (u32, bool) {
+        let (a, b1) = self.overflowing_sub(other);
+        let (b, b2) = a.overflowing_sub(borrow as u32);
+        (b, b1 | b2)
+    }
+    #[cfg(feature = "float")]
+    fn fm2(op : FOp, r1 : u32, r2 : u32) -> u32 {f32_m2(op, r1, r2)}
     fn zero() -> u32 {0}
     fn one() -> u32 {1}
 }
 
 impl Prim for i32 {
+    const BYTES : usize = 4;
     fn to_usize(&self) -> usize {*self as usize}
+    fn to_u64(&self) -> u64 {*self as u64}
+    fn from_u64(val : u64) -> i32 {val as i32}
+    fn wrapping_add(&self, other : i32) -> i32 {i32::wrapping_add(*self, other)}
+    fn wrapping_sub(&self, other : i32) -> i32 {i32::wrapping_sub(*self, other)}
+    fn wrapping_mul(&self, other : i32) -> i32 {i32::wrapping_mul(*self, other)}
+    fn div_rem(&self, other : i32) -> Option<(i32, i32)> {
+        if other == 0 {None} else {Some((self.wrapping_div(other), self.wrapping_rem(other)))}
+    }
+    fn fx_mul(&self, other : i32, frac : u32, saturate : bool) -> i32 {
+        narrow_i32((*self as i64 * other as i64) >> frac, saturate)
+    }
+    fn fx_div(&self, other : i32, frac : u32, saturate : bool) -> Option<i32> {
+        if other == 0 {return None};
+        Some(narrow_i32(((*self as i64) << frac) / other as i64, saturate))
+    }
+    fn mul_hi(&self, other : i32) -> i32 {((*self as i64 * other as i64) >> 32) as i32}
+    fn carrying_add(&self, other : i32, carry : bool) -> (i32, bool) {
+        let (a, c1) = (*self as u32).overflowing_add(other as u32);
+        let (b, c2) = a.overflowing_add(carry as u32);
+        (b as i32, c1 | c2)
+    }
+    fn borrowing_sub(&self, other : i32, borrow : bool) -> (i32, bool) {
+        let (a, b1) = (*self as u32).overflowing_sub(other as u32);
+        let (b, b2) = a.overflowing_sub(borrow as u32);
+        (b as i32, b1 | b2)
+    }
+    #[cfg(feature = "float")]
+    fn fm2(op : FOp, r1 : i32, r2 : i32) -> i32 {f32_m2(op, r1 as u32, r2 as u32) as i32}
     fn zero() -> i32 {0}
     fn one() -> i32 {1}
 }
@@ -42,6 +158,66 @@ impl Compl<u32> for i32 {
     fn compl(&self) -> u32 {*self as u32}
 }
 
+// Back from the double-width intermediates used by fixed-point arithmetic.
+
+fn narrow_u64(x : u128, saturate : bool) -> u64 {
+    if saturate && x > u64::MAX as u128 {u64::MAX} else {x as u64}
+}
+
+fn narrow_i64(x : i128, saturate : bool) -> i64 {
+    if !saturate {x as i64}
+    else if x > i64::MAX as i128 {i64::MAX}
+    else if x < i64::MIN as i128 {i64::MIN}
+    else {x as i64}
+}
+
+fn narrow_u32(x : u64, saturate : bool) -> u32 {
+    if saturate && x > u32::MAX as u64 {u32::MAX} else {x as u32}
+}
+
+fn narrow_i32(x : i64, saturate : bool) -> i32 {
+    if !saturate {x as i32}
+    else if x > i32::MAX as i64 {i32::MAX}
+    else if x < i32::MIN as i64 {i32::MIN}
+    else {x as i32}
+}
+
+// Rust's float arithmetic is IEEE 754 with round-to-nearest-even. On targets
+// without an FPU (e.g. thumbv6m, riscv32imac) the compiler lowers it to the
+// soft-float routines in compiler-builtins, so no separate fallback is needed.
+
+#[cfg(feature = "float")]
+fn f32_m2(op : FOp, r1 : u32, r2 : u32) -> u32 {
+    let (a, b) = (f32::from_bits(r1), f32::from_bits(r2));
+    match op {
+        FOp::Add => (a + b).to_bits(),
+        FOp::Sub => (a - b).to_bits(),
+        FOp::Mul => (a * b).to_bits(),
+        FOp::Div => (a / b).to_bits(),
+        FOp::Equ => (a == b) as u32,
+        FOp::Lt => (a < b) as u32,
+        FOp::Gt => (a > b) as u32,
+        FOp::FromInt => (r1 as i32 as f32).to_bits(),
+        FOp::ToInt => a as i32 as u32
+    }
+}
+
+#[cfg(feature = "float")]
+fn f64_m2(op : FOp, r1 : u64, r2 : u64) -> u64 {
+    let (a, b) = (f64::from_bits(r1), f64::from_bits(r2));
+    match op {
+        FOp::Add => (a + b).to_bits(),
+        FOp::Sub => (a - b).to_bits(),
+        FOp::Mul => (a * b).to_bits(),
+        FOp::Div => (a / b).to_bits(),
+        FOp::Equ => (a == b) as u64,
+        FOp::Lt => (a < b) as u64,
+        FOp::Gt => (a > b) as u64,
+        FOp::FromInt => (r1 as i64 as f64).to_bits(),
+        FOp::ToInt => a as i64 as u64
+    }
+}
+
 use core::slice::IterMut;
 
 pub fn read_buf<It:Iterator<Item=u8>, T : Read> (dst : IterMut<T>, src :&mut It) -> Result<(),()> {
@@ -111,4 +287,16 @@ impl Write for i32 {
     fn write<Sink : WriteSink>(&self, sink: &mut Sink) -> () {
         (*self as u32).write(sink)
     }
-}
\ No newline at end of file
+}
+impl Write for u64 {
+    fn write<Sink : WriteSink>(&self, sink: &mut Sink) -> () {
+        ((*self >> 32) as u32).write(sink);
+        (*self as u32).write(sink);
+    }
+}
+
+impl Write for i64 {
+    fn write<Sink : WriteSink>(&self, sink: &mut Sink) -> () {
+        (*self as u64).write(sink)
+    }
+}
@@ -0,0 +1,207 @@
+use evaluator::*;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+// Drives a State as a plain Future, for hosts whose libraries are async.
+// Each Call is handed to an AsyncHost and the guest stays suspended until
+// the host is ready; on Thrash the runner wakes itself and returns Pending,
+// so other tasks on the executor get a turn. It completes when the guest
+// halts, does I/O, or fails. Nothing here allocates or needs std.
+// A call the host isn't ready for is cancelled when the runner returns
+// Pending, and the guest makes it again on the next poll. So dropping a
+// Run leaves the state on that Call, ready to be run again.
+
+pub trait AsyncHost<U> {
+    // Polled until it returns Ready, with the call's slice each time. The
+    // guest may have run, e.g. an interrupt handler, between two polls. The
+    // value becomes the guest's call result; see calls::status.
+    fn poll_call(&mut self, cx : &mut Context, major : U, minor : U, slice : &mut [U]) -> Poll<U>;
+}
+
+// Why a run finished. The state can be resumed afterwards, e.g. after
+// answering an In with State::resume_in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exit<U> {
+    Halt,
+    Out{port:U, out:U},
+    In{port:U}
+}
+
+pub struct Run<'r, 'a : 'r, U : 'a + Compl<S>, S : 'a + Compl<U>, F : 'r, H : 'r> {
+    state : &'r mut State<'a, U, S>,
+    code : &'r mut F,
+    host : &'r mut H,
+    fuel : U
+}
+
+// Runs state from where it is, fuel units at a time between yields.
+pub fn run<'r, 'a, U, S, F, H>(state : &'r mut State<'a, U, S>, code : &'r mut F, host : &'r mut H, fuel : U) -> Run<'r, 'a, U, S, F, H>
+    where U : Compl<S>, S : Compl<U>, F : Fetcher<U, Instruction<U>>, H : AsyncHost<U> {
+    Run { state, code, host, fuel }
+}
+
+impl<'r, 'a, U, S, F, H> Future for Run<'r, 'a, U, S, F, H>
+    where U : Compl<S> + Unpin, S : Compl<U>, F : Fetcher<U, Instruction<U>>, H : AsyncHost<U> {
+    type Output = Result<Exit<U>, Failure<U>>;
+
+    fn poll(self : Pin<&mut Self>, cx : &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            let exit = match this.state.eval_instrs(this.fuel, this.code) {
+                Err(e) => Err(e),
+                Ok(MutNotice::Thrash) => {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending
+                },
+                Ok(MutNotice::Call{major, minor, slice, reply}) => {
+                    match this.host.poll_call(cx, major, minor, slice) {
+                        Poll::Pending => {
+                            reply.cancel();
+                            return Poll::Pending
+                        },
+                        Poll::Ready(val) => {
+                            reply.resume(val);
+                            continue
+                        }
+                    }
+                },
+                Ok(MutNotice::Halt) => Ok(Exit::Halt),
+                Ok(MutNotice::Out{port, out}) => Ok(Exit::Out{port, out}),
+                Ok(MutNotice::In{port}) => Ok(Exit::In{port})
+            };
+            return Poll::Ready(exit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evaluator::Instruction::*;
+    use evaluator::Reg::*;
+    use mem::MemFetch;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Wake, Waker};
+    use std::vec::Vec;
+
+    struct Wakes(AtomicUsize);
+
+    impl Wake for Wakes {
+        fn wake(self : Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    // Keeps each call waiting for delay polls, then answers major * 10 +
+    // minor and writes the number of calls so far to the slice.
+    struct Host {
+        delay : usize,
+        waited : usize,
+        calls : Vec<(u32, u32)>
+    }
+
+    impl AsyncHost<u32> for Host {
+        fn poll_call(&mut self, _ : &mut Context, major : u32, minor : u32, slice : &mut [u32]) -> Poll<u32> {
+            if self.waited < self.delay {
+                self.waited += 1;
+                return Poll::Pending
+            }
+            self.waited = 0;
+            self.calls.push((major, minor));
+            slice[0] = self.calls.len() as u32;
+            Poll::Ready(major * 10 + minor)
+        }
+    }
+
+    fn host(delay : usize) -> Host {
+        Host { delay, waited : 0, calls : Vec::new() }
+    }
+
+    // Makes calls 1.0, 1.1 and 1.2 on ram[0 .. 1], then puts the sum of their
+    // results out on port 1
+    const CALLS : [Instruction<u32>; 11] = [
+        Lit{val : 1, reg : R1},
+        Lit{val : 0, reg : R2},
+        Lit{val : 0, reg : R3},
+        Lit{val : 1, reg : R4},
+        Call{major : R1, minor : R2, arg : R3, len : R4, res : R5},
+        Um2{op : M2Op::Add, r1 : R6, r2 : R5, r3 : R6},
+        Um2I{op : M2Op::Add, r1 : R2, imm : 1, r3 : R2},
+        Um2I{op : M2Op::Sub, r1 : R2, imm : 3, r3 : R7},
+        UJumpI{cond : Cond::NeZ, flag : R7, mode : Addr::Abs, target : 4},
+        Out{reg : R6, port : 1},
+        Halt
+    ];
+
+    // Polls until Ready, counting the Pendings
+    fn finish<F : Future + Unpin>(fut : &mut F, cx : &mut Context) -> (F::Output, usize) {
+        let mut pendings = 0;
+        loop {
+            match Pin::new(&mut *fut).poll(cx) {
+                Poll::Ready(out) => return (out, pendings),
+                Poll::Pending => pendings += 1
+            }
+        }
+    }
+
+    #[test]
+    fn waits_for_the_host() {
+        let wakes = Arc::new(Wakes(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut ram = [0u32; 4];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let (mut code, mut h) = (MemFetch(&CALLS[..]), host(2));
+        {
+            let mut r = run(&mut state, &mut code, &mut h, 100);
+            assert_eq!(Pin::new(&mut r).poll(&mut cx), Poll::Pending);
+            assert_eq!(Pin::new(&mut r).poll(&mut cx), Poll::Pending);
+            // Waking is the host's business while it holds the call
+            assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+            let (out, pendings) = finish(&mut r, &mut cx);
+            assert_eq!((out, pendings), (Ok(Exit::Out{port : 1, out : 33}), 4));
+        }
+        assert_eq!(h.calls, [(1, 0), (1, 1), (1, 2)]);
+        assert_eq!((state.regs()[5], state.ram()[0]), (12, 3));
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+        let mut r = run(&mut state, &mut code, &mut h, 100);
+        assert_eq!(Pin::new(&mut r).poll(&mut cx), Poll::Ready(Ok(Exit::Halt)));
+    }
+
+    #[test]
+    fn yields_on_thrash() {
+        let wakes = Arc::new(Wakes(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut ram = [0u32; 4];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let (mut code, mut h) = (MemFetch(&CALLS[..]), host(0));
+        let (out, pendings) = finish(&mut run(&mut state, &mut code, &mut h, 2), &mut cx);
+        assert_eq!(out, Ok(Exit::Out{port : 1, out : 33}));
+        assert!(pendings > 0);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), pendings);
+        let bad = [Halt, Halt];
+        let (out, _) = finish(&mut run(&mut state, &mut MemFetch(&bad[..]), &mut h, 2), &mut cx);
+        assert_eq!(out, Err(Failure::CodeOob{pc : 10}));
+    }
+
+    #[test]
+    fn dropping_leaves_the_call_to_be_made_again() {
+        let waker = Waker::from(Arc::new(Wakes(AtomicUsize::new(0))));
+        let mut cx = Context::from_waker(&waker);
+        let mut ram = [0u32; 4];
+        let mut state : State<u32, i32> = State::new(&mut ram);
+        let (mut code, mut h) = (MemFetch(&CALLS[..]), host(5));
+        {
+            let mut r = run(&mut state, &mut code, &mut h, 100);
+            assert_eq!(Pin::new(&mut r).poll(&mut cx), Poll::Pending);
+        }
+        assert_eq!((state.pc(), state.regs()[5]), (4, 0));
+        let mut h = host(0);
+        let (out, _) = finish(&mut run(&mut state, &mut code, &mut h, 100), &mut cx);
+        assert_eq!(out, Ok(Exit::Out{port : 1, out : 33}));
+        assert_eq!(h.calls, [(1, 0), (1, 1), (1, 2)]);
+    }
+}
@@ -0,0 +1,133 @@
+// native::translate against eval_instrs. translated.rs is the translator's
+// output for the programs in programs.rs, checked in so that the generated
+// code is compiled with the tests. The native_fixture test fails when it goes
+// stale; it doesn't compile translated.rs, so it still runs when the stale
+// file no longer compiles. After changing the translator or the programs,
+// regenerate it with
+//
+//     TPM_BLESS=1 cargo test --test native_fixture
+//
+// Each translated function is run in lockstep with eval_instrs on its own
+// copy of the same State, with random fuel, interrupts and host answers, and
+// after every run both must have stopped the same way with the same pc,
+// registers and RAM.
+
+extern crate tpm;
+
+mod programs;
+mod translated;
+
+use programs::*;
+use std::fmt::Debug;
+use tpm::evaluator::*;
+use tpm::mem::MemFetch;
+
+const STACK : (u64, u64) = (64, 192);
+const VECTORS : u64 = 224;
+const RUNS : usize = 50;
+const ROUNDS : usize = 40;
+
+// What a translated function is
+pub type Run<U, S> = for<'t, 'a> fn(&'t mut State<'a, U, S>, U) -> Result<MutNotice<'t, U>, Failure<U>>;
+
+#[derive(Debug, PartialEq)]
+enum Outcome<U : Copy> {
+    Thrash,
+    Halt,
+    Out(U, U),
+    In(U),
+    Call(U, U, Vec<U>),
+    Fail(Failure<U>)
+}
+
+// Answers a Call with the result and words to fill the start of its slice
+// with, or cancels it on None.
+fn outcome<U : Prim>(res : Result<MutNotice<U>, Failure<U>>, answer : Option<(U, &[U])>) -> Outcome<U> {
+    match res {
+        Err(f) => Outcome::Fail(f),
+        Ok(MutNotice::Thrash) => Outcome::Thrash,
+        Ok(MutNotice::Halt) => Outcome::Halt,
+        Ok(MutNotice::Out{port, out}) => Outcome::Out(port, out),
+        Ok(MutNotice::In{port}) => Outcome::In(port),
+        Ok(MutNotice::Call{major, minor, slice, reply}) => {
+            let got = Outcome::Call(major, minor, slice.to_vec());
+            match answer {
+                None => reply.cancel(),
+                Some((val, fill)) => {
+                    for (w, f) in slice.iter_mut().zip(fill) {
+                        *w = *f;
+                    }
+                    reply.resume(val)
+                }
+            }
+            got
+        }
+    }
+}
+
+fn lockstep<U : Compl<S> + Debug, S : Compl<U>>(name : &str, program : &[Instruction<U>], run : Run<U, S>, seed : u64) {
+    let mut rng = Rng::new(seed);
+    let mut ram = vec![U::zero(); RAM_WORDS];
+    for v in ram[VECTORS as usize ..].iter_mut() {
+        *v = U::from_u64(rng.below(program.len() as u64 + 2));
+    }
+    let mut native_ram = ram.clone();
+    let mut interp = State::new(&mut ram);
+    let mut native = State::new(&mut native_ram);
+    for state in [&mut interp, &mut native].iter_mut() {
+        state.set_stack(U::from_u64(STACK.0), U::from_u64(STACK.1)).unwrap();
+        state.set_vectors(U::from_u64(VECTORS)).unwrap();
+    }
+    for round in 0..ROUNDS {
+        if rng.chance(20) {
+            let n = rng.below(4) as usize;
+            interp.raise(n).unwrap();
+            native.raise(n).unwrap();
+        }
+        let fuel = U::from_u64(1 + rng.below(80));
+        let answer : U = value(&mut rng);
+        let fill : Vec<U> = (0..4).map(|_| value(&mut rng)).collect();
+        let reply = if rng.chance(20) {None} else {Some((answer, &fill[..]))};
+        let expected = outcome(interp.eval_instrs(fuel, &mut MemFetch(program)), reply);
+        let got = outcome(run(&mut native, fuel), reply);
+        assert_eq!(got, expected, "{} (seed {}) round {}", name, seed, round);
+        assert_eq!(native.pc(), interp.pc(), "{} (seed {}) round {}: pc", name, seed, round);
+        assert_eq!(native.regs(), interp.regs(), "{} (seed {}) round {}: registers", name, seed, round);
+        assert_eq!(native.ram(), interp.ram(), "{} (seed {}) round {}: ram", name, seed, round);
+        if let Outcome::In(_) = got {
+            interp.resume_in(answer).unwrap();
+            native.resume_in(answer).unwrap();
+        }
+    }
+}
+
+fn check<U : Compl<S> + Debug, S : Compl<U>>(runs : &[Run<U, S>]) {
+    let programs = programs::<U>();
+    assert_eq!(runs.len(), programs.len(), "tests/native/translated.rs is stale; see native_fixture");
+    for ((name, code), &run) in programs.iter().zip(runs) {
+        for seed in 0..RUNS as u64 {
+            lockstep(name, code, run, seed);
+        }
+    }
+}
+
+#[test]
+fn matches_eval_instrs_32() {
+    check(translated::U32);
+}
+
+#[test]
+fn matches_eval_instrs_64() {
+    check(translated::U64);
+}
+
+#[test]
+fn fib_outputs_144() {
+    let mut ram = vec![0u32; RAM_WORDS];
+    let mut state = State::new(&mut ram);
+    state.set_stack(STACK.0 as u32, STACK.1 as u32).unwrap();
+    match translated::fib_32(&mut state, !0) {
+        Ok(MutNotice::Out{port:1, out:144}) => (),
+        other => panic!("{:?}", other)
+    }
+}
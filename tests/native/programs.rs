@@ -0,0 +1,172 @@
+// The programs translated.rs is made from, shared by the native test and
+// native_fixture, which regenerates translated.rs. Three are written by hand
+// and the rest are random, from a fixed seed.
+
+use tpm::evaluator::*;
+use tpm::evaluator::Instruction::*;
+use tpm::evaluator::Reg::*;
+
+pub const RAM_WORDS : usize = 256;
+const RANDOM : usize = 12;
+const RANDOM_LEN : u64 = 40;
+
+// xorshift64*, as in the conformance suite
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed : u64) -> Rng {
+        Rng(seed | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    pub fn below(&mut self, n : u64) -> u64 {
+        self.next() % n
+    }
+
+    pub fn chance(&mut self, percent : u64) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<T : Copy>(&mut self, xs : &[T]) -> T {
+        xs[self.below(xs.len() as u64) as usize]
+    }
+}
+
+fn w<U : Prim>(v : i64) -> U {
+    U::from_u64(v as u64)
+}
+
+// Counts R0 up to 1000
+fn count<U : Prim>() -> Vec<Instruction<U>> {
+    vec![
+        Lit{val:w(0), reg:R0},
+        Lit{val:w(1000), reg:R1},
+        Um2I{op:M2Op::Add, r1:R0, imm:w(1), r3:R0},
+        Um2{op:M2Op::Sub, r1:R1, r2:R0, r3:R2},
+        UJumpI{cond:Cond::NeZ, flag:R2, mode:Addr::Rel, target:w(-2)},
+        Halt
+    ]
+}
+
+// Outputs fib(12), recursively on the stack. The caller puts its return
+// address in RE.
+fn fib<U : Prim>() -> Vec<Instruction<U>> {
+    vec![
+        Lit{val:w(12), reg:R0},
+        Lit{val:w(4), reg:RE},
+        Lit{val:w(6), reg:RD},
+        UJump{cond:Cond::Always, flag:R0, dest:RD},
+        Out{reg:R1, port:w(1)},
+        Halt,
+        // fib: R1 = fib(R0)
+        Um2I{op:M2Op::Lt, r1:R0, imm:w(2), r3:R2},
+        UJumpI{cond:Cond::EqZ, flag:R2, mode:Addr::Abs, target:w(10)},
+        Um2I{op:M2Op::Add, r1:R0, imm:w(0), r3:R1},
+        UJump{cond:Cond::Always, flag:R0, dest:RE},
+        Push{reg:RE},
+        Push{reg:R0},
+        Um2I{op:M2Op::Sub, r1:R0, imm:w(1), r3:R0},
+        Lit{val:w(15), reg:RE},
+        UJumpI{cond:Cond::Always, flag:R0, mode:Addr::Abs, target:w(6)},
+        Pop{reg:R0},
+        Push{reg:R1},
+        Um2I{op:M2Op::Sub, r1:R0, imm:w(2), r3:R0},
+        Lit{val:w(20), reg:RE},
+        UJumpI{cond:Cond::Always, flag:R0, mode:Addr::Abs, target:w(6)},
+        Pop{reg:R2},
+        Um2{op:M2Op::Add, r1:R1, r2:R2, r3:R1},
+        Pop{reg:RE},
+        UJump{cond:Cond::Always, flag:R0, dest:RE}
+    ]
+}
+
+// Talks to the host with interrupts enabled; the handler is at 12
+fn io<U : Prim>() -> Vec<Instruction<U>> {
+    vec![
+        Lit{val:w(1), reg:R0},
+        IntEn{flag:R0},
+        In{reg:R1, port:w(7)},
+        Lit{val:w(8), reg:R2},
+        Lit{val:w(4), reg:R3},
+        Lit{val:w(2), reg:R4},
+        Call{major:R4, minor:R1, arg:R2, len:R3, res:R5},
+        Sm2{op:M2Op::Div, r1:R5, r2:R1, r3:R6},
+        MemCopy{dst:R3, src:R2, len:R3},
+        SSelect{cond:Cond::LtZ, flag:R6, r1:R5, r2:R1, r3:R7},
+        Out{reg:R7, port:w(3)},
+        UJumpI{cond:Cond::Always, flag:R0, mode:Addr::Abs, target:w(2)},
+        Out{reg:R0, port:w(9)},
+        Iret
+    ]
+}
+
+pub fn value<U : Prim>(rng : &mut Rng) -> U {
+    let v = match rng.below(5) {
+        0 | 1 => rng.below(RAM_WORDS as u64 + 4),
+        2 => rng.below(RANDOM_LEN + 8),
+        3 => (rng.below(9) as i64 - 4) as u64,
+        _ => rng.next()
+    };
+    U::from_u64(v)
+}
+
+fn reg(rng : &mut Rng) -> Reg {
+    if rng.chance(80) {Reg::ARR[rng.below(8) as usize]} else {rng.pick(Reg::ARR)}
+}
+
+fn pick<T : Fin>(rng : &mut Rng) -> T {
+    rng.pick(T::ARR)
+}
+
+fn instruction<U : Prim>(rng : &mut Rng) -> Instruction<U> {
+    let r = reg;
+    let target = |rng : &mut Rng| U::from_u64(rng.below(RANDOM_LEN + 8));
+    match rng.below(34) {
+        0 ..= 3 => Lit{val:value(rng), reg:r(rng)},
+        4 | 5 => Um2{op:pick(rng), r1:r(rng), r2:r(rng), r3:r(rng)},
+        6 | 7 => Sm2{op:pick(rng), r1:r(rng), r2:r(rng), r3:r(rng)},
+        8 => Um2I{op:pick(rng), r1:r(rng), imm:value(rng), r3:r(rng)},
+        9 => Sm2I{op:pick(rng), r1:r(rng), imm:value(rng), r3:r(rng)},
+        10 => Um4{op:pick(rng), r1:r(rng), r2:r(rng), r3:r(rng), r4:r(rng)},
+        11 => UJump{cond:pick(rng), flag:r(rng), dest:r(rng)},
+        12 => SJump{cond:pick(rng), flag:r(rng), dest:r(rng)},
+        13 => UJumpI{cond:pick(rng), flag:r(rng), mode:Addr::Abs, target:target(rng)},
+        14 => SJumpI{cond:pick(rng), flag:r(rng), mode:Addr::Rel, target:w((rng.below(9) as i64) - 4)},
+        15 => UJumpCmp{cond:pick(rng), r1:r(rng), r2:r(rng), dest:r(rng)},
+        16 => SJumpCmp{cond:pick(rng), r1:r(rng), r2:r(rng), dest:r(rng)},
+        17 => USelect{cond:pick(rng), flag:r(rng), r1:r(rng), r2:r(rng), r3:r(rng)},
+        18 => SSelect{cond:pick(rng), flag:r(rng), r1:r(rng), r2:r(rng), r3:r(rng)},
+        19 | 20 => Ram{dir:pick(rng), ptr:r(rng), val:r(rng)},
+        21 | 22 => RamI{dir:pick(rng), ptr:r(rng), off:value(rng), val:r(rng)},
+        23 => ULoad{width:pick(rng), ptr:r(rng), val:r(rng)},
+        24 => Store{width:pick(rng), ptr:r(rng), val:r(rng)},
+        25 => MemFill{dst:r(rng), val:r(rng), len:r(rng)},
+        26 => Push{reg:r(rng)},
+        27 => Pop{reg:r(rng)},
+        28 => Call{major:r(rng), minor:r(rng), arg:r(rng), len:r(rng), res:r(rng)},
+        29 => if rng.chance(50) {Out{reg:r(rng), port:value(rng)}} else {In{reg:r(rng), port:value(rng)}},
+        30 => IntEn{flag:r(rng)},
+        31 => Iret,
+        _ => if rng.chance(80) {Halt} else {Invalid}
+    }
+}
+
+// Name and code of each program, the same for both widths
+pub fn programs<U : Prim>() -> Vec<(String, Vec<Instruction<U>>)> {
+    let mut all = vec![("count".to_string(), count()), ("fib".to_string(), fib()), ("io".to_string(), io())];
+    let mut rng = Rng::new(46);
+    for i in 0..RANDOM {
+        // Seed the registers so the first instructions have something to work on
+        let mut code : Vec<Instruction<U>> = Reg::ARR[.. 8].iter().map(|&reg| Lit{val:value(&mut rng), reg}).collect();
+        let len = RANDOM_LEN / 2 + rng.below(RANDOM_LEN / 2);
+        code.extend((0..len).map(|_| instruction::<U>(&mut rng)));
+        all.push((format!("random{}", i), code));
+    }
+    all
+}
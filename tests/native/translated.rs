@@ -0,0 +1,1454 @@
+// Generated by tests/native_fixture.rs. Do not edit.
+
+// 6 instructions, translated by ::tpm::evaluator::native
+pub fn count_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 0u32); 1u32},
+            1 => {n.set(1, 1000u32); 2u32},
+            2 => {let v = n.get(0).wrapping_add(1u32); n.set(0, v); 3u32},
+            3 => {let v = n.get(1).wrapping_sub(n.get(0)); n.set(2, v); 4u32},
+            4 => if Cond::NeZ.holds(n.get(2).cmp(&0u32)) {2u32} else {5u32},
+            5 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 24 instructions, translated by ::tpm::evaluator::native
+pub fn fib_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 12u32); 1u32},
+            1 => {n.set(14, 4u32); 2u32},
+            2 => {n.set(13, 6u32); 3u32},
+            3 => n.get(13),
+            4 => match n.eval(&mut pc, &Out{reg:Reg::R1, port:1u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            5 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            6 => {let v = (n.get(0) < 2u32) as u32; n.set(2, v); 7u32},
+            7 => if Cond::EqZ.holds(n.get(2).cmp(&0u32)) {10u32} else {8u32},
+            8 => {let v = n.get(0).wrapping_add(0u32); n.set(1, v); 9u32},
+            9 => n.get(14),
+            10 => match n.eval(&mut pc, &Push{reg:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => match n.eval(&mut pc, &Push{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => {let v = n.get(0).wrapping_sub(1u32); n.set(0, v); 13u32},
+            13 => {n.set(14, 15u32); 14u32},
+            14 => 6u32,
+            15 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => match n.eval(&mut pc, &Push{reg:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {let v = n.get(0).wrapping_sub(2u32); n.set(0, v); 18u32},
+            18 => {n.set(14, 20u32); 19u32},
+            19 => 6u32,
+            20 => match n.eval(&mut pc, &Pop{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let v = n.get(1).wrapping_add(n.get(2)); n.set(1, v); 22u32},
+            22 => match n.eval(&mut pc, &Pop{reg:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => n.get(14),
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 14 instructions, translated by ::tpm::evaluator::native
+pub fn io_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 1u32); 1u32},
+            1 => match n.eval(&mut pc, &IntEn{flag:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            2 => match n.eval(&mut pc, &In{reg:Reg::R1, port:7u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            3 => {n.set(2, 8u32); 4u32},
+            4 => {n.set(3, 4u32); 5u32},
+            5 => {n.set(4, 2u32); 6u32},
+            6 => match n.eval(&mut pc, &Call{major:Reg::R4, minor:Reg::R1, arg:Reg::R2, len:Reg::R3, res:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            7 => match n.eval(&mut pc, &Sm2{op:M2Op::Div, r1:Reg::R5, r2:Reg::R1, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            8 => match n.eval(&mut pc, &MemCopy{dst:Reg::R3, src:Reg::R2, len:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => {let m = (Cond::LtZ.holds((n.get(6) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(5) & m) | (n.get(1) & !m); n.set(7, v); 10u32},
+            10 => match n.eval(&mut pc, &Out{reg:Reg::R7, port:3u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => 2u32,
+            12 => match n.eval(&mut pc, &Out{reg:Reg::R0, port:9u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 41 instructions, translated by ::tpm::evaluator::native
+pub fn random0_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 30u32); 1u32},
+            1 => {n.set(1, 154u32); 2u32},
+            2 => {n.set(2, 178u32); 3u32},
+            3 => {n.set(3, 45u32); 4u32},
+            4 => {n.set(4, 42u32); 5u32},
+            5 => {n.set(5, 4294967293u32); 6u32},
+            6 => {n.set(6, 379445446u32); 7u32},
+            7 => {n.set(7, 374599635u32); 8u32},
+            8 => {n.set(1, 220u32); 9u32},
+            9 => {let m = (Cond::EqZ.holds((n.get(5) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(1) & m) | (n.get(7) & !m); n.set(2, v); 10u32},
+            10 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {n.set(1, 4294967293u32); 12u32},
+            12 => {n.set(6, 200u32); 13u32},
+            13 => match n.eval(&mut pc, &ULoad{width:Width::Byte, ptr:Reg::R5, val:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => {let m = (Cond::LtZ.holds((n.get(2) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(4) & m) | (n.get(7) & !m); n.set(5, v); 15u32},
+            15 => {n.set(2, 0u32); 16u32},
+            16 => {let v = n.get(7).wrapping_mul(n.get(2)); n.set(7, v); 17u32},
+            17 => {n.set(0, 47u32); 18u32},
+            18 => match n.eval(&mut pc, &Call{major:Reg::R6, minor:Reg::R0, arg:Reg::R2, len:Reg::R8, res:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => {let a = n.get(2).wrapping_add(111u32); let v = n.get(8); n.write(19u32, a, v)?; 20u32},
+            20 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let m = (Cond::LeZ.holds(n.get(2).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(0) & m) | (n.get(6) & !m); n.set(0, v); 22u32},
+            22 => {n.set(6, 251u32); 23u32},
+            23 => if Cond::NeZ.holds((n.get(5) as i32).cmp(&(n.get(0) as i32))) {n.get(3)} else {24u32},
+            24 => match n.eval(&mut pc, &Pop{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Call{major:Reg::R6, minor:Reg::R3, arg:Reg::R2, len:Reg::R4, res:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => if Cond::EqZ.holds(n.get(1).cmp(&n.get(2))) {n.get(2)} else {27u32},
+            27 => {let a = n.get(4).wrapping_add(33u32); let v = n.read(27u32, a)?; n.set(7, v); 28u32},
+            28 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R9, val:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDiv, r1:Reg::R7, r2:Reg::RA, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &IntEn{flag:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &Call{major:Reg::R4, minor:Reg::R7, arg:Reg::R1, len:Reg::R2, res:Reg::RC}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => {let a = n.get(4); let v = n.read(32u32, a)?; n.set(2, v); 33u32},
+            33 => {n.set(3, 4u32); 34u32},
+            34 => {let m = (Cond::Always.holds(n.get(3).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(4) & m) | (n.get(7) & !m); n.set(3, v); 35u32},
+            35 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R4, r2:Reg::R3, r3:Reg::R7, r4:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R7, val:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => {let a = n.get(10); let v = n.get(4); n.write(38u32, a, v)?; 39u32},
+            39 => {let m = (Cond::NeZ.holds(n.get(1).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(6) & m) | (n.get(6) & !m); n.set(2, v); 40u32},
+            40 => {let a = n.get(5); let v = n.get(0); n.write(40u32, a, v)?; 41u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 31 instructions, translated by ::tpm::evaluator::native
+pub fn random1_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 2u32); 1u32},
+            1 => {n.set(1, 14u32); 2u32},
+            2 => {n.set(2, 163u32); 3u32},
+            3 => {n.set(3, 4257092821u32); 4u32},
+            4 => {n.set(4, 12u32); 5u32},
+            5 => {n.set(5, 1u32); 6u32},
+            6 => {n.set(6, 4u32); 7u32},
+            7 => {n.set(7, 18752295u32); 8u32},
+            8 => if Cond::LtZ.holds(n.get(1).cmp(&0u32)) {34u32} else {9u32},
+            9 => {let v = n.get(6).wrapping_mul(4294967293u32); n.set(1, v); 10u32},
+            10 => {n.set(3, 90u32); 11u32},
+            11 => match n.eval(&mut pc, &IntEn{flag:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => match n.eval(&mut pc, &IntEn{flag:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => {n.set(4, 31u32); 14u32},
+            14 => {let a = n.get(8).wrapping_add(102u32); let v = n.get(6); n.write(14u32, a, v)?; 15u32},
+            15 => {n.set(7, 4294967295u32); 16u32},
+            16 => {let m = (Cond::NeZ.holds(n.get(1).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(6) & m) | (n.get(0) & !m); n.set(8, v); 17u32},
+            17 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMulSat, r1:Reg::R7, imm:171u32, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            18 => match n.eval(&mut pc, &Um2{op:M2Op::FxDiv, r1:Reg::R1, r2:Reg::R2, r3:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R2, val:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => {let m = (Cond::LtZ.holds(n.get(7).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(12) & m) | (n.get(2) & !m); n.set(6, v); 21u32},
+            21 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R1, val:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => {let v = n.get(3).wrapping_mul(n.get(4)); n.set(4, v); 23u32},
+            23 => if Cond::LeZ.holds((n.get(5) as i32).cmp(&(0u32 as i32))) {19u32} else {24u32},
+            24 => {let m = (Cond::LtZ.holds(n.get(3).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(7) & m) | (n.get(3) & !m); n.set(2, v); 25u32},
+            25 => {let a = n.get(6); let v = n.read(25u32, a)?; n.set(5, v); 26u32},
+            26 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R5, r2:Reg::R3, r3:Reg::R3, r4:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => {let v = n.get(6).wrapping_add(n.get(0)); n.set(15, v); 29u32},
+            29 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMulSat, r1:Reg::R0, imm:159u32, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R6, val:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 36 instructions, translated by ::tpm::evaluator::native
+pub fn random2_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 27u32); 1u32},
+            1 => {n.set(1, 4294967292u32); 2u32},
+            2 => {n.set(2, 81u32); 3u32},
+            3 => {n.set(3, 4294967294u32); 4u32},
+            4 => {n.set(4, 134u32); 5u32},
+            5 => {n.set(5, 137u32); 6u32},
+            6 => {n.set(6, 4u32); 7u32},
+            7 => {n.set(7, 31u32); 8u32},
+            8 => match n.eval(&mut pc, &Pop{reg:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => match n.eval(&mut pc, &Push{reg:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            10 => {n.set(5, 93u32); 11u32},
+            11 => {let v = ((n.get(7) as i32) > (n.get(4) as i32)) as u32; n.set(2, v); 12u32},
+            12 => {let v = (n.get(7) == n.get(6)) as u32; n.set(5, v); 13u32},
+            13 => {let v = (n.get(4) > n.get(1)) as u32; n.set(1, v); 14u32},
+            14 => match n.eval(&mut pc, &Pop{reg:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => match n.eval(&mut pc, &Um4{op:M4Op::DivRem, r1:Reg::R5, r2:Reg::R0, r3:Reg::R3, r4:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let v = (n.get(6) == n.get(1)) as u32; n.set(6, v); 17u32},
+            17 => {let a = n.get(1); let v = n.read(17u32, a)?; n.set(6, v); 18u32},
+            18 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMul, r1:Reg::R7, r2:Reg::R4, r3:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => match n.eval(&mut pc, &Call{major:Reg::R6, minor:Reg::R1, arg:Reg::R8, len:Reg::R4, res:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &MemFill{dst:Reg::R1, val:Reg::R7, len:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => match n.eval(&mut pc, &MemFill{dst:Reg::R0, val:Reg::R3, len:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => {n.set(5, 0u32); 24u32},
+            24 => {let a = n.get(10).wrapping_add(762335429u32); let v = n.read(24u32, a)?; n.set(1, v); 25u32},
+            25 => if Cond::GtZ.holds(n.get(7).cmp(&0u32)) {n.get(0)} else {26u32},
+            26 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => {let v = n.get(5).wrapping_sub(4294967292u32); n.set(5, v); 29u32},
+            29 => {let v = ((n.get(7) as i32) > (n.get(0) as i32)) as u32; n.set(0, v); 30u32},
+            30 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => {n.set(7, 7u32); 32u32},
+            32 => {n.set(4, 256u32); 33u32},
+            33 => if Cond::NeZ.holds(n.get(10).cmp(&n.get(3))) {n.get(5)} else {34u32},
+            34 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R4, val:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 35 instructions, translated by ::tpm::evaluator::native
+pub fn random3_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 104u32); 1u32},
+            1 => {n.set(1, 2u32); 2u32},
+            2 => {n.set(2, 13u32); 3u32},
+            3 => {n.set(3, 437593328u32); 4u32},
+            4 => {n.set(4, 1832251717u32); 5u32},
+            5 => {n.set(5, 3027683419u32); 6u32},
+            6 => {n.set(6, 202u32); 7u32},
+            7 => {n.set(7, 56u32); 8u32},
+            8 => {let a = n.get(1); let v = n.read(8u32, a)?; n.set(0, v); 9u32},
+            9 => if Cond::GtZ.holds(n.get(10).cmp(&0u32)) {n.get(2)} else {10u32},
+            10 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => match n.eval(&mut pc, &MemFill{dst:Reg::R0, val:Reg::R4, len:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => {let a = n.get(0).wrapping_add(3u32); let v = n.get(5); n.write(12u32, a, v)?; 13u32},
+            13 => n.get(5),
+            14 => {let a = n.get(1); let v = n.get(2); n.write(14u32, a, v)?; 15u32},
+            15 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let v = ((n.get(3) as i32) > (24u32 as i32)) as u32; n.set(0, v); 17u32},
+            17 => if Cond::NeZ.holds((n.get(6) as i32).cmp(&(n.get(0) as i32))) {n.get(1)} else {18u32},
+            18 => if Cond::GeZ.holds(n.get(0).cmp(&0u32)) {n.get(5)} else {19u32},
+            19 => {n.set(5, 144u32); 20u32},
+            20 => match n.eval(&mut pc, &Sm2I{op:M2Op::MulHi, r1:Reg::R6, imm:3285660037u32, r3:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let v = n.get(6).wrapping_add(n.get(4)); n.set(2, v); 22u32},
+            22 => {let a = n.get(2).wrapping_add(228u32); let v = n.read(22u32, a)?; n.set(3, v); 23u32},
+            23 => 45u32,
+            24 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R0, val:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMul, r1:Reg::R4, r2:Reg::R7, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => if Cond::EqZ.holds((n.get(10) as i32).cmp(&(0u32 as i32))) {29u32} else {28u32},
+            28 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMul, r1:Reg::R6, r2:Reg::R7, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &MemFill{dst:Reg::R6, val:Reg::R7, len:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R6, val:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => {n.set(13, 0u32); 32u32},
+            32 => if Cond::EqZ.holds(n.get(1).cmp(&0u32)) {44u32} else {33u32},
+            33 => if Cond::NeZ.holds(n.get(1).cmp(&0u32)) {n.get(6)} else {34u32},
+            34 => match n.eval(&mut pc, &Out{reg:Reg::R5, port:5u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 41 instructions, translated by ::tpm::evaluator::native
+pub fn random4_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 4u32); 1u32},
+            1 => {n.set(1, 19u32); 2u32},
+            2 => {n.set(2, 673072398u32); 3u32},
+            3 => {n.set(3, 4017387418u32); 4u32},
+            4 => {n.set(4, 0u32); 5u32},
+            5 => {n.set(5, 156u32); 6u32},
+            6 => {n.set(6, 3u32); 7u32},
+            7 => {n.set(7, 102u32); 8u32},
+            8 => {n.set(5, 2510802188u32); 9u32},
+            9 => {let a = n.get(3).wrapping_add(107u32); let v = n.read(9u32, a)?; n.set(4, v); 10u32},
+            10 => {n.set(3, 1u32); 11u32},
+            11 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => {n.set(0, 15u32); 13u32},
+            13 => match n.eval(&mut pc, &MemFill{dst:Reg::R0, val:Reg::R2, len:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => {let a = n.get(4).wrapping_add(2385121157u32); let v = n.read(15u32, a)?; n.set(0, v); 16u32},
+            16 => match n.eval(&mut pc, &Pop{reg:Reg::R9}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {n.set(3, 99u32); 18u32},
+            18 => if Cond::GeZ.holds((n.get(6) as i32).cmp(&(0u32 as i32))) {n.get(6)} else {19u32},
+            19 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMulSat, r1:Reg::R5, r2:Reg::R6, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => {let a = n.get(0).wrapping_add(70u32); let v = n.read(20u32, a)?; n.set(8, v); 21u32},
+            21 => match n.eval(&mut pc, &Push{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => match n.eval(&mut pc, &Push{reg:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => {let a = n.get(0).wrapping_add(214u32); let v = n.get(3); n.write(23u32, a, v)?; 24u32},
+            24 => {let a = n.get(7); let v = n.get(2); n.write(24u32, a, v)?; 25u32},
+            25 => match n.eval(&mut pc, &Pop{reg:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => match n.eval(&mut pc, &IntEn{flag:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => {n.set(5, 135u32); 28u32},
+            28 => {let m = (Cond::EqZ.holds((n.get(0) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(6) & m) | (n.get(0) & !m); n.set(7, v); 29u32},
+            29 => {n.set(4, 3823527283u32); 30u32},
+            30 => if Cond::NeZ.holds((n.get(1) as i32).cmp(&(n.get(3) as i32))) {n.get(0)} else {31u32},
+            31 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R7, r2:Reg::R2, r3:Reg::R1, r4:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => if Cond::GeZ.holds((n.get(2) as i32).cmp(&(0u32 as i32))) {28u32} else {33u32},
+            33 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R4, val:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => {let v = (n.get(6) > n.get(3)) as u32; n.set(2, v); 37u32},
+            37 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMul, r1:Reg::R2, imm:112u32, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => match n.eval(&mut pc, &Pop{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            39 => {let a = n.get(3).wrapping_add(24u32); let v = n.get(1); n.write(39u32, a, v)?; 40u32},
+            40 => match n.eval(&mut pc, &MemFill{dst:Reg::R5, val:Reg::R0, len:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 45 instructions, translated by ::tpm::evaluator::native
+pub fn random5_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 153478674u32); 1u32},
+            1 => {n.set(1, 45u32); 2u32},
+            2 => {n.set(2, 21u32); 3u32},
+            3 => {n.set(3, 3974656341u32); 4u32},
+            4 => {n.set(4, 19u32); 5u32},
+            5 => {n.set(5, 2u32); 6u32},
+            6 => {n.set(6, 158u32); 7u32},
+            7 => {n.set(7, 42u32); 8u32},
+            8 => {n.set(1, 46u32); 9u32},
+            9 => match n.eval(&mut pc, &Sm2I{op:M2Op::Div, r1:Reg::R2, imm:108u32, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            10 => {let a = n.get(4); let v = n.get(5); n.write(10u32, a, v)?; 11u32},
+            11 => match n.eval(&mut pc, &Push{reg:Reg::R9}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => match n.eval(&mut pc, &MemFill{dst:Reg::RB, val:Reg::R1, len:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Out{reg:Reg::R8, port:2u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => {let v = ((n.get(7) as i32) < (4294967293u32 as i32)) as u32; n.set(8, v); 15u32},
+            15 => {let v = ((n.get(1) as i32) < (n.get(11) as i32)) as u32; n.set(3, v); 16u32},
+            16 => match n.eval(&mut pc, &Um4{op:M4Op::DivRem, r1:Reg::R5, r2:Reg::R3, r3:Reg::R7, r4:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {let a = n.get(13).wrapping_add(1u32); let v = n.get(2); n.write(17u32, a, v)?; 18u32},
+            18 => {n.set(3, 44u32); 19u32},
+            19 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {n.set(3, 211u32); 22u32},
+            22 => {n.set(6, 4294967293u32); 23u32},
+            23 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => if Cond::LtZ.holds(n.get(15).cmp(&n.get(4))) {n.get(3)} else {25u32},
+            25 => {let a = n.get(1).wrapping_add(14u32); let v = n.get(1); n.write(25u32, a, v)?; 26u32},
+            26 => {let a = n.get(1).wrapping_add(22u32); let v = n.read(26u32, a)?; n.set(14, v); 27u32},
+            27 => if Cond::LeZ.holds((n.get(0) as i32).cmp(&(n.get(7) as i32))) {n.get(2)} else {28u32},
+            28 => if Cond::GtZ.holds(n.get(3).cmp(&n.get(2))) {n.get(6)} else {29u32},
+            29 => n.get(6),
+            30 => match n.eval(&mut pc, &Sm2I{op:M2Op::MulHi, r1:Reg::R2, imm:38u32, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &Um2{op:M2Op::FxMulSat, r1:Reg::R5, r2:Reg::R5, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => if Cond::LtZ.holds(n.get(13).cmp(&n.get(2))) {n.get(1)} else {33u32},
+            33 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => {let v = (n.get(1) < n.get(6)) as u32; n.set(5, v); 35u32},
+            35 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R3, r2:Reg::R7, r3:Reg::R3, r4:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => match n.eval(&mut pc, &Um2I{op:M2Op::FxDiv, r1:Reg::RC, imm:103u32, r3:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => match n.eval(&mut pc, &Out{reg:Reg::R2, port:110u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => {let a = n.get(0).wrapping_add(3590897036u32); let v = n.get(2); n.write(38u32, a, v)?; 39u32},
+            39 => {let m = (Cond::GeZ.holds(n.get(4).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(5) & m) | (n.get(2) & !m); n.set(5, v); 40u32},
+            40 => {n.set(6, 399194405u32); 41u32},
+            41 => {n.set(12, 254u32); 42u32},
+            42 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R2, r2:Reg::R3, r3:Reg::RF, r4:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            43 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            44 => {let a = n.get(2).wrapping_add(4117709405u32); let v = n.get(0); n.write(44u32, a, v)?; 45u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 38 instructions, translated by ::tpm::evaluator::native
+pub fn random6_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 3597576394u32); 1u32},
+            1 => {n.set(1, 254u32); 2u32},
+            2 => {n.set(2, 7u32); 3u32},
+            3 => {n.set(3, 38u32); 4u32},
+            4 => {n.set(4, 42u32); 5u32},
+            5 => {n.set(5, 139u32); 6u32},
+            6 => {n.set(6, 10u32); 7u32},
+            7 => {n.set(7, 4294967293u32); 8u32},
+            8 => if Cond::LtZ.holds((n.get(6) as i32).cmp(&(n.get(3) as i32))) {n.get(4)} else {9u32},
+            9 => {let v = n.get(7).wrapping_add(38u32); n.set(1, v); 10u32},
+            10 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R0, r2:Reg::R5, r3:Reg::R2, r4:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {n.set(0, 0u32); 12u32},
+            12 => {let a = n.get(2); let v = n.get(6); n.write(12u32, a, v)?; 13u32},
+            13 => {let m = (Cond::LeZ.holds((n.get(7) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(7) & m) | (n.get(2) & !m); n.set(2, v); 14u32},
+            14 => if Cond::GtZ.holds((n.get(5) as i32).cmp(&(0u32 as i32))) {n.get(6)} else {15u32},
+            15 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDivSat, r1:Reg::R4, r2:Reg::R7, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let v = n.get(2).wrapping_add(n.get(2)); n.set(11, v); 17u32},
+            17 => match n.eval(&mut pc, &Pop{reg:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            18 => {let a = n.get(5); let v = n.read(18u32, a)?; n.set(3, v); 19u32},
+            19 => match n.eval(&mut pc, &Um2I{op:M2Op::FxDiv, r1:Reg::R6, imm:16u32, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &Um2{op:M2Op::FxDivSat, r1:Reg::R2, r2:Reg::RD, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let v = (n.get(4) == n.get(4)) as u32; n.set(1, v); 22u32},
+            22 => {let v = n.get(2).wrapping_mul(12u32); n.set(11, v); 23u32},
+            23 => match n.eval(&mut pc, &ULoad{width:Width::Byte, ptr:Reg::R6, val:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => match n.eval(&mut pc, &Um2I{op:M2Op::FxMulSat, r1:Reg::R5, imm:2u32, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R0, r2:Reg::R4, r3:Reg::R5, r4:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMulSat, r1:Reg::R1, imm:3245063262u32, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => {let a = n.get(6); let v = n.get(1); n.write(29u32, a, v)?; 30u32},
+            30 => match n.eval(&mut pc, &Call{major:Reg::RF, minor:Reg::R4, arg:Reg::R6, len:Reg::R1, res:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => {let m = (Cond::LtZ.holds((n.get(0) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(4) & m) | (n.get(7) & !m); n.set(13, v); 32u32},
+            32 => match n.eval(&mut pc, &Pop{reg:Reg::RC}) {None => pc, Some(stop) => return n.stop(stop)},
+            33 => match n.eval(&mut pc, &Um2{op:M2Op::FxMulSat, r1:Reg::R4, r2:Reg::R4, r3:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => match n.eval(&mut pc, &Call{major:Reg::R8, minor:Reg::R4, arg:Reg::R3, len:Reg::R1, res:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => if Cond::LtZ.holds((n.get(5) as i32).cmp(&(0u32 as i32))) {n.get(0)} else {36u32},
+            36 => if Cond::EqZ.holds(n.get(5).cmp(&n.get(6))) {n.get(3)} else {37u32},
+            37 => {let v = (n.get(0) > n.get(10)) as u32; n.set(6, v); 38u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 30 instructions, translated by ::tpm::evaluator::native
+pub fn random7_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 114u32); 1u32},
+            1 => {n.set(1, 26u32); 2u32},
+            2 => {n.set(2, 18u32); 3u32},
+            3 => {n.set(3, 37u32); 4u32},
+            4 => {n.set(4, 2719480328u32); 5u32},
+            5 => {n.set(5, 15u32); 6u32},
+            6 => {n.set(6, 41u32); 7u32},
+            7 => {n.set(7, 1u32); 8u32},
+            8 => {let v = (n.get(0) < n.get(4)) as u32; n.set(2, v); 9u32},
+            9 => if Cond::GtZ.holds((n.get(1) as i32).cmp(&(0u32 as i32))) {n.get(4)} else {10u32},
+            10 => match n.eval(&mut pc, &ULoad{width:Width::Byte, ptr:Reg::R6, val:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => 39u32,
+            12 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => if Cond::GeZ.holds(n.get(3).cmp(&0u32)) {n.get(14)} else {14u32},
+            14 => {let a = n.get(2); let v = n.read(14u32, a)?; n.set(4, v); 15u32},
+            15 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => match n.eval(&mut pc, &Um2{op:M2Op::FxDivSat, r1:Reg::R2, r2:Reg::R2, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {n.set(15, 3u32); 18u32},
+            18 => {let v = n.get(4).wrapping_sub(n.get(6)); n.set(4, v); 19u32},
+            19 => {let a = n.get(2).wrapping_add(4294967294u32); let v = n.read(19u32, a)?; n.set(2, v); 20u32},
+            20 => {let v = ((n.get(3) as i32) > (7u32 as i32)) as u32; n.set(5, v); 21u32},
+            21 => match n.eval(&mut pc, &Um4{op:M4Op::DivRem, r1:Reg::R5, r2:Reg::R1, r3:Reg::R1, r4:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => match n.eval(&mut pc, &Um2{op:M2Op::MulHi, r1:Reg::RD, r2:Reg::R9, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => {let v = ((n.get(3) as i32) > (4294967292u32 as i32)) as u32; n.set(2, v); 24u32},
+            24 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R3, r2:Reg::R6, r3:Reg::R2, r4:Reg::RA}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Pop{reg:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => {let a = n.get(5).wrapping_add(2376945372u32); let v = n.get(1); n.write(26u32, a, v)?; 27u32},
+            27 => {n.set(3, 37u32); 28u32},
+            28 => {n.set(2, 51u32); 29u32},
+            29 => {let a = n.get(5); let v = n.get(6); n.write(29u32, a, v)?; 30u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 43 instructions, translated by ::tpm::evaluator::native
+pub fn random8_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 32u32); 1u32},
+            1 => {n.set(1, 2u32); 2u32},
+            2 => {n.set(2, 2927491196u32); 3u32},
+            3 => {n.set(3, 7u32); 4u32},
+            4 => {n.set(4, 882490116u32); 5u32},
+            5 => {n.set(5, 46u32); 6u32},
+            6 => {n.set(6, 246u32); 7u32},
+            7 => {n.set(7, 109773778u32); 8u32},
+            8 => match n.eval(&mut pc, &In{reg:Reg::R2, port:248u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => if Cond::NeZ.holds((n.get(7) as i32).cmp(&(n.get(2) as i32))) {n.get(4)} else {10u32},
+            10 => match n.eval(&mut pc, &In{reg:Reg::R9, port:42u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {let a = n.get(0); let v = n.get(6); n.write(11u32, a, v)?; 12u32},
+            12 => {let a = n.get(1).wrapping_add(7u32); let v = n.get(2); n.write(12u32, a, v)?; 13u32},
+            13 => {let v = ((n.get(0) as i32) < (1119027042u32 as i32)) as u32; n.set(5, v); 14u32},
+            14 => if Cond::EqZ.holds(n.get(6).cmp(&0u32)) {n.get(1)} else {15u32},
+            15 => {let a = n.get(1).wrapping_add(4294967295u32); let v = n.read(15u32, a)?; n.set(7, v); 16u32},
+            16 => match n.eval(&mut pc, &Um2{op:M2Op::FxMulSat, r1:Reg::R2, r2:Reg::R4, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {let m = (Cond::GtZ.holds((n.get(0) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(7) & m) | (n.get(3) & !m); n.set(4, v); 18u32},
+            18 => if Cond::LtZ.holds((n.get(0) as i32).cmp(&(0u32 as i32))) {16u32} else {19u32},
+            19 => {let v = n.get(13).wrapping_mul(n.get(5)); n.set(2, v); 20u32},
+            20 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDivSat, r1:Reg::R4, r2:Reg::RB, r3:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let m = (Cond::LeZ.holds((n.get(0) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(13) & m) | (n.get(0) & !m); n.set(7, v); 22u32},
+            22 => {let a = n.get(6).wrapping_add(3346632229u32); let v = n.get(1); n.write(22u32, a, v)?; 23u32},
+            23 => match n.eval(&mut pc, &Call{major:Reg::R4, minor:Reg::R2, arg:Reg::R4, len:Reg::R6, res:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => {let v = (n.get(4) == n.get(3)) as u32; n.set(1, v); 25u32},
+            25 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R2, val:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => {let v = n.get(6).wrapping_mul(n.get(7)); n.set(7, v); 27u32},
+            27 => {let a = n.get(5).wrapping_add(310716430u32); let v = n.read(27u32, a)?; n.set(7, v); 28u32},
+            28 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDiv, r1:Reg::R1, r2:Reg::R6, r3:Reg::RF}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R5, r2:Reg::R6, r3:Reg::R1, r4:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => {n.set(0, 254u32); 33u32},
+            33 => if Cond::GtZ.holds((n.get(5) as i32).cmp(&(n.get(5) as i32))) {n.get(3)} else {34u32},
+            34 => {let v = (n.get(2) == n.get(2)) as u32; n.set(2, v); 35u32},
+            35 => match n.eval(&mut pc, &MemFill{dst:Reg::R3, val:Reg::R5, len:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => match n.eval(&mut pc, &MemFill{dst:Reg::R3, val:Reg::R4, len:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => {let v = (n.get(5) < 258u32) as u32; n.set(7, v); 39u32},
+            39 => if Cond::GeZ.holds(n.get(0).cmp(&0u32)) {7u32} else {40u32},
+            40 => {let v = n.get(12).wrapping_mul(n.get(6)); n.set(6, v); 41u32},
+            41 => {let a = n.get(5).wrapping_add(443925295u32); let v = n.read(41u32, a)?; n.set(5, v); 42u32},
+            42 => {n.set(5, 4294967293u32); 43u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 39 instructions, translated by ::tpm::evaluator::native
+pub fn random9_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 242u32); 1u32},
+            1 => {n.set(1, 56u32); 2u32},
+            2 => {n.set(2, 4294967294u32); 3u32},
+            3 => {n.set(3, 30u32); 4u32},
+            4 => {n.set(4, 935991068u32); 5u32},
+            5 => {n.set(5, 4u32); 6u32},
+            6 => {n.set(6, 4294967294u32); 7u32},
+            7 => {n.set(7, 207u32); 8u32},
+            8 => match n.eval(&mut pc, &IntEn{flag:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => {let a = n.get(4); let v = n.read(9u32, a)?; n.set(7, v); 10u32},
+            10 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R4, val:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {let v = n.get(2).wrapping_sub(26u32); n.set(6, v); 12u32},
+            12 => match n.eval(&mut pc, &In{reg:Reg::R1, port:143u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => {let m = (Cond::NeZ.holds((n.get(2) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(7) & m) | (n.get(12) & !m); n.set(4, v); 15u32},
+            15 => match n.eval(&mut pc, &Push{reg:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => n.get(0),
+            17 => {let v = n.get(6).wrapping_sub(229u32); n.set(1, v); 18u32},
+            18 => {n.set(7, 236u32); 19u32},
+            19 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R3, r2:Reg::R2, r3:Reg::R4, r4:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => {let a = n.get(7).wrapping_add(14u32); let v = n.get(6); n.write(20u32, a, v)?; 21u32},
+            21 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R4, val:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => {n.set(11, 12u32); 23u32},
+            23 => {let v = ((n.get(1) as i32) > (n.get(4) as i32)) as u32; n.set(3, v); 24u32},
+            24 => {let a = n.get(6); let v = n.get(10); n.write(24u32, a, v)?; 25u32},
+            25 => if Cond::EqZ.holds(n.get(7).cmp(&0u32)) {n.get(1)} else {26u32},
+            26 => {n.set(2, 105151997u32); 27u32},
+            27 => {let v = n.get(6).wrapping_add(n.get(2)); n.set(3, v); 28u32},
+            28 => {let m = (Cond::LeZ.holds(n.get(5).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(7) & m) | (n.get(3) & !m); n.set(1, v); 29u32},
+            29 => {let a = n.get(1); let v = n.get(9); n.write(29u32, a, v)?; 30u32},
+            30 => match n.eval(&mut pc, &Out{reg:Reg::R7, port:38u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => n.get(1),
+            32 => {let m = (Cond::NeZ.holds(n.get(4).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(2) & m) | (n.get(0) & !m); n.set(2, v); 33u32},
+            33 => match n.eval(&mut pc, &Call{major:Reg::R0, minor:Reg::R6, arg:Reg::R5, len:Reg::R3, res:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => {n.set(4, 126u32); 35u32},
+            35 => if Cond::LeZ.holds(n.get(7).cmp(&n.get(2))) {n.get(4)} else {36u32},
+            36 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => if Cond::GeZ.holds((n.get(4) as i32).cmp(&(0u32 as i32))) {n.get(3)} else {38u32},
+            38 => match n.eval(&mut pc, &Call{major:Reg::R5, minor:Reg::R0, arg:Reg::RB, len:Reg::R2, res:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 46 instructions, translated by ::tpm::evaluator::native
+pub fn random10_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 5u32); 1u32},
+            1 => {n.set(1, 47u32); 2u32},
+            2 => {n.set(2, 4u32); 3u32},
+            3 => {n.set(3, 16u32); 4u32},
+            4 => {n.set(4, 4294967294u32); 5u32},
+            5 => {n.set(5, 25u32); 6u32},
+            6 => {n.set(6, 33u32); 7u32},
+            7 => {n.set(7, 4294967292u32); 8u32},
+            8 => {let a = n.get(3); let v = n.read(8u32, a)?; n.set(2, v); 9u32},
+            9 => if Cond::GeZ.holds(n.get(1).cmp(&0u32)) {n.get(5)} else {10u32},
+            10 => match n.eval(&mut pc, &Call{major:Reg::R1, minor:Reg::R7, arg:Reg::R2, len:Reg::R5, res:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => if Cond::NeZ.holds(n.get(1).cmp(&0u32)) {n.get(0)} else {12u32},
+            12 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R3, val:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Push{reg:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R3, val:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => {let a = n.get(2).wrapping_add(33u32); let v = n.read(15u32, a)?; n.set(2, v); 16u32},
+            16 => {let a = n.get(4).wrapping_add(14u32); let v = n.get(12); n.write(16u32, a, v)?; 17u32},
+            17 => if Cond::LeZ.holds(n.get(0).cmp(&n.get(6))) {n.get(5)} else {18u32},
+            18 => match n.eval(&mut pc, &Call{major:Reg::R2, minor:Reg::R6, arg:Reg::R4, len:Reg::R4, res:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => {let v = n.get(6).wrapping_sub(n.get(6)); n.set(4, v); 20u32},
+            20 => match n.eval(&mut pc, &Sm2I{op:M2Op::MulHi, r1:Reg::R3, imm:213u32, r3:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => if Cond::EqZ.holds((n.get(6) as i32).cmp(&(0u32 as i32))) {23u32} else {22u32},
+            22 => if Cond::EqZ.holds(n.get(6).cmp(&0u32)) {n.get(7)} else {23u32},
+            23 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => {let a = n.get(4); let v = n.get(0); n.write(24u32, a, v)?; 25u32},
+            25 => match n.eval(&mut pc, &MemFill{dst:Reg::R5, val:Reg::R2, len:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => {let m = (Cond::NeZ.holds((n.get(6) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(5) & m) | (n.get(3) & !m); n.set(3, v); 27u32},
+            27 => match n.eval(&mut pc, &Push{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R5, r2:Reg::R7, r3:Reg::R7, r4:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &IntEn{flag:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxDivSat, r1:Reg::R1, imm:100u32, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            33 => if Cond::LeZ.holds(n.get(0).cmp(&0u32)) {28u32} else {34u32},
+            34 => match n.eval(&mut pc, &In{reg:Reg::R2, port:3560712493u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => {let m = (Cond::LeZ.holds((n.get(1) as i32).cmp(&(0u32 as i32))) as u32).wrapping_neg(); let v = (n.get(7) & m) | (n.get(0) & !m); n.set(4, v); 36u32},
+            36 => match n.eval(&mut pc, &Sm2{op:M2Op::MulHi, r1:Reg::R7, r2:Reg::R5, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => {let m = (Cond::EqZ.holds(n.get(5).cmp(&0u32)) as u32).wrapping_neg(); let v = (n.get(8) & m) | (n.get(7) & !m); n.set(5, v); 38u32},
+            38 => {let a = n.get(6); let v = n.get(0); n.write(38u32, a, v)?; 39u32},
+            39 => if Cond::NeZ.holds((n.get(5) as i32).cmp(&(n.get(6) as i32))) {n.get(4)} else {40u32},
+            40 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R7, r2:Reg::R7, r3:Reg::R7, r4:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            41 => match n.eval(&mut pc, &Call{major:Reg::R5, minor:Reg::R2, arg:Reg::R7, len:Reg::R5, res:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            42 => {n.set(4, 15u32); 43u32},
+            43 => if Cond::GtZ.holds(n.get(15).cmp(&0u32)) {n.get(5)} else {44u32},
+            44 => {let a = n.get(4); let v = n.read(44u32, a)?; n.set(3, v); 45u32},
+            45 => {let v = n.get(3).wrapping_sub(4294967292u32); n.set(1, v); 46u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 31 instructions, translated by ::tpm::evaluator::native
+pub fn random11_32<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u32, i32>, thrash_cnt : u32)
+    -> Result<::tpm::evaluator::MutNotice<'t, u32>, ::tpm::evaluator::Failure<u32>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 4u32); 1u32},
+            1 => {n.set(1, 3230517991u32); 2u32},
+            2 => {n.set(2, 33u32); 3u32},
+            3 => {n.set(3, 205u32); 4u32},
+            4 => {n.set(4, 4148980900u32); 5u32},
+            5 => {n.set(5, 78u32); 6u32},
+            6 => {n.set(6, 4294967292u32); 7u32},
+            7 => {n.set(7, 1u32); 8u32},
+            8 => if Cond::LtZ.holds(n.get(0).cmp(&0u32)) {n.get(1)} else {9u32},
+            9 => if Cond::EqZ.holds((n.get(1) as i32).cmp(&(n.get(0) as i32))) {n.get(5)} else {10u32},
+            10 => match n.eval(&mut pc, &Pop{reg:Reg::RF}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => if Cond::EqZ.holds(n.get(4).cmp(&0u32)) {1u32} else {12u32},
+            12 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R5, val:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &IntEn{flag:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::RD, val:Reg::RF}) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => match n.eval(&mut pc, &Pop{reg:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let a = n.get(8).wrapping_add(0u32); let v = n.get(2); n.write(16u32, a, v)?; 17u32},
+            17 => match n.eval(&mut pc, &MemFill{dst:Reg::R1, val:Reg::R4, len:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            18 => {let v = ((n.get(7) as i32) > (2098244284u32 as i32)) as u32; n.set(0, v); 19u32},
+            19 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => if Cond::NeZ.holds(n.get(2).cmp(&0u32)) {n.get(5)} else {22u32},
+            22 => match n.eval(&mut pc, &Out{reg:Reg::R0, port:24u32}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => 19u32,
+            24 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => if Cond::GeZ.holds(n.get(6).cmp(&n.get(5))) {n.get(0)} else {26u32},
+            26 => if Cond::GeZ.holds(n.get(6).cmp(&n.get(15))) {n.get(7)} else {27u32},
+            27 => if Cond::LeZ.holds(n.get(1).cmp(&n.get(1))) {n.get(7)} else {28u32},
+            28 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R5, val:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => {n.set(6, 9u32); 31u32},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 6 instructions, translated by ::tpm::evaluator::native
+pub fn count_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 0u64); 1u64},
+            1 => {n.set(1, 1000u64); 2u64},
+            2 => {let v = n.get(0).wrapping_add(1u64); n.set(0, v); 3u64},
+            3 => {let v = n.get(1).wrapping_sub(n.get(0)); n.set(2, v); 4u64},
+            4 => if Cond::NeZ.holds(n.get(2).cmp(&0u64)) {2u64} else {5u64},
+            5 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 24 instructions, translated by ::tpm::evaluator::native
+pub fn fib_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 12u64); 1u64},
+            1 => {n.set(14, 4u64); 2u64},
+            2 => {n.set(13, 6u64); 3u64},
+            3 => n.get(13),
+            4 => match n.eval(&mut pc, &Out{reg:Reg::R1, port:1u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            5 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            6 => {let v = (n.get(0) < 2u64) as u64; n.set(2, v); 7u64},
+            7 => if Cond::EqZ.holds(n.get(2).cmp(&0u64)) {10u64} else {8u64},
+            8 => {let v = n.get(0).wrapping_add(0u64); n.set(1, v); 9u64},
+            9 => n.get(14),
+            10 => match n.eval(&mut pc, &Push{reg:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => match n.eval(&mut pc, &Push{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => {let v = n.get(0).wrapping_sub(1u64); n.set(0, v); 13u64},
+            13 => {n.set(14, 15u64); 14u64},
+            14 => 6u64,
+            15 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => match n.eval(&mut pc, &Push{reg:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {let v = n.get(0).wrapping_sub(2u64); n.set(0, v); 18u64},
+            18 => {n.set(14, 20u64); 19u64},
+            19 => 6u64,
+            20 => match n.eval(&mut pc, &Pop{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let v = n.get(1).wrapping_add(n.get(2)); n.set(1, v); 22u64},
+            22 => match n.eval(&mut pc, &Pop{reg:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => n.get(14),
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 14 instructions, translated by ::tpm::evaluator::native
+pub fn io_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 1u64); 1u64},
+            1 => match n.eval(&mut pc, &IntEn{flag:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            2 => match n.eval(&mut pc, &In{reg:Reg::R1, port:7u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            3 => {n.set(2, 8u64); 4u64},
+            4 => {n.set(3, 4u64); 5u64},
+            5 => {n.set(4, 2u64); 6u64},
+            6 => match n.eval(&mut pc, &Call{major:Reg::R4, minor:Reg::R1, arg:Reg::R2, len:Reg::R3, res:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            7 => match n.eval(&mut pc, &Sm2{op:M2Op::Div, r1:Reg::R5, r2:Reg::R1, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            8 => match n.eval(&mut pc, &MemCopy{dst:Reg::R3, src:Reg::R2, len:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => {let m = (Cond::LtZ.holds((n.get(6) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(5) & m) | (n.get(1) & !m); n.set(7, v); 10u64},
+            10 => match n.eval(&mut pc, &Out{reg:Reg::R7, port:3u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => 2u64,
+            12 => match n.eval(&mut pc, &Out{reg:Reg::R0, port:9u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 41 instructions, translated by ::tpm::evaluator::native
+pub fn random0_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 30u64); 1u64},
+            1 => {n.set(1, 154u64); 2u64},
+            2 => {n.set(2, 178u64); 3u64},
+            3 => {n.set(3, 45u64); 4u64},
+            4 => {n.set(4, 42u64); 5u64},
+            5 => {n.set(5, 18446744073709551613u64); 6u64},
+            6 => {n.set(6, 14973253264166740166u64); 7u64},
+            7 => {n.set(7, 13506618084010749907u64); 8u64},
+            8 => {n.set(1, 220u64); 9u64},
+            9 => {let m = (Cond::EqZ.holds((n.get(5) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(1) & m) | (n.get(7) & !m); n.set(2, v); 10u64},
+            10 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {n.set(1, 18446744073709551613u64); 12u64},
+            12 => {n.set(6, 200u64); 13u64},
+            13 => match n.eval(&mut pc, &ULoad{width:Width::Byte, ptr:Reg::R5, val:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => {let m = (Cond::LtZ.holds((n.get(2) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(4) & m) | (n.get(7) & !m); n.set(5, v); 15u64},
+            15 => {n.set(2, 0u64); 16u64},
+            16 => {let v = n.get(7).wrapping_mul(n.get(2)); n.set(7, v); 17u64},
+            17 => {n.set(0, 47u64); 18u64},
+            18 => match n.eval(&mut pc, &Call{major:Reg::R6, minor:Reg::R0, arg:Reg::R2, len:Reg::R8, res:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => {let a = n.get(2).wrapping_add(111u64); let v = n.get(8); n.write(19u64, a, v)?; 20u64},
+            20 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let m = (Cond::LeZ.holds(n.get(2).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(0) & m) | (n.get(6) & !m); n.set(0, v); 22u64},
+            22 => {n.set(6, 251u64); 23u64},
+            23 => if Cond::NeZ.holds((n.get(5) as i64).cmp(&(n.get(0) as i64))) {n.get(3)} else {24u64},
+            24 => match n.eval(&mut pc, &Pop{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Call{major:Reg::R6, minor:Reg::R3, arg:Reg::R2, len:Reg::R4, res:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => if Cond::EqZ.holds(n.get(1).cmp(&n.get(2))) {n.get(2)} else {27u64},
+            27 => {let a = n.get(4).wrapping_add(33u64); let v = n.read(27u64, a)?; n.set(7, v); 28u64},
+            28 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R9, val:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDiv, r1:Reg::R7, r2:Reg::RA, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &IntEn{flag:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &Call{major:Reg::R4, minor:Reg::R7, arg:Reg::R1, len:Reg::R2, res:Reg::RC}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => {let a = n.get(4); let v = n.read(32u64, a)?; n.set(2, v); 33u64},
+            33 => {n.set(3, 4u64); 34u64},
+            34 => {let m = (Cond::Always.holds(n.get(3).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(4) & m) | (n.get(7) & !m); n.set(3, v); 35u64},
+            35 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R4, r2:Reg::R3, r3:Reg::R7, r4:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R7, val:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => {let a = n.get(10); let v = n.get(4); n.write(38u64, a, v)?; 39u64},
+            39 => {let m = (Cond::NeZ.holds(n.get(1).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(6) & m) | (n.get(6) & !m); n.set(2, v); 40u64},
+            40 => {let a = n.get(5); let v = n.get(0); n.write(40u64, a, v)?; 41u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 31 instructions, translated by ::tpm::evaluator::native
+pub fn random1_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 2u64); 1u64},
+            1 => {n.set(1, 14u64); 2u64},
+            2 => {n.set(2, 163u64); 3u64},
+            3 => {n.set(3, 12748333365447234773u64); 4u64},
+            4 => {n.set(4, 12u64); 5u64},
+            5 => {n.set(5, 1u64); 6u64},
+            6 => {n.set(6, 4u64); 7u64},
+            7 => {n.set(7, 8619783433609945895u64); 8u64},
+            8 => if Cond::LtZ.holds(n.get(1).cmp(&0u64)) {34u64} else {9u64},
+            9 => {let v = n.get(6).wrapping_mul(18446744073709551613u64); n.set(1, v); 10u64},
+            10 => {n.set(3, 90u64); 11u64},
+            11 => match n.eval(&mut pc, &IntEn{flag:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => match n.eval(&mut pc, &IntEn{flag:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => {n.set(4, 31u64); 14u64},
+            14 => {let a = n.get(8).wrapping_add(102u64); let v = n.get(6); n.write(14u64, a, v)?; 15u64},
+            15 => {n.set(7, 18446744073709551615u64); 16u64},
+            16 => {let m = (Cond::NeZ.holds(n.get(1).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(6) & m) | (n.get(0) & !m); n.set(8, v); 17u64},
+            17 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMulSat, r1:Reg::R7, imm:171u64, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            18 => match n.eval(&mut pc, &Um2{op:M2Op::FxDiv, r1:Reg::R1, r2:Reg::R2, r3:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R2, val:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => {let m = (Cond::LtZ.holds(n.get(7).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(12) & m) | (n.get(2) & !m); n.set(6, v); 21u64},
+            21 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R1, val:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => {let v = n.get(3).wrapping_mul(n.get(4)); n.set(4, v); 23u64},
+            23 => if Cond::LeZ.holds((n.get(5) as i64).cmp(&(0u64 as i64))) {19u64} else {24u64},
+            24 => {let m = (Cond::LtZ.holds(n.get(3).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(7) & m) | (n.get(3) & !m); n.set(2, v); 25u64},
+            25 => {let a = n.get(6); let v = n.read(25u64, a)?; n.set(5, v); 26u64},
+            26 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R5, r2:Reg::R3, r3:Reg::R3, r4:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => {let v = n.get(6).wrapping_add(n.get(0)); n.set(15, v); 29u64},
+            29 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMulSat, r1:Reg::R0, imm:159u64, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R6, val:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 36 instructions, translated by ::tpm::evaluator::native
+pub fn random2_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 27u64); 1u64},
+            1 => {n.set(1, 18446744073709551612u64); 2u64},
+            2 => {n.set(2, 81u64); 3u64},
+            3 => {n.set(3, 18446744073709551614u64); 4u64},
+            4 => {n.set(4, 134u64); 5u64},
+            5 => {n.set(5, 137u64); 6u64},
+            6 => {n.set(6, 4u64); 7u64},
+            7 => {n.set(7, 31u64); 8u64},
+            8 => match n.eval(&mut pc, &Pop{reg:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => match n.eval(&mut pc, &Push{reg:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            10 => {n.set(5, 93u64); 11u64},
+            11 => {let v = ((n.get(7) as i64) > (n.get(4) as i64)) as u64; n.set(2, v); 12u64},
+            12 => {let v = (n.get(7) == n.get(6)) as u64; n.set(5, v); 13u64},
+            13 => {let v = (n.get(4) > n.get(1)) as u64; n.set(1, v); 14u64},
+            14 => match n.eval(&mut pc, &Pop{reg:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => match n.eval(&mut pc, &Um4{op:M4Op::DivRem, r1:Reg::R5, r2:Reg::R0, r3:Reg::R3, r4:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let v = (n.get(6) == n.get(1)) as u64; n.set(6, v); 17u64},
+            17 => {let a = n.get(1); let v = n.read(17u64, a)?; n.set(6, v); 18u64},
+            18 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMul, r1:Reg::R7, r2:Reg::R4, r3:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => match n.eval(&mut pc, &Call{major:Reg::R6, minor:Reg::R1, arg:Reg::R8, len:Reg::R4, res:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &MemFill{dst:Reg::R1, val:Reg::R7, len:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => match n.eval(&mut pc, &MemFill{dst:Reg::R0, val:Reg::R3, len:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => {n.set(5, 0u64); 24u64},
+            24 => {let a = n.get(10).wrapping_add(12408240302218825925u64); let v = n.read(24u64, a)?; n.set(1, v); 25u64},
+            25 => if Cond::GtZ.holds(n.get(7).cmp(&0u64)) {n.get(0)} else {26u64},
+            26 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => {let v = n.get(5).wrapping_sub(18446744073709551612u64); n.set(5, v); 29u64},
+            29 => {let v = ((n.get(7) as i64) > (n.get(0) as i64)) as u64; n.set(0, v); 30u64},
+            30 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => {n.set(7, 7u64); 32u64},
+            32 => {n.set(4, 256u64); 33u64},
+            33 => if Cond::NeZ.holds(n.get(10).cmp(&n.get(3))) {n.get(5)} else {34u64},
+            34 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R4, val:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 35 instructions, translated by ::tpm::evaluator::native
+pub fn random3_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 104u64); 1u64},
+            1 => {n.set(1, 2u64); 2u64},
+            2 => {n.set(2, 13u64); 3u64},
+            3 => {n.set(3, 17742599153928971504u64); 4u64},
+            4 => {n.set(4, 5824210688703590725u64); 5u64},
+            5 => {n.set(5, 3030667482730252379u64); 6u64},
+            6 => {n.set(6, 202u64); 7u64},
+            7 => {n.set(7, 56u64); 8u64},
+            8 => {let a = n.get(1); let v = n.read(8u64, a)?; n.set(0, v); 9u64},
+            9 => if Cond::GtZ.holds(n.get(10).cmp(&0u64)) {n.get(2)} else {10u64},
+            10 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => match n.eval(&mut pc, &MemFill{dst:Reg::R0, val:Reg::R4, len:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => {let a = n.get(0).wrapping_add(3u64); let v = n.get(5); n.write(12u64, a, v)?; 13u64},
+            13 => n.get(5),
+            14 => {let a = n.get(1); let v = n.get(2); n.write(14u64, a, v)?; 15u64},
+            15 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let v = ((n.get(3) as i64) > (24u64 as i64)) as u64; n.set(0, v); 17u64},
+            17 => if Cond::NeZ.holds((n.get(6) as i64).cmp(&(n.get(0) as i64))) {n.get(1)} else {18u64},
+            18 => if Cond::GeZ.holds(n.get(0).cmp(&0u64)) {n.get(5)} else {19u64},
+            19 => {n.set(5, 144u64); 20u64},
+            20 => match n.eval(&mut pc, &Sm2I{op:M2Op::MulHi, r1:Reg::R6, imm:2304076093018550661u64, r3:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let v = n.get(6).wrapping_add(n.get(4)); n.set(2, v); 22u64},
+            22 => {let a = n.get(2).wrapping_add(228u64); let v = n.read(22u64, a)?; n.set(3, v); 23u64},
+            23 => 45u64,
+            24 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R0, val:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMul, r1:Reg::R4, r2:Reg::R7, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => if Cond::EqZ.holds((n.get(10) as i64).cmp(&(0u64 as i64))) {29u64} else {28u64},
+            28 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMul, r1:Reg::R6, r2:Reg::R7, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &MemFill{dst:Reg::R6, val:Reg::R7, len:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R6, val:Reg::RE}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => {n.set(13, 0u64); 32u64},
+            32 => if Cond::EqZ.holds(n.get(1).cmp(&0u64)) {44u64} else {33u64},
+            33 => if Cond::NeZ.holds(n.get(1).cmp(&0u64)) {n.get(6)} else {34u64},
+            34 => match n.eval(&mut pc, &Out{reg:Reg::R5, port:5u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 41 instructions, translated by ::tpm::evaluator::native
+pub fn random4_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 4u64); 1u64},
+            1 => {n.set(1, 19u64); 2u64},
+            2 => {n.set(2, 12958438897081009422u64); 3u64},
+            3 => {n.set(3, 6481117786963998618u64); 4u64},
+            4 => {n.set(4, 0u64); 5u64},
+            5 => {n.set(5, 156u64); 6u64},
+            6 => {n.set(6, 3u64); 7u64},
+            7 => {n.set(7, 102u64); 8u64},
+            8 => {n.set(5, 6340928455188139276u64); 9u64},
+            9 => {let a = n.get(3).wrapping_add(107u64); let v = n.read(9u64, a)?; n.set(4, v); 10u64},
+            10 => {n.set(3, 1u64); 11u64},
+            11 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => {n.set(0, 15u64); 13u64},
+            13 => match n.eval(&mut pc, &MemFill{dst:Reg::R0, val:Reg::R2, len:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => {let a = n.get(4).wrapping_add(4778298990614155141u64); let v = n.read(15u64, a)?; n.set(0, v); 16u64},
+            16 => match n.eval(&mut pc, &Pop{reg:Reg::R9}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {n.set(3, 99u64); 18u64},
+            18 => if Cond::GeZ.holds((n.get(6) as i64).cmp(&(0u64 as i64))) {n.get(6)} else {19u64},
+            19 => match n.eval(&mut pc, &Sm2{op:M2Op::FxMulSat, r1:Reg::R5, r2:Reg::R6, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => {let a = n.get(0).wrapping_add(70u64); let v = n.read(20u64, a)?; n.set(8, v); 21u64},
+            21 => match n.eval(&mut pc, &Push{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => match n.eval(&mut pc, &Push{reg:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => {let a = n.get(0).wrapping_add(214u64); let v = n.get(3); n.write(23u64, a, v)?; 24u64},
+            24 => {let a = n.get(7); let v = n.get(2); n.write(24u64, a, v)?; 25u64},
+            25 => match n.eval(&mut pc, &Pop{reg:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => match n.eval(&mut pc, &IntEn{flag:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => {n.set(5, 135u64); 28u64},
+            28 => {let m = (Cond::EqZ.holds((n.get(0) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(6) & m) | (n.get(0) & !m); n.set(7, v); 29u64},
+            29 => {n.set(4, 10752501404462900595u64); 30u64},
+            30 => if Cond::NeZ.holds((n.get(1) as i64).cmp(&(n.get(3) as i64))) {n.get(0)} else {31u64},
+            31 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R7, r2:Reg::R2, r3:Reg::R1, r4:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => if Cond::GeZ.holds((n.get(2) as i64).cmp(&(0u64 as i64))) {28u64} else {33u64},
+            33 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R4, val:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => {let v = (n.get(6) > n.get(3)) as u64; n.set(2, v); 37u64},
+            37 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMul, r1:Reg::R2, imm:112u64, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => match n.eval(&mut pc, &Pop{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            39 => {let a = n.get(3).wrapping_add(24u64); let v = n.get(1); n.write(39u64, a, v)?; 40u64},
+            40 => match n.eval(&mut pc, &MemFill{dst:Reg::R5, val:Reg::R0, len:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 45 instructions, translated by ::tpm::evaluator::native
+pub fn random5_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 17589654728284169746u64); 1u64},
+            1 => {n.set(1, 45u64); 2u64},
+            2 => {n.set(2, 21u64); 3u64},
+            3 => {n.set(3, 3531109423670391125u64); 4u64},
+            4 => {n.set(4, 19u64); 5u64},
+            5 => {n.set(5, 2u64); 6u64},
+            6 => {n.set(6, 158u64); 7u64},
+            7 => {n.set(7, 42u64); 8u64},
+            8 => {n.set(1, 46u64); 9u64},
+            9 => match n.eval(&mut pc, &Sm2I{op:M2Op::Div, r1:Reg::R2, imm:108u64, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            10 => {let a = n.get(4); let v = n.get(5); n.write(10u64, a, v)?; 11u64},
+            11 => match n.eval(&mut pc, &Push{reg:Reg::R9}) {None => pc, Some(stop) => return n.stop(stop)},
+            12 => match n.eval(&mut pc, &MemFill{dst:Reg::RB, val:Reg::R1, len:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Out{reg:Reg::R8, port:2u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => {let v = ((n.get(7) as i64) < (18446744073709551613u64 as i64)) as u64; n.set(8, v); 15u64},
+            15 => {let v = ((n.get(1) as i64) < (n.get(11) as i64)) as u64; n.set(3, v); 16u64},
+            16 => match n.eval(&mut pc, &Um4{op:M4Op::DivRem, r1:Reg::R5, r2:Reg::R3, r3:Reg::R7, r4:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {let a = n.get(13).wrapping_add(1u64); let v = n.get(2); n.write(17u64, a, v)?; 18u64},
+            18 => {n.set(3, 44u64); 19u64},
+            19 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {n.set(3, 211u64); 22u64},
+            22 => {n.set(6, 18446744073709551613u64); 23u64},
+            23 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => if Cond::LtZ.holds(n.get(15).cmp(&n.get(4))) {n.get(3)} else {25u64},
+            25 => {let a = n.get(1).wrapping_add(14u64); let v = n.get(1); n.write(25u64, a, v)?; 26u64},
+            26 => {let a = n.get(1).wrapping_add(22u64); let v = n.read(26u64, a)?; n.set(14, v); 27u64},
+            27 => if Cond::LeZ.holds((n.get(0) as i64).cmp(&(n.get(7) as i64))) {n.get(2)} else {28u64},
+            28 => if Cond::GtZ.holds(n.get(3).cmp(&n.get(2))) {n.get(6)} else {29u64},
+            29 => n.get(6),
+            30 => match n.eval(&mut pc, &Sm2I{op:M2Op::MulHi, r1:Reg::R2, imm:38u64, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &Um2{op:M2Op::FxMulSat, r1:Reg::R5, r2:Reg::R5, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => if Cond::LtZ.holds(n.get(13).cmp(&n.get(2))) {n.get(1)} else {33u64},
+            33 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => {let v = (n.get(1) < n.get(6)) as u64; n.set(5, v); 35u64},
+            35 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R3, r2:Reg::R7, r3:Reg::R3, r4:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => match n.eval(&mut pc, &Um2I{op:M2Op::FxDiv, r1:Reg::RC, imm:103u64, r3:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => match n.eval(&mut pc, &Out{reg:Reg::R2, port:110u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => {let a = n.get(0).wrapping_add(14330180020445166988u64); let v = n.get(2); n.write(38u64, a, v)?; 39u64},
+            39 => {let m = (Cond::GeZ.holds(n.get(4).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(5) & m) | (n.get(2) & !m); n.set(5, v); 40u64},
+            40 => {n.set(6, 15344833674059921701u64); 41u64},
+            41 => {n.set(12, 254u64); 42u64},
+            42 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R2, r2:Reg::R3, r3:Reg::RF, r4:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            43 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            44 => {let a = n.get(2).wrapping_add(4971224482286879325u64); let v = n.get(0); n.write(44u64, a, v)?; 45u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 38 instructions, translated by ::tpm::evaluator::native
+pub fn random6_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 1146221466596845770u64); 1u64},
+            1 => {n.set(1, 254u64); 2u64},
+            2 => {n.set(2, 7u64); 3u64},
+            3 => {n.set(3, 38u64); 4u64},
+            4 => {n.set(4, 42u64); 5u64},
+            5 => {n.set(5, 139u64); 6u64},
+            6 => {n.set(6, 10u64); 7u64},
+            7 => {n.set(7, 18446744073709551613u64); 8u64},
+            8 => if Cond::LtZ.holds((n.get(6) as i64).cmp(&(n.get(3) as i64))) {n.get(4)} else {9u64},
+            9 => {let v = n.get(7).wrapping_add(38u64); n.set(1, v); 10u64},
+            10 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R0, r2:Reg::R5, r3:Reg::R2, r4:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {n.set(0, 0u64); 12u64},
+            12 => {let a = n.get(2); let v = n.get(6); n.write(12u64, a, v)?; 13u64},
+            13 => {let m = (Cond::LeZ.holds((n.get(7) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(7) & m) | (n.get(2) & !m); n.set(2, v); 14u64},
+            14 => if Cond::GtZ.holds((n.get(5) as i64).cmp(&(0u64 as i64))) {n.get(6)} else {15u64},
+            15 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDivSat, r1:Reg::R4, r2:Reg::R7, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let v = n.get(2).wrapping_add(n.get(2)); n.set(11, v); 17u64},
+            17 => match n.eval(&mut pc, &Pop{reg:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            18 => {let a = n.get(5); let v = n.read(18u64, a)?; n.set(3, v); 19u64},
+            19 => match n.eval(&mut pc, &Um2I{op:M2Op::FxDiv, r1:Reg::R6, imm:16u64, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &Um2{op:M2Op::FxDivSat, r1:Reg::R2, r2:Reg::RD, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let v = (n.get(4) == n.get(4)) as u64; n.set(1, v); 22u64},
+            22 => {let v = n.get(2).wrapping_mul(12u64); n.set(11, v); 23u64},
+            23 => match n.eval(&mut pc, &ULoad{width:Width::Byte, ptr:Reg::R6, val:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => match n.eval(&mut pc, &Um2I{op:M2Op::FxMulSat, r1:Reg::R5, imm:2u64, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            27 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R0, r2:Reg::R4, r3:Reg::R5, r4:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxMulSat, r1:Reg::R1, imm:12391197307535342686u64, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => {let a = n.get(6); let v = n.get(1); n.write(29u64, a, v)?; 30u64},
+            30 => match n.eval(&mut pc, &Call{major:Reg::RF, minor:Reg::R4, arg:Reg::R6, len:Reg::R1, res:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => {let m = (Cond::LtZ.holds((n.get(0) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(4) & m) | (n.get(7) & !m); n.set(13, v); 32u64},
+            32 => match n.eval(&mut pc, &Pop{reg:Reg::RC}) {None => pc, Some(stop) => return n.stop(stop)},
+            33 => match n.eval(&mut pc, &Um2{op:M2Op::FxMulSat, r1:Reg::R4, r2:Reg::R4, r3:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => match n.eval(&mut pc, &Call{major:Reg::R8, minor:Reg::R4, arg:Reg::R3, len:Reg::R1, res:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => if Cond::LtZ.holds((n.get(5) as i64).cmp(&(0u64 as i64))) {n.get(0)} else {36u64},
+            36 => if Cond::EqZ.holds(n.get(5).cmp(&n.get(6))) {n.get(3)} else {37u64},
+            37 => {let v = (n.get(0) > n.get(10)) as u64; n.set(6, v); 38u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 30 instructions, translated by ::tpm::evaluator::native
+pub fn random7_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 114u64); 1u64},
+            1 => {n.set(1, 26u64); 2u64},
+            2 => {n.set(2, 18u64); 3u64},
+            3 => {n.set(3, 37u64); 4u64},
+            4 => {n.set(4, 14951422647820876296u64); 5u64},
+            5 => {n.set(5, 15u64); 6u64},
+            6 => {n.set(6, 41u64); 7u64},
+            7 => {n.set(7, 1u64); 8u64},
+            8 => {let v = (n.get(0) < n.get(4)) as u64; n.set(2, v); 9u64},
+            9 => if Cond::GtZ.holds((n.get(1) as i64).cmp(&(0u64 as i64))) {n.get(4)} else {10u64},
+            10 => match n.eval(&mut pc, &ULoad{width:Width::Byte, ptr:Reg::R6, val:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => 39u64,
+            12 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => if Cond::GeZ.holds(n.get(3).cmp(&0u64)) {n.get(14)} else {14u64},
+            14 => {let a = n.get(2); let v = n.read(14u64, a)?; n.set(4, v); 15u64},
+            15 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => match n.eval(&mut pc, &Um2{op:M2Op::FxDivSat, r1:Reg::R2, r2:Reg::R2, r3:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {n.set(15, 3u64); 18u64},
+            18 => {let v = n.get(4).wrapping_sub(n.get(6)); n.set(4, v); 19u64},
+            19 => {let a = n.get(2).wrapping_add(18446744073709551614u64); let v = n.read(19u64, a)?; n.set(2, v); 20u64},
+            20 => {let v = ((n.get(3) as i64) > (7u64 as i64)) as u64; n.set(5, v); 21u64},
+            21 => match n.eval(&mut pc, &Um4{op:M4Op::DivRem, r1:Reg::R5, r2:Reg::R1, r3:Reg::R1, r4:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => match n.eval(&mut pc, &Um2{op:M2Op::MulHi, r1:Reg::RD, r2:Reg::R9, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => {let v = ((n.get(3) as i64) > (18446744073709551612u64 as i64)) as u64; n.set(2, v); 24u64},
+            24 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R3, r2:Reg::R6, r3:Reg::R2, r4:Reg::RA}) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => match n.eval(&mut pc, &Pop{reg:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => {let a = n.get(5).wrapping_add(7342573471964942044u64); let v = n.get(1); n.write(26u64, a, v)?; 27u64},
+            27 => {n.set(3, 37u64); 28u64},
+            28 => {n.set(2, 51u64); 29u64},
+            29 => {let a = n.get(5); let v = n.get(6); n.write(29u64, a, v)?; 30u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 43 instructions, translated by ::tpm::evaluator::native
+pub fn random8_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 32u64); 1u64},
+            1 => {n.set(1, 2u64); 2u64},
+            2 => {n.set(2, 13484419397770410108u64); 3u64},
+            3 => {n.set(3, 7u64); 4u64},
+            4 => {n.set(4, 278965883468626692u64); 5u64},
+            5 => {n.set(5, 46u64); 6u64},
+            6 => {n.set(6, 246u64); 7u64},
+            7 => {n.set(7, 3241612964604347346u64); 8u64},
+            8 => match n.eval(&mut pc, &In{reg:Reg::R2, port:248u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => if Cond::NeZ.holds((n.get(7) as i64).cmp(&(n.get(2) as i64))) {n.get(4)} else {10u64},
+            10 => match n.eval(&mut pc, &In{reg:Reg::R9, port:42u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {let a = n.get(0); let v = n.get(6); n.write(11u64, a, v)?; 12u64},
+            12 => {let a = n.get(1).wrapping_add(7u64); let v = n.get(2); n.write(12u64, a, v)?; 13u64},
+            13 => {let v = ((n.get(0) as i64) < (10522222005719334754u64 as i64)) as u64; n.set(5, v); 14u64},
+            14 => if Cond::EqZ.holds(n.get(6).cmp(&0u64)) {n.get(1)} else {15u64},
+            15 => {let a = n.get(1).wrapping_add(18446744073709551615u64); let v = n.read(15u64, a)?; n.set(7, v); 16u64},
+            16 => match n.eval(&mut pc, &Um2{op:M2Op::FxMulSat, r1:Reg::R2, r2:Reg::R4, r3:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            17 => {let m = (Cond::GtZ.holds((n.get(0) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(7) & m) | (n.get(3) & !m); n.set(4, v); 18u64},
+            18 => if Cond::LtZ.holds((n.get(0) as i64).cmp(&(0u64 as i64))) {16u64} else {19u64},
+            19 => {let v = n.get(13).wrapping_mul(n.get(5)); n.set(2, v); 20u64},
+            20 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDivSat, r1:Reg::R4, r2:Reg::RB, r3:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => {let m = (Cond::LeZ.holds((n.get(0) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(13) & m) | (n.get(0) & !m); n.set(7, v); 22u64},
+            22 => {let a = n.get(6).wrapping_add(18232013965867191845u64); let v = n.get(1); n.write(22u64, a, v)?; 23u64},
+            23 => match n.eval(&mut pc, &Call{major:Reg::R4, minor:Reg::R2, arg:Reg::R4, len:Reg::R6, res:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => {let v = (n.get(4) == n.get(3)) as u64; n.set(1, v); 25u64},
+            25 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R2, val:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => {let v = n.get(6).wrapping_mul(n.get(7)); n.set(7, v); 27u64},
+            27 => {let a = n.get(5).wrapping_add(13285110995401123854u64); let v = n.read(27u64, a)?; n.set(7, v); 28u64},
+            28 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Sm2{op:M2Op::FxDiv, r1:Reg::R1, r2:Reg::R6, r3:Reg::RF}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R5, r2:Reg::R6, r3:Reg::R1, r4:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => {n.set(0, 254u64); 33u64},
+            33 => if Cond::GtZ.holds((n.get(5) as i64).cmp(&(n.get(5) as i64))) {n.get(3)} else {34u64},
+            34 => {let v = (n.get(2) == n.get(2)) as u64; n.set(2, v); 35u64},
+            35 => match n.eval(&mut pc, &MemFill{dst:Reg::R3, val:Reg::R5, len:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            36 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => match n.eval(&mut pc, &MemFill{dst:Reg::R3, val:Reg::R4, len:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            38 => {let v = (n.get(5) < 258u64) as u64; n.set(7, v); 39u64},
+            39 => if Cond::GeZ.holds(n.get(0).cmp(&0u64)) {7u64} else {40u64},
+            40 => {let v = n.get(12).wrapping_mul(n.get(6)); n.set(6, v); 41u64},
+            41 => {let a = n.get(5).wrapping_add(16924800718936064815u64); let v = n.read(41u64, a)?; n.set(5, v); 42u64},
+            42 => {n.set(5, 18446744073709551613u64); 43u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 39 instructions, translated by ::tpm::evaluator::native
+pub fn random9_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 242u64); 1u64},
+            1 => {n.set(1, 56u64); 2u64},
+            2 => {n.set(2, 18446744073709551614u64); 3u64},
+            3 => {n.set(3, 30u64); 4u64},
+            4 => {n.set(4, 6158066282501117724u64); 5u64},
+            5 => {n.set(5, 4u64); 6u64},
+            6 => {n.set(6, 18446744073709551614u64); 7u64},
+            7 => {n.set(7, 207u64); 8u64},
+            8 => match n.eval(&mut pc, &IntEn{flag:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            9 => {let a = n.get(4); let v = n.read(9u64, a)?; n.set(7, v); 10u64},
+            10 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R4, val:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => {let v = n.get(2).wrapping_sub(26u64); n.set(6, v); 12u64},
+            12 => match n.eval(&mut pc, &In{reg:Reg::R1, port:143u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => {let m = (Cond::NeZ.holds((n.get(2) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(7) & m) | (n.get(12) & !m); n.set(4, v); 15u64},
+            15 => match n.eval(&mut pc, &Push{reg:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => n.get(0),
+            17 => {let v = n.get(6).wrapping_sub(229u64); n.set(1, v); 18u64},
+            18 => {n.set(7, 236u64); 19u64},
+            19 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R3, r2:Reg::R2, r3:Reg::R4, r4:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => {let a = n.get(7).wrapping_add(14u64); let v = n.get(6); n.write(20u64, a, v)?; 21u64},
+            21 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R4, val:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            22 => {n.set(11, 12u64); 23u64},
+            23 => {let v = ((n.get(1) as i64) > (n.get(4) as i64)) as u64; n.set(3, v); 24u64},
+            24 => {let a = n.get(6); let v = n.get(10); n.write(24u64, a, v)?; 25u64},
+            25 => if Cond::EqZ.holds(n.get(7).cmp(&0u64)) {n.get(1)} else {26u64},
+            26 => {n.set(2, 11428052855362452989u64); 27u64},
+            27 => {let v = n.get(6).wrapping_add(n.get(2)); n.set(3, v); 28u64},
+            28 => {let m = (Cond::LeZ.holds(n.get(5).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(7) & m) | (n.get(3) & !m); n.set(1, v); 29u64},
+            29 => {let a = n.get(1); let v = n.get(9); n.write(29u64, a, v)?; 30u64},
+            30 => match n.eval(&mut pc, &Out{reg:Reg::R7, port:38u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => n.get(1),
+            32 => {let m = (Cond::NeZ.holds(n.get(4).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(2) & m) | (n.get(0) & !m); n.set(2, v); 33u64},
+            33 => match n.eval(&mut pc, &Call{major:Reg::R0, minor:Reg::R6, arg:Reg::R5, len:Reg::R3, res:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            34 => {n.set(4, 126u64); 35u64},
+            35 => if Cond::LeZ.holds(n.get(7).cmp(&n.get(2))) {n.get(4)} else {36u64},
+            36 => match n.eval(&mut pc, &Pop{reg:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => if Cond::GeZ.holds((n.get(4) as i64).cmp(&(0u64 as i64))) {n.get(3)} else {38u64},
+            38 => match n.eval(&mut pc, &Call{major:Reg::R5, minor:Reg::R0, arg:Reg::RB, len:Reg::R2, res:Reg::RB}) {None => pc, Some(stop) => return n.stop(stop)},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 46 instructions, translated by ::tpm::evaluator::native
+pub fn random10_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 5u64); 1u64},
+            1 => {n.set(1, 47u64); 2u64},
+            2 => {n.set(2, 4u64); 3u64},
+            3 => {n.set(3, 16u64); 4u64},
+            4 => {n.set(4, 18446744073709551614u64); 5u64},
+            5 => {n.set(5, 25u64); 6u64},
+            6 => {n.set(6, 33u64); 7u64},
+            7 => {n.set(7, 18446744073709551612u64); 8u64},
+            8 => {let a = n.get(3); let v = n.read(8u64, a)?; n.set(2, v); 9u64},
+            9 => if Cond::GeZ.holds(n.get(1).cmp(&0u64)) {n.get(5)} else {10u64},
+            10 => match n.eval(&mut pc, &Call{major:Reg::R1, minor:Reg::R7, arg:Reg::R2, len:Reg::R5, res:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => if Cond::NeZ.holds(n.get(1).cmp(&0u64)) {n.get(0)} else {12u64},
+            12 => match n.eval(&mut pc, &Store{width:Width::Half, ptr:Reg::R3, val:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &Push{reg:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R3, val:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => {let a = n.get(2).wrapping_add(33u64); let v = n.read(15u64, a)?; n.set(2, v); 16u64},
+            16 => {let a = n.get(4).wrapping_add(14u64); let v = n.get(12); n.write(16u64, a, v)?; 17u64},
+            17 => if Cond::LeZ.holds(n.get(0).cmp(&n.get(6))) {n.get(5)} else {18u64},
+            18 => match n.eval(&mut pc, &Call{major:Reg::R2, minor:Reg::R6, arg:Reg::R4, len:Reg::R4, res:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            19 => {let v = n.get(6).wrapping_sub(n.get(6)); n.set(4, v); 20u64},
+            20 => match n.eval(&mut pc, &Sm2I{op:M2Op::MulHi, r1:Reg::R3, imm:213u64, r3:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => if Cond::EqZ.holds((n.get(6) as i64).cmp(&(0u64 as i64))) {23u64} else {22u64},
+            22 => if Cond::EqZ.holds(n.get(6).cmp(&0u64)) {n.get(7)} else {23u64},
+            23 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            24 => {let a = n.get(4); let v = n.get(0); n.write(24u64, a, v)?; 25u64},
+            25 => match n.eval(&mut pc, &MemFill{dst:Reg::R5, val:Reg::R2, len:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            26 => {let m = (Cond::NeZ.holds((n.get(6) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(5) & m) | (n.get(3) & !m); n.set(3, v); 27u64},
+            27 => match n.eval(&mut pc, &Push{reg:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            28 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Um4{op:M4Op::AddC, r1:Reg::R5, r2:Reg::R7, r3:Reg::R7, r4:Reg::R3}) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => match n.eval(&mut pc, &Iret) {None => pc, Some(stop) => return n.stop(stop)},
+            31 => match n.eval(&mut pc, &IntEn{flag:Reg::R1}) {None => pc, Some(stop) => return n.stop(stop)},
+            32 => match n.eval(&mut pc, &Sm2I{op:M2Op::FxDivSat, r1:Reg::R1, imm:100u64, r3:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            33 => if Cond::LeZ.holds(n.get(0).cmp(&0u64)) {28u64} else {34u64},
+            34 => match n.eval(&mut pc, &In{reg:Reg::R2, port:2099760719451662637u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            35 => {let m = (Cond::LeZ.holds((n.get(1) as i64).cmp(&(0u64 as i64))) as u64).wrapping_neg(); let v = (n.get(7) & m) | (n.get(0) & !m); n.set(4, v); 36u64},
+            36 => match n.eval(&mut pc, &Sm2{op:M2Op::MulHi, r1:Reg::R7, r2:Reg::R5, r3:Reg::R6}) {None => pc, Some(stop) => return n.stop(stop)},
+            37 => {let m = (Cond::EqZ.holds(n.get(5).cmp(&0u64)) as u64).wrapping_neg(); let v = (n.get(8) & m) | (n.get(7) & !m); n.set(5, v); 38u64},
+            38 => {let a = n.get(6); let v = n.get(0); n.write(38u64, a, v)?; 39u64},
+            39 => if Cond::NeZ.holds((n.get(5) as i64).cmp(&(n.get(6) as i64))) {n.get(4)} else {40u64},
+            40 => match n.eval(&mut pc, &Um4{op:M4Op::SubB, r1:Reg::R7, r2:Reg::R7, r3:Reg::R7, r4:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            41 => match n.eval(&mut pc, &Call{major:Reg::R5, minor:Reg::R2, arg:Reg::R7, len:Reg::R5, res:Reg::R4}) {None => pc, Some(stop) => return n.stop(stop)},
+            42 => {n.set(4, 15u64); 43u64},
+            43 => if Cond::GtZ.holds(n.get(15).cmp(&0u64)) {n.get(5)} else {44u64},
+            44 => {let a = n.get(4); let v = n.read(44u64, a)?; n.set(3, v); 45u64},
+            45 => {let v = n.get(3).wrapping_sub(18446744073709551612u64); n.set(1, v); 46u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+// 31 instructions, translated by ::tpm::evaluator::native
+pub fn random11_64<'t, 'a>(state : &'t mut ::tpm::evaluator::State<'a, u64, i64>, thrash_cnt : u64)
+    -> Result<::tpm::evaluator::MutNotice<'t, u64>, ::tpm::evaluator::Failure<u64>> {
+    use ::tpm::evaluator::*;
+    use ::tpm::evaluator::Instruction::*;
+    let mut n = native::Native::new(state, thrash_cnt)?;
+    let mut pc = n.pc();
+    while n.tick(&mut pc) {
+        pc = match pc {
+            0 => {n.set(0, 4u64); 1u64},
+            1 => {n.set(1, 6955806616932174567u64); 2u64},
+            2 => {n.set(2, 33u64); 3u64},
+            3 => {n.set(3, 205u64); 4u64},
+            4 => {n.set(4, 2830754936819313828u64); 5u64},
+            5 => {n.set(5, 78u64); 6u64},
+            6 => {n.set(6, 18446744073709551612u64); 7u64},
+            7 => {n.set(7, 1u64); 8u64},
+            8 => if Cond::LtZ.holds(n.get(0).cmp(&0u64)) {n.get(1)} else {9u64},
+            9 => if Cond::EqZ.holds((n.get(1) as i64).cmp(&(n.get(0) as i64))) {n.get(5)} else {10u64},
+            10 => match n.eval(&mut pc, &Pop{reg:Reg::RF}) {None => pc, Some(stop) => return n.stop(stop)},
+            11 => if Cond::EqZ.holds(n.get(4).cmp(&0u64)) {1u64} else {12u64},
+            12 => match n.eval(&mut pc, &Store{width:Width::Byte, ptr:Reg::R5, val:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            13 => match n.eval(&mut pc, &IntEn{flag:Reg::R0}) {None => pc, Some(stop) => return n.stop(stop)},
+            14 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::RD, val:Reg::RF}) {None => pc, Some(stop) => return n.stop(stop)},
+            15 => match n.eval(&mut pc, &Pop{reg:Reg::R5}) {None => pc, Some(stop) => return n.stop(stop)},
+            16 => {let a = n.get(8).wrapping_add(0u64); let v = n.get(2); n.write(16u64, a, v)?; 17u64},
+            17 => match n.eval(&mut pc, &MemFill{dst:Reg::R1, val:Reg::R4, len:Reg::R7}) {None => pc, Some(stop) => return n.stop(stop)},
+            18 => {let v = ((n.get(7) as i64) > (13073208559323359932u64 as i64)) as u64; n.set(0, v); 19u64},
+            19 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            20 => match n.eval(&mut pc, &Invalid) {None => pc, Some(stop) => return n.stop(stop)},
+            21 => if Cond::NeZ.holds(n.get(2).cmp(&0u64)) {n.get(5)} else {22u64},
+            22 => match n.eval(&mut pc, &Out{reg:Reg::R0, port:24u64}) {None => pc, Some(stop) => return n.stop(stop)},
+            23 => 19u64,
+            24 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            25 => if Cond::GeZ.holds(n.get(6).cmp(&n.get(5))) {n.get(0)} else {26u64},
+            26 => if Cond::GeZ.holds(n.get(6).cmp(&n.get(15))) {n.get(7)} else {27u64},
+            27 => if Cond::LeZ.holds(n.get(1).cmp(&n.get(1))) {n.get(7)} else {28u64},
+            28 => match n.eval(&mut pc, &ULoad{width:Width::Half, ptr:Reg::R5, val:Reg::R2}) {None => pc, Some(stop) => return n.stop(stop)},
+            29 => match n.eval(&mut pc, &Halt) {None => pc, Some(stop) => return n.stop(stop)},
+            30 => {n.set(6, 9u64); 31u64},
+            _ => return n.code_oob(pc)
+        };
+    }
+    Ok(MutNotice::Thrash)
+}
+
+pub const U32 : &[super::Run<u32, i32>] = &[count_32, fib_32, io_32, random0_32, random1_32, random2_32, random3_32, random4_32, random5_32, random6_32, random7_32, random8_32, random9_32, random10_32, random11_32];
+pub const U64 : &[super::Run<u64, i64>] = &[count_64, fib_64, io_64, random0_64, random1_64, random2_64, random3_64, random4_64, random5_64, random6_64, random7_64, random8_64, random9_64, random10_64, random11_64];
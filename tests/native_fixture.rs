@@ -0,0 +1,43 @@
+// Regenerates tests/native/translated.rs and compares it with the checked-in
+// file, or with TPM_BLESS set, overwrites the file; see tests/native/main.rs.
+
+extern crate tpm;
+
+#[path = "native/programs.rs"]
+mod programs;
+
+use programs::*;
+use std::env;
+use std::fs;
+use tpm::evaluator::Prim;
+use tpm::evaluator::native;
+
+fn translate<U : Prim>(out : &mut String, bits : u32) -> Vec<String> {
+    programs::<U>().iter().map(|(name, code)| {
+        let name = format!("{}_{}", name, bits);
+        native::translate(code, &name, "::tpm", out).unwrap();
+        out.push('\n');
+        name
+    }).collect()
+}
+
+fn fixture() -> String {
+    let mut out = String::from("// Generated by tests/native_fixture.rs. Do not edit.\n\n");
+    let names32 = translate::<u32>(&mut out, 32);
+    let names64 = translate::<u64>(&mut out, 64);
+    out.push_str(&format!("pub const U32 : &[super::Run<u32, i32>] = &[{}];\n", names32.join(", ")));
+    out.push_str(&format!("pub const U64 : &[super::Run<u64, i64>] = &[{}];\n", names64.join(", ")));
+    out
+}
+
+#[test]
+fn fixture_is_current() {
+    let text = fixture();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/native/translated.rs");
+    if env::var_os("TPM_BLESS").is_some() {
+        fs::write(path, &text).unwrap();
+        return
+    }
+    let current = fs::read_to_string(path).unwrap();
+    assert!(text == current, "tests/native/translated.rs is stale; rerun with TPM_BLESS=1");
+}